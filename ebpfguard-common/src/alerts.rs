@@ -1,19 +1,33 @@
+use crate::{
+    consts::{AF_INET, AF_INET6, UNIX_PATH_MAX},
+    inode::Inode,
+    subject::Subject,
+};
+
+/// Alert sent by the eBPF programs when a policy denies an operation.
+/// `enforced` is `0` when the policy is in audit mode and the operation was
+/// allowed anyway.
 pub trait Alert {}
 
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct BprmCheckSecurity {
     pub pid: u32,
-    _padding: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding: [u8; 3],
+    pub subject: Subject,
+    /// Inode of the executed binary.
+    pub inode: Inode,
 }
 
 impl BprmCheckSecurity {
-    pub fn new(pid: u32, binprm_inode: u64) -> Self {
+    pub fn new(pid: u32, enforced: bool, subject: Subject, inode: Inode) -> Self {
         Self {
             pid,
-            _padding: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding: [0; 3],
+            subject,
+            inode,
         }
     }
 }
@@ -24,17 +38,35 @@ impl Alert for BprmCheckSecurity {}
 #[derive(Copy, Clone)]
 pub struct FileOpen {
     pub pid: u32,
-    _padding: u32,
-    pub binprm_inode: u64,
-    pub inode: u64,
+    pub enforced: u8,
+    /// Requested kinds of access, e.g.
+    /// [`ACCESS_READ`](crate::consts::ACCESS_READ).
+    pub access: u8,
+    /// `1` if some kind of access was decided without checking all parent
+    /// directories of the file, because there are more of them than the
+    /// configured depth.
+    pub truncated: u8,
+    _padding: u8,
+    pub subject: Subject,
+    pub inode: Inode,
 }
 
 impl FileOpen {
-    pub fn new(pid: u32, binprm_inode: u64, inode: u64) -> Self {
+    pub fn new(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        inode: Inode,
+        access: u8,
+        truncated: bool,
+    ) -> Self {
         Self {
             pid,
+            enforced: enforced as u8,
+            access,
+            truncated: truncated as u8,
             _padding: 0,
-            binprm_inode,
+            subject,
             inode,
         }
     }
@@ -46,8 +78,9 @@ impl Alert for FileOpen {}
 #[derive(Copy, Clone)]
 pub struct TaskFixSetuid {
     pub pid: u32,
-    _padding: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding: [u8; 3],
+    pub subject: Subject,
     pub old_uid: u32,
     pub old_gid: u32,
     pub new_uid: u32,
@@ -57,7 +90,8 @@ pub struct TaskFixSetuid {
 impl TaskFixSetuid {
     pub fn new(
         pid: u32,
-        binprm_inode: u64,
+        enforced: bool,
+        subject: Subject,
         old_uid: u32,
         old_gid: u32,
         new_uid: u32,
@@ -65,8 +99,9 @@ impl TaskFixSetuid {
     ) -> Self {
         Self {
             pid,
-            _padding: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding: [0; 3],
+            subject,
             old_uid,
             old_gid,
             new_uid,
@@ -81,16 +116,18 @@ impl Alert for TaskFixSetuid {}
 #[derive(Copy, Clone)]
 pub struct SbMount {
     pub pid: u32,
-    _padding: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding: [u8; 3],
+    pub subject: Subject,
 }
 
 impl SbMount {
-    pub fn new(pid: u32, binprm_inode: u64) -> Self {
+    pub fn new(pid: u32, enforced: bool, subject: Subject) -> Self {
         Self {
             pid,
-            _padding: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding: [0; 3],
+            subject,
         }
     }
 }
@@ -101,16 +138,18 @@ impl Alert for SbMount {}
 #[derive(Copy, Clone)]
 pub struct SbRemount {
     pub pid: u32,
-    _padding: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding: [u8; 3],
+    pub subject: Subject,
 }
 
 impl SbRemount {
-    pub fn new(pid: u32, binprm_inode: u64) -> Self {
+    pub fn new(pid: u32, enforced: bool, subject: Subject) -> Self {
         Self {
             pid,
-            _padding: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding: [0; 3],
+            subject,
         }
     }
 }
@@ -121,16 +160,18 @@ impl Alert for SbRemount {}
 #[derive(Copy, Clone)]
 pub struct SbUmount {
     pub pid: u32,
-    _padding: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding: [u8; 3],
+    pub subject: Subject,
 }
 
 impl SbUmount {
-    pub fn new(pid: u32, binprm_inode: u64) -> Self {
+    pub fn new(pid: u32, enforced: bool, subject: Subject) -> Self {
         Self {
             pid,
-            _padding: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding: [0; 3],
+            subject,
         }
     }
 }
@@ -141,55 +182,156 @@ impl Alert for SbUmount {}
 #[derive(Copy, Clone)]
 pub struct SocketBind {
     pub pid: u32,
-    _padding1: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding1: [u8; 3],
+    pub subject: Subject,
     pub port: u16,
-    _padding2: [u16; 3],
+    /// Address family of the socket, [`AF_INET`] or [`AF_INET6`].
+    pub family: u16,
+    pub addr_v4: u32,
+    pub addr_v6: [u8; 16],
 }
 
 impl SocketBind {
-    pub fn new(pid: u32, binprm_inode: u64, port: u16) -> Self {
+    pub fn new_ipv4(pid: u32, enforced: bool, subject: Subject, port: u16, addr_v4: u32) -> Self {
         Self {
             pid,
-            _padding1: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
             port,
-            _padding2: [0; 3],
+            family: AF_INET,
+            addr_v4,
+            addr_v6: [0; 16],
+        }
+    }
+
+    pub fn new_ipv6(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        port: u16,
+        addr_v6: [u8; 16],
+    ) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            port,
+            family: AF_INET6,
+            addr_v4: 0,
+            addr_v6,
         }
     }
 }
 
 impl Alert for SocketBind {}
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SocketListen {
+    pub pid: u32,
+    pub enforced: u8,
+    _padding1: [u8; 3],
+    pub subject: Subject,
+    /// Local port of the socket, `0` if it isn't bound yet.
+    pub port: u16,
+    /// Address family of the socket, [`AF_INET`] or [`AF_INET6`].
+    pub family: u16,
+    pub addr_v4: u32,
+    pub addr_v6: [u8; 16],
+}
+
+impl SocketListen {
+    pub fn new_ipv4(pid: u32, enforced: bool, subject: Subject, port: u16, addr_v4: u32) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            port,
+            family: AF_INET,
+            addr_v4,
+            addr_v6: [0; 16],
+        }
+    }
+
+    pub fn new_ipv6(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        port: u16,
+        addr_v6: [u8; 16],
+    ) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            port,
+            family: AF_INET6,
+            addr_v4: 0,
+            addr_v6,
+        }
+    }
+}
+
+impl Alert for SocketListen {}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct SocketConnect {
     pub pid: u32,
-    _padding1: u32,
-    pub binprm_inode: u64,
+    pub enforced: u8,
+    _padding1: [u8; 3],
+    pub subject: Subject,
     pub addr_v4: u32,
-    _padding2: u32,
+    pub port: u16,
+    /// IP protocol number of the socket.
+    pub protocol: u8,
+    _padding2: u8,
     pub addr_v6: [u8; 16],
 }
 
 impl SocketConnect {
-    pub fn new_ipv4(pid: u32, binprm_inode: u64, addr_v4: u32) -> Self {
+    pub fn new_ipv4(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        protocol: u8,
+        addr_v4: u32,
+        port: u16,
+    ) -> Self {
         Self {
             pid,
-            _padding1: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
             addr_v4,
+            port,
+            protocol,
             _padding2: 0,
             addr_v6: [0; 16],
         }
     }
 
-    pub fn new_ipv6(pid: u32, binprm_inode: u64, addr_v6: [u8; 16]) -> Self {
+    pub fn new_ipv6(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        protocol: u8,
+        addr_v6: [u8; 16],
+        port: u16,
+    ) -> Self {
         Self {
             pid,
-            _padding1: 0,
-            binprm_inode,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
             addr_v4: 0,
+            port,
+            protocol,
             _padding2: 0,
             addr_v6,
         }
@@ -198,6 +340,139 @@ impl SocketConnect {
 
 impl Alert for SocketConnect {}
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct InetConnRequest {
+    /// PID of the process which started listening on the socket, 0 if it's
+    /// not known.
+    pub pid: u32,
+    pub enforced: u8,
+    _padding1: [u8; 3],
+    /// Subject of the process which started listening on the socket.
+    pub subject: Subject,
+    /// Address of the peer.
+    pub addr_v4: u32,
+    /// Local port of the socket.
+    pub port: u16,
+    /// IP protocol number of the socket.
+    pub protocol: u8,
+    _padding2: u8,
+    /// Address of the peer.
+    pub addr_v6: [u8; 16],
+}
+
+impl InetConnRequest {
+    pub fn new_ipv4(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        protocol: u8,
+        addr_v4: u32,
+        port: u16,
+    ) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            addr_v4,
+            port,
+            protocol,
+            _padding2: 0,
+            addr_v6: [0; 16],
+        }
+    }
+
+    pub fn new_ipv6(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        protocol: u8,
+        addr_v6: [u8; 16],
+        port: u16,
+    ) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            addr_v4: 0,
+            port,
+            protocol,
+            _padding2: 0,
+            addr_v6,
+        }
+    }
+}
+
+impl Alert for InetConnRequest {}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SocketConnectUnix {
+    pub pid: u32,
+    pub enforced: u8,
+    _padding1: [u8; 3],
+    pub subject: Subject,
+    /// Path of the socket as in `sun_path`, padded with zeros. Names in the
+    /// abstract namespace start with a zero byte.
+    pub path: [u8; UNIX_PATH_MAX],
+    _padding2: [u8; 4],
+}
+
+impl SocketConnectUnix {
+    pub fn new(pid: u32, enforced: bool, subject: Subject, path: [u8; UNIX_PATH_MAX]) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            path,
+            _padding2: [0; 4],
+        }
+    }
+}
+
+impl Alert for SocketConnectUnix {}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SocketCreate {
+    pub pid: u32,
+    pub enforced: u8,
+    _padding1: [u8; 3],
+    pub subject: Subject,
+    /// Arguments of `socket`, without the flags of the type.
+    pub family: u32,
+    pub socket_type: u32,
+    pub protocol: u32,
+    _padding2: u32,
+}
+
+impl SocketCreate {
+    pub fn new(
+        pid: u32,
+        enforced: bool,
+        subject: Subject,
+        family: u32,
+        socket_type: u32,
+        protocol: u32,
+    ) -> Self {
+        Self {
+            pid,
+            enforced: enforced as u8,
+            _padding1: [0; 3],
+            subject,
+            family,
+            socket_type,
+            protocol,
+            _padding2: 0,
+        }
+    }
+}
+
+impl Alert for SocketCreate {}
+
 #[cfg(feature = "user")]
 pub mod user {
     use super::*;
@@ -206,8 +481,12 @@ pub mod user {
 
     unsafe impl Pod for BprmCheckSecurity {}
     unsafe impl Pod for FileOpen {}
+    unsafe impl Pod for InetConnRequest {}
     unsafe impl Pod for SbMount {}
     unsafe impl Pod for SocketBind {}
     unsafe impl Pod for SocketConnect {}
+    unsafe impl Pod for SocketConnectUnix {}
+    unsafe impl Pod for SocketCreate {}
+    unsafe impl Pod for SocketListen {}
     unsafe impl Pod for TaskFixSetuid {}
 }
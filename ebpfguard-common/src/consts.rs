@@ -1,2 +1,163 @@
+use crate::{inode::Inode, subject::Subject};
+
 /// Wildcard for the inode.
-pub const INODE_WILDCARD: u64 = 0;
+pub const INODE_WILDCARD: Inode = Inode::new(0, 0);
+
+/// Wildcard for the cgroup.
+pub const CGROUP_WILDCARD: u64 = 0;
+
+/// Wildcard for the user ID. `(uid_t)-1` is never a valid user ID.
+pub const UID_WILDCARD: u32 = u32::MAX;
+
+/// Wildcard for the group ID. `(gid_t)-1` is never a valid group ID.
+pub const GID_WILDCARD: u32 = u32::MAX;
+
+/// Wildcard for the subject, matching all processes.
+pub const SUBJECT_WILDCARD: Subject = Subject::new(INODE_WILDCARD, CGROUP_WILDCARD);
+
+/// Inode of the binary of a subject which is not known, e.g. the process
+/// which started listening on a socket before `inet_conn_request` was
+/// attached. It matches no per-binary policies. No file has this inode.
+pub const INODE_UNKNOWN: Inode = Inode::new(u64::MAX, u64::MAX);
+
+/// Flag set in the values of policy map entries which belong to policies in
+/// audit mode. Denials caused by such entries are alerted, but not enforced.
+pub const POLICY_AUDIT: u8 = 1;
+
+/// Flag set in the values of the default action map entries which deny
+/// operations. Entries without it allow them.
+pub const DEFAULT_DENY: u8 = 2;
+
+/// Flags set in the values of `file_open` map entries, with the kinds of
+/// access the entry applies to. Entries without any of them apply to all
+/// kinds of access.
+pub const ACCESS_READ: u8 = 4;
+pub const ACCESS_WRITE: u8 = 8;
+pub const ACCESS_EXEC: u8 = 16;
+pub const ACCESS_ALL: u8 = ACCESS_READ | ACCESS_WRITE | ACCESS_EXEC;
+
+/// IDs of the hooks in the keys of the default action map.
+pub const HOOK_FILE_OPEN: u32 = 1;
+pub const HOOK_SB_MOUNT: u32 = 2;
+pub const HOOK_SB_REMOUNT: u32 = 3;
+pub const HOOK_SB_UMOUNT: u32 = 4;
+pub const HOOK_SOCKET_BIND: u32 = 5;
+pub const HOOK_SOCKET_CONNECT: u32 = 6;
+pub const HOOK_TASK_FIX_SETUID: u32 = 7;
+pub const HOOK_BPRM_CHECK_SECURITY: u32 = 8;
+pub const HOOK_SOCKET_CONNECT_UNIX: u32 = 9;
+pub const HOOK_SOCKET_LISTEN: u32 = 10;
+pub const HOOK_INET_CONN_REQUEST: u32 = 11;
+pub const HOOK_SOCKET_CREATE: u32 = 12;
+
+/// Unix domain socket address family.
+pub const AF_UNIX: u16 = 1;
+
+/// IPv4 address family.
+pub const AF_INET: u16 = 2;
+
+/// IPv6 address family.
+pub const AF_INET6: u16 = 10;
+
+/// Length of the path in a Unix domain socket address (`sun_path`).
+pub const UNIX_PATH_MAX: usize = 108;
+
+/// Wildcard for the path of a Unix domain socket, matching all sockets.
+pub const UNIX_PATH_WILDCARD: [u8; UNIX_PATH_MAX] = [0; UNIX_PATH_MAX];
+
+/// Wildcard for the protocol in `socket_connect` policies, matching all
+/// protocols.
+pub const PROTOCOL_WILDCARD: u8 = 0;
+
+/// TCP protocol number.
+pub const IPPROTO_TCP: u8 = 6;
+
+/// UDP protocol number.
+pub const IPPROTO_UDP: u8 = 17;
+
+/// Wildcards for the address family, the type and the protocol in
+/// `socket_create` policies. `(u32)-1` is never passed to `socket`
+/// successfully, while `0` is a valid protocol (the default one).
+pub const SOCKET_FAMILY_WILDCARD: u32 = u32::MAX;
+pub const SOCKET_TYPE_WILDCARD: u32 = u32::MAX;
+pub const SOCKET_PROTOCOL_WILDCARD: u32 = u32::MAX;
+
+/// Maximum number of entries in the policy maps keyed by subject and object
+/// (e.g. `bprm_check_security`, `file_open`, `socket_bind`, `socket_connect`,
+/// `socket_connect_unix`, `socket_listen`, `inet_conn_request`,
+/// `socket_create`).
+/// In LPM tries, every port range takes one entry per aligned block.
+pub const MAX_OBJECT_ENTRIES: u32 = 8192;
+
+/// Maximum number of entries in the policy maps keyed only by subject (e.g.
+/// `task_fix_setuid`, `sb_mount`).
+pub const MAX_SUBJECT_ENTRIES: u32 = 1024;
+
+/// Maximum number of entries in the default action map.
+pub const MAX_DEFAULT_ENTRIES: u32 = MAX_SUBJECT_ENTRIES;
+
+/// Maximum number of entries in the map with paths of the inodes used in
+/// policies.
+pub const MAX_INODE_INFO_ENTRIES: u32 = MAX_SUBJECT_ENTRIES + MAX_OBJECT_ENTRIES;
+
+/// Maximum number of listening sockets whose subjects are recorded for
+/// `inet_conn_request`. The least recently used ones are evicted, the
+/// listening processes of their sockets are not known anymore.
+pub const MAX_LISTENER_ENTRIES: u32 = 16384;
+
+/// Maximum length of a path stored in the map with paths of the inodes,
+/// including the terminating NUL byte.
+pub const MAX_PATH_LEN: usize = 4096;
+
+/// Maximum length of a path prefix in `file_open` prefix policies. LPM trie
+/// keys are limited to 256 bytes, including the subject.
+pub const FILE_PREFIX_MAX: usize = 224;
+
+/// Number of parent directories of an opened file checked for `file_open`
+/// policies when it's not configured.
+pub const DEFAULT_DIR_DEPTH: u32 = 16;
+
+/// Maximum number of parent directories of an opened file which can be
+/// checked for `file_open` policies.
+pub const MAX_DIR_DEPTH: u32 = 64;
+
+/// Maximum number of prefixes of the path of an opened file which are
+/// checked for `file_open` prefix policies of a single subject.
+pub const MAX_PREFIX_MATCHES: u32 = 16;
+
+/// Flag in `file_open` settings enabling the lookup of prefix policies. The
+/// path of every opened file is resolved only when it's set.
+pub const FILE_OPEN_PREFIXES: u8 = 1;
+
+/// Flags in the values of the maps with subjects of `file_open` and
+/// `bprm_check_security` policies, set if the subject has entries which
+/// match paths under them: directories (or inodes which may be ones) and
+/// path prefixes. Parent directories and prefixes of a file are checked only
+/// for subjects which have such entries.
+pub const SUBJECT_DIRS: u8 = 1;
+pub const SUBJECT_PREFIXES: u8 = 2;
+
+/// Flag in `file_open` settings alerting, but allowing, access which can't
+/// be decided because an opened file has more parent directories than the
+/// configured depth and isn't denied by the entry for all paths. Without it,
+/// such access is denied.
+pub const FILE_OPEN_ALLOW_TRUNCATED: u8 = 2;
+
+/// Flag in `bprm_check_security` settings alerting, but allowing, executions
+/// which can't be decided because the binary has more parent directories
+/// than the configured depth and isn't denied by the entry for all paths.
+/// Without it, such executions are denied.
+pub const BPRM_CHECK_SECURITY_ALLOW_TRUNCATED: u8 = 1;
+
+/// Flag in `inet_conn_request` settings set when any policy has a binary
+/// subject. Connections allowed to sockets whose listening process is not
+/// known are then alerted, because a per-binary policy might deny them.
+pub const INET_CONN_REQUEST_BINARY_SUBJECTS: u8 = 1;
+
+/// Flag in `inet_conn_request` settings denying, instead of only alerting,
+/// the connections described in [`INET_CONN_REQUEST_BINARY_SUBJECTS`].
+pub const INET_CONN_REQUEST_DENY_UNKNOWN: u8 = 2;
+
+/// Maximum length of a policy name stored in the map with paths of the
+/// inodes.
+pub const MAX_POLICY_NAME_LEN: usize = 32;
@@ -0,0 +1,99 @@
+use core::fmt::{Display, Formatter};
+
+use crate::consts::{MAX_PATH_LEN, MAX_POLICY_NAME_LEN};
+
+/// Identity of a file, consisting of the device of its filesystem and the
+/// inode number. Inode numbers are unique only within a single filesystem,
+/// so both are needed to tell files apart.
+///
+/// `dev` uses the kernel encoding of device numbers (`s_dev` of the super
+/// block), which differs from `st_dev` returned by `stat`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Inode {
+    pub dev: u64,
+    pub ino: u64,
+}
+
+impl Inode {
+    pub const fn new(dev: u64, ino: u64) -> Self {
+        Self { dev, ino }
+    }
+
+    /// Major number of the device.
+    pub const fn major(&self) -> u64 {
+        self.dev >> MINOR_BITS
+    }
+
+    /// Minor number of the device.
+    pub const fn minor(&self) -> u64 {
+        self.dev & ((1 << MINOR_BITS) - 1)
+    }
+}
+
+/// Number of bits used by the minor number in the kernel encoding of device
+/// numbers.
+const MINOR_BITS: u64 = 20;
+
+impl Display for Inode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}:{}/{}", self.major(), self.minor(), self.ino)
+    }
+}
+
+/// Metadata of an inode used in a policy. It's stored next to the policy maps,
+/// so every process managing them can resolve inodes back to paths.
+///
+/// Strings are NUL-padded.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct InodeInfo {
+    /// Time when the inode was added, in seconds since the Unix epoch.
+    pub added: u64,
+    /// Name of the policy which added the inode (e.g. `file_open`).
+    pub policy: [u8; MAX_POLICY_NAME_LEN],
+    /// Path which the inode was resolved from.
+    pub path: [u8; MAX_PATH_LEN],
+}
+
+impl InodeInfo {
+    /// Creates the metadata. Returns `None` if the path or the policy name
+    /// doesn't fit.
+    pub fn new(path: &[u8], policy: &[u8], added: u64) -> Option<Self> {
+        if path.len() >= MAX_PATH_LEN || policy.len() >= MAX_POLICY_NAME_LEN {
+            return None;
+        }
+
+        let mut info = Self {
+            added,
+            policy: [0; MAX_POLICY_NAME_LEN],
+            path: [0; MAX_PATH_LEN],
+        };
+        info.policy[..policy.len()].copy_from_slice(policy);
+        info.path[..path.len()].copy_from_slice(path);
+        Some(info)
+    }
+
+    pub fn path(&self) -> &[u8] {
+        until_nul(&self.path)
+    }
+
+    pub fn policy(&self) -> &[u8] {
+        until_nul(&self.policy)
+    }
+}
+
+fn until_nul(buf: &[u8]) -> &[u8] {
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    &buf[..len]
+}
+
+#[cfg(feature = "user")]
+pub mod user {
+    use super::*;
+
+    use aya::Pod;
+
+    unsafe impl Pod for Inode {}
+    unsafe impl Pod for InodeInfo {}
+}
@@ -2,4 +2,6 @@
 
 pub mod alerts;
 pub mod consts;
+pub mod inode;
 pub mod policy;
+pub mod subject;
@@ -1,98 +1,409 @@
-pub const MAX_PATHS: usize = 4;
-pub const MAX_PORTS: usize = 4;
-pub const MAX_IPV4ADDRS: usize = 1;
-pub const MAX_IPV6ADDRS: usize = 1;
+use crate::{
+    consts::{FILE_PREFIX_MAX, UNIX_PATH_MAX},
+    inode::Inode,
+    subject::Subject,
+};
 
+/// Key of the maps with file policies, consisting of the subject and the inode
+/// of the file or directory.
+///
+/// [`INODE_WILDCARD`](crate::consts::INODE_WILDCARD) as `path` matches all
+/// files.
 #[repr(C)]
 #[derive(Copy, Clone)]
-pub struct Paths {
-    pub paths: [u64; MAX_PATHS],
+pub struct PathKey {
+    pub subject: Subject,
+    pub path: Inode,
 }
 
+impl PathKey {
+    pub fn new(subject: Subject, path: Inode) -> Self {
+        Self { subject, path }
+    }
+}
+
+/// Key of the maps with Unix domain socket policies, consisting of the
+/// subject and the path of the socket.
+///
+/// [`UNIX_PATH_WILDCARD`](crate::consts::UNIX_PATH_WILDCARD) as `path`
+/// matches all sockets.
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
-pub struct Ports {
-    pub ports: [u16; MAX_PORTS],
+#[derive(Copy, Clone)]
+pub struct UnixPathKey {
+    pub subject: Subject,
+    /// Path of the socket as in `sun_path`, padded with zeros. Names in the
+    /// abstract namespace start with a zero byte.
+    pub path: [u8; UNIX_PATH_MAX],
+    _padding: [u8; 4],
 }
 
-impl Ports {
-    pub fn new(ports: [u16; MAX_PORTS]) -> Self {
-        Self { ports }
+impl UnixPathKey {
+    pub fn new(subject: Subject, path: [u8; UNIX_PATH_MAX]) -> Self {
+        Self {
+            subject,
+            path,
+            _padding: [0; 4],
+        }
     }
+}
+
+/// Key of the `socket_create` policy maps, consisting of the subject and
+/// the arguments of `socket`. Each of the family, the type and the protocol
+/// can be a wildcard (e.g.
+/// [`SOCKET_FAMILY_WILDCARD`](crate::consts::SOCKET_FAMILY_WILDCARD)).
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SocketKey {
+    pub subject: Subject,
+    pub family: u32,
+    pub socket_type: u32,
+    pub protocol: u32,
+    _padding: u32,
+}
 
-    pub fn new_all() -> Self {
+impl SocketKey {
+    pub fn new(subject: Subject, family: u32, socket_type: u32, protocol: u32) -> Self {
         Self {
-            ports: [0; MAX_PORTS],
+            subject,
+            family,
+            socket_type,
+            protocol,
+            _padding: 0,
         }
     }
+}
 
-    pub fn all(&self) -> bool {
-        self.ports[0] == 0
+/// Key of the map with default actions, consisting of the subject and the ID
+/// of the hook (e.g. [`HOOK_FILE_OPEN`](crate::consts::HOOK_FILE_OPEN)).
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct DefaultKey {
+    pub subject: Subject,
+    pub hook: u32,
+    _padding: u32,
+}
+
+impl DefaultKey {
+    pub fn new(subject: Subject, hook: u32) -> Self {
+        Self {
+            subject,
+            hook,
+            _padding: 0,
+        }
     }
 }
 
-pub trait IpAddrs<T, const U: usize> {
-    fn all(&self) -> bool;
-    fn addrs(&self) -> [T; U];
+/// Length (in bits) of the subject part of the address LPM trie keys.
+pub const SUBJECT_PREFIX_LEN: u32 = (core::mem::size_of::<Subject>() * 8) as u32;
+
+/// Data of the LPM trie key for port policies.
+///
+/// Ranges of ports are stored as aligned blocks, e.g. `8000-8063` is the
+/// port `8000` with prefix length 10. Prefix length 0 matches all ports.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct PortKey {
+    pub subject: Subject,
+    /// Port in network byte order, so prefixes match ranges of ports.
+    pub port: [u8; 2],
 }
 
+impl PortKey {
+    /// Prefix length of the key with a single port.
+    pub const PREFIX_LEN: u32 = SUBJECT_PREFIX_LEN + 16;
+
+    pub fn new(subject: Subject, port: u16) -> Self {
+        Self {
+            subject,
+            port: port.to_be_bytes(),
+        }
+    }
+}
+
+/// Prefix length of the subject and the protocol in the keys of
+/// `socket_connect` policies.
+pub const PROTOCOL_PREFIX_LEN: u32 = SUBJECT_PREFIX_LEN + 8;
+
+/// Data of the LPM trie key for `socket_connect` port policies which apply
+/// to all addresses.
+///
+/// The subject and the protocol come first, so they're always fully matched
+/// before the port prefix is considered.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct ProtocolPortKey {
+    pub subject: Subject,
+    /// IP protocol number, or [`PROTOCOL_WILDCARD`](crate::consts::PROTOCOL_WILDCARD).
+    pub protocol: u8,
+    /// Port in network byte order, so prefixes match ranges of ports.
+    pub port: [u8; 2],
+}
+
+impl ProtocolPortKey {
+    /// Prefix length of the key with a single port.
+    pub const PREFIX_LEN: u32 = PROTOCOL_PREFIX_LEN + 16;
+
+    pub fn new(subject: Subject, protocol: u8, port: u16) -> Self {
+        Self {
+            subject,
+            protocol,
+            port: port.to_be_bytes(),
+        }
+    }
+}
+
+/// Key of the `socket_connect` maps with rules for all addresses of a
+/// family, consisting of the subject, the address family and the protocol.
+/// Such rules are kept apart from the address tries, where they would be
+/// hidden by longer prefixes.
 #[repr(C)]
 #[derive(Copy, Clone)]
-pub struct Ipv4Addrs {
-    pub addrs: [u32; MAX_IPV4ADDRS],
+pub struct FamilyKey {
+    pub subject: Subject,
+    /// [`AF_INET`](crate::consts::AF_INET) or [`AF_INET6`](crate::consts::AF_INET6).
+    pub family: u16,
+    /// IP protocol number, or [`PROTOCOL_WILDCARD`](crate::consts::PROTOCOL_WILDCARD).
+    pub protocol: u8,
+    _padding: [u8; 5],
 }
 
-impl Ipv4Addrs {
-    pub fn new(addrs: [u32; MAX_IPV4ADDRS]) -> Self {
-        Self { addrs }
+impl FamilyKey {
+    pub fn new(subject: Subject, family: u16, protocol: u8) -> Self {
+        Self {
+            subject,
+            family,
+            protocol,
+            _padding: [0; 5],
+        }
     }
+}
 
-    pub fn new_all() -> Self {
+/// Data of the LPM trie key for IPv4 `socket_connect` address policies.
+///
+/// The subject and the protocol come first, so they're always fully matched
+/// before the address prefix is considered. The address comes before the
+/// port, so a network prefix matches all ports, while a full address can be
+/// followed by a block of ports.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct Ipv4Key {
+    pub subject: Subject,
+    /// IP protocol number, or [`PROTOCOL_WILDCARD`](crate::consts::PROTOCOL_WILDCARD).
+    pub protocol: u8,
+    pub addr: [u8; 4],
+    /// Port in network byte order, so prefixes match ranges of ports.
+    pub port: [u8; 2],
+}
+
+impl Ipv4Key {
+    /// Prefix length of the key with a full address and a single port.
+    pub const PREFIX_LEN: u32 = PROTOCOL_PREFIX_LEN + 32 + 16;
+
+    pub fn new(subject: Subject, protocol: u8, addr: [u8; 4], port: u16) -> Self {
         Self {
-            addrs: [0; MAX_IPV4ADDRS],
+            subject,
+            protocol,
+            addr,
+            port: port.to_be_bytes(),
         }
     }
 }
 
-impl IpAddrs<u32, MAX_IPV4ADDRS> for Ipv4Addrs {
-    #[inline(always)]
-    fn all(&self) -> bool {
-        self.addrs[0] == 0
+/// Data of the LPM trie key for IPv6 `socket_connect` address policies.
+///
+/// The subject and the protocol come first, so they're always fully matched
+/// before the address prefix is considered. The address comes before the
+/// port, so a network prefix matches all ports, while a full address can be
+/// followed by a block of ports.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct Ipv6Key {
+    pub subject: Subject,
+    /// IP protocol number, or [`PROTOCOL_WILDCARD`](crate::consts::PROTOCOL_WILDCARD).
+    pub protocol: u8,
+    pub addr: [u8; 16],
+    /// Port in network byte order, so prefixes match ranges of ports.
+    pub port: [u8; 2],
+}
+
+impl Ipv6Key {
+    /// Prefix length of the key with a full address and a single port.
+    pub const PREFIX_LEN: u32 = PROTOCOL_PREFIX_LEN + 128 + 16;
+
+    pub fn new(subject: Subject, protocol: u8, addr: [u8; 16], port: u16) -> Self {
+        Self {
+            subject,
+            protocol,
+            addr,
+            port: port.to_be_bytes(),
+        }
+    }
+}
+
+/// Data of the LPM trie key for IPv4 bind address policies.
+///
+/// The address comes before the port, so a network prefix matches all
+/// ports, while a full address can be followed by a block of ports.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct Ipv4PortKey {
+    pub subject: Subject,
+    pub addr: [u8; 4],
+    /// Port in network byte order, so prefixes match ranges of ports.
+    pub port: [u8; 2],
+}
+
+impl Ipv4PortKey {
+    /// Prefix length of the key with a full address and a single port.
+    pub const PREFIX_LEN: u32 = SUBJECT_PREFIX_LEN + 32 + 16;
+
+    pub fn new(subject: Subject, addr: [u8; 4], port: u16) -> Self {
+        Self {
+            subject,
+            addr,
+            port: port.to_be_bytes(),
+        }
     }
+}
+
+/// Data of the LPM trie key for IPv6 bind address policies.
+///
+/// The address comes before the port, so a network prefix matches all
+/// ports, while a full address can be followed by a block of ports.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct Ipv6PortKey {
+    pub subject: Subject,
+    pub addr: [u8; 16],
+    /// Port in network byte order, so prefixes match ranges of ports.
+    pub port: [u8; 2],
+}
 
-    #[inline(always)]
-    fn addrs(&self) -> [u32; MAX_IPV4ADDRS] {
-        self.addrs
+impl Ipv6PortKey {
+    /// Prefix length of the key with a full address and a single port.
+    pub const PREFIX_LEN: u32 = SUBJECT_PREFIX_LEN + 128 + 16;
+
+    pub fn new(subject: Subject, addr: [u8; 16], port: u16) -> Self {
+        Self {
+            subject,
+            addr,
+            port: port.to_be_bytes(),
+        }
     }
 }
 
+/// Value of the LPM trie entries for address and port policies.
 #[repr(C)]
 #[derive(Copy, Clone)]
-pub struct Ipv6Addrs {
-    pub addrs: [[u8; 16]; MAX_IPV4ADDRS],
+pub struct PrefixValue {
+    /// Prefix length of the network or the block of ports (or both, in
+    /// address policies with ports), without the subject and the protocol.
+    /// Used to tell exact matches from the longest prefix matches.
+    pub prefix_len: u8,
+    /// Flags of the policy, e.g. [`POLICY_AUDIT`](crate::consts::POLICY_AUDIT).
+    pub flags: u8,
+}
+
+impl PrefixValue {
+    pub fn new(prefix_len: u8, flags: u8) -> Self {
+        Self { prefix_len, flags }
+    }
+}
+
+/// Data of the LPM trie key for `file_open` prefix policies, consisting of
+/// the subject and the path of the file.
+///
+/// Paths are matched byte by byte, so userspace stores a directory as two
+/// entries: the path followed by a NUL byte, matching the directory itself,
+/// and the path followed by `/`, matching everything under it. Paths longer
+/// than [`FILE_PREFIX_MAX`] are matched by their first bytes.
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct FilePrefixKey {
+    pub subject: Subject,
+    pub path: [u8; FILE_PREFIX_MAX],
 }
 
-impl Ipv6Addrs {
-    pub fn new(addrs: [[u8; 16]; MAX_IPV4ADDRS]) -> Self {
-        Self { addrs }
+impl FilePrefixKey {
+    /// Returns the prefix length (in bits) of the key with the given number
+    /// of path bytes.
+    pub const fn prefix_len(path_len: usize) -> u32 {
+        SUBJECT_PREFIX_LEN + (path_len * 8) as u32
+    }
+
+    pub fn new(subject: Subject, path: [u8; FILE_PREFIX_MAX]) -> Self {
+        Self { subject, path }
     }
+}
+
+/// Value of the LPM trie entries for `file_open` prefix policies.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FilePrefixValue {
+    /// Number of path bytes of the entry. Used to look up shorter prefixes
+    /// once the entry is matched.
+    pub path_len: u16,
+    /// Flags of the policy, e.g. [`POLICY_AUDIT`](crate::consts::POLICY_AUDIT)
+    /// and [`ACCESS_READ`](crate::consts::ACCESS_READ).
+    pub flags: u8,
+    _padding: u8,
+}
 
-    pub fn new_all() -> Self {
+impl FilePrefixValue {
+    pub fn new(path_len: u16, flags: u8) -> Self {
         Self {
-            addrs: [[0; 16]; MAX_IPV4ADDRS],
+            path_len,
+            flags,
+            _padding: 0,
         }
     }
 }
 
-impl IpAddrs<[u8; 16], MAX_IPV6ADDRS> for Ipv6Addrs {
-    #[inline(always)]
-    fn all(&self) -> bool {
-        self.addrs[0] == [0; 16]
+/// Settings of the `file_open` program, stored in the only entry of an
+/// array map. All fields set to zero mean the defaults.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct FileOpenSettings {
+    /// Number of parent directories checked for policies, up to
+    /// [`MAX_DIR_DEPTH`](crate::consts::MAX_DIR_DEPTH). `0` means
+    /// [`DEFAULT_DIR_DEPTH`](crate::consts::DEFAULT_DIR_DEPTH).
+    pub max_depth: u32,
+    /// Flags, e.g. [`FILE_OPEN_PREFIXES`](crate::consts::FILE_OPEN_PREFIXES).
+    pub flags: u8,
+    _padding: [u8; 3],
+}
+
+impl FileOpenSettings {
+    pub fn new(max_depth: u32, flags: u8) -> Self {
+        Self {
+            max_depth,
+            flags,
+            _padding: [0; 3],
+        }
     }
+}
 
-    #[inline(always)]
-    fn addrs(&self) -> [[u8; 16]; MAX_IPV6ADDRS] {
-        self.addrs
+/// Settings of the `bprm_check_security` program, stored in the only entry
+/// of an array map. All fields set to zero mean the defaults.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct BprmCheckSecuritySettings {
+    /// Number of parent directories checked for policies, like in
+    /// [`FileOpenSettings`].
+    pub max_depth: u32,
+    /// Flags, e.g.
+    /// [`BPRM_CHECK_SECURITY_ALLOW_TRUNCATED`](crate::consts::BPRM_CHECK_SECURITY_ALLOW_TRUNCATED).
+    pub flags: u8,
+    _padding: [u8; 3],
+}
+
+impl BprmCheckSecuritySettings {
+    pub fn new(max_depth: u32, flags: u8) -> Self {
+        Self {
+            max_depth,
+            flags,
+            _padding: [0; 3],
+        }
     }
 }
 
@@ -102,8 +413,20 @@ pub mod user {
 
     use aya::Pod;
 
-    unsafe impl Pod for Paths {}
-    unsafe impl Pod for Ports {}
-    unsafe impl Pod for Ipv4Addrs {}
-    unsafe impl Pod for Ipv6Addrs {}
+    unsafe impl Pod for PathKey {}
+    unsafe impl Pod for UnixPathKey {}
+    unsafe impl Pod for SocketKey {}
+    unsafe impl Pod for PortKey {}
+    unsafe impl Pod for DefaultKey {}
+    unsafe impl Pod for ProtocolPortKey {}
+    unsafe impl Pod for FamilyKey {}
+    unsafe impl Pod for Ipv4Key {}
+    unsafe impl Pod for Ipv6Key {}
+    unsafe impl Pod for Ipv4PortKey {}
+    unsafe impl Pod for Ipv6PortKey {}
+    unsafe impl Pod for PrefixValue {}
+    unsafe impl Pod for FilePrefixKey {}
+    unsafe impl Pod for FilePrefixValue {}
+    unsafe impl Pod for FileOpenSettings {}
+    unsafe impl Pod for BprmCheckSecuritySettings {}
 }
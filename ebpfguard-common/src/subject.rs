@@ -0,0 +1,79 @@
+use crate::{
+    consts::{CGROUP_WILDCARD, GID_WILDCARD, INODE_WILDCARD, UID_WILDCARD},
+    inode::Inode,
+};
+
+/// Subject of a policy, consisting of the binary inode, the id of the
+/// cgroup (v2) the process runs in and its user and group IDs.
+///
+/// [`INODE_WILDCARD`] as `binary` matches all binaries, [`CGROUP_WILDCARD`]
+/// as `cgroup` matches all cgroups, [`UID_WILDCARD`] as `uid` matches all
+/// users and [`GID_WILDCARD`] as `gid` matches all groups.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Subject {
+    pub binary: Inode,
+    pub cgroup: u64,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Subject {
+    /// Number of subjects returned by [`Subject::candidates`].
+    pub const CANDIDATES: usize = 6;
+
+    /// Creates a subject matching the given binary in the given cgroup,
+    /// regardless of the user and group.
+    pub const fn new(binary: Inode, cgroup: u64) -> Self {
+        Self {
+            binary,
+            cgroup,
+            uid: UID_WILDCARD,
+            gid: GID_WILDCARD,
+        }
+    }
+
+    /// Creates a subject matching all processes of the given user.
+    pub const fn user(uid: u32) -> Self {
+        Self {
+            uid,
+            ..Self::new(INODE_WILDCARD, CGROUP_WILDCARD)
+        }
+    }
+
+    /// Creates a subject matching all processes of the given group.
+    pub const fn group(gid: u32) -> Self {
+        Self {
+            gid,
+            ..Self::new(INODE_WILDCARD, CGROUP_WILDCARD)
+        }
+    }
+
+    /// Returns the subject of a process with the given binary, cgroup and
+    /// credentials.
+    pub const fn with_credentials(self, uid: u32, gid: u32) -> Self {
+        Self { uid, gid, ..self }
+    }
+
+    /// Returns all subjects which match a process with this binary, cgroup
+    /// and credentials, from the most to the least specific one.
+    pub const fn candidates(&self) -> [Subject; Self::CANDIDATES] {
+        [
+            Subject::new(self.binary, self.cgroup),
+            Subject::new(INODE_WILDCARD, self.cgroup),
+            Subject::new(self.binary, CGROUP_WILDCARD),
+            Subject::user(self.uid),
+            Subject::group(self.gid),
+            Subject::new(INODE_WILDCARD, CGROUP_WILDCARD),
+        ]
+    }
+}
+
+#[cfg(feature = "user")]
+pub mod user {
+    use super::*;
+
+    use aya::Pod;
+
+    unsafe impl Pod for Subject {}
+}
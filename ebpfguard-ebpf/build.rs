@@ -16,10 +16,13 @@ fn main() {
 
     let names: Vec<&str> = vec![
         "cred",
+        "request_sock",
         "sock",
         "sockaddr",
         "sockaddr_in",
         "sockaddr_in6",
+        "sockaddr_un",
+        "socket",
         "task_struct",
     ];
 
@@ -1,11 +1,19 @@
 use aya_bpf::{
     cty::c_long,
-    helpers::{bpf_get_current_task, bpf_probe_read_kernel},
+    helpers::{
+        bpf_get_current_task, bpf_get_current_uid_gid, bpf_probe_read_kernel,
+        gen::bpf_get_current_cgroup_id,
+    },
 };
 
-use crate::{exe_file_inode, inode_i_ino, mm_exe_file, task_struct_mm, vmlinux::task_struct};
+use ebpfguard_common::{inode::Inode, subject::Subject};
 
-/// Returns the inode of the current binary.
+use crate::{
+    exe_file_inode, inode_i_ino, inode_i_sb, mm_exe_file, super_block_s_dev, task_struct_mm,
+    vmlinux::task_struct,
+};
+
+/// Returns the inode (with the device) of the current binary.
 ///
 /// # Examples
 ///
@@ -18,13 +26,26 @@ use crate::{exe_file_inode, inode_i_ino, mm_exe_file, task_struct_mm, vmlinux::t
 /// # }
 /// ```
 #[inline(always)]
-pub(crate) fn current_binprm_inode() -> Result<u64, c_long> {
+pub(crate) fn current_binprm_inode() -> Result<Inode, c_long> {
     let binprm_inode = unsafe {
         let task = bpf_get_current_task() as *mut task_struct;
         let mm = bpf_probe_read_kernel(task_struct_mm(task))?;
         let file = bpf_probe_read_kernel(mm_exe_file(mm))?;
         let f_inode = bpf_probe_read_kernel(exe_file_inode(file))?;
-        bpf_probe_read_kernel(inode_i_ino(f_inode))?
+        let sb = bpf_probe_read_kernel(inode_i_sb(f_inode))?;
+        let dev = bpf_probe_read_kernel(super_block_s_dev(sb))?;
+        let ino = bpf_probe_read_kernel(inode_i_ino(f_inode))?;
+        Inode::new(dev.into(), ino)
     };
     Ok(binprm_inode)
 }
+
+/// Returns the subject of the current process, consisting of the inode of its
+/// binary, the id of its cgroup and its (real) user and group IDs.
+#[inline(always)]
+pub(crate) fn current_subject() -> Result<Subject, c_long> {
+    let binprm_inode = current_binprm_inode()?;
+    let cgroup = unsafe { bpf_get_current_cgroup_id() };
+    let uid_gid = bpf_get_current_uid_gid();
+    Ok(Subject::new(binprm_inode, cgroup).with_credentials(uid_gid as u32, (uid_gid >> 32) as u32))
+}
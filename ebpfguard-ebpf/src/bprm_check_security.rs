@@ -1,25 +1,92 @@
 use aya_bpf::{cty::c_long, programs::LsmContext, BpfContext};
-use ebpfguard_common::alerts;
+use ebpfguard_common::{
+    alerts,
+    consts::{ACCESS_EXEC, BPRM_CHECK_SECURITY_ALLOW_TRUNCATED, HOOK_BPRM_CHECK_SECURITY},
+    inode::Inode,
+    subject::Subject,
+};
 
 use crate::{
-    binprm::current_binprm_inode, linux_binprm_argc, maps::ALERT_BPRM_CHECK_SECURITY,
-    vmlinux::linux_binprm,
+    binprm::current_subject,
+    default_action, file_inode,
+    file_open::{path_action, Prefixes},
+    file_s_dev, linux_binprm_argc, linux_binprm_file,
+    maps::{
+        ALERT_BPRM_CHECK_SECURITY, ALLOWED_BPRM_CHECK_SECURITY, BPRM_CHECK_SECURITY_SETTINGS,
+        BPRM_CHECK_SECURITY_SUBJECT_FLAGS, DENIED_BPRM_CHECK_SECURITY,
+    },
+    vmlinux::{file, linux_binprm},
+    Action,
 };
 
-pub fn bprm_check_security(ctx: LsmContext) -> Result<i32, c_long> {
+/// Inspects the context of `bprm_check_security` LSM hook and decides whether
+/// to allow or deny the execution based on the state of the
+/// `ALLOWED_BPRM_CHECK_SECURITY` and `DENIED_BPRM_CHECK_SECURITY` maps. The
+/// subject is the process calling `execve`, the object is the executed
+/// binary, matched the same way as files in `file_open`, with the depth of
+/// checked parent directories and the handling of binaries nested deeper set
+/// in `BPRM_CHECK_SECURITY_SETTINGS`. Executions without arguments
+/// (`argc < 1`) are always denied.
+///
+/// If denied, the operation is logged to the `ALERT_BPRM_CHECK_SECURITY` map.
+/// Operations denied by policies in audit mode are logged, but allowed.
+///
+/// # Example
+///
+/// ```rust
+/// use aya_bpf::{macros::lsm, programs::LsmContext};
+/// use ebpfguard_ebpf::bprm_check_security;
+///
+/// #[lsm(name = "my_program")]
+/// pub fn my_program(ctx: LsmContext) -> i32 {
+///     match bprm_check_security::bprm_check_security(ctx) {
+///         Ok(ret) => ret.into(),
+///         Err(_) => 0,
+///     }
+/// }
+/// ```
+pub fn bprm_check_security(ctx: LsmContext) -> Result<Action, c_long> {
     let new_binprm: *const linux_binprm = unsafe { ctx.arg(0) };
     let argc = unsafe { linux_binprm_argc(new_binprm) };
+    let file: *const file = unsafe { linux_binprm_file(new_binprm) };
 
-    let old_binprm_inode = current_binprm_inode()?;
+    let subject = current_subject()?;
+    let inode = unsafe { Inode::new(file_s_dev(file).into(), file_inode(file)) };
 
     if argc < 1 {
+        return Ok(alert(&ctx, subject, inode, Action::Deny));
+    }
+
+    let settings = BPRM_CHECK_SECURITY_SETTINGS
+        .get(0)
+        .copied()
+        .unwrap_or_default();
+    let action = path_action(
+        &ALLOWED_BPRM_CHECK_SECURITY,
+        &DENIED_BPRM_CHECK_SECURITY,
+        &BPRM_CHECK_SECURITY_SUBJECT_FLAGS,
+        subject,
+        file,
+        inode,
+        ACCESS_EXEC,
+        Prefixes::Disabled,
+        settings.max_depth,
+        settings.flags & BPRM_CHECK_SECURITY_ALLOW_TRUNCATED != 0,
+    )
+    .action
+    .unwrap_or_else(|| default_action(HOOK_BPRM_CHECK_SECURITY, subject));
+
+    Ok(alert(&ctx, subject, inode, action))
+}
+
+#[inline(always)]
+fn alert(ctx: &LsmContext, subject: Subject, inode: Inode, action: Action) -> Action {
+    if action.is_alerted() {
         ALERT_BPRM_CHECK_SECURITY.output(
-            &ctx,
-            &alerts::BprmCheckSecurity::new(ctx.pid(), old_binprm_inode),
+            ctx,
+            &alerts::BprmCheckSecurity::new(ctx.pid(), action.is_enforced(), subject, inode),
             0,
         );
-        return Ok(-1);
     }
-
-    Ok(0)
+    action
 }
@@ -1,4 +1,2 @@
-/// IPv4 family.
-pub const AF_INET: u16 = 2;
-/// IPv6 family.
-pub const AF_INET6: u16 = 10;
+/// Flag for maps which must not be preallocated (required by LPM tries).
+pub const BPF_F_NO_PREALLOC: u32 = 1;
@@ -1,25 +1,68 @@
-use aya_bpf::{cty::c_long, maps::HashMap, programs::LsmContext, BpfContext};
+use aya_bpf::{
+    cty::{c_char, c_long},
+    helpers::{bpf_probe_read_kernel_buf, gen::bpf_d_path},
+    maps::{lpm_trie::Key, HashMap},
+    programs::LsmContext,
+    BpfContext,
+};
 use ebpfguard_common::{
     alerts,
-    consts::INODE_WILDCARD,
-    policy::{Paths, MAX_PATHS},
+    consts::{
+        ACCESS_ALL, ACCESS_EXEC, ACCESS_READ, ACCESS_WRITE, DEFAULT_DIR_DEPTH,
+        FILE_OPEN_ALLOW_TRUNCATED, FILE_OPEN_PREFIXES, FILE_PREFIX_MAX, HOOK_FILE_OPEN,
+        INODE_WILDCARD, MAX_DIR_DEPTH, MAX_PATH_LEN, MAX_PREFIX_MATCHES, SUBJECT_DIRS,
+        SUBJECT_PREFIXES,
+    },
+    inode::Inode,
+    policy::{FileOpenSettings, FilePrefixKey, PathKey},
+    subject::Subject,
 };
 
 use crate::{
-    binprm::current_binprm_inode,
-    dentry_i_ino, file_dentry, file_inode,
-    maps::{ALERT_FILE_OPEN, ALLOWED_FILE_OPEN, DENIED_FILE_OPEN},
-    vmlinux::file,
-    Action, Mode,
+    binprm::current_subject,
+    default_action, dentry_i_ino, dentry_s_dev, file_dentry, file_f_mode, file_f_path, file_inode,
+    file_s_dev,
+    maps::{
+        ALERT_FILE_OPEN, ALLOWED_FILE_OPEN, ALLOWED_FILE_OPEN_PREFIX, DENIED_FILE_OPEN,
+        DENIED_FILE_OPEN_PREFIX, FILE_OPEN_PATH, FILE_OPEN_PREFIX_KEY, FILE_OPEN_SETTINGS,
+        FILE_OPEN_SUBJECT_FLAGS,
+    },
+    vmlinux::{file, path},
+    Action,
 };
 
-const MAX_DIR_DEPTH: usize = 16;
+/// Flags of `file->f_mode`.
+const FMODE_READ: u32 = 0x1;
+const FMODE_WRITE: u32 = 0x2;
+const FMODE_EXEC: u32 = 0x20;
 
 /// Inspects the context of `file_open` LSM hook and decides whether to allow or
 /// deny the operation based on the state of the `ALLOWED_FILE_OPEN` and
-/// `DENIED_FILE_OPEN` maps.
+/// `DENIED_FILE_OPEN` maps, and of the `ALLOWED_FILE_OPEN_PREFIX` and
+/// `DENIED_FILE_OPEN_PREFIX` maps if prefix policies are enabled in
+/// `FILE_OPEN_SETTINGS`.
+///
+/// The requested kinds of access (read, write, exec) are taken from
+/// `file->f_mode`. Subjects are checked from the most specific one. For
+/// every subject, the entry of the file wins over the entries of its parent
+/// directories (the closest one first), which win over the entries of path
+/// prefixes (the longest one first) and over the entry for all paths. Every
+/// kind of access is decided by the first entry which applies to it, the
+/// operation is allowed only if all of them are allowed. If some kind of
+/// access has no matching entry, the default action of the hook applies.
 ///
-/// If denied, the operation is logged to the `ALERT_FILE_OPEN` map.
+/// Parent directories are checked up to the configured depth, and only for
+/// subjects which have directory entries (according to
+/// `FILE_OPEN_SUBJECT_FLAGS`). Prefixes are checked only for subjects which
+/// have prefix entries. If such a subject can't be decided because the file
+/// has more parent directories, or its path can't be resolved for prefix
+/// policies, the remaining kinds of access are not decided by less specific
+/// subjects: they're denied if the entry of the subject for all paths denies
+/// them. Otherwise they're denied too, unless configured to be alerted, but
+/// allowed.
+///
+/// If denied, the operation is logged to the `ALERT_FILE_OPEN` map. Operations
+/// denied by policies in audit mode are logged, but allowed.
 ///
 /// # Example
 ///
@@ -35,135 +78,377 @@ const MAX_DIR_DEPTH: usize = 16;
 pub fn file_open(ctx: LsmContext) -> Result<Action, c_long> {
     let file: *const file = unsafe { ctx.arg(0) };
 
-    let binprm_inode = current_binprm_inode()?;
-    let inode = unsafe { file_inode(file) };
+    let subject = current_subject()?;
+    let inode = unsafe { Inode::new(file_s_dev(file).into(), file_inode(file)) };
+    let access = requested_access(unsafe { file_f_mode(file) });
 
-    if let Some(paths) = unsafe { ALLOWED_FILE_OPEN.get(&INODE_WILDCARD) } {
-        if paths.paths[0] == 0 {
-            return Ok(check_conditions_and_alert(
-                &ctx,
-                &DENIED_FILE_OPEN,
-                file,
-                inode,
-                binprm_inode,
-                Mode::Denylist,
-            ));
-        }
-    }
+    let settings = settings();
+    let prefixes = if settings.flags & FILE_OPEN_PREFIXES != 0 {
+        resolve_path(file)
+    } else {
+        Prefixes::Disabled
+    };
 
-    if let Some(paths) = unsafe { DENIED_FILE_OPEN.get(&INODE_WILDCARD) } {
-        if paths.paths[0] == 0 {
-            return Ok(check_conditions_and_alert(
-                &ctx,
-                &ALLOWED_FILE_OPEN,
-                file,
+    let decision = path_action(
+        &ALLOWED_FILE_OPEN,
+        &DENIED_FILE_OPEN,
+        &FILE_OPEN_SUBJECT_FLAGS,
+        subject,
+        file,
+        inode,
+        access,
+        prefixes,
+        settings.max_depth,
+        settings.flags & FILE_OPEN_ALLOW_TRUNCATED != 0,
+    );
+    let action = decision
+        .action
+        .unwrap_or_else(|| default_action(HOOK_FILE_OPEN, subject));
+
+    if action.is_alerted() {
+        ALERT_FILE_OPEN.output(
+            &ctx,
+            &alerts::FileOpen::new(
+                ctx.pid(),
+                action.is_enforced(),
+                subject,
                 inode,
-                binprm_inode,
-                Mode::Allowlist,
-            ));
-        }
+                access,
+                decision.truncated,
+            ),
+            0,
+        );
     }
 
-    Ok(Action::Allow)
+    Ok(action)
 }
 
+/// Path of the opened file in the key of prefix policy lookups.
+pub(crate) enum Prefixes {
+    /// Prefix policies are not checked.
+    Disabled,
+    /// The path couldn't be resolved, prefix policies can't be checked.
+    Unresolved,
+    /// The key with the resolved path and the number of its bytes, including
+    /// the terminating NUL byte, but at most [`FILE_PREFIX_MAX`].
+    Resolved(*mut Key<FilePrefixKey>, usize),
+}
+
+/// Action decided by the policies for an opened file.
+pub(crate) struct Decision {
+    /// `None` if some kind of access has no matching entry.
+    pub(crate) action: Option<Action>,
+    /// `true` if the action was decided without checking all the entries
+    /// which could apply to the file.
+    pub(crate) truncated: bool,
+}
+
+/// Returns the settings of the `file_open` program.
 #[inline(always)]
-fn check_conditions_and_alert(
-    ctx: &LsmContext,
-    map: &HashMap<u64, Paths>,
-    file: *const file,
-    inode: u64,
-    binprm_inode: u64,
-    mode: Mode,
-) -> Action {
-    match check_conditions(map, file, inode, binprm_inode, mode) {
-        Action::Allow => Action::Allow,
-        Action::Deny => {
-            ALERT_FILE_OPEN.output(
-                ctx,
-                &alerts::FileOpen::new(ctx.pid(), binprm_inode, inode),
-                0,
-            );
-            Action::Deny
+fn settings() -> FileOpenSettings {
+    FILE_OPEN_SETTINGS.get(0).copied().unwrap_or_default()
+}
+
+/// Resolves the path of the file (as seen from the root directory of the
+/// current process) into the key of prefix policy lookups.
+#[inline(always)]
+fn resolve_path(file: *const file) -> Prefixes {
+    let (Some(buf), Some(key)) = (
+        FILE_OPEN_PATH.get_ptr_mut(0),
+        FILE_OPEN_PREFIX_KEY.get_ptr_mut(0),
+    ) else {
+        return Prefixes::Unresolved;
+    };
+    unsafe {
+        let len = bpf_d_path(
+            file_f_path(file) as *mut path as *mut _,
+            buf as *mut c_char,
+            MAX_PATH_LEN as u32,
+        );
+        if len <= 0 {
+            return Prefixes::Unresolved;
+        }
+        if bpf_probe_read_kernel_buf(buf as *const u8, &mut (*key).data.path).is_err() {
+            return Prefixes::Unresolved;
         }
+        Prefixes::Resolved(key, (len as usize).min(FILE_PREFIX_MAX))
     }
 }
 
+/// Returns the kinds of access requested by opening a file with the given
+/// `f_mode`. Opening a binary for execution counts only as exec access.
+/// Files opened neither for reading nor for writing (e.g. with `O_ACCMODE`
+/// for ioctls) need both.
 #[inline(always)]
-fn check_conditions(
-    map: &HashMap<u64, Paths>,
-    file: *const file,
-    inode: u64,
-    binprm_inode: u64,
-    mode: Mode,
-) -> Action {
-    if let Some(paths) = unsafe { map.get(&INODE_WILDCARD) } {
-        if let Some(action) = check_paths(&paths.paths, file, inode, &mode) {
-            return action;
-        }
+fn requested_access(f_mode: u32) -> u8 {
+    if f_mode & FMODE_EXEC != 0 {
+        return ACCESS_EXEC;
     }
+    let mut access = 0;
+    if f_mode & FMODE_READ != 0 {
+        access |= ACCESS_READ;
+    }
+    if f_mode & FMODE_WRITE != 0 {
+        access |= ACCESS_WRITE;
+    }
+    if access == 0 {
+        access = ACCESS_READ | ACCESS_WRITE;
+    }
+    access
+}
 
-    if let Some(paths) = unsafe { map.get(&binprm_inode) } {
-        if let Some(action) = check_paths(&paths.paths, file, inode, &mode) {
-            return action;
+/// Returns the action for the given kinds of access decided by the entries
+/// of the most specific subjects matching the given one for the file (or
+/// one of its parent directories, or a prefix of its path, or all paths) in
+/// the given maps. The action is `None` if some kind of access has no
+/// matching entry. `subject_flags` tells which subjects have entries of
+/// directories or prefixes, see [`SUBJECT_DIRS`]. Up to `max_depth` parent
+/// directories are checked (`0` means [`DEFAULT_DIR_DEPTH`]). Access which
+/// can't be decided within them is allowed, but alerted, only with
+/// `allow_truncated`.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn path_action(
+    allowed: &HashMap<PathKey, u8>,
+    denied: &HashMap<PathKey, u8>,
+    subject_flags: &HashMap<Subject, u8>,
+    subject: Subject,
+    file: *const file,
+    inode: Inode,
+    mut access: u8,
+    prefixes: Prefixes,
+    max_depth: u32,
+    allow_truncated: bool,
+) -> Decision {
+    let max_depth = match max_depth {
+        0 => DEFAULT_DIR_DEPTH,
+        depth => depth.min(MAX_DIR_DEPTH),
+    };
+
+    for subject in subject.candidates() {
+        let flags = unsafe { subject_flags.get(&subject) }.copied().unwrap_or(0);
+        let mut truncated = false;
+        let action = check_paths(
+            allowed,
+            denied,
+            subject,
+            flags,
+            file,
+            inode,
+            max_depth,
+            &prefixes,
+            &mut access,
+            &mut truncated,
+        );
+        if action.is_some() {
+            return Decision {
+                action,
+                truncated: false,
+            };
+        }
+        // Directory or prefix entries of the subject which weren't checked
+        // could be more specific than its entry for all paths and than the
+        // entries of the following subjects. A denying entry for all paths
+        // still applies, so allowlists can't be bypassed by nesting files
+        // deeper.
+        if truncated {
+            let wildcard = entry_action(
+                allowed,
+                denied,
+                &PathKey::new(subject, INODE_WILDCARD),
+                &mut access,
+            );
+            let action = match wildcard {
+                Some(Action::Allow) | None if allow_truncated => Action::Audit,
+                Some(Action::Allow) | None => Action::Deny,
+                Some(action) => action,
+            };
+            return Decision {
+                action: Some(action),
+                truncated: true,
+            };
         }
     }
-
-    match mode {
-        Mode::Allowlist => Action::Deny,
-        Mode::Denylist => Action::Allow,
+    Decision {
+        action: None,
+        truncated: false,
     }
 }
 
 #[inline(always)]
+#[allow(clippy::too_many_arguments)]
 fn check_paths(
-    paths: &[u64; MAX_PATHS],
+    allowed: &HashMap<PathKey, u8>,
+    denied: &HashMap<PathKey, u8>,
+    subject: Subject,
+    flags: u8,
     file: *const file,
-    inode: u64,
-    mode: &Mode,
+    inode: Inode,
+    max_depth: u32,
+    prefixes: &Prefixes,
+    access: &mut u8,
+    truncated: &mut bool,
 ) -> Option<Action> {
-    if paths[0] == 0 {
-        return Some(match mode {
-            Mode::Allowlist => Action::Allow,
-            Mode::Denylist => Action::Deny,
-        });
+    if let Some(action) = entry_action(allowed, denied, &PathKey::new(subject, inode), access) {
+        return Some(action);
     }
 
-    if paths[..MAX_PATHS - 1].contains(&inode) {
-        return Some(match mode {
-            Mode::Allowlist => Action::Allow,
-            Mode::Denylist => Action::Deny,
-        });
+    if flags & SUBJECT_DIRS != 0 {
+        if let Some(action) = check_parents(
+            allowed, denied, subject, file, inode, max_depth, access, truncated,
+        ) {
+            return Some(action);
+        }
     }
 
-    check_parents(paths, file, inode, mode)
+    if flags & SUBJECT_PREFIXES != 0 {
+        match *prefixes {
+            Prefixes::Disabled => {}
+            Prefixes::Unresolved => *truncated = true,
+            Prefixes::Resolved(key, len) => {
+                if let Some(action) = check_prefixes(key, len, subject, access, truncated) {
+                    return Some(action);
+                }
+            }
+        }
+    }
+    if *truncated {
+        return None;
+    }
+
+    entry_action(
+        allowed,
+        denied,
+        &PathKey::new(subject, INODE_WILDCARD),
+        access,
+    )
 }
 
+/// Checks the parent directories of the file, up to `max_depth` of them.
+/// Sets `truncated` if the root directory isn't reached.
 #[inline(always)]
+#[allow(clippy::too_many_arguments)]
 fn check_parents(
-    paths: &[u64; MAX_PATHS],
+    allowed: &HashMap<PathKey, u8>,
+    denied: &HashMap<PathKey, u8>,
+    subject: Subject,
     file: *const file,
-    mut previous_inode: u64,
-    mode: &Mode,
+    mut previous_inode: Inode,
+    max_depth: u32,
+    access: &mut u8,
+    truncated: &mut bool,
 ) -> Option<Action> {
     let mut parent_dentry = unsafe { file_dentry(file) };
-    for _ in 0..MAX_DIR_DEPTH {
+    for depth in 0..MAX_DIR_DEPTH {
         if parent_dentry.is_null() {
-            break;
+            return None;
         }
-        let inode = unsafe { dentry_i_ino(parent_dentry) };
+        let inode = unsafe {
+            Inode::new(
+                dentry_s_dev(parent_dentry).into(),
+                dentry_i_ino(parent_dentry),
+            )
+        };
+        // The root directory is its own parent.
         if inode == previous_inode {
+            return None;
+        }
+        if depth >= max_depth {
             break;
         }
-        if paths[..MAX_PATHS - 1].contains(&inode) {
-            return Some(match mode {
-                Mode::Allowlist => Action::Allow,
-                Mode::Denylist => Action::Deny,
-            });
+        if let Some(action) = entry_action(allowed, denied, &PathKey::new(subject, inode), access) {
+            return Some(action);
         }
         previous_inode = inode;
         parent_dentry = unsafe { (*parent_dentry).d_parent };
     }
 
+    *truncated = true;
+    None
+}
+
+/// Checks the prefixes of the path in the key, from the longest one, up to
+/// [`MAX_PREFIX_MATCHES`] of them. Sets `truncated` if there are more.
+#[inline(always)]
+fn check_prefixes(
+    key: *mut Key<FilePrefixKey>,
+    mut len: usize,
+    subject: Subject,
+    access: &mut u8,
+    truncated: &mut bool,
+) -> Option<Action> {
+    let key = unsafe { &mut *key };
+    key.data.subject = subject;
+    for _ in 0..MAX_PREFIX_MATCHES {
+        key.prefix_len = FilePrefixKey::prefix_len(len);
+        // Lookups return the longest matching prefix, the stored length tells
+        // which of the maps has the more specific entry.
+        let denied = DENIED_FILE_OPEN_PREFIX.get(key);
+        let allowed = ALLOWED_FILE_OPEN_PREFIX.get(key);
+        let denied_len = denied.map_or(0, |value| value.path_len);
+        let allowed_len = allowed.map_or(0, |value| value.path_len);
+        let matched = denied_len.max(allowed_len);
+        if matched == 0 {
+            return None;
+        }
+
+        let denied = denied.filter(|value| value.path_len == matched);
+        let allowed = allowed.filter(|value| value.path_len == matched);
+        if let Some(action) = decide(
+            denied.map(|value| value.flags),
+            allowed.map(|value| value.flags),
+            access,
+        ) {
+            return Some(action);
+        }
+        len = usize::from(matched) - 1;
+    }
+
+    *truncated = true;
     None
 }
+
+/// Returns the action of the entries with the given key for the kinds of
+/// access which are not decided yet. Denying entries win over allowing ones.
+/// Kinds of access allowed by the entry are removed from `access`, the
+/// action is `Allow` once none is left.
+#[inline(always)]
+fn entry_action(
+    allowed: &HashMap<PathKey, u8>,
+    denied: &HashMap<PathKey, u8>,
+    key: &PathKey,
+    access: &mut u8,
+) -> Option<Action> {
+    decide(
+        unsafe { denied.get(key) }.copied(),
+        unsafe { allowed.get(key) }.copied(),
+        access,
+    )
+}
+
+/// Returns the action of the denying and allowing entries (with the given
+/// flags) of the same path. See [`entry_action`].
+#[inline(always)]
+fn decide(denied: Option<u8>, allowed: Option<u8>, access: &mut u8) -> Option<Action> {
+    if let Some(flags) = denied {
+        if entry_access(flags) & *access != 0 {
+            return Some(Action::deny(flags));
+        }
+    }
+    if let Some(flags) = allowed {
+        *access &= !entry_access(flags);
+        if *access == 0 {
+            return Some(Action::Allow);
+        }
+    }
+    None
+}
+
+/// Returns the kinds of access an entry with the given flags applies to.
+#[inline(always)]
+fn entry_access(flags: u8) -> u8 {
+    match flags & ACCESS_ALL {
+        0 => ACCESS_ALL,
+        access => access,
+    }
+}
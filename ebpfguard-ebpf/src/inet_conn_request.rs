@@ -0,0 +1,265 @@
+use aya_bpf::{cty::c_long, maps::lpm_trie::Key, programs::LsmContext, BpfContext};
+use ebpfguard_common::{
+    alerts,
+    consts::{
+        AF_INET, AF_INET6, HOOK_INET_CONN_REQUEST, INET_CONN_REQUEST_BINARY_SUBJECTS,
+        INET_CONN_REQUEST_DENY_UNKNOWN, INODE_UNKNOWN, INODE_WILDCARD,
+    },
+    policy::{Ipv4Key, Ipv6Key},
+    subject::Subject,
+};
+
+use crate::{
+    binprm::current_subject,
+    cred_gid_val, cred_uid_val, default_action, ipv4_mapped,
+    maps::{
+        ALERT_INET_CONN_REQUEST, ALLOWED_INET_CONN_REQUEST, ALLOWED_INET_CONN_REQUEST_ALL,
+        ALLOWED_INET_CONN_REQUEST_V4, ALLOWED_INET_CONN_REQUEST_V6, DENIED_INET_CONN_REQUEST,
+        DENIED_INET_CONN_REQUEST_ALL, DENIED_INET_CONN_REQUEST_V4, DENIED_INET_CONN_REQUEST_V6,
+        INET_CONN_REQUEST_SETTINGS, LISTENERS,
+    },
+    request_sock_daddr, request_sock_family, request_sock_num, request_sock_v6_daddr,
+    sock_cgroup_id, sock_file_cred, sock_sk_protocol,
+    socket_connect::connect_action,
+    socket_sk,
+    vmlinux::{request_sock, sock, socket},
+    Action,
+};
+
+/// Process which started listening on a socket.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Listener {
+    pub pid: u32,
+    _padding: u32,
+    pub subject: Subject,
+}
+
+/// Records the subject of the process calling `listen` in the `LISTENERS`
+/// map, so connections to the socket can be matched by
+/// [`inet_conn_request`], which doesn't run in the context of that process.
+/// Meant to be attached to `socket_listen` LSM hook.
+pub fn record_listener(ctx: LsmContext) -> Result<(), c_long> {
+    let sock: *const socket = unsafe { ctx.arg(0) };
+    let sk = unsafe { socket_sk(sock) };
+
+    let listener = Listener {
+        pid: ctx.pid(),
+        _padding: 0,
+        subject: current_subject()?,
+    };
+    LISTENERS.insert(&(sk as u64), &listener, 0)
+}
+
+/// Inspects the context of `inet_conn_request` LSM hook, called when a
+/// listening TCP socket receives a connection request, and decides whether
+/// to allow or drop it based on the state of the
+/// `ALLOWED_INET_CONN_REQUEST_V4`/`ALLOWED_INET_CONN_REQUEST_V6` and
+/// `DENIED_INET_CONN_REQUEST_V4`/`DENIED_INET_CONN_REQUEST_V6` maps, with
+/// rules for peer networks and addresses, the `ALLOWED_INET_CONN_REQUEST`
+/// and `DENIED_INET_CONN_REQUEST` maps, with rules for local ports on all
+/// peer addresses, and the `ALLOWED_INET_CONN_REQUEST_ALL` and
+/// `DENIED_INET_CONN_REQUEST_ALL` maps, with rules for all peer addresses of
+/// the family. Rules are compared like in
+/// [`socket_connect`](crate::socket_connect::socket_connect), IPv4-mapped
+/// IPv6 peer addresses are matched by IPv4 rules.
+///
+/// The hook runs in the context of the network stack, so the subject is
+/// the one recorded by [`record_listener`] when the socket started
+/// listening. For sockets without a recorded subject (listening before the
+/// programs were attached or evicted from `LISTENERS`), the binary is not
+/// known and the cgroup and credentials are read from the socket, so only
+/// policies for cgroups, users, groups and all processes match. If any
+/// policy has a binary subject (`INET_CONN_REQUEST_SETTINGS`), connections
+/// which such sockets would allow are alerted, or denied with
+/// `INET_CONN_REQUEST_DENY_UNKNOWN`, since they might be denied for the
+/// unknown binary.
+///
+/// If denied, the operation is logged to the `ALERT_INET_CONN_REQUEST` map.
+/// Operations denied by policies in audit mode are logged, but allowed.
+///
+/// # Example
+///
+/// ```rust
+/// use aya_bpf::{macros::lsm, programs::LsmContext};
+/// use ebpfguard_ebpf::inet_conn_request;
+///
+/// #[lsm(name = "my_program")]
+/// pub fn my_program(ctx: LsmContext) -> i32 {
+///     match inet_conn_request::inet_conn_request(ctx) {
+///         Ok(ret) => ret.into(),
+///         Err(_) => 0,
+///     }
+/// }
+/// ```
+pub fn inet_conn_request(ctx: LsmContext) -> Result<Action, c_long> {
+    let sk: *const sock = unsafe { ctx.arg(0) };
+    let req: *const request_sock = unsafe { ctx.arg(2) };
+
+    match unsafe { request_sock_family(req) } {
+        AF_INET => inet_conn_request_v4(ctx, sk, req),
+        AF_INET6 => inet_conn_request_v6(ctx, sk, req),
+        _ => Ok(Action::Allow),
+    }
+}
+
+#[inline(always)]
+fn inet_conn_request_v4(
+    ctx: LsmContext,
+    sk: *const sock,
+    req: *const request_sock,
+) -> Result<Action, c_long> {
+    let addr = u32::from_be(unsafe { request_sock_daddr(req) });
+    let port = unsafe { request_sock_num(req) };
+    let protocol = unsafe { sock_sk_protocol(sk) as u8 };
+    let (pid, subject) = listener(sk);
+
+    let action = unknown_listener_action(
+        subject,
+        conn_request_action_v4(subject, protocol, addr.to_be_bytes(), port),
+    );
+    if action.is_alerted() {
+        ALERT_INET_CONN_REQUEST.output(
+            &ctx,
+            &alerts::InetConnRequest::new_ipv4(
+                pid,
+                action.is_enforced(),
+                alert_subject(subject),
+                protocol,
+                addr,
+                port,
+            ),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+#[inline(always)]
+fn inet_conn_request_v6(
+    ctx: LsmContext,
+    sk: *const sock,
+    req: *const request_sock,
+) -> Result<Action, c_long> {
+    let addr: [u8; 16] = [0; 16];
+    unsafe { request_sock_v6_daddr(req, &addr) };
+    let port = unsafe { request_sock_num(req) };
+    let protocol = unsafe { sock_sk_protocol(sk) as u8 };
+    let (pid, subject) = listener(sk);
+
+    let action = match ipv4_mapped(&addr) {
+        Some(addr) => conn_request_action_v4(subject, protocol, addr, port),
+        None => connect_action(
+            &ALLOWED_INET_CONN_REQUEST_V6,
+            &DENIED_INET_CONN_REQUEST_V6,
+            &ALLOWED_INET_CONN_REQUEST,
+            &DENIED_INET_CONN_REQUEST,
+            &ALLOWED_INET_CONN_REQUEST_ALL,
+            &DENIED_INET_CONN_REQUEST_ALL,
+            subject,
+            AF_INET6,
+            protocol,
+            port,
+            |subject, protocol| {
+                Key::new(
+                    Ipv6Key::PREFIX_LEN,
+                    Ipv6Key::new(subject, protocol, addr, port),
+                )
+            },
+        )
+        .unwrap_or_else(|| default_action(HOOK_INET_CONN_REQUEST, subject)),
+    };
+    let action = unknown_listener_action(subject, action);
+    if action.is_alerted() {
+        ALERT_INET_CONN_REQUEST.output(
+            &ctx,
+            &alerts::InetConnRequest::new_ipv6(
+                pid,
+                action.is_enforced(),
+                alert_subject(subject),
+                protocol,
+                addr,
+                port,
+            ),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+#[inline(always)]
+fn conn_request_action_v4(subject: Subject, protocol: u8, addr: [u8; 4], port: u16) -> Action {
+    connect_action(
+        &ALLOWED_INET_CONN_REQUEST_V4,
+        &DENIED_INET_CONN_REQUEST_V4,
+        &ALLOWED_INET_CONN_REQUEST,
+        &DENIED_INET_CONN_REQUEST,
+        &ALLOWED_INET_CONN_REQUEST_ALL,
+        &DENIED_INET_CONN_REQUEST_ALL,
+        subject,
+        AF_INET,
+        protocol,
+        port,
+        |subject, protocol| {
+            Key::new(
+                Ipv4Key::PREFIX_LEN,
+                Ipv4Key::new(subject, protocol, addr, port),
+            )
+        },
+    )
+    .unwrap_or_else(|| default_action(HOOK_INET_CONN_REQUEST, subject))
+}
+
+/// Returns the PID and the subject of the process which started listening
+/// on the socket. If it's not known, returns PID 0 and a subject with
+/// [`INODE_UNKNOWN`] binary and the cgroup and credentials of the socket.
+#[inline(always)]
+fn listener(sk: *const sock) -> (u32, Subject) {
+    match unsafe { LISTENERS.get(&(sk as u64)) } {
+        Some(listener) => (listener.pid, listener.subject),
+        None => (0, socket_subject(sk)),
+    }
+}
+
+/// Returns the subject of a socket whose listening process is not known.
+/// The cgroup is the one the socket was created in, the credentials are
+/// the ones it was opened with, if it has a file.
+#[inline(always)]
+fn socket_subject(sk: *const sock) -> Subject {
+    let subject = Subject::new(INODE_UNKNOWN, unsafe { sock_cgroup_id(sk) });
+    let cred = unsafe { sock_file_cred(sk) };
+    if cred.is_null() {
+        return subject;
+    }
+    subject.with_credentials(unsafe { cred_uid_val(cred) }, unsafe { cred_gid_val(cred) })
+}
+
+/// Alerts, or denies, connections allowed to a socket whose listening
+/// process is not known, if any policy might deny them for its binary.
+#[inline(always)]
+fn unknown_listener_action(subject: Subject, action: Action) -> Action {
+    if subject.binary != INODE_UNKNOWN || action.is_alerted() {
+        return action;
+    }
+    let flags = INET_CONN_REQUEST_SETTINGS.get(0).copied().unwrap_or(0);
+    if flags & INET_CONN_REQUEST_BINARY_SUBJECTS == 0 {
+        action
+    } else if flags & INET_CONN_REQUEST_DENY_UNKNOWN != 0 {
+        Action::Deny
+    } else {
+        Action::Audit
+    }
+}
+
+/// Returns the subject reported in alerts, with the wildcard instead of the
+/// unknown binary.
+#[inline(always)]
+fn alert_subject(subject: Subject) -> Subject {
+    if subject.binary == INODE_UNKNOWN {
+        Subject::new(INODE_WILDCARD, subject.cgroup).with_credentials(subject.uid, subject.gid)
+    } else {
+        subject
+    }
+}
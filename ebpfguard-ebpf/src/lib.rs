@@ -5,12 +5,16 @@ pub mod binprm;
 pub mod bprm_check_security;
 pub mod consts;
 pub mod file_open;
+pub mod inet_conn_request;
 pub mod maps;
 pub mod sb_mount;
 pub mod sb_remount;
 pub mod sb_umount;
 pub mod socket_bind;
 pub mod socket_connect;
+pub mod socket_connect_unix;
+pub mod socket_create;
+pub mod socket_listen;
 pub mod task_fix_setuid;
 #[allow(non_upper_case_globals)]
 #[allow(non_snake_case)]
@@ -18,8 +22,16 @@ pub mod task_fix_setuid;
 #[allow(dead_code)]
 pub mod vmlinux;
 
-use aya_bpf::cty::{c_ushort, c_void};
-use aya_bpf::{cty::c_int, cty::c_uint, cty::c_ulong};
+use aya_bpf::cty::{c_uchar, c_ulonglong, c_ushort, c_void};
+use aya_bpf::maps::{lpm_trie::Key, HashMap, LpmTrie};
+use aya_bpf::{cty::c_char, cty::c_int, cty::c_uint, cty::c_ulong};
+use ebpfguard_common::{
+    consts::{DEFAULT_DENY, POLICY_AUDIT},
+    policy::{DefaultKey, PrefixValue},
+    subject::Subject,
+};
+
+use maps::DEFAULT_ACTIONS;
 
 use vmlinux::cred;
 use vmlinux::dentry;
@@ -27,9 +39,15 @@ use vmlinux::file;
 use vmlinux::inode;
 use vmlinux::linux_binprm;
 use vmlinux::mm_struct;
+use vmlinux::path;
+use vmlinux::request_sock;
+use vmlinux::sock;
 use vmlinux::sockaddr;
 use vmlinux::sockaddr_in;
 use vmlinux::sockaddr_in6;
+use vmlinux::sockaddr_un;
+use vmlinux::socket;
+use vmlinux::super_block;
 use vmlinux::task_struct;
 
 #[allow(improper_ctypes)]
@@ -37,12 +55,22 @@ extern "C" {
     fn cred_gid_val(target: *const cred) -> c_uint;
     fn cred_uid_val(target: *const cred) -> c_uint;
     fn dentry_i_ino(target: *const dentry) -> c_ulong;
+    fn dentry_s_dev(target: *const dentry) -> c_uint;
     fn exe_file_inode(target: *const file) -> *const *const inode;
     fn file_dentry(target: *const file) -> *const dentry;
+    fn file_f_mode(target: *const file) -> c_uint;
+    fn file_f_path(target: *const file) -> *const path;
     fn file_inode(target: *const file) -> c_ulong;
+    fn file_s_dev(target: *const file) -> c_uint;
     fn inode_i_ino(inode: *const inode) -> *const c_ulong;
+    fn inode_i_sb(inode: *const inode) -> *const *const super_block;
     fn linux_binprm_argc(task: *const linux_binprm) -> c_int;
+    fn linux_binprm_file(target: *const linux_binprm) -> *const file;
     fn mm_exe_file(target: *const mm_struct) -> *const *const file;
+    fn request_sock_daddr(target: *const request_sock) -> c_uint;
+    fn request_sock_family(target: *const request_sock) -> c_ushort;
+    fn request_sock_num(target: *const request_sock) -> c_ushort;
+    fn request_sock_v6_daddr(target: *const request_sock, array: &[u8; 16]) -> c_void;
     fn sockaddr_in_sin_addr_s_addr(task: *const sockaddr_in) -> c_uint;
     fn sockaddr_in_sin_port(target: *const sockaddr_in) -> c_ushort;
     fn sockaddr_sa_family(task: *const sockaddr) -> c_ushort;
@@ -50,24 +78,138 @@ extern "C" {
         sockaddr: *const sockaddr_in6,
         array: &[u8; 16],
     ) -> c_void;
+    fn sockaddr_in6_sin6_port(target: *const sockaddr_in6) -> c_ushort;
+    fn sockaddr_un_sun_path(target: *const sockaddr_un) -> *const c_char;
+    fn sock_cgroup_id(target: *const sock) -> c_ulonglong;
+    fn sock_file_cred(target: *const sock) -> *const cred;
+    fn sock_sk_protocol(target: *const sock) -> c_ushort;
+    fn socket_sk(target: *const socket) -> *const sock;
+    fn socket_sk_family(target: *const socket) -> c_ushort;
+    fn socket_sk_ipv6only(target: *const socket) -> c_uchar;
+    fn socket_sk_num(target: *const socket) -> c_ushort;
+    fn socket_sk_protocol(target: *const socket) -> c_ushort;
+    fn socket_sk_rcv_saddr(target: *const socket) -> c_uint;
+    fn socket_sk_v6_rcv_saddr(target: *const socket, array: &[u8; 16]) -> c_void;
+    fn super_block_s_dev(sb: *const super_block) -> *const c_uint;
     fn task_struct_mm(target: *const task_struct) -> *const *const mm_struct;
 }
 
-pub enum Mode {
-    Allowlist,
-    Denylist,
-}
-
 pub enum Action {
     Allow,
     Deny,
+    /// Denied by a policy in audit mode. The operation is alerted, but
+    /// allowed.
+    Audit,
+}
+
+impl Action {
+    /// Returns the action for an operation denied by a map entry with the
+    /// given flags.
+    #[inline(always)]
+    pub fn deny(flags: u8) -> Self {
+        if flags & POLICY_AUDIT != 0 {
+            Action::Audit
+        } else {
+            Action::Deny
+        }
+    }
+
+    /// Returns `true` if the operation has to be alerted.
+    #[inline(always)]
+    pub fn is_alerted(&self) -> bool {
+        !matches!(self, Action::Allow)
+    }
+
+    /// Returns `true` if the operation is denied.
+    #[inline(always)]
+    pub fn is_enforced(&self) -> bool {
+        matches!(self, Action::Deny)
+    }
 }
 
 impl From<Action> for i32 {
     fn from(action: Action) -> Self {
         match action {
-            Action::Allow => 0,
+            Action::Allow | Action::Audit => 0,
             Action::Deny => -1,
         }
     }
 }
+
+/// Returns the action of the most specific subject matching the given one
+/// which has an entry in the allow or deny map of a hook keyed only by
+/// subjects, or `None` if there is no such subject. Denying entries win over
+/// allowing ones of the same subject.
+#[inline(always)]
+pub(crate) fn subject_action(
+    allowed: &HashMap<Subject, u8>,
+    denied: &HashMap<Subject, u8>,
+    subject: Subject,
+) -> Option<Action> {
+    for subject in subject.candidates() {
+        if let Some(flags) = unsafe { denied.get(&subject) } {
+            return Some(Action::deny(*flags));
+        }
+        if unsafe { allowed.get(&subject) }.is_some() {
+            return Some(Action::Allow);
+        }
+    }
+    None
+}
+
+/// Returns the embedded IPv4 address if the IPv6 address is an IPv4-mapped
+/// one (`::ffff:a.b.c.d`). Such addresses are matched by IPv4 rules.
+#[inline(always)]
+pub(crate) fn ipv4_mapped(addr: &[u8; 16]) -> Option<[u8; 4]> {
+    if addr[..10] == [0; 10] && addr[10] == 0xff && addr[11] == 0xff {
+        Some([addr[12], addr[13], addr[14], addr[15]])
+    } else {
+        None
+    }
+}
+
+/// Returns the action of the longest prefix matching the key in the LPM
+/// tries with allowed and denied prefixes. Denied prefixes win over allowed
+/// ones with the same length.
+#[inline(always)]
+pub(crate) fn trie_action<K>(
+    allowed: &LpmTrie<K, PrefixValue>,
+    denied: &LpmTrie<K, PrefixValue>,
+    key: &Key<K>,
+) -> Option<Action> {
+    trie_match(allowed, denied, key).map(|(_, action)| action)
+}
+
+/// Like [`trie_action`], but returns the prefix length of the matching
+/// entry too.
+#[inline(always)]
+pub(crate) fn trie_match<K>(
+    allowed: &LpmTrie<K, PrefixValue>,
+    denied: &LpmTrie<K, PrefixValue>,
+    key: &Key<K>,
+) -> Option<(u8, Action)> {
+    match (allowed.get(key), denied.get(key)) {
+        (Some(allowed), Some(denied)) if allowed.prefix_len > denied.prefix_len => {
+            Some((allowed.prefix_len, Action::Allow))
+        }
+        (_, Some(denied)) => Some((denied.prefix_len, Action::deny(denied.flags))),
+        (Some(allowed), None) => Some((allowed.prefix_len, Action::Allow)),
+        (None, None) => None,
+    }
+}
+
+/// Returns the default action of the given hook for the most specific
+/// subject matching the given one in the `DEFAULT_ACTIONS` map. Operations
+/// are allowed if no default action is set.
+#[inline(always)]
+pub(crate) fn default_action(hook: u32, subject: Subject) -> Action {
+    for subject in subject.candidates() {
+        if let Some(flags) = unsafe { DEFAULT_ACTIONS.get(&DefaultKey::new(subject, hook)) } {
+            if *flags & DEFAULT_DENY != 0 {
+                return Action::deny(*flags);
+            }
+            return Action::Allow;
+        }
+    }
+    Action::Allow
+}
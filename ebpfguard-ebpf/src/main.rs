@@ -4,15 +4,24 @@
 use aya_bpf::{macros::lsm, programs::LsmContext};
 
 use ebpfguard_ebpf::{
-    bprm_check_security::bprm_check_security, file_open::file_open, sb_mount::sb_mount,
-    sb_remount::sb_remount, sb_umount::sb_umount, socket_bind::socket_bind,
-    socket_connect::socket_connect, task_fix_setuid::task_fix_setuid,
+    bprm_check_security::bprm_check_security,
+    file_open::file_open,
+    inet_conn_request::{inet_conn_request, record_listener},
+    sb_mount::sb_mount,
+    sb_remount::sb_remount,
+    sb_umount::sb_umount,
+    socket_bind::socket_bind,
+    socket_connect::socket_connect,
+    socket_connect_unix::socket_connect_unix,
+    socket_create::socket_create,
+    socket_listen::socket_listen,
+    task_fix_setuid::task_fix_setuid,
 };
 
 #[lsm(name = "bprm_check_security")]
 pub fn prog_bprm_check_security(ctx: LsmContext) -> i32 {
     match bprm_check_security(ctx) {
-        Ok(ret) => ret,
+        Ok(ret) => ret.into(),
         Err(_) => 0,
     }
 }
@@ -73,6 +82,49 @@ pub fn prog_socket_connect(ctx: LsmContext) -> i32 {
     }
 }
 
+/// Attached to `socket_connect` LSM hook next to `prog_socket_connect`, so
+/// Unix domain socket policies can be enforced independently.
+#[lsm(name = "socket_connect_unix")]
+pub fn prog_socket_connect_unix(ctx: LsmContext) -> i32 {
+    match socket_connect_unix(ctx) {
+        Ok(ret) => ret.into(),
+        Err(_) => 0,
+    }
+}
+
+#[lsm(name = "socket_create")]
+pub fn prog_socket_create(ctx: LsmContext) -> i32 {
+    match socket_create(ctx) {
+        Ok(ret) => ret.into(),
+        Err(_) => 0,
+    }
+}
+
+#[lsm(name = "socket_listen")]
+pub fn prog_socket_listen(ctx: LsmContext) -> i32 {
+    match socket_listen(ctx) {
+        Ok(ret) => ret.into(),
+        Err(_) => 0,
+    }
+}
+
+#[lsm(name = "inet_conn_request")]
+pub fn prog_inet_conn_request(ctx: LsmContext) -> i32 {
+    match inet_conn_request(ctx) {
+        Ok(ret) => ret.into(),
+        Err(_) => 0,
+    }
+}
+
+/// Attached to `socket_listen` LSM hook next to `prog_socket_listen`, so the
+/// subjects of listening sockets are known to `prog_inet_conn_request`.
+/// Never denies the operation.
+#[lsm(name = "inet_conn_request_listen")]
+pub fn prog_inet_conn_request_listen(ctx: LsmContext) -> i32 {
+    let _ = record_listener(ctx);
+    0
+}
+
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
     unsafe { core::hint::unreachable_unchecked() }
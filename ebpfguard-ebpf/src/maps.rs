@@ -1,103 +1,384 @@
 use aya_bpf::{
     macros::map,
-    maps::{HashMap, PerfEventArray},
+    maps::{lpm_trie::Key, Array, HashMap, LpmTrie, LruHashMap, PerCpuArray, PerfEventArray},
 };
-use ebpfguard_common::{alerts, policy};
+use ebpfguard_common::{
+    alerts,
+    consts::{
+        MAX_DEFAULT_ENTRIES, MAX_INODE_INFO_ENTRIES, MAX_LISTENER_ENTRIES, MAX_OBJECT_ENTRIES,
+        MAX_PATH_LEN, MAX_SUBJECT_ENTRIES,
+    },
+    inode::{Inode, InodeInfo},
+    policy,
+    subject::Subject,
+};
+
+use crate::{consts::BPF_F_NO_PREALLOC, inet_conn_request::Listener};
+
+/// Map of paths of the inodes used in policies. It's not used by the programs,
+/// it's pinned together with the policy maps, so all processes managing them
+/// can resolve inodes back to paths.
+#[map]
+pub static INODE_INFO: HashMap<Inode, InodeInfo> =
+    HashMap::pinned(MAX_INODE_INFO_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of actions applied to operations which aren't matched by any policy,
+/// keyed by subject and hook ID. Values are flags, see
+/// [`DEFAULT_DENY`](ebpfguard_common::consts::DEFAULT_DENY).
+#[map]
+pub static DEFAULT_ACTIONS: HashMap<policy::DefaultKey, u8> =
+    HashMap::pinned(MAX_DEFAULT_ENTRIES, 0);
+
+/// Map of allowed executables, keyed by subject (the process calling
+/// `execve`) and executable (or directory) inode.
+#[map]
+pub static ALLOWED_BPRM_CHECK_SECURITY: HashMap<policy::PathKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of denied executables, keyed by subject (the process calling
+/// `execve`) and executable (or directory) inode.
+#[map]
+pub static DENIED_BPRM_CHECK_SECURITY: HashMap<policy::PathKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of the subjects of `bprm_check_security` policies with directory
+/// entries, see [`SUBJECT_DIRS`](ebpfguard_common::consts::SUBJECT_DIRS).
+#[map]
+pub static BPRM_CHECK_SECURITY_SUBJECT_FLAGS: HashMap<Subject, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
 
+/// Map with the settings of the `bprm_check_security` program in its only
+/// entry.
+#[map]
+pub static BPRM_CHECK_SECURITY_SETTINGS: Array<policy::BprmCheckSecuritySettings> =
+    Array::pinned(1, 0);
+
+/// Map of alerts for `bprm_check_security` LSM hook inspection.
 #[map]
 pub static ALERT_BPRM_CHECK_SECURITY: PerfEventArray<alerts::BprmCheckSecurity> =
     PerfEventArray::pinned(1024, 0);
 
-/// Map of allowed file open paths for each binary.
+/// Map of allowed file open paths, keyed by subject and path inode.
+#[map]
+pub static ALLOWED_FILE_OPEN: HashMap<policy::PathKey, u8> = HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of denied file open paths, keyed by subject and path inode.
+#[map]
+pub static DENIED_FILE_OPEN: HashMap<policy::PathKey, u8> = HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of allowed file open path prefixes, keyed by subject and path.
+#[map]
+pub static ALLOWED_FILE_OPEN_PREFIX: LpmTrie<policy::FilePrefixKey, policy::FilePrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied file open path prefixes, keyed by subject and path.
+#[map]
+pub static DENIED_FILE_OPEN_PREFIX: LpmTrie<policy::FilePrefixKey, policy::FilePrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of the subjects of `file_open` policies with directory or prefix
+/// entries, see [`SUBJECT_DIRS`](ebpfguard_common::consts::SUBJECT_DIRS).
+#[map]
+pub static FILE_OPEN_SUBJECT_FLAGS: HashMap<Subject, u8> = HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map with the settings of the `file_open` program in its only entry.
 #[map]
-pub static ALLOWED_FILE_OPEN: HashMap<u64, policy::Paths> = HashMap::pinned(1024, 0);
+pub static FILE_OPEN_SETTINGS: Array<policy::FileOpenSettings> = Array::pinned(1, 0);
 
-/// Map of denied file open paths for each binary.
+/// Per-CPU buffer for the path of an opened file, too big for the stack.
 #[map]
-pub static DENIED_FILE_OPEN: HashMap<u64, policy::Paths> = HashMap::pinned(1024, 0);
+pub static FILE_OPEN_PATH: PerCpuArray<[u8; MAX_PATH_LEN]> = PerCpuArray::with_max_entries(1, 0);
+
+/// Per-CPU buffer for the key of prefix policy lookups, too big for the
+/// stack.
+#[map]
+pub static FILE_OPEN_PREFIX_KEY: PerCpuArray<Key<policy::FilePrefixKey>> =
+    PerCpuArray::with_max_entries(1, 0);
 
 /// Map of alerts for `file_open` LSM hook inspection.
 #[map]
 pub static ALERT_FILE_OPEN: PerfEventArray<alerts::FileOpen> = PerfEventArray::pinned(1024, 0);
 
-/// Map indicating which binaries are allowed to use `setuid`.
+/// Map indicating which subjects are allowed to use `setuid`.
 #[map]
-pub static ALLOWED_TASK_FIX_SETUID: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static ALLOWED_TASK_FIX_SETUID: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
-/// Map indicating which binaries are denied to use `setuid`.
+/// Map indicating which subjects are denied to use `setuid`.
 #[map]
-pub static DENIED_TASK_FIX_SETUID: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static DENIED_TASK_FIX_SETUID: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
 /// Map of alerts for `setuid` LSM hook inspection.
 #[map]
 pub static ALERT_TASK_FIX_SETUID: PerfEventArray<alerts::TaskFixSetuid> =
     PerfEventArray::pinned(1024, 0);
 
-// Map indicating which binaries are allowed to mount filesystems.
+// Map indicating which subjects are allowed to mount filesystems.
 #[map]
-pub static ALLOWED_SB_MOUNT: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static ALLOWED_SB_MOUNT: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
-// Map indicating which binaries are denied to mount filesystems.
+// Map indicating which subjects are denied to mount filesystems.
 #[map]
-pub static DENIED_SB_MOUNT: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static DENIED_SB_MOUNT: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
 // Map of alerts for `sb_mount` LSM hook inspection.
 #[map]
 pub static ALERT_SB_MOUNT: PerfEventArray<alerts::SbMount> = PerfEventArray::pinned(1024, 0);
 
-// Map indicating which binaries are allowed to remount filesystems.
+// Map indicating which subjects are allowed to remount filesystems.
 #[map]
-pub static ALLOWED_SB_REMOUNT: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static ALLOWED_SB_REMOUNT: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
-// Map indicating which binaries are denied to remount filesystems.
+// Map indicating which subjects are denied to remount filesystems.
 #[map]
-pub static DENIED_SB_REMOUNT: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static DENIED_SB_REMOUNT: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
 // Map of alerts for `sb_remount` LSM hook inspection.
 #[map]
 pub static ALERT_SB_REMOUNT: PerfEventArray<alerts::SbRemount> = PerfEventArray::pinned(1024, 0);
 
-// Map indicating which binaries are allowed to unmount filesystems.
+// Map indicating which subjects are allowed to unmount filesystems.
 #[map]
-pub static ALLOWED_SB_UMOUNT: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static ALLOWED_SB_UMOUNT: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
-// Map indicating which binaries are denied to unmount filesystems.
+// Map indicating which subjects are denied to unmount filesystems.
 #[map]
-pub static DENIED_SB_UMOUNT: HashMap<u64, u8> = HashMap::pinned(1024, 0);
+pub static DENIED_SB_UMOUNT: HashMap<Subject, u8> = HashMap::pinned(MAX_SUBJECT_ENTRIES, 0);
 
 // Map of alerts for `sb_umount` LSM hook inspection.
 #[map]
 pub static ALERT_SB_UMOUNT: PerfEventArray<alerts::SbUmount> = PerfEventArray::pinned(1024, 0);
 
-/// Map of allowed socket bind ports for each binary.
+/// Map of allowed socket bind port ranges for each subject. Values are the
+/// prefix lengths of the blocks of ports.
+#[map]
+pub static ALLOWED_SOCKET_BIND: LpmTrie<policy::PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket bind port ranges for each subject. Values are the
+/// prefix lengths of the blocks of ports.
+#[map]
+pub static DENIED_SOCKET_BIND: LpmTrie<policy::PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of allowed socket bind IPv4 networks (with blocks of ports for single
+/// addresses) for each subject.
 #[map]
-pub static ALLOWED_SOCKET_BIND: HashMap<u64, policy::Ports> = HashMap::pinned(1024, 0);
+pub static ALLOWED_SOCKET_BIND_V4: LpmTrie<policy::Ipv4PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
 
-/// Map of denied socket bind ports for each binary.
+/// Map of denied socket bind IPv4 networks (with blocks of ports for single
+/// addresses) for each subject.
 #[map]
-pub static DENIED_SOCKET_BIND: HashMap<u64, policy::Ports> = HashMap::pinned(1024, 0);
+pub static DENIED_SOCKET_BIND_V4: LpmTrie<policy::Ipv4PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of allowed socket bind IPv6 networks (with blocks of ports for single
+/// addresses) for each subject.
+#[map]
+pub static ALLOWED_SOCKET_BIND_V6: LpmTrie<policy::Ipv6PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket bind IPv6 networks (with blocks of ports for single
+/// addresses) for each subject.
+#[map]
+pub static DENIED_SOCKET_BIND_V6: LpmTrie<policy::Ipv6PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
 
 /// Map of alerts for `socket_bind` LSM hook inspection.
 #[map]
 pub static ALERT_SOCKET_BIND: PerfEventArray<alerts::SocketBind> = PerfEventArray::pinned(1024, 0);
 
-/// Map of allowed socket connect IPv4 addresses for each binary.
+/// Map of allowed socket listen port ranges for each subject. Values are the
+/// prefix lengths of the blocks of ports.
+#[map]
+pub static ALLOWED_SOCKET_LISTEN: LpmTrie<policy::PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket listen port ranges for each subject. Values are the
+/// prefix lengths of the blocks of ports.
+#[map]
+pub static DENIED_SOCKET_LISTEN: LpmTrie<policy::PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of allowed socket listen IPv4 networks (with blocks of ports for
+/// single addresses) for each subject.
+#[map]
+pub static ALLOWED_SOCKET_LISTEN_V4: LpmTrie<policy::Ipv4PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket listen IPv4 networks (with blocks of ports for
+/// single addresses) for each subject.
+#[map]
+pub static DENIED_SOCKET_LISTEN_V4: LpmTrie<policy::Ipv4PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of allowed socket listen IPv6 networks (with blocks of ports for
+/// single addresses) for each subject.
+#[map]
+pub static ALLOWED_SOCKET_LISTEN_V6: LpmTrie<policy::Ipv6PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket listen IPv6 networks (with blocks of ports for
+/// single addresses) for each subject.
+#[map]
+pub static DENIED_SOCKET_LISTEN_V6: LpmTrie<policy::Ipv6PortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of alerts for `socket_listen` LSM hook inspection.
+#[map]
+pub static ALERT_SOCKET_LISTEN: PerfEventArray<alerts::SocketListen> =
+    PerfEventArray::pinned(1024, 0);
+
+/// Map of allowed socket connect ports to all addresses for each subject and
+/// protocol.
+#[map]
+pub static ALLOWED_SOCKET_CONNECT: LpmTrie<policy::ProtocolPortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket connect ports to all addresses for each subject and
+/// protocol.
+#[map]
+pub static DENIED_SOCKET_CONNECT: LpmTrie<policy::ProtocolPortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of socket connect rules allowing all addresses of a family for each
+/// subject and protocol.
 #[map]
-pub static ALLOWED_SOCKET_CONNECT_V4: HashMap<u64, policy::Ipv4Addrs> = HashMap::pinned(1024, 0);
+pub static ALLOWED_SOCKET_CONNECT_ALL: HashMap<policy::FamilyKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
 
-/// Map of denied socket connect IPv4 addresses for each binary.
+/// Map of socket connect rules denying all addresses of a family for each
+/// subject and protocol.
 #[map]
-pub static DENIED_SOCKET_CONNECT_V4: HashMap<u64, policy::Ipv4Addrs> = HashMap::pinned(1024, 0);
+pub static DENIED_SOCKET_CONNECT_ALL: HashMap<policy::FamilyKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
 
-/// Map of allowed socket connect IPv6 addresses for each binary.
+/// Map of allowed socket connect IPv4 networks (with blocks of ports for
+/// single addresses) for each subject and protocol.
 #[map]
-pub static ALLOWED_SOCKET_CONNECT_V6: HashMap<u64, policy::Ipv6Addrs> = HashMap::pinned(1024, 0);
+pub static ALLOWED_SOCKET_CONNECT_V4: LpmTrie<policy::Ipv4Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
 
-/// Map of denied socket connect IPv6 addresses for each binary.
+/// Map of denied socket connect IPv4 networks (with blocks of ports for
+/// single addresses) for each subject and protocol.
 #[map]
-pub static DENIED_SOCKET_CONNECT_V6: HashMap<u64, policy::Ipv6Addrs> = HashMap::pinned(1024, 0);
+pub static DENIED_SOCKET_CONNECT_V4: LpmTrie<policy::Ipv4Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of allowed socket connect IPv6 networks (with blocks of ports for
+/// single addresses) for each subject and protocol.
+#[map]
+pub static ALLOWED_SOCKET_CONNECT_V6: LpmTrie<policy::Ipv6Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied socket connect IPv6 networks (with blocks of ports for
+/// single addresses) for each subject and protocol.
+#[map]
+pub static DENIED_SOCKET_CONNECT_V6: LpmTrie<policy::Ipv6Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
 
 /// Map of alerts for `socket_connect` LSM hook inspection.
 #[map]
 pub static ALERT_SOCKET_CONNECT: PerfEventArray<alerts::SocketConnect> =
     PerfEventArray::pinned(1024, 0);
+
+/// Map of allowed Unix domain socket paths, keyed by subject and path.
+#[map]
+pub static ALLOWED_SOCKET_CONNECT_UNIX: HashMap<policy::UnixPathKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of denied Unix domain socket paths, keyed by subject and path.
+#[map]
+pub static DENIED_SOCKET_CONNECT_UNIX: HashMap<policy::UnixPathKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of alerts for Unix domain socket connections in `socket_connect` LSM
+/// hook inspection.
+#[map]
+pub static ALERT_SOCKET_CONNECT_UNIX: PerfEventArray<alerts::SocketConnectUnix> =
+    PerfEventArray::pinned(1024, 0);
+
+/// Map of local ports allowed for peers on all addresses, for each subject
+/// and protocol of the listening socket.
+#[map]
+pub static ALLOWED_INET_CONN_REQUEST: LpmTrie<policy::ProtocolPortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of local ports denied for peers on all addresses, for each subject
+/// and protocol of the listening socket.
+#[map]
+pub static DENIED_INET_CONN_REQUEST: LpmTrie<policy::ProtocolPortKey, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of rules allowing peers on all addresses of a family for each subject
+/// and protocol of the listening socket.
+#[map]
+pub static ALLOWED_INET_CONN_REQUEST_ALL: HashMap<policy::FamilyKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of rules denying peers on all addresses of a family for each subject
+/// and protocol of the listening socket.
+#[map]
+pub static DENIED_INET_CONN_REQUEST_ALL: HashMap<policy::FamilyKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of allowed peer IPv4 networks (with blocks of local ports for single
+/// addresses) for each subject and protocol of the listening socket.
+#[map]
+pub static ALLOWED_INET_CONN_REQUEST_V4: LpmTrie<policy::Ipv4Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied peer IPv4 networks (with blocks of local ports for single
+/// addresses) for each subject and protocol of the listening socket.
+#[map]
+pub static DENIED_INET_CONN_REQUEST_V4: LpmTrie<policy::Ipv4Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of allowed peer IPv6 networks (with blocks of local ports for single
+/// addresses) for each subject and protocol of the listening socket.
+#[map]
+pub static ALLOWED_INET_CONN_REQUEST_V6: LpmTrie<policy::Ipv6Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of denied peer IPv6 networks (with blocks of local ports for single
+/// addresses) for each subject and protocol of the listening socket.
+#[map]
+pub static DENIED_INET_CONN_REQUEST_V6: LpmTrie<policy::Ipv6Key, policy::PrefixValue> =
+    LpmTrie::pinned(MAX_OBJECT_ENTRIES, BPF_F_NO_PREALLOC);
+
+/// Map of the processes which started listening on sockets, keyed by the
+/// address of `struct sock`. Entries of closed sockets are not removed,
+/// they are overwritten when the address is reused by another listening
+/// socket. When the map is full, the least recently used entries are
+/// evicted, even if their sockets are still listening. Sockets without an
+/// entry (including the ones listening before the programs were attached)
+/// have an unknown listening process.
+#[map]
+pub static LISTENERS: LruHashMap<u64, Listener> = LruHashMap::pinned(MAX_LISTENER_ENTRIES, 0);
+
+/// Settings of the `inet_conn_request` program, see
+/// [`INET_CONN_REQUEST_BINARY_SUBJECTS`](ebpfguard_common::consts::INET_CONN_REQUEST_BINARY_SUBJECTS).
+#[map]
+pub static INET_CONN_REQUEST_SETTINGS: Array<u8> = Array::pinned(1, 0);
+
+/// Map of alerts for `inet_conn_request` LSM hook inspection.
+#[map]
+pub static ALERT_INET_CONN_REQUEST: PerfEventArray<alerts::InetConnRequest> =
+    PerfEventArray::pinned(1024, 0);
+
+/// Map of allowed socket families, types and protocols, keyed by subject and
+/// the arguments of `socket`.
+#[map]
+pub static ALLOWED_SOCKET_CREATE: HashMap<policy::SocketKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of denied socket families, types and protocols, keyed by subject and
+/// the arguments of `socket`.
+#[map]
+pub static DENIED_SOCKET_CREATE: HashMap<policy::SocketKey, u8> =
+    HashMap::pinned(MAX_OBJECT_ENTRIES, 0);
+
+/// Map of alerts for `socket_create` LSM hook inspection.
+#[map]
+pub static ALERT_SOCKET_CREATE: PerfEventArray<alerts::SocketCreate> =
+    PerfEventArray::pinned(1024, 0);
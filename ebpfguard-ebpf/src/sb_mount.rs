@@ -1,17 +1,20 @@
-use aya_bpf::{maps::HashMap, programs::LsmContext, BpfContext, cty::c_long};
-use ebpfguard_common::{alerts, consts::INODE_WILDCARD};
+use aya_bpf::{cty::c_long, programs::LsmContext, BpfContext};
+use ebpfguard_common::{alerts, consts::HOOK_SB_MOUNT, subject::Subject};
 
 use crate::{
-    binprm::current_binprm_inode,
+    binprm::current_subject,
+    default_action,
     maps::{ALERT_SB_MOUNT, ALLOWED_SB_MOUNT, DENIED_SB_MOUNT},
-    Action, Mode,
+    subject_action, Action,
 };
 
 /// Inspects the context of `sb_mount` LSM hook and decides whether to allow or
 /// deny the operation based on the state of the `ALLOWED_SB_MOUNT` and
-/// `DENIED_SB_MOUNT` maps.
+/// `DENIED_SB_MOUNT` maps. The policy of the most specific subject decides,
+/// the default action of the hook applies if no subject has a policy.
 ///
-/// If denied, the operation is logged to the `ALERT_SB_MOUNT` map.
+/// If denied, the operation is logged to the `ALERT_SB_MOUNT` map. Operations
+/// denied by policies in audit mode are logged, but allowed.
 ///
 /// # Example
 ///
@@ -24,53 +27,22 @@ use crate::{
 /// }
 /// ```
 pub fn sb_mount(ctx: LsmContext) -> Result<Action, c_long> {
-    let binprm_inode = current_binprm_inode()?;
+    let subject = current_subject()?;
 
-    if unsafe { ALLOWED_SB_MOUNT.get(&INODE_WILDCARD).is_some() } {
-        return Ok(check_conditions_and_alert(&ctx, &DENIED_SB_MOUNT, binprm_inode, Mode::Denylist));
-    }
-
-    if unsafe { DENIED_SB_MOUNT.get(&INODE_WILDCARD).is_some() } {
-        return Ok(check_conditions_and_alert(&ctx, &ALLOWED_SB_MOUNT, binprm_inode, Mode::Allowlist));
-    }
+    let action = subject_action(&ALLOWED_SB_MOUNT, &DENIED_SB_MOUNT, subject)
+        .unwrap_or_else(|| default_action(HOOK_SB_MOUNT, subject));
 
-    Ok(Action::Allow)
+    Ok(alert(&ctx, subject, action))
 }
 
 #[inline(always)]
-fn check_conditions_and_alert(
-    ctx: &LsmContext,
-    map: &HashMap<u64, u8>,
-    binprm_inode: u64,
-    mode: Mode,
-) -> Action {
-    match check_conditions(map, binprm_inode, mode) {
-        Action::Deny => {
-            ALERT_SB_MOUNT.output(ctx, &alerts::SbMount::new(ctx.pid(), binprm_inode), 0);
-            Action::Deny
-        }
-        action => action,
-    }
-}
-
-#[inline(always)]
-fn check_conditions(map: &HashMap<u64, u8>, binprm_inode: u64, mode: Mode) -> Action {
-    if unsafe { map.get(&INODE_WILDCARD).is_some() } {
-        return match mode {
-            Mode::Allowlist => Action::Allow,
-            Mode::Denylist => Action::Deny,
-        };
-    }
-
-    if unsafe { map.get(&binprm_inode).is_some() } {
-        return match mode {
-            Mode::Allowlist => Action::Allow,
-            Mode::Denylist => Action::Deny,
-        };
-    }
-
-    match mode {
-        Mode::Allowlist => Action::Deny,
-        Mode::Denylist => Action::Allow,
+fn alert(ctx: &LsmContext, subject: Subject, action: Action) -> Action {
+    if action.is_alerted() {
+        ALERT_SB_MOUNT.output(
+            ctx,
+            &alerts::SbMount::new(ctx.pid(), action.is_enforced(), subject),
+            0,
+        );
     }
+    action
 }
@@ -1,20 +1,54 @@
-use aya_bpf::{cty::c_long, programs::LsmContext, BpfContext};
-use ebpfguard_common::{alerts, consts::INODE_WILDCARD, policy::MAX_PORTS};
+use aya_bpf::{
+    cty::c_long,
+    helpers::bpf_probe_read_kernel,
+    maps::{lpm_trie::Key, LpmTrie},
+    programs::LsmContext,
+    BpfContext,
+};
+use ebpfguard_common::{
+    alerts,
+    consts::{AF_INET, AF_INET6, HOOK_SOCKET_BIND},
+    policy::{Ipv4PortKey, Ipv6PortKey, PortKey, PrefixValue},
+    subject::Subject,
+};
 
 use crate::{
-    binprm::current_binprm_inode,
-    consts::AF_INET,
-    maps::{ALERT_SOCKET_BIND, ALLOWED_SOCKET_BIND, DENIED_SOCKET_BIND},
-    sockaddr_in_sin_port, sockaddr_sa_family,
-    vmlinux::{sockaddr, sockaddr_in},
+    binprm::current_subject,
+    default_action, ipv4_mapped,
+    maps::{
+        ALERT_SOCKET_BIND, ALLOWED_SOCKET_BIND, ALLOWED_SOCKET_BIND_V4, ALLOWED_SOCKET_BIND_V6,
+        DENIED_SOCKET_BIND, DENIED_SOCKET_BIND_V4, DENIED_SOCKET_BIND_V6,
+    },
+    sockaddr_in6_sin6_addr_in6_u_u6_addr8, sockaddr_in6_sin6_port, sockaddr_in_sin_addr_s_addr,
+    sockaddr_in_sin_port, sockaddr_sa_family, socket_sk_ipv6only, trie_action,
+    vmlinux::{sockaddr, sockaddr_in, sockaddr_in6, socket},
     Action,
 };
 
 /// Inspects the context of `socket_bind` LSM hook and decides whether to allow
 /// or deny the bind operation based on the state of the `ALLOWED_SOCKET_BIND`
-/// and `DENIED_SOCKET_BIND` maps.
+/// and `DENIED_SOCKET_BIND` maps, with rules for all addresses, and the
+/// `ALLOWED_SOCKET_BIND_V4`/`ALLOWED_SOCKET_BIND_V6` and
+/// `DENIED_SOCKET_BIND_V4`/`DENIED_SOCKET_BIND_V6` maps, with rules for the
+/// given addresses.
+///
+/// The maps are LPM tries keyed by the subject, the address (only in the
+/// address maps) and the port, ranges of ports are stored as aligned blocks.
+/// Subjects are checked from the most specific one. For every subject, rules
+/// for the bound address win over rules for all addresses. Among them, the
+/// longest matching prefix decides (a single port wins over a range, which
+/// wins over all ports). If no subject has a matching entry, the default
+/// action of the hook applies.
+///
+/// Port policies apply to both IPv4 and IPv6 sockets. IPv4-mapped IPv6
+/// addresses are matched by IPv4 rules, and binding a dual-stack socket (one
+/// without `IPV6_V6ONLY`) to `::` is checked against the rules for both `::`
+/// and `0.0.0.0`, the stricter action applies. Binding to port 0 lets the
+/// kernel pick a port, so only rules for all ports of the bound address
+/// apply to it, and it's allowed if there are none.
 ///
 /// If denied, the operation is logged to the `ALERT_SOCKET_BIND` map.
+/// Operations denied by policies in audit mode are logged, but allowed.
 ///
 /// # Example
 ///
@@ -32,100 +66,179 @@ use crate::{
 /// ```
 #[inline(always)]
 pub fn socket_bind(ctx: LsmContext) -> Result<Action, c_long> {
+    let sock: *const socket = unsafe { ctx.arg(0) };
     let sockaddr: *const sockaddr = unsafe { ctx.arg(1) };
 
-    if unsafe { sockaddr_sa_family(sockaddr) } != AF_INET {
-        return Ok(Action::Allow);
+    match unsafe { sockaddr_sa_family(sockaddr) } {
+        AF_INET => socket_bind_v4(ctx, sockaddr),
+        AF_INET6 => socket_bind_v6(ctx, sock, sockaddr),
+        _ => Ok(Action::Allow),
     }
+}
 
+#[inline(always)]
+fn socket_bind_v4(ctx: LsmContext, sockaddr: *const sockaddr) -> Result<Action, c_long> {
     let sockaddr_in: *const sockaddr_in = sockaddr as *const sockaddr_in;
     let port = u16::from_be(unsafe { sockaddr_in_sin_port(sockaddr_in) });
+    let addr = u32::from_be(unsafe { sockaddr_in_sin_addr_s_addr(sockaddr_in) });
+    let subject = current_subject()?;
+
+    let action = bind_action_v4(subject, addr.to_be_bytes(), port);
+    if action.is_alerted() {
+        ALERT_SOCKET_BIND.output(
+            &ctx,
+            &alerts::SocketBind::new_ipv4(ctx.pid(), action.is_enforced(), subject, port, addr),
+            0,
+        );
+    }
+
+    Ok(action)
+}
 
+#[inline(always)]
+fn socket_bind_v6(
+    ctx: LsmContext,
+    sock: *const socket,
+    sockaddr: *const sockaddr,
+) -> Result<Action, c_long> {
+    let sockaddr_in6: *const sockaddr_in6 = sockaddr as *const sockaddr_in6;
+    let port = u16::from_be(unsafe { sockaddr_in6_sin6_port(sockaddr_in6) });
+
+    let sockaddr_in6: sockaddr_in6 = unsafe { bpf_probe_read_kernel(sockaddr_in6)? };
+    let addr: [u8; 16] = [0; 16];
+    unsafe { sockaddr_in6_sin6_addr_in6_u_u6_addr8(&sockaddr_in6, &addr) };
+
+    let subject = current_subject()?;
+
+    let action = if let Some(addr_v4) = ipv4_mapped(&addr) {
+        bind_action_v4(subject, addr_v4, port)
+    } else if addr == [0; 16] && unsafe { socket_sk_ipv6only(sock) } == 0 {
+        // Dual-stack sockets bound to `::` accept IPv4 connections on
+        // `0.0.0.0` too.
+        stricter(
+            bind_action_v6(subject, addr, port),
+            bind_action_v4(subject, [0; 4], port),
+        )
+    } else {
+        bind_action_v6(subject, addr, port)
+    };
+    if action.is_alerted() {
+        ALERT_SOCKET_BIND.output(
+            &ctx,
+            &alerts::SocketBind::new_ipv6(ctx.pid(), action.is_enforced(), subject, port, addr),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+#[inline(always)]
+fn bind_action_v4(subject: Subject, addr: [u8; 4], port: u16) -> Action {
+    let prefix_len = addr_prefix_len(Ipv4PortKey::PREFIX_LEN, port);
+    let keys = subject
+        .candidates()
+        .map(|subject| Key::new(prefix_len, Ipv4PortKey::new(subject, addr, port)));
+    bind_action(
+        &ALLOWED_SOCKET_BIND_V4,
+        &DENIED_SOCKET_BIND_V4,
+        &keys,
+        subject,
+        port,
+    )
+}
+
+#[inline(always)]
+fn bind_action_v6(subject: Subject, addr: [u8; 16], port: u16) -> Action {
+    let prefix_len = addr_prefix_len(Ipv6PortKey::PREFIX_LEN, port);
+    let keys = subject
+        .candidates()
+        .map(|subject| Key::new(prefix_len, Ipv6PortKey::new(subject, addr, port)));
+    bind_action(
+        &ALLOWED_SOCKET_BIND_V6,
+        &DENIED_SOCKET_BIND_V6,
+        &keys,
+        subject,
+        port,
+    )
+}
+
+/// Returns the prefix length of the keys of the address maps. For port 0
+/// the port is left out, so only entries for all ports of an address or
+/// network match.
+#[inline(always)]
+fn addr_prefix_len(prefix_len: u32, port: u16) -> u32 {
     if port == 0 {
-        return Ok(Action::Allow);
+        prefix_len - 16
+    } else {
+        prefix_len
     }
+}
 
-    let binprm_inode = current_binprm_inode()?;
-
-    if let Some(ports) = unsafe { ALLOWED_SOCKET_BIND.get(&INODE_WILDCARD) } {
-        if ports.all() {
-            if let Some(ports) = unsafe { DENIED_SOCKET_BIND.get(&INODE_WILDCARD) } {
-                if ports.all() {
-                    ALERT_SOCKET_BIND.output(
-                        &ctx,
-                        &alerts::SocketBind::new(ctx.pid(), binprm_inode, port),
-                        0,
-                    );
-                    return Ok(Action::Deny);
-                }
-                if ports.ports[..MAX_PORTS - 1].contains(&port) {
-                    ALERT_SOCKET_BIND.output(
-                        &ctx,
-                        &alerts::SocketBind::new(ctx.pid(), binprm_inode, port),
-                        0,
-                    );
-                    return Ok(Action::Deny);
-                }
-            }
+/// Returns the stricter of two actions.
+#[inline(always)]
+fn stricter(a: Action, b: Action) -> Action {
+    match (a, b) {
+        (Action::Deny, _) | (_, Action::Deny) => Action::Deny,
+        (Action::Audit, _) | (_, Action::Audit) => Action::Audit,
+        (Action::Allow, Action::Allow) => Action::Allow,
+    }
+}
 
-            if let Some(ports) = unsafe { DENIED_SOCKET_BIND.get(&binprm_inode) } {
-                if ports.all() {
-                    ALERT_SOCKET_BIND.output(
-                        &ctx,
-                        &alerts::SocketBind::new(ctx.pid(), binprm_inode, port),
-                        0,
-                    );
-                    return Ok(Action::Deny);
-                }
-                if ports.ports[..MAX_PORTS - 1].contains(&port) {
-                    ALERT_SOCKET_BIND.output(
-                        &ctx,
-                        &alerts::SocketBind::new(ctx.pid(), binprm_inode, port),
-                        0,
-                    );
-                    return Ok(Action::Deny);
-                }
+/// Returns the action of the most specific subject with a rule matching the
+/// bound address (`addr_keys`, for every candidate subject) or the port.
+/// For port 0 only the address rules are checked.
+#[inline(always)]
+fn bind_action<K>(
+    allowed: &LpmTrie<K, PrefixValue>,
+    denied: &LpmTrie<K, PrefixValue>,
+    addr_keys: &[Key<K>; Subject::CANDIDATES],
+    subject: Subject,
+    port: u16,
+) -> Action {
+    if port == 0 {
+        for addr_key in addr_keys {
+            if let Some(action) = trie_action(allowed, denied, addr_key) {
+                return action;
             }
-        } else if ports.ports[..MAX_PORTS - 1].contains(&port) {
-            return Ok(Action::Allow);
         }
+        return Action::Allow;
     }
 
-    if let Some(ports) = unsafe { DENIED_SOCKET_BIND.get(&INODE_WILDCARD) } {
-        if ports.all() {
-            if let Some(ports) = unsafe { ALLOWED_SOCKET_BIND.get(&INODE_WILDCARD) } {
-                if ports.all() {
-                    return Ok(Action::Allow);
-                }
-                if ports.ports[..MAX_PORTS - 1].contains(&port) {
-                    return Ok(Action::Allow);
-                }
-            }
-
-            if let Some(ports) = unsafe { ALLOWED_SOCKET_BIND.get(&binprm_inode) } {
-                if ports.all() {
-                    return Ok(Action::Allow);
-                }
-                if ports.ports[..MAX_PORTS - 1].contains(&port) {
-                    return Ok(Action::Allow);
-                }
-            }
+    let port_keys = subject
+        .candidates()
+        .map(|subject| Key::new(PortKey::PREFIX_LEN, PortKey::new(subject, port)));
+    port_action(
+        allowed,
+        denied,
+        addr_keys,
+        &ALLOWED_SOCKET_BIND,
+        &DENIED_SOCKET_BIND,
+        &port_keys,
+    )
+    .unwrap_or_else(|| default_action(HOOK_SOCKET_BIND, subject))
+}
 
-            ALERT_SOCKET_BIND.output(
-                &ctx,
-                &alerts::SocketBind::new(ctx.pid(), binprm_inode, port),
-                0,
-            );
-            return Ok(Action::Deny);
-        } else if ports.ports[..MAX_PORTS - 1].contains(&port) {
-            ALERT_SOCKET_BIND.output(
-                &ctx,
-                &alerts::SocketBind::new(ctx.pid(), binprm_inode, port),
-                0,
-            );
-            return Ok(Action::Deny);
+/// Returns the action of the most specific subject with a rule matching the
+/// local address (`addr_keys`) or the port (`port_keys`), given for every
+/// candidate subject, or `None` if there is no such subject. Rules for the
+/// address win over rules for all addresses.
+#[inline(always)]
+pub(crate) fn port_action<K>(
+    allowed: &LpmTrie<K, PrefixValue>,
+    denied: &LpmTrie<K, PrefixValue>,
+    addr_keys: &[Key<K>; Subject::CANDIDATES],
+    allowed_ports: &LpmTrie<PortKey, PrefixValue>,
+    denied_ports: &LpmTrie<PortKey, PrefixValue>,
+    port_keys: &[Key<PortKey>; Subject::CANDIDATES],
+) -> Option<Action> {
+    for (addr_key, port_key) in addr_keys.iter().zip(port_keys.iter()) {
+        if let Some(action) = trie_action(allowed, denied, addr_key) {
+            return Some(action);
+        }
+        if let Some(action) = trie_action(allowed_ports, denied_ports, port_key) {
+            return Some(action);
         }
     }
-
-    Ok(Action::Allow)
+    None
 }
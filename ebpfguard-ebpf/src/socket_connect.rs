@@ -1,28 +1,53 @@
 use aya_bpf::{
-    cty::c_long, helpers::bpf_probe_read_kernel, maps::HashMap, programs::LsmContext, BpfContext,
+    cty::c_long,
+    helpers::bpf_probe_read_kernel,
+    maps::{lpm_trie::Key, HashMap, LpmTrie},
+    programs::LsmContext,
+    BpfContext,
 };
 use ebpfguard_common::{
     alerts,
-    consts::INODE_WILDCARD,
-    policy::{IpAddrs, Ipv4Addrs, Ipv6Addrs},
+    consts::{AF_INET, AF_INET6, HOOK_SOCKET_CONNECT, PROTOCOL_WILDCARD},
+    policy::{FamilyKey, Ipv4Key, Ipv6Key, PrefixValue, ProtocolPortKey},
+    subject::Subject,
 };
 
 use crate::{
-    binprm::current_binprm_inode,
-    consts::{AF_INET, AF_INET6},
+    binprm::current_subject,
+    default_action, ipv4_mapped,
     maps::{
-        ALERT_SOCKET_CONNECT, ALLOWED_SOCKET_CONNECT_V4, ALLOWED_SOCKET_CONNECT_V6,
-        DENIED_SOCKET_CONNECT_V4, DENIED_SOCKET_CONNECT_V6,
+        ALERT_SOCKET_CONNECT, ALLOWED_SOCKET_CONNECT, ALLOWED_SOCKET_CONNECT_ALL,
+        ALLOWED_SOCKET_CONNECT_V4, ALLOWED_SOCKET_CONNECT_V6, DENIED_SOCKET_CONNECT,
+        DENIED_SOCKET_CONNECT_ALL, DENIED_SOCKET_CONNECT_V4, DENIED_SOCKET_CONNECT_V6,
     },
-    sockaddr_in6_sin6_addr_in6_u_u6_addr8, sockaddr_in_sin_addr_s_addr, sockaddr_sa_family,
-    vmlinux::{sockaddr, sockaddr_in, sockaddr_in6},
-    Action, Mode,
+    sockaddr_in6_sin6_addr_in6_u_u6_addr8, sockaddr_in6_sin6_port, sockaddr_in_sin_addr_s_addr,
+    sockaddr_in_sin_port, sockaddr_sa_family, socket_sk_protocol, trie_match,
+    vmlinux::{sockaddr, sockaddr_in, sockaddr_in6, socket},
+    Action,
 };
 
 /// Inspects the context of `socket_connect` LSM hook and decides whether to
 /// allow or deny the operation based on the state of the
 /// `ALLOWED_SOCKET_CONNECT_V4`/`ALLOWED_SOCKET_CONNECT_V6` and
-/// `DENIED_SOCKET_CONNECT_V4`/`DENIED_SOCKET_CONNECT_V6` maps.
+/// `DENIED_SOCKET_CONNECT_V4`/`DENIED_SOCKET_CONNECT_V6` maps, with rules for
+/// networks and addresses, the `ALLOWED_SOCKET_CONNECT` and
+/// `DENIED_SOCKET_CONNECT` maps, with rules for ports on all addresses, and
+/// the `ALLOWED_SOCKET_CONNECT_ALL` and `DENIED_SOCKET_CONNECT_ALL` maps, with
+/// rules for all addresses of the family.
+///
+/// The maps of networks and ports are LPM tries keyed by the subject, the
+/// protocol, the address (only in the address maps) and the port, so
+/// policies can match whole networks (e.g. `10.0.0.0/8`) and ranges of
+/// ports. Rules for all addresses are kept in hash maps keyed by the subject,
+/// the family and the protocol. Subjects are checked
+/// from the most specific one. For every subject, rules for a network or an
+/// address win over rules for ports on all addresses, which win over rules
+/// for all addresses of the family. Among them, the longest matching prefix
+/// decides, denied prefixes win over allowed ones with the same length and
+/// rules for the protocol of the socket win over rules for all protocols. If
+/// no subject has a matching rule, the default action of the hook applies.
+/// IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) are matched by IPv4 rules.
+/// Connections denied by policies in audit mode are logged, but allowed.
 ///
 /// # Example
 ///
@@ -39,177 +64,216 @@ use crate::{
 /// }
 /// ```
 pub fn socket_connect(ctx: LsmContext) -> Result<Action, c_long> {
+    let sock: *const socket = unsafe { ctx.arg(0) };
     let sockaddr: *const sockaddr = unsafe { ctx.arg(1) };
     let sa_family = unsafe { sockaddr_sa_family(sockaddr) };
 
     match sa_family {
-        AF_INET => socket_connect_v4(ctx, sockaddr),
-        AF_INET6 => socket_connect_v6(ctx, sockaddr),
+        AF_INET => socket_connect_v4(ctx, sock, sockaddr),
+        AF_INET6 => socket_connect_v6(ctx, sock, sockaddr),
         _ => Ok(Action::Allow),
     }
 }
 
 #[inline(always)]
-fn socket_connect_v4(ctx: LsmContext, sockaddr: *const sockaddr) -> Result<Action, c_long> {
+fn socket_connect_v4(
+    ctx: LsmContext,
+    sock: *const socket,
+    sockaddr: *const sockaddr,
+) -> Result<Action, c_long> {
     let sockaddr_in: *const sockaddr_in = sockaddr as *const sockaddr_in;
     let addr = u32::from_be(unsafe { sockaddr_in_sin_addr_s_addr(sockaddr_in) });
+    let port = u16::from_be(unsafe { sockaddr_in_sin_port(sockaddr_in) });
+    let protocol = socket_protocol(sock);
 
-    let binprm_inode = current_binprm_inode()?;
+    let subject = current_subject()?;
 
-    if let Some(addrs) = unsafe { ALLOWED_SOCKET_CONNECT_V4.get(&INODE_WILDCARD) } {
-        if addrs.all() {
-            return Ok(check_conditions_and_alert_v4(
-                &ctx,
-                &DENIED_SOCKET_CONNECT_V4,
+    let action = connect_action_v4(subject, protocol, addr.to_be_bytes(), port);
+    if action.is_alerted() {
+        ALERT_SOCKET_CONNECT.output(
+            &ctx,
+            &alerts::SocketConnect::new_ipv4(
+                ctx.pid(),
+                action.is_enforced(),
+                subject,
+                protocol,
                 addr,
-                binprm_inode,
-                Mode::Denylist,
-            ));
-        }
+                port,
+            ),
+            0,
+        );
     }
 
-    if let Some(addrs) = unsafe { DENIED_SOCKET_CONNECT_V4.get(&INODE_WILDCARD) } {
-        if addrs.all() {
-            return Ok(check_conditions_and_alert_v4(
-                &ctx,
-                &ALLOWED_SOCKET_CONNECT_V4,
-                addr,
-                binprm_inode,
-                Mode::Allowlist,
-            ));
-        }
-    }
-
-    Ok(Action::Allow)
+    Ok(action)
 }
 
 #[inline(always)]
-fn socket_connect_v6(ctx: LsmContext, sockaddr: *const sockaddr) -> Result<Action, c_long> {
+fn socket_connect_v6(
+    ctx: LsmContext,
+    sock: *const socket,
+    sockaddr: *const sockaddr,
+) -> Result<Action, c_long> {
     let sockaddr_in6: *const sockaddr_in6 = sockaddr as *const sockaddr_in6;
+    let port = u16::from_be(unsafe { sockaddr_in6_sin6_port(sockaddr_in6) });
+    let protocol = socket_protocol(sock);
 
     let sockaddr_in6: sockaddr_in6 = unsafe { bpf_probe_read_kernel(sockaddr_in6)? };
     let addr: [u8; 16] = [0; 16];
     unsafe { sockaddr_in6_sin6_addr_in6_u_u6_addr8(&sockaddr_in6, &addr) };
 
-    let binprm_inode = current_binprm_inode()?;
+    let subject = current_subject()?;
 
-    if let Some(addrs) = unsafe { ALLOWED_SOCKET_CONNECT_V6.get(&INODE_WILDCARD) } {
-        if addrs.all() {
-            return Ok(check_conditions_and_alert_v6(
-                &ctx,
-                &DENIED_SOCKET_CONNECT_V6,
+    let action = match ipv4_mapped(&addr) {
+        Some(addr) => connect_action_v4(subject, protocol, addr, port),
+        None => connect_action(
+            &ALLOWED_SOCKET_CONNECT_V6,
+            &DENIED_SOCKET_CONNECT_V6,
+            &ALLOWED_SOCKET_CONNECT,
+            &DENIED_SOCKET_CONNECT,
+            &ALLOWED_SOCKET_CONNECT_ALL,
+            &DENIED_SOCKET_CONNECT_ALL,
+            subject,
+            AF_INET6,
+            protocol,
+            port,
+            |subject, protocol| {
+                Key::new(
+                    Ipv6Key::PREFIX_LEN,
+                    Ipv6Key::new(subject, protocol, addr, port),
+                )
+            },
+        )
+        .unwrap_or_else(|| default_action(HOOK_SOCKET_CONNECT, subject)),
+    };
+    if action.is_alerted() {
+        ALERT_SOCKET_CONNECT.output(
+            &ctx,
+            &alerts::SocketConnect::new_ipv6(
+                ctx.pid(),
+                action.is_enforced(),
+                subject,
+                protocol,
                 addr,
-                binprm_inode,
-                Mode::Denylist,
-            ));
-        }
+                port,
+            ),
+            0,
+        );
     }
 
-    if let Some(addrs) = unsafe { DENIED_SOCKET_CONNECT_V6.get(&INODE_WILDCARD) } {
-        if addrs.all() {
-            return Ok(check_conditions_and_alert_v6(
-                &ctx,
-                &ALLOWED_SOCKET_CONNECT_V6,
-                addr,
-                binprm_inode,
-                Mode::Allowlist,
-            ));
-        }
-    }
-
-    Ok(Action::Allow)
+    Ok(action)
 }
 
 #[inline(always)]
-fn check_conditions_and_alert_v4(
-    ctx: &LsmContext,
-    map: &HashMap<u64, Ipv4Addrs>,
-    addr: u32,
-    binprm_inode: u64,
-    mode: Mode,
-) -> Action {
-    match check_conditions(map, addr, binprm_inode, mode) {
-        Action::Deny => {
-            ALERT_SOCKET_CONNECT.output(
-                ctx,
-                &alerts::SocketConnect::new_ipv4(ctx.pid(), binprm_inode, addr),
-                0,
-            );
-            Action::Deny
-        }
-        action => action,
-    }
+fn connect_action_v4(subject: Subject, protocol: u8, addr: [u8; 4], port: u16) -> Action {
+    connect_action(
+        &ALLOWED_SOCKET_CONNECT_V4,
+        &DENIED_SOCKET_CONNECT_V4,
+        &ALLOWED_SOCKET_CONNECT,
+        &DENIED_SOCKET_CONNECT,
+        &ALLOWED_SOCKET_CONNECT_ALL,
+        &DENIED_SOCKET_CONNECT_ALL,
+        subject,
+        AF_INET,
+        protocol,
+        port,
+        |subject, protocol| {
+            Key::new(
+                Ipv4Key::PREFIX_LEN,
+                Ipv4Key::new(subject, protocol, addr, port),
+            )
+        },
+    )
+    .unwrap_or_else(|| default_action(HOOK_SOCKET_CONNECT, subject))
 }
 
+/// Returns the IP protocol number of the socket. Protocol numbers above 255
+/// (only MPTCP, which is stored as TCP) are truncated.
 #[inline(always)]
-fn check_conditions_and_alert_v6(
-    ctx: &LsmContext,
-    map: &HashMap<u64, Ipv6Addrs>,
-    addr: [u8; 16],
-    binprm_inode: u64,
-    mode: Mode,
-) -> Action {
-    match check_conditions(map, addr, binprm_inode, mode) {
-        Action::Deny => {
-            ALERT_SOCKET_CONNECT.output(
-                ctx,
-                &alerts::SocketConnect::new_ipv6(ctx.pid(), binprm_inode, addr),
-                0,
-            );
-            Action::Deny
-        }
-        action => action,
-    }
+fn socket_protocol(sock: *const socket) -> u8 {
+    unsafe { socket_sk_protocol(sock) as u8 }
 }
 
+/// Returns the action of the most specific subject with a rule matching the
+/// connection, or `None` if there is no such subject. `allowed` and `denied`
+/// are the tries of the address family, `allowed_ports` and `denied_ports`
+/// the ones with rules for ports on all addresses, `allowed_all` and
+/// `denied_all` the maps with rules for all addresses of a family. `addr_key`
+/// returns the key of the address and the port for the given subject and
+/// protocol.
+#[allow(clippy::too_many_arguments)]
 #[inline(always)]
-fn check_conditions<T, U, const V: usize>(
-    map: &HashMap<u64, T>,
-    addr: U,
-    binprm_inode: u64,
-    mode: Mode,
-) -> Action
+pub(crate) fn connect_action<K, F>(
+    allowed: &LpmTrie<K, PrefixValue>,
+    denied: &LpmTrie<K, PrefixValue>,
+    allowed_ports: &LpmTrie<ProtocolPortKey, PrefixValue>,
+    denied_ports: &LpmTrie<ProtocolPortKey, PrefixValue>,
+    allowed_all: &HashMap<FamilyKey, u8>,
+    denied_all: &HashMap<FamilyKey, u8>,
+    subject: Subject,
+    family: u16,
+    protocol: u8,
+    port: u16,
+    addr_key: F,
+) -> Option<Action>
 where
-    T: IpAddrs<U, V>,
-    U: Copy + PartialEq,
+    F: Fn(Subject, u8) -> Key<K>,
 {
-    if let Some(addrs) = unsafe { map.get(&INODE_WILDCARD) } {
-        if let Some(action) = check_addresses(addrs, addr, &mode) {
-            return action;
+    for subject in subject.candidates() {
+        let addr_match = longer(
+            trie_match(allowed, denied, &addr_key(subject, protocol)),
+            trie_match(allowed, denied, &addr_key(subject, PROTOCOL_WILDCARD)),
+        );
+        if let Some((_, action)) = addr_match {
+            return Some(action);
         }
-    }
 
-    if let Some(addrs) = unsafe { map.get(&binprm_inode) } {
-        if let Some(action) = check_addresses(addrs, addr, &mode) {
-            return action;
+        let port_key = |protocol| {
+            Key::new(
+                ProtocolPortKey::PREFIX_LEN,
+                ProtocolPortKey::new(subject, protocol, port),
+            )
+        };
+        let port_match = longer(
+            trie_match(allowed_ports, denied_ports, &port_key(protocol)),
+            trie_match(allowed_ports, denied_ports, &port_key(PROTOCOL_WILDCARD)),
+        );
+        if let Some((_, action)) = port_match {
+            return Some(action);
         }
-    }
 
-    match mode {
-        Mode::Allowlist => Action::Deny,
-        Mode::Denylist => Action::Allow,
+        // Rules for all addresses of the family apply only if there is no
+        // rule for the network or the port.
+        let all_key = |protocol| FamilyKey::new(subject, family, protocol);
+        let all_action = family_action(allowed_all, denied_all, &all_key(protocol))
+            .or_else(|| family_action(allowed_all, denied_all, &all_key(PROTOCOL_WILDCARD)));
+        if all_action.is_some() {
+            return all_action;
+        }
     }
+    None
 }
 
+/// Returns the action of the rule for all addresses with the given key.
+/// Denying rules win over allowing ones.
 #[inline(always)]
-fn check_addresses<T, U, const V: usize>(addrs: &T, addr: U, mode: &Mode) -> Option<Action>
-where
-    T: IpAddrs<U, V>,
-    U: Copy + PartialEq,
-{
-    if addrs.all() {
-        return Some(match mode {
-            Mode::Allowlist => Action::Allow,
-            Mode::Denylist => Action::Deny,
-        });
+fn family_action(
+    allowed: &HashMap<FamilyKey, u8>,
+    denied: &HashMap<FamilyKey, u8>,
+    key: &FamilyKey,
+) -> Option<Action> {
+    if let Some(flags) = unsafe { denied.get(key) } {
+        return Some(Action::deny(*flags));
     }
+    unsafe { allowed.get(key) }.map(|_| Action::Allow)
+}
 
-    if addrs.addrs()[..V].contains(&addr) {
-        return Some(match mode {
-            Mode::Allowlist => Action::Allow,
-            Mode::Denylist => Action::Deny,
-        });
+/// Returns the match with the longer prefix, preferring `specific` (the
+/// match for the protocol of the socket) over `any` on ties.
+#[inline(always)]
+fn longer(specific: Option<(u8, Action)>, any: Option<(u8, Action)>) -> Option<(u8, Action)> {
+    match (specific, any) {
+        (Some((specific_len, _)), Some(any)) if any.0 > specific_len => Some(any),
+        (Some(specific), _) => Some(specific),
+        (None, any) => any,
     }
-
-    None
 }
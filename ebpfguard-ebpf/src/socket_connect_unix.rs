@@ -0,0 +1,135 @@
+use aya_bpf::{
+    cty::{c_int, c_long},
+    helpers::bpf_probe_read_kernel_buf,
+    programs::LsmContext,
+    BpfContext,
+};
+use ebpfguard_common::{
+    alerts,
+    consts::{AF_UNIX, HOOK_SOCKET_CONNECT_UNIX, UNIX_PATH_MAX, UNIX_PATH_WILDCARD},
+    policy::UnixPathKey,
+    subject::Subject,
+};
+
+use crate::{
+    binprm::current_subject,
+    default_action,
+    maps::{ALERT_SOCKET_CONNECT_UNIX, ALLOWED_SOCKET_CONNECT_UNIX, DENIED_SOCKET_CONNECT_UNIX},
+    sockaddr_sa_family, sockaddr_un_sun_path,
+    vmlinux::{sockaddr, sockaddr_un},
+    Action,
+};
+
+/// Offset of `sun_path` in `struct sockaddr_un`.
+const SUN_PATH_OFFSET: usize = 2;
+
+/// Inspects the context of `socket_connect` LSM hook for Unix domain sockets
+/// and decides whether to allow or deny the operation based on the state of
+/// the `ALLOWED_SOCKET_CONNECT_UNIX` and `DENIED_SOCKET_CONNECT_UNIX` maps.
+/// Connections of other address families are allowed, they are inspected by
+/// [`socket_connect`](crate::socket_connect::socket_connect).
+///
+/// Sockets are matched by the path given to `connect`, both in the
+/// filesystem and in the abstract namespace. Relative paths and symlinks are
+/// not resolved. Subjects are checked from the most specific one. For every
+/// subject, the entry of the path wins over the entry for all sockets. If no
+/// subject has a matching entry, the default action of the hook applies.
+///
+/// If denied, the operation is logged to the `ALERT_SOCKET_CONNECT_UNIX` map.
+/// Operations denied by policies in audit mode are logged, but allowed.
+///
+/// # Example
+///
+/// ```rust
+/// use aya_bpf::{macros::lsm, programs::LsmContext};
+/// use ebpfguard_ebpf::socket_connect_unix;
+///
+/// #[lsm(name = "my_program")]
+/// pub fn my_program(ctx: LsmContext) -> i32 {
+///     match socket_connect_unix::socket_connect_unix(ctx) {
+///         Ok(ret) => ret.into(),
+///         Err(_) => 0,
+///     }
+/// }
+/// ```
+pub fn socket_connect_unix(ctx: LsmContext) -> Result<Action, c_long> {
+    let sockaddr: *const sockaddr = unsafe { ctx.arg(1) };
+    let addrlen: c_int = unsafe { ctx.arg(2) };
+
+    if unsafe { sockaddr_sa_family(sockaddr) } != AF_UNIX {
+        return Ok(Action::Allow);
+    }
+
+    let subject = current_subject()?;
+
+    let mut path = UNIX_PATH_WILDCARD;
+    unsafe {
+        let sun_path = sockaddr_un_sun_path(sockaddr as *const sockaddr_un);
+        bpf_probe_read_kernel_buf(sun_path as *const u8, &mut path)?;
+    }
+    trim_path(&mut path, addrlen);
+
+    let action = path_action(subject, &path)
+        .unwrap_or_else(|| default_action(HOOK_SOCKET_CONNECT_UNIX, subject));
+
+    if action.is_alerted() {
+        ALERT_SOCKET_CONNECT_UNIX.output(
+            &ctx,
+            &alerts::SocketConnectUnix::new(ctx.pid(), action.is_enforced(), subject, path),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+/// Zeroes the bytes of `sun_path` which are not part of the address: the
+/// ones beyond `addrlen` and, for filesystem paths, the ones after the
+/// terminating zero byte. Names in the abstract namespace may contain zero
+/// bytes.
+#[inline(always)]
+fn trim_path(path: &mut [u8; UNIX_PATH_MAX], addrlen: c_int) {
+    let is_abstract = path[0] == 0;
+    let mut len = (addrlen.max(0) as usize)
+        .saturating_sub(SUN_PATH_OFFSET)
+        .min(UNIX_PATH_MAX);
+    for (i, byte) in path.iter_mut().enumerate() {
+        if i < len && !is_abstract && *byte == 0 {
+            len = i;
+        }
+        if i >= len {
+            *byte = 0;
+        }
+    }
+}
+
+/// Returns the action of the most specific subject matching the given one
+/// which has an entry for the path (or for all sockets), or `None` if there
+/// is no such subject.
+#[inline(always)]
+fn path_action(subject: Subject, path: &[u8; UNIX_PATH_MAX]) -> Option<Action> {
+    let mut key = UnixPathKey::new(subject, *path);
+    for subject in subject.candidates() {
+        key.subject = subject;
+        key.path = *path;
+        if let Some(action) = entry_action(&key) {
+            return Some(action);
+        }
+        key.path = UNIX_PATH_WILDCARD;
+        if let Some(action) = entry_action(&key) {
+            return Some(action);
+        }
+    }
+    None
+}
+
+#[inline(always)]
+fn entry_action(key: &UnixPathKey) -> Option<Action> {
+    if let Some(flags) = unsafe { DENIED_SOCKET_CONNECT_UNIX.get(key) } {
+        return Some(Action::deny(*flags));
+    }
+    if unsafe { ALLOWED_SOCKET_CONNECT_UNIX.get(key) }.is_some() {
+        return Some(Action::Allow);
+    }
+    None
+}
@@ -0,0 +1,120 @@
+use aya_bpf::{cty::c_long, programs::LsmContext, BpfContext};
+use ebpfguard_common::{
+    alerts,
+    consts::{
+        HOOK_SOCKET_CREATE, SOCKET_FAMILY_WILDCARD, SOCKET_PROTOCOL_WILDCARD, SOCKET_TYPE_WILDCARD,
+    },
+    policy::SocketKey,
+    subject::Subject,
+};
+
+use crate::{
+    binprm::current_subject,
+    default_action,
+    maps::{ALERT_SOCKET_CREATE, ALLOWED_SOCKET_CREATE, DENIED_SOCKET_CREATE},
+    Action,
+};
+
+/// Inspects the context of `socket_create` LSM hook and decides whether to
+/// allow or deny the operation based on the state of the
+/// `ALLOWED_SOCKET_CREATE` and `DENIED_SOCKET_CREATE` maps. Sockets created
+/// by the kernel are always allowed.
+///
+/// Sockets are matched by the family, the type and the protocol given to
+/// `socket`. Subjects are checked from the most specific one. For every
+/// subject, the entry with all three wins over the entries for all
+/// protocols, then for all types, then for all sockets of the family and
+/// finally for all sockets. If no subject has a matching entry, the default
+/// action of the hook applies.
+///
+/// If denied, the operation is logged to the `ALERT_SOCKET_CREATE` map.
+/// Operations denied by policies in audit mode are logged, but allowed.
+///
+/// # Example
+///
+/// ```rust
+/// use aya_bpf::{macros::lsm, programs::LsmContext};
+/// use ebpfguard_ebpf::socket_create;
+///
+/// #[lsm(name = "my_program")]
+/// pub fn my_program(ctx: LsmContext) -> i32 {
+///     match socket_create::socket_create(ctx) {
+///         Ok(ret) => ret.into(),
+///         Err(_) => 0,
+///     }
+/// }
+/// ```
+pub fn socket_create(ctx: LsmContext) -> Result<Action, c_long> {
+    let family: u32 = unsafe { ctx.arg(0) };
+    let socket_type: u32 = unsafe { ctx.arg(1) };
+    let protocol: u32 = unsafe { ctx.arg(2) };
+    let kern: u32 = unsafe { ctx.arg(3) };
+
+    if kern != 0 {
+        return Ok(Action::Allow);
+    }
+
+    let subject = current_subject()?;
+
+    let action = socket_action(subject, family, socket_type, protocol)
+        .unwrap_or_else(|| default_action(HOOK_SOCKET_CREATE, subject));
+
+    if action.is_alerted() {
+        ALERT_SOCKET_CREATE.output(
+            &ctx,
+            &alerts::SocketCreate::new(
+                ctx.pid(),
+                action.is_enforced(),
+                subject,
+                family,
+                socket_type,
+                protocol,
+            ),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+/// Returns the action of the most specific subject matching the given one
+/// which has an entry for the socket (or for a wildcard covering it), or
+/// `None` if there is no such subject.
+#[inline(always)]
+fn socket_action(subject: Subject, family: u32, socket_type: u32, protocol: u32) -> Option<Action> {
+    let patterns = [
+        (family, socket_type, protocol),
+        (family, socket_type, SOCKET_PROTOCOL_WILDCARD),
+        (family, SOCKET_TYPE_WILDCARD, protocol),
+        (family, SOCKET_TYPE_WILDCARD, SOCKET_PROTOCOL_WILDCARD),
+        (
+            SOCKET_FAMILY_WILDCARD,
+            SOCKET_TYPE_WILDCARD,
+            SOCKET_PROTOCOL_WILDCARD,
+        ),
+    ];
+    let mut key = SocketKey::new(subject, family, socket_type, protocol);
+    for subject in subject.candidates() {
+        key.subject = subject;
+        for (family, socket_type, protocol) in patterns {
+            key.family = family;
+            key.socket_type = socket_type;
+            key.protocol = protocol;
+            if let Some(action) = entry_action(&key) {
+                return Some(action);
+            }
+        }
+    }
+    None
+}
+
+#[inline(always)]
+fn entry_action(key: &SocketKey) -> Option<Action> {
+    if let Some(flags) = unsafe { DENIED_SOCKET_CREATE.get(key) } {
+        return Some(Action::deny(*flags));
+    }
+    if unsafe { ALLOWED_SOCKET_CREATE.get(key) }.is_some() {
+        return Some(Action::Allow);
+    }
+    None
+}
@@ -0,0 +1,168 @@
+use aya_bpf::{
+    cty::c_long,
+    maps::{lpm_trie::Key, LpmTrie},
+    programs::LsmContext,
+    BpfContext,
+};
+use ebpfguard_common::{
+    alerts,
+    consts::{AF_INET, AF_INET6, HOOK_SOCKET_LISTEN},
+    policy::{Ipv4PortKey, Ipv6PortKey, PortKey, PrefixValue},
+    subject::Subject,
+};
+
+use crate::{
+    binprm::current_subject,
+    default_action,
+    maps::{
+        ALERT_SOCKET_LISTEN, ALLOWED_SOCKET_LISTEN, ALLOWED_SOCKET_LISTEN_V4,
+        ALLOWED_SOCKET_LISTEN_V6, DENIED_SOCKET_LISTEN, DENIED_SOCKET_LISTEN_V4,
+        DENIED_SOCKET_LISTEN_V6,
+    },
+    socket_bind::port_action,
+    socket_sk_family, socket_sk_num, socket_sk_rcv_saddr, socket_sk_v6_rcv_saddr,
+    vmlinux::socket,
+    Action,
+};
+
+/// Number of bits of the port in the keys.
+const PORT_PREFIX_LEN: u32 = 16;
+
+/// Inspects the context of `socket_listen` LSM hook and decides whether to
+/// allow or deny the listen operation based on the state of the
+/// `ALLOWED_SOCKET_LISTEN` and `DENIED_SOCKET_LISTEN` maps, with rules for
+/// all addresses, and the `ALLOWED_SOCKET_LISTEN_V4`/`ALLOWED_SOCKET_LISTEN_V6`
+/// and `DENIED_SOCKET_LISTEN_V4`/`DENIED_SOCKET_LISTEN_V6` maps, with rules
+/// for the given addresses.
+///
+/// The socket is matched by its local address and port, the maps are
+/// organized like the ones of [`socket_bind`](crate::socket_bind::socket_bind).
+/// A socket which isn't bound yet listens on a port picked by the kernel, it
+/// is matched (on the unspecified address) only by rules for all ports.
+/// Sockets of other address families are allowed.
+///
+/// If denied, the operation is logged to the `ALERT_SOCKET_LISTEN` map.
+/// Operations denied by policies in audit mode are logged, but allowed.
+///
+/// # Example
+///
+/// ```rust
+/// use aya_bpf::{macros::lsm, programs::LsmContext};
+/// use ebpfguard_ebpf::socket_listen;
+///
+/// #[lsm(name = "my_program")]
+/// pub fn my_program(ctx: LsmContext) -> i32 {
+///     match socket_listen::socket_listen(ctx) {
+///         Ok(ret) => ret.into(),
+///         Err(_) => 0,
+///     }
+/// }
+/// ```
+#[inline(always)]
+pub fn socket_listen(ctx: LsmContext) -> Result<Action, c_long> {
+    let sock: *const socket = unsafe { ctx.arg(0) };
+
+    match unsafe { socket_sk_family(sock) } {
+        AF_INET => socket_listen_v4(ctx, sock),
+        AF_INET6 => socket_listen_v6(ctx, sock),
+        _ => Ok(Action::Allow),
+    }
+}
+
+#[inline(always)]
+fn socket_listen_v4(ctx: LsmContext, sock: *const socket) -> Result<Action, c_long> {
+    let port = unsafe { socket_sk_num(sock) };
+    let addr = u32::from_be(unsafe { socket_sk_rcv_saddr(sock) });
+    let subject = current_subject()?;
+
+    let addr_len = port_prefix_len(Ipv4PortKey::PREFIX_LEN, port);
+    let keys = subject.candidates().map(|subject| {
+        Key::new(
+            addr_len,
+            Ipv4PortKey::new(subject, addr.to_be_bytes(), port),
+        )
+    });
+
+    let action = listen_action(
+        &ALLOWED_SOCKET_LISTEN_V4,
+        &DENIED_SOCKET_LISTEN_V4,
+        &keys,
+        subject,
+        port,
+    );
+    if action.is_alerted() {
+        ALERT_SOCKET_LISTEN.output(
+            &ctx,
+            &alerts::SocketListen::new_ipv4(ctx.pid(), action.is_enforced(), subject, port, addr),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+#[inline(always)]
+fn socket_listen_v6(ctx: LsmContext, sock: *const socket) -> Result<Action, c_long> {
+    let port = unsafe { socket_sk_num(sock) };
+    let addr: [u8; 16] = [0; 16];
+    unsafe { socket_sk_v6_rcv_saddr(sock, &addr) };
+    let subject = current_subject()?;
+
+    let addr_len = port_prefix_len(Ipv6PortKey::PREFIX_LEN, port);
+    let keys = subject
+        .candidates()
+        .map(|subject| Key::new(addr_len, Ipv6PortKey::new(subject, addr, port)));
+
+    let action = listen_action(
+        &ALLOWED_SOCKET_LISTEN_V6,
+        &DENIED_SOCKET_LISTEN_V6,
+        &keys,
+        subject,
+        port,
+    );
+    if action.is_alerted() {
+        ALERT_SOCKET_LISTEN.output(
+            &ctx,
+            &alerts::SocketListen::new_ipv6(ctx.pid(), action.is_enforced(), subject, port, addr),
+            0,
+        );
+    }
+
+    Ok(action)
+}
+
+/// Returns the prefix length of a lookup key. Sockets without a port are
+/// looked up without it, so they match only rules for all ports.
+#[inline(always)]
+fn port_prefix_len(prefix_len: u32, port: u16) -> u32 {
+    if port == 0 {
+        prefix_len - PORT_PREFIX_LEN
+    } else {
+        prefix_len
+    }
+}
+
+/// Returns the action of the most specific subject with a rule matching the
+/// local address (`addr_keys`, for every candidate subject) or the port.
+#[inline(always)]
+fn listen_action<K>(
+    allowed: &LpmTrie<K, PrefixValue>,
+    denied: &LpmTrie<K, PrefixValue>,
+    addr_keys: &[Key<K>; Subject::CANDIDATES],
+    subject: Subject,
+    port: u16,
+) -> Action {
+    let port_len = port_prefix_len(PortKey::PREFIX_LEN, port);
+    let port_keys = subject
+        .candidates()
+        .map(|subject| Key::new(port_len, PortKey::new(subject, port)));
+    port_action(
+        allowed,
+        denied,
+        addr_keys,
+        &ALLOWED_SOCKET_LISTEN,
+        &DENIED_SOCKET_LISTEN,
+        &port_keys,
+    )
+    .unwrap_or_else(|| default_action(HOOK_SOCKET_LISTEN, subject))
+}
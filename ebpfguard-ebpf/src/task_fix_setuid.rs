@@ -1,18 +1,21 @@
 use aya_bpf::{cty::c_long, programs::LsmContext, BpfContext};
-use ebpfguard_common::{alerts, consts::INODE_WILDCARD};
+use ebpfguard_common::{alerts, consts::HOOK_TASK_FIX_SETUID};
 
 use crate::{
-    binprm::current_binprm_inode,
-    cred_gid_val, cred_uid_val,
+    binprm::current_subject,
+    cred_gid_val, cred_uid_val, default_action,
     maps::{ALERT_TASK_FIX_SETUID, ALLOWED_TASK_FIX_SETUID, DENIED_TASK_FIX_SETUID},
+    subject_action,
     vmlinux::cred,
 };
 
 /// Inspects the context of `task_fix_setuid` LSM hook and decides whether to
 /// allow or deny the operation based on the state of the `ALLOWED_SETUID`
-/// and `DENIED_SETUID` maps.
+/// and `DENIED_SETUID` maps. The policy of the most specific subject decides,
+/// the default action of the hook applies if no subject has a policy.
 ///
-/// If denied, the operation is logged to the `ALERT_SETUID` map.
+/// If denied, the operation is logged to the `ALERT_SETUID` map. Operations
+/// denied by policies in audit mode are logged, but allowed.
 ///
 /// # Example
 ///
@@ -36,36 +39,18 @@ pub fn task_fix_setuid(ctx: LsmContext) -> Result<i32, c_long> {
     let new_uid = unsafe { cred_uid_val(new) };
     let new_gid = unsafe { cred_gid_val(new) };
 
-    let binprm_inode = current_binprm_inode()?;
+    let subject = current_subject()?;
 
-    if unsafe { ALLOWED_TASK_FIX_SETUID.get(&INODE_WILDCARD) }.is_some() {
-        if unsafe { DENIED_TASK_FIX_SETUID.get(&binprm_inode).is_some() } {
-            ALERT_TASK_FIX_SETUID.output(
-                &ctx,
-                &alerts::TaskFixSetuid::new(
-                    ctx.pid(),
-                    binprm_inode,
-                    old_uid,
-                    old_gid,
-                    new_uid,
-                    new_gid,
-                ),
-                0,
-            );
-            return Ok(-1);
-        }
-        return Ok(0);
-    }
+    let action = subject_action(&ALLOWED_TASK_FIX_SETUID, &DENIED_TASK_FIX_SETUID, subject)
+        .unwrap_or_else(|| default_action(HOOK_TASK_FIX_SETUID, subject));
 
-    if unsafe { DENIED_TASK_FIX_SETUID.get(&INODE_WILDCARD) }.is_some() {
-        if unsafe { ALLOWED_TASK_FIX_SETUID.get(&binprm_inode).is_some() } {
-            return Ok(0);
-        }
+    if action.is_alerted() {
         ALERT_TASK_FIX_SETUID.output(
             &ctx,
             &alerts::TaskFixSetuid::new(
                 ctx.pid(),
-                binprm_inode,
+                action.is_enforced(),
+                subject,
                 old_uid,
                 old_gid,
                 new_uid,
@@ -73,8 +58,7 @@ pub fn task_fix_setuid(ctx: LsmContext) -> Result<i32, c_long> {
             ),
             0,
         );
-        return Ok(-1);
     }
 
-    Ok(0)
+    Ok(action.into())
 }
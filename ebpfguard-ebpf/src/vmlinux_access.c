@@ -30,6 +30,21 @@ uint64_t file_inode(struct file *target)
 	return __builtin_preserve_access_index(target->f_path.dentry->d_inode->i_ino);
 }
 
+dev_t file_s_dev(struct file *target)
+{
+	return __builtin_preserve_access_index(target->f_path.dentry->d_inode->i_sb->s_dev);
+}
+
+fmode_t file_f_mode(struct file *target)
+{
+	return __builtin_preserve_access_index(target->f_mode);
+}
+
+struct path* file_f_path(struct file *target)
+{
+	return __builtin_preserve_access_index(&target->f_path);
+}
+
 struct dentry* file_dentry(struct file *target)
 {
 	return __builtin_preserve_access_index(target->f_path.dentry->d_parent);
@@ -40,16 +55,36 @@ uint64_t dentry_i_ino(struct dentry *target)
 	return __builtin_preserve_access_index(target->d_inode->i_ino);
 }
 
+dev_t dentry_s_dev(struct dentry *target)
+{
+	return __builtin_preserve_access_index(target->d_inode->i_sb->s_dev);
+}
+
 uint64_t * inode_i_ino(struct inode *inode)
 {
 	return __builtin_preserve_access_index(&inode->i_ino);
 }
 
+struct super_block ** inode_i_sb(struct inode *inode)
+{
+	return __builtin_preserve_access_index(&inode->i_sb);
+}
+
+dev_t * super_block_s_dev(struct super_block *sb)
+{
+	return __builtin_preserve_access_index(&sb->s_dev);
+}
+
 int32_t linux_binprm_argc(struct linux_binprm *target)
 {
 	return __builtin_preserve_access_index(target->argc);
 }
 
+struct file * linux_binprm_file(struct linux_binprm *target)
+{
+	return __builtin_preserve_access_index(target->file);
+}
+
 int16_t sockaddr_sa_family(struct sockaddr *target)
 {
 	return __builtin_preserve_access_index(target->sa_family);
@@ -81,3 +116,109 @@ uint16_t sockaddr_in_sin_port(struct sockaddr_in *target)
 {
 	return __builtin_preserve_access_index(target->sin_port);
 }
+
+uint16_t sockaddr_in6_sin6_port(struct sockaddr_in6 *target)
+{
+	return __builtin_preserve_access_index(target->sin6_port);
+}
+
+uint16_t socket_sk_protocol(struct socket *target)
+{
+	return __builtin_preserve_access_index(target->sk->sk_protocol);
+}
+
+uint16_t socket_sk_family(struct socket *target)
+{
+	return __builtin_preserve_access_index(target->sk->__sk_common.skc_family);
+}
+
+uint16_t socket_sk_num(struct socket *target)
+{
+	return __builtin_preserve_access_index(target->sk->__sk_common.skc_num);
+}
+
+uint32_t socket_sk_rcv_saddr(struct socket *target)
+{
+	return __builtin_preserve_access_index(target->sk->__sk_common.skc_rcv_saddr);
+}
+
+/* Reads the `skc_ipv6only` bitfield the same way as `BPF_CORE_READ_BITFIELD`. */
+uint8_t socket_sk_ipv6only(struct socket *target)
+{
+	struct sock_common *common = __builtin_preserve_access_index(&target->sk->__sk_common);
+	const void *p = (const void *)common + __builtin_preserve_field_info(common->skc_ipv6only, 0 /* BPF_FIELD_BYTE_OFFSET */);
+	unsigned long long val;
+
+	switch (__builtin_preserve_field_info(common->skc_ipv6only, 1 /* BPF_FIELD_BYTE_SIZE */)) {
+	case 1: val = *(const uint8_t *)p; break;
+	case 2: val = *(const uint16_t *)p; break;
+	case 4: val = *(const uint32_t *)p; break;
+	default: val = *(const uint64_t *)p; break;
+	}
+	val <<= __builtin_preserve_field_info(common->skc_ipv6only, 4 /* BPF_FIELD_LSHIFT_U64 */);
+	val >>= __builtin_preserve_field_info(common->skc_ipv6only, 5 /* BPF_FIELD_RSHIFT_U64 */);
+	return val;
+}
+
+void socket_sk_v6_rcv_saddr(struct socket *target, uint8_t* res)
+{
+	for (int i=0; i < 16; ++i) {
+		res[i] = __builtin_preserve_access_index(target->sk->__sk_common.skc_v6_rcv_saddr.in6_u.u6_addr8[i]);
+	}
+}
+
+struct sock * socket_sk(struct socket *target)
+{
+	return __builtin_preserve_access_index(target->sk);
+}
+
+uint16_t sock_sk_protocol(struct sock *target)
+{
+	return __builtin_preserve_access_index(target->sk_protocol);
+}
+
+uint64_t sock_cgroup_id(struct sock *target)
+{
+	struct cgroup *cgrp = __builtin_preserve_access_index(target->sk_cgrp_data.cgroup);
+	if (!cgrp)
+		return 0;
+	return __builtin_preserve_access_index(cgrp->kn->id);
+}
+
+const struct cred * sock_file_cred(struct sock *target)
+{
+	struct socket *sock = __builtin_preserve_access_index(target->sk_socket);
+	if (!sock)
+		return 0;
+	struct file *file = __builtin_preserve_access_index(sock->file);
+	if (!file)
+		return 0;
+	return __builtin_preserve_access_index(file->f_cred);
+}
+
+uint16_t request_sock_family(struct request_sock *target)
+{
+	return __builtin_preserve_access_index(target->__req_common.skc_family);
+}
+
+uint16_t request_sock_num(struct request_sock *target)
+{
+	return __builtin_preserve_access_index(target->__req_common.skc_num);
+}
+
+uint32_t request_sock_daddr(struct request_sock *target)
+{
+	return __builtin_preserve_access_index(target->__req_common.skc_daddr);
+}
+
+void request_sock_v6_daddr(struct request_sock *target, uint8_t* res)
+{
+	for (int i=0; i < 16; ++i) {
+		res[i] = __builtin_preserve_access_index(target->__req_common.skc_v6_daddr.in6_u.u6_addr8[i]);
+	}
+}
+
+char * sockaddr_un_sun_path(struct sockaddr_un *target)
+{
+	return __builtin_preserve_access_index(target->sun_path);
+}
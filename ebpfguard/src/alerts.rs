@@ -1,27 +1,52 @@
-use ebpfguard_common::alerts;
+use ebpfguard_common::{alerts, consts::AF_INET6};
 use serde::Serialize;
 use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     path::PathBuf,
 };
 
-use crate::policy::PolicySubject;
+use crate::{
+    policy::{Access, PolicySubject, SocketFamily, UnixPath},
+    InodeSubjectMap,
+};
 
 pub trait Alert: Serialize {}
 
+/// Conversion of alerts sent by the eBPF programs, resolving the inodes they
+/// carry to paths.
+pub trait ResolveAlert<E>: Sized {
+    fn resolve(alert: E, paths: &mut InodeSubjectMap) -> Self;
+}
+
 #[derive(Debug, Serialize)]
 pub struct BprmCheckSecurity {
     pub pid: u32,
+    /// Whether the operation was denied. Operations denied by policies in
+    /// audit mode are only alerted.
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
+    /// Path of the executed binary.
+    pub path: PathBuf,
 }
 
 impl Alert for BprmCheckSecurity {}
 
-impl From<alerts::BprmCheckSecurity> for BprmCheckSecurity {
-    fn from(alert: alerts::BprmCheckSecurity) -> Self {
+impl ResolveAlert<alerts::BprmCheckSecurity> for BprmCheckSecurity {
+    fn resolve(alert: alerts::BprmCheckSecurity, paths: &mut InodeSubjectMap) -> Self {
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
+            path: paths.resolve_object(alert.inode),
         }
     }
 }
@@ -29,18 +54,37 @@ impl From<alerts::BprmCheckSecurity> for BprmCheckSecurity {
 #[derive(Debug, Serialize)]
 pub struct FileOpen {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
     pub path: PathBuf,
+    /// Requested kinds of access.
+    pub access: Vec<Access>,
+    /// `true` if the file has more parent directories than the configured
+    /// depth (or its path couldn't be resolved for prefix policies) and the
+    /// access was decided without checking all policies which could apply.
+    pub truncated: bool,
 }
 
 impl Alert for FileOpen {}
 
-impl From<alerts::FileOpen> for FileOpen {
-    fn from(alert: alerts::FileOpen) -> Self {
+impl ResolveAlert<alerts::FileOpen> for FileOpen {
+    fn resolve(alert: alerts::FileOpen, paths: &mut InodeSubjectMap) -> Self {
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
-            path: PathBuf::from(alert.inode.to_string()),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
+            path: paths.resolve_object(alert.inode),
+            access: Access::from_flags(alert.access),
+            truncated: alert.truncated != 0,
         }
     }
 }
@@ -48,16 +92,27 @@ impl From<alerts::FileOpen> for FileOpen {
 #[derive(Debug, Serialize)]
 pub struct SbMount {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
 }
 
 impl Alert for SbMount {}
 
-impl From<alerts::SbMount> for SbMount {
-    fn from(alert: alerts::SbMount) -> Self {
+impl ResolveAlert<alerts::SbMount> for SbMount {
+    fn resolve(alert: alerts::SbMount, paths: &mut InodeSubjectMap) -> Self {
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
         }
     }
 }
@@ -65,16 +120,27 @@ impl From<alerts::SbMount> for SbMount {
 #[derive(Debug, Serialize)]
 pub struct SbRemount {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
 }
 
 impl Alert for SbRemount {}
 
-impl From<alerts::SbRemount> for SbRemount {
-    fn from(alert: alerts::SbRemount) -> Self {
+impl ResolveAlert<alerts::SbRemount> for SbRemount {
+    fn resolve(alert: alerts::SbRemount, paths: &mut InodeSubjectMap) -> Self {
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
         }
     }
 }
@@ -82,16 +148,27 @@ impl From<alerts::SbRemount> for SbRemount {
 #[derive(Debug, Serialize)]
 pub struct SbUmount {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
 }
 
 impl Alert for SbUmount {}
 
-impl From<alerts::SbUmount> for SbUmount {
-    fn from(alert: alerts::SbUmount) -> Self {
+impl ResolveAlert<alerts::SbUmount> for SbUmount {
+    fn resolve(alert: alerts::SbUmount, paths: &mut InodeSubjectMap) -> Self {
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
         }
     }
 }
@@ -99,18 +176,78 @@ impl From<alerts::SbUmount> for SbUmount {
 #[derive(Debug, Serialize)]
 pub struct SocketBind {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
     pub port: u16,
+    /// Address the socket was being bound to. Its variant is the address
+    /// family of the socket.
+    pub addr: IpAddr,
 }
 
 impl Alert for SocketBind {}
 
-impl From<alerts::SocketBind> for SocketBind {
-    fn from(alert: alerts::SocketBind) -> Self {
+impl ResolveAlert<alerts::SocketBind> for SocketBind {
+    fn resolve(alert: alerts::SocketBind, paths: &mut InodeSubjectMap) -> Self {
+        let addr = if alert.family == AF_INET6 {
+            IpAddr::V6(Ipv6Addr::from(alert.addr_v6))
+        } else {
+            IpAddr::V4(Ipv4Addr::from(alert.addr_v4))
+        };
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
             port: alert.port,
+            addr,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SocketListen {
+    pub pid: u32,
+    pub enforced: bool,
+    pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
+    /// Local port of the socket, 0 if it wasn't bound yet.
+    pub port: u16,
+    /// Local address of the socket. Its variant is the address
+    /// family of the socket.
+    pub addr: IpAddr,
+}
+
+impl Alert for SocketListen {}
+
+impl ResolveAlert<alerts::SocketListen> for SocketListen {
+    fn resolve(alert: alerts::SocketListen, paths: &mut InodeSubjectMap) -> Self {
+        let addr = if alert.family == AF_INET6 {
+            IpAddr::V6(Ipv6Addr::from(alert.addr_v6))
+        } else {
+            IpAddr::V4(Ipv4Addr::from(alert.addr_v4))
+        };
+        Self {
+            pid: alert.pid,
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
+            port: alert.port,
+            addr,
         }
     }
 }
@@ -118,14 +255,24 @@ impl From<alerts::SocketBind> for SocketBind {
 #[derive(Debug, Serialize)]
 pub struct SocketConnect {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
     pub addr: IpAddr,
+    pub port: u16,
+    /// IP protocol number of the socket (6 for TCP, 17 for UDP).
+    pub protocol: u8,
 }
 
 impl Alert for SocketConnect {}
 
-impl From<alerts::SocketConnect> for SocketConnect {
-    fn from(alert: alerts::SocketConnect) -> Self {
+impl ResolveAlert<alerts::SocketConnect> for SocketConnect {
+    fn resolve(alert: alerts::SocketConnect, paths: &mut InodeSubjectMap) -> Self {
         let addr = if alert.addr_v4 != 0 {
             IpAddr::V4(Ipv4Addr::from(alert.addr_v4))
         } else {
@@ -133,8 +280,127 @@ impl From<alerts::SocketConnect> for SocketConnect {
         };
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
             addr,
+            port: alert.port,
+            protocol: alert.protocol,
+        }
+    }
+}
+
+/// Incoming connection to a listening socket. The process is the one which
+/// started listening, its PID is 0 if it's not known.
+#[derive(Debug, Serialize)]
+pub struct InetConnRequest {
+    pub pid: u32,
+    pub enforced: bool,
+    pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
+    /// Address of the peer.
+    pub addr: IpAddr,
+    /// Local port of the socket.
+    pub port: u16,
+    /// IP protocol number of the socket (6 for TCP, 17 for UDP).
+    pub protocol: u8,
+}
+
+impl Alert for InetConnRequest {}
+
+impl ResolveAlert<alerts::InetConnRequest> for InetConnRequest {
+    fn resolve(alert: alerts::InetConnRequest, paths: &mut InodeSubjectMap) -> Self {
+        let addr = if alert.addr_v4 != 0 {
+            IpAddr::V4(Ipv4Addr::from(alert.addr_v4))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(alert.addr_v6))
+        };
+        Self {
+            pid: alert.pid,
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
+            addr,
+            port: alert.port,
+            protocol: alert.protocol,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SocketConnectUnix {
+    pub pid: u32,
+    pub enforced: bool,
+    pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
+    /// Path of the socket, as given to `connect`.
+    pub path: UnixPath,
+}
+
+impl Alert for SocketConnectUnix {}
+
+impl ResolveAlert<alerts::SocketConnectUnix> for SocketConnectUnix {
+    fn resolve(alert: alerts::SocketConnectUnix, paths: &mut InodeSubjectMap) -> Self {
+        Self {
+            pid: alert.pid,
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
+            path: UnixPath::from_sun_path(&alert.path),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SocketCreate {
+    pub pid: u32,
+    pub enforced: bool,
+    pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
+    /// Address family of the socket.
+    pub family: SocketFamily,
+    /// Type of the socket (`SOCK_*` number, without flags).
+    #[serde(rename = "type")]
+    pub socket_type: u32,
+    /// Protocol number given to `socket`, `0` for the default one.
+    pub protocol: u32,
+}
+
+impl Alert for SocketCreate {}
+
+impl ResolveAlert<alerts::SocketCreate> for SocketCreate {
+    fn resolve(alert: alerts::SocketCreate, paths: &mut InodeSubjectMap) -> Self {
+        Self {
+            pid: alert.pid,
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
+            family: SocketFamily::from_id(alert.family),
+            socket_type: alert.socket_type,
+            protocol: alert.protocol,
         }
     }
 }
@@ -142,7 +408,14 @@ impl From<alerts::SocketConnect> for SocketConnect {
 #[derive(Debug, Serialize)]
 pub struct TaskFixSetuid {
     pub pid: u32,
+    pub enforced: bool,
     pub subject: PolicySubject,
+    /// Path of the cgroup (v2) of the process.
+    pub cgroup: PathBuf,
+    /// Real user ID of the process.
+    pub uid: u32,
+    /// Real group ID of the process.
+    pub gid: u32,
     pub old_uid: u32,
     pub old_gid: u32,
     pub new_uid: u32,
@@ -151,11 +424,15 @@ pub struct TaskFixSetuid {
 
 impl Alert for TaskFixSetuid {}
 
-impl From<alerts::TaskFixSetuid> for TaskFixSetuid {
-    fn from(alert: alerts::TaskFixSetuid) -> Self {
+impl ResolveAlert<alerts::TaskFixSetuid> for TaskFixSetuid {
+    fn resolve(alert: alerts::TaskFixSetuid, paths: &mut InodeSubjectMap) -> Self {
         Self {
             pid: alert.pid,
-            subject: PolicySubject::Binary(PathBuf::from(alert.binprm_inode.to_string())),
+            enforced: alert.enforced != 0,
+            subject: paths.resolve_process(alert.subject.binary, alert.pid),
+            cgroup: paths.resolve_cgroup(alert.subject.cgroup),
+            uid: alert.subject.uid,
+            gid: alert.subject.gid,
             old_uid: alert.old_uid,
             old_gid: alert.old_gid,
             new_uid: alert.new_uid,
@@ -1,5 +1,11 @@
 use thiserror::Error;
 
+use std::path::PathBuf;
+
+use ipnet::IpNet;
+
+use crate::policy::{reader::Location, PolicySubject, PortRange};
+
 #[derive(Debug, Error)]
 pub enum EbpfguardError {
     #[error(
@@ -27,4 +33,104 @@ pub enum EbpfguardError {
 
     #[error("Failed to parse policies from YAML: {0}")]
     YAML(#[from] serde_yaml::Error),
+
+    #[error("Conflicting {hook} policies for {subject}: {object} is both allowed and denied")]
+    PolicyConflict {
+        hook: &'static str,
+        subject: PolicySubject,
+        object: String,
+    },
+
+    #[error("Conflicting {hook} policies for {subject}: enforce and audit policies can't be mixed for one subject")]
+    ModeConflict {
+        hook: &'static str,
+        subject: PolicySubject,
+    },
+
+    #[error("Invalid port range {0}")]
+    InvalidPortRange(String),
+
+    #[error("Invalid Unix socket path {0}")]
+    InvalidUnixPath(String),
+
+    #[error("Invalid path pattern {}", .0.display())]
+    InvalidPathPattern(PathBuf),
+
+    #[error("Invalid path prefix {}, it has to be an absolute path of at most {max} bytes", .path.display())]
+    InvalidPathPrefix { path: PathBuf, max: usize },
+
+    #[error("Invalid file_open settings, the depth has to be between 1 and {max}")]
+    InvalidDirDepth { max: u32 },
+
+    #[error(
+        "Port range {ports} can't be combined with network {network}, only with single addresses"
+    )]
+    UnsupportedPortRange { network: IpNet, ports: PortRange },
+
+    #[error("Unknown {kind} {name}")]
+    UnknownAccount { kind: &'static str, name: String },
+
+    #[error("{location}: {} does not exist", .path.display())]
+    PathNotFound { location: Location, path: PathBuf },
+
+    #[error("{location}: {hook} policies exceed the limit of {max} map entries")]
+    TooManyEntries {
+        location: Location,
+        hook: &'static str,
+        max: u32,
+    },
+
+    #[error("{location}: {object} is both allowed and denied for {subject} in {hook} policies")]
+    ContradictoryPolicy {
+        location: Location,
+        hook: &'static str,
+        subject: Box<PolicySubject>,
+        object: String,
+    },
+
+    #[error("{location}: {hook} policy for {subject} is already defined at {first}")]
+    DuplicateSubject {
+        location: Location,
+        first: Location,
+        hook: &'static str,
+        subject: Box<PolicySubject>,
+    },
+
+    #[error(
+        "{location}: {hook} policy for {subject} has a different mode than the one at {first}"
+    )]
+    MixedModes {
+        location: Location,
+        first: Location,
+        hook: &'static str,
+        subject: Box<PolicySubject>,
+    },
+
+    #[error("{location}: {hook} rule for {object} of {subject} is never evaluated, {reason}")]
+    UnreachableRule {
+        location: Location,
+        hook: &'static str,
+        subject: Box<PolicySubject>,
+        object: String,
+        reason: &'static str,
+    },
+
+    #[error("{location}: {hook} rule for {object} of {subject} is not supported, {reason}")]
+    UnsupportedRule {
+        location: Location,
+        hook: &'static str,
+        subject: Box<PolicySubject>,
+        object: String,
+        reason: &'static str,
+    },
+
+    #[error("Invalid entry in a pinned map: {0}")]
+    InvalidMapEntry(String),
+
+    #[error("Invalid policies:{}", display_errors(.0))]
+    InvalidPolicies(Vec<EbpfguardError>),
+}
+
+fn display_errors(errors: &[EbpfguardError]) -> String {
+    errors.iter().map(|e| format!("\n  {e}")).collect()
 }
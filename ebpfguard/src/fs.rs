@@ -1,8 +1,227 @@
-use std::{fs, os::unix::fs::MetadataExt, path::Path};
+use std::{
+    ffi::OsStr,
+    fs,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
 
-pub fn inode<P: AsRef<Path>>(path: P) -> Result<u64, std::io::Error> {
+use ebpfguard_common::inode::Inode;
+
+use crate::error::EbpfguardError;
+
+/// Mount table of the mount namespace of the current process.
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// Returns the inode of the given path, together with the device of its
+/// superblock in the encoding used by the kernel.
+///
+/// Filesystems without a block device of their own (major number 0) can
+/// report a different device in `st_dev` than the one of their superblock,
+/// which is seen by the eBPF programs, e.g. btrfs for every subvolume or
+/// overlayfs for files of its layers. For them, the device is taken from
+/// the mount containing the path in `/proc/self/mountinfo`, and resolving
+/// fails if it can't be found.
+pub fn inode<P: AsRef<Path>>(path: P) -> Result<Inode, std::io::Error> {
     let path = path.as_ref();
     let metadata = fs::metadata(path)?;
-    let inode = metadata.ino();
-    Ok(inode)
+    let dev = match kernel_dev(metadata.dev()) {
+        dev if dev >> 20 == 0 => {
+            let path = fs::canonicalize(path)?;
+            mount_dev(&fs::read_to_string(MOUNTINFO)?, &path).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("no mount found for {}", path.display()),
+                )
+            })?
+        }
+        dev => dev,
+    };
+    Ok(Inode::new(dev, metadata.ino()))
+}
+
+/// Returns the device, in the kernel encoding, of the mount in the given
+/// mount table which contains the absolute path. Of the mounts on the same
+/// mount point, the last one is on top.
+fn mount_dev(mountinfo: &str, path: &Path) -> Option<u64> {
+    let (mut found_len, mut found) = (0, None);
+    for line in mountinfo.lines() {
+        let mut fields = line.split(' ');
+        let (Some(dev), Some(mount_point)) = (fields.nth(2), fields.nth(1)) else {
+            continue;
+        };
+        let Some((major, minor)) = dev.split_once(':') else {
+            continue;
+        };
+        let (Ok(major), Ok(minor)) = (major.parse::<u64>(), minor.parse::<u64>()) else {
+            continue;
+        };
+        let mount_point = PathBuf::from(OsStr::from_bytes(&unescape(mount_point)));
+        let len = mount_point.components().count();
+        if path.starts_with(&mount_point) && len >= found_len {
+            (found_len, found) = (len, Some((major << 20) | minor));
+        }
+    }
+    found
+}
+
+/// Decodes the octal escapes (e.g. `\040` for a space) of a path in the
+/// mount table.
+fn unescape(path: &str) -> Vec<u8> {
+    let bytes = path.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|octal| u8::from_str_radix(std::str::from_utf8(octal).ok()?, 8).ok());
+        match escaped {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+/// Returns `true` if the path contains glob wildcards (`*`, `?` or `[`).
+pub fn is_pattern(path: &Path) -> bool {
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .any(|byte| matches!(byte, b'*' | b'?' | b'['))
+}
+
+/// Fails with [`EbpfguardError::InvalidPathPattern`] if the path is not a
+/// valid glob pattern.
+pub fn check_pattern(pattern: &Path) -> Result<(), EbpfguardError> {
+    if !is_pattern(pattern) {
+        return Ok(());
+    }
+    match pattern.to_str().map(glob::Pattern::new) {
+        Some(Ok(_)) => Ok(()),
+        _ => Err(EbpfguardError::InvalidPathPattern(pattern.to_owned())),
+    }
+}
+
+/// Returns existing paths matching the glob pattern. Paths without wildcards
+/// match only themselves.
+pub fn expand(pattern: &Path) -> Result<Vec<PathBuf>, EbpfguardError> {
+    if !is_pattern(pattern) {
+        return Ok(pattern
+            .symlink_metadata()
+            .map(|_| vec![pattern.to_owned()])
+            .unwrap_or_default());
+    }
+    let invalid = || EbpfguardError::InvalidPathPattern(pattern.to_owned());
+    let paths = glob::glob(pattern.to_str().ok_or_else(invalid)?).map_err(|_| invalid())?;
+    // Directories which can't be read while matching are skipped.
+    Ok(paths.flatten().collect())
+}
+
+/// Returns the directories in which creating, renaming or deleting an entry
+/// can change the paths matching the glob pattern. Of the leading components
+/// without wildcards, only the deepest existing directory is returned: once
+/// it's removed, its parent takes its place.
+pub fn pattern_dirs(pattern: &Path) -> Result<Vec<PathBuf>, EbpfguardError> {
+    let components = pattern.components().collect::<Vec<_>>();
+    let parents = components.len().saturating_sub(1);
+    let literal = components[..parents]
+        .iter()
+        .position(|component| is_pattern(Path::new(component)))
+        .unwrap_or(parents);
+
+    let mut dir = components[..literal].iter().collect::<PathBuf>();
+    while !dir.is_dir() {
+        if !dir.pop() {
+            return Ok(Vec::new());
+        }
+    }
+    let complete = dir.components().count() == literal;
+    let mut dirs = vec![dir];
+
+    if complete {
+        for len in literal + 1..=parents {
+            let prefix = components[..len].iter().collect::<PathBuf>();
+            dirs.extend(expand(&prefix)?.into_iter().filter(|path| path.is_dir()));
+        }
+    }
+    Ok(dirs)
+}
+
+/// Converts a device number from the userspace encoding (`st_dev`) to the
+/// kernel one (`s_dev`), which is seen by the eBPF programs.
+fn kernel_dev(dev: u64) -> u64 {
+    let major = ((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff);
+    let minor = ((dev >> 12) & 0xffff_ff00) | (dev & 0x0000_00ff);
+    (major << 20) | minor
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_kernel_dev() {
+        // 8:3
+        assert_eq!(kernel_dev(0x803), 0x80_0003);
+        // 259:65536
+        let inode = Inode::new(kernel_dev(0x1001_0300), 1);
+        assert_eq!((inode.major(), inode.minor()), (259, 65536));
+    }
+
+    #[test]
+    fn test_mount_dev() {
+        let mountinfo = "\
+22 1 0:21 / / rw,relatime shared:1 - btrfs /dev/sda2 rw,subvol=/@
+23 22 0:21 /@home /home rw,relatime shared:2 - btrfs /dev/sda2 rw,subvol=/@home
+24 22 0:45 / /var/lib/my\\040data rw - overlay overlay rw
+25 24 0:46 / /var/lib/my\\040data rw - overlay overlay rw
+26 22 8:3 / /boot rw - ext4 /dev/sda3 rw
+";
+        assert_eq!(mount_dev(mountinfo, Path::new("/home/user")), Some(21));
+        assert_eq!(mount_dev(mountinfo, Path::new("/homes")), Some(21));
+        assert_eq!(
+            mount_dev(mountinfo, Path::new("/var/lib/my data/file")),
+            Some(46)
+        );
+        assert_eq!(mount_dev(mountinfo, Path::new("/boot")), Some(0x80_0003));
+        assert_eq!(mount_dev("", Path::new("/")), None);
+    }
+
+    #[test]
+    fn test_expand() {
+        let dir = std::env::temp_dir().join(format!("ebpfguard-expand-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("a").join("1.key"), "").unwrap();
+        fs::write(dir.join("a").join("2.pem"), "").unwrap();
+        fs::write(dir.join("b").join("3.key"), "").unwrap();
+
+        assert_eq!(
+            expand(&dir.join("*").join("*.key")).unwrap(),
+            vec![dir.join("a").join("1.key"), dir.join("b").join("3.key")]
+        );
+        assert_eq!(expand(&dir.join("a")).unwrap(), vec![dir.join("a")]);
+        assert!(expand(&dir.join("c")).unwrap().is_empty());
+        assert!(expand(&dir.join("[")).is_err());
+
+        // Creating a directory matching `*` or renaming `c` changes the
+        // matches.
+        assert_eq!(
+            pattern_dirs(&dir.join("*").join("*.key")).unwrap(),
+            vec![dir.clone(), dir.join("a"), dir.join("b")]
+        );
+        assert_eq!(
+            pattern_dirs(&dir.join("c").join("d").join("*.key")).unwrap(),
+            vec![dir.clone()]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,25 +1,270 @@
 use aya::{
-    maps::{AsyncPerfEventArray, MapData},
+    maps::{Array, AsyncPerfEventArray, HashMap, MapData},
     programs::lsm::LsmLink,
 };
-use ebpfguard_common::alerts as ebpf_alerts;
+use ebpfguard_common::{
+    alerts as ebpf_alerts,
+    consts::{
+        BPRM_CHECK_SECURITY_ALLOW_TRUNCATED, DEFAULT_DIR_DEPTH, INODE_WILDCARD, MAX_DIR_DEPTH,
+        SUBJECT_DIRS,
+    },
+    inode::Inode,
+    policy as ebpf_policy,
+    subject::Subject,
+};
 use tokio::sync::mpsc::Receiver;
 
-use crate::{alerts, error::EbpfguardError};
+use crate::{alerts, error::EbpfguardError, policy};
+
+use super::{
+    file_open::{add_subject_flag, inode_subject_flags, store_subject_flags},
+    found, perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    remove_keys, SubjectMap,
+};
 
-use super::perf_array_alerts;
+/// Settings of `bprm_check_security` policies, shared by all processes
+/// managing the same maps. They're independent of the settings of
+/// `file_open` policies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BprmCheckSecuritySettings {
+    /// Number of parent directories of an executed binary checked for
+    /// policies, between 1 and [`MAX_DIR_DEPTH`].
+    pub max_depth: u32,
+    /// Deny executions which can't be decided because the binary has more
+    /// parent directories than `max_depth` and the subject has directory
+    /// entries. Otherwise such executions are alerted, but allowed, unless
+    /// the entry of the subject for all paths denies them. Enabled by
+    /// default.
+    pub deny_truncated: bool,
+}
+
+impl Default for BprmCheckSecuritySettings {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_DIR_DEPTH,
+            deny_truncated: true,
+        }
+    }
+}
 
 pub struct BprmCheckSecurity {
     #[allow(dead_code)]
     pub(crate) program_link: Option<LsmLink>,
+    pub(crate) allowed_map: HashMap<MapData, ebpf_policy::PathKey, u8>,
+    pub(crate) denied_map: HashMap<MapData, ebpf_policy::PathKey, u8>,
+    pub(crate) subject_flags_map: HashMap<MapData, Subject, u8>,
+    pub(crate) settings_map: Array<MapData, ebpf_policy::BprmCheckSecuritySettings>,
+    pub(crate) subjects: SubjectMap,
     pub(crate) perf_array: AsyncPerfEventArray<MapData>,
 }
 
 impl BprmCheckSecurity {
+    /// Adds the policy. Executables are merged with the ones already allowed
+    /// or denied for the same subject.
+    pub async fn add_policy(
+        &mut self,
+        policy: policy::BprmCheckSecurity,
+    ) -> Result<(), EbpfguardError> {
+        policy::validate::validate(&[policy::Policy::BprmCheckSecurity(policy.clone())], &[])?;
+
+        let (subject_key, entries) = {
+            let mut map = self.subjects.lock().await;
+            let subject_key = map.resolve_subject(policy.subject.clone(), Self::HOOK)?;
+            let entries = SubjectEntries {
+                allow: map
+                    .resolve_paths(policy.allow, Self::HOOK)?
+                    .into_iter()
+                    .collect(),
+                deny: map
+                    .resolve_paths(policy.deny, Self::HOOK)?
+                    .into_iter()
+                    .collect(),
+                mode: policy.mode,
+            };
+            (subject_key, entries)
+        };
+
+        self.merge_entries(&policy.subject, subject_key, entries)?;
+        self.update_subject_flags().await
+    }
+
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)?;
+        remove_keys(&mut self.subject_flags_map, |subject| {
+            *subject == subject_key
+        })?;
+
+        Ok(())
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)?;
+        remove_keys(&mut self.subject_flags_map, |_| true)?;
+
+        Ok(())
+    }
+
+    /// Returns the settings of `bprm_check_security` policies.
+    pub fn settings(&self) -> Result<BprmCheckSecuritySettings, EbpfguardError> {
+        let settings = self.settings_map.get(&0, 0)?;
+        Ok(BprmCheckSecuritySettings {
+            max_depth: match settings.max_depth {
+                0 => DEFAULT_DIR_DEPTH,
+                depth => depth.min(MAX_DIR_DEPTH),
+            },
+            deny_truncated: settings.flags & BPRM_CHECK_SECURITY_ALLOW_TRUNCATED == 0,
+        })
+    }
+
+    /// Changes the settings of `bprm_check_security` policies. They apply to
+    /// the policies of all processes sharing the maps.
+    pub fn set_settings(
+        &mut self,
+        settings: BprmCheckSecuritySettings,
+    ) -> Result<(), EbpfguardError> {
+        if !(1..=MAX_DIR_DEPTH).contains(&settings.max_depth) {
+            return Err(EbpfguardError::InvalidDirDepth { max: MAX_DIR_DEPTH });
+        }
+        let flags = if settings.deny_truncated {
+            0
+        } else {
+            BPRM_CHECK_SECURITY_ALLOW_TRUNCATED
+        };
+        self.settings_map.set(
+            0,
+            ebpf_policy::BprmCheckSecuritySettings::new(settings.max_depth, flags),
+            0,
+        )?;
+        Ok(())
+    }
+
+    /// Stores which subjects have directory entries, so the program checks
+    /// parent directories of executables only for them. See
+    /// [`FileOpen::update_subject_flags`](super::file_open::FileOpen::update_subject_flags).
+    pub(crate) async fn update_subject_flags(&mut self) -> Result<(), EbpfguardError> {
+        let flags = {
+            let mut map = self.subjects.lock().await;
+            inode_subject_flags([&self.allowed_map, &self.denied_map], &mut map)?
+        };
+        store_subject_flags(&mut self.subject_flags_map, &flags)
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<policy::BprmCheckSecurity>, EbpfguardError> {
+        let mut policies = Vec::new();
+
+        let mut map = self.subjects.lock().await;
+        for (subject_key, entries) in self.entries()? {
+            policies.push(policy::BprmCheckSecurity {
+                subject: map.policy_subject(subject_key),
+                mode: entries.mode,
+                allow: map.resolve_inodes(entries.allow.into_iter().collect()),
+                deny: map.resolve_inodes(entries.deny.into_iter().collect()),
+            });
+        }
+
+        Ok(policies)
+    }
+
     pub async fn alerts(&mut self) -> Result<Receiver<alerts::BprmCheckSecurity>, EbpfguardError> {
         perf_array_alerts::<ebpf_alerts::BprmCheckSecurity, alerts::BprmCheckSecurity>(
             &mut self.perf_array,
+            &self.subjects,
         )
         .await
     }
 }
+
+impl PolicyMaps for BprmCheckSecurity {
+    const HOOK: &'static str = "bprm_check_security";
+
+    type Object = Inode;
+
+    fn describe(inode: &Inode) -> String {
+        match *inode {
+            INODE_WILDCARD => "all executables".to_owned(),
+            inode => format!("inode {inode}"),
+        }
+    }
+
+    fn entries(&self) -> Result<Entries<Inode>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for res in self.allowed_map.iter() {
+            let (key, flags) = res?;
+            entries
+                .entry(key.subject)
+                .or_default()
+                .insert_loaded(key.path, true, flags);
+        }
+        for res in self.denied_map.iter() {
+            let (key, flags) = res?;
+            entries
+                .entry(key.subject)
+                .or_default()
+                .insert_loaded(key.path, false, flags);
+        }
+
+        Ok(entries)
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        path: &Inode,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        let map = if allow {
+            &self.allowed_map
+        } else {
+            &self.denied_map
+        };
+        let res = map.get(&ebpf_policy::PathKey::new(subject, *path), 0);
+        Ok(found(res)?.is_some())
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        path: &Inode,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        // Any inode may be a directory until the flags are updated.
+        if *path != INODE_WILDCARD {
+            add_subject_flag(&mut self.subject_flags_map, subject, SUBJECT_DIRS)?;
+        }
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.insert(ebpf_policy::PathKey::new(subject, *path), mode.flags(), 0)?;
+        Ok(())
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        path: &Inode,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.remove(&ebpf_policy::PathKey::new(subject, *path))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use aya::maps::{HashMap, MapData};
+use ebpfguard_common::{policy as ebpf_policy, subject::Subject};
+
+use crate::{error::EbpfguardError, policy};
+
+use super::{
+    found,
+    reconcile::{Entries, PolicyMaps},
+    SubjectMap,
+};
+
+/// Default actions of all hooks, applied to operations which don't match any
+/// policy.
+pub struct Defaults {
+    pub(crate) map: HashMap<MapData, ebpf_policy::DefaultKey, u8>,
+    pub(crate) subjects: SubjectMap,
+}
+
+impl Defaults {
+    /// Sets the default action of the subject in the hook, replacing the
+    /// current one.
+    pub async fn add_policy(
+        &mut self,
+        policy: policy::DefaultAction,
+    ) -> Result<(), EbpfguardError> {
+        policy::validate::validate(&[policy::Policy::Default(policy.clone())], &[])?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject, policy.hook.default_name())?
+        };
+
+        self.map.insert(
+            ebpf_policy::DefaultKey::new(subject_key, policy.hook.id()),
+            policy.action.flags(policy.mode),
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    /// Removes default actions of the given subject in all hooks.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        for hook in policy::Hook::ALL {
+            self.hook(hook)
+                .remove_entries(|subject| subject == subject_key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes all default actions.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        for hook in policy::Hook::ALL {
+            self.hook(hook).remove_entries(|_| true)?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<policy::DefaultAction>, EbpfguardError> {
+        let mut policies = Vec::new();
+
+        for res in self.map.iter() {
+            let (key, flags) = res?;
+            let Some(hook) = policy::Hook::ALL
+                .into_iter()
+                .find(|hook| hook.id() == key.hook)
+            else {
+                continue;
+            };
+
+            let subject = {
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(key.subject)
+            };
+
+            policies.push(policy::DefaultAction {
+                hook,
+                subject,
+                mode: policy::PolicyMode::from_flags(flags),
+                action: policy::Action::from_flags(flags),
+            });
+        }
+
+        Ok(policies)
+    }
+
+    /// Returns the subjects with a default action in any hook.
+    pub(crate) fn subjects(&self) -> Result<Vec<Subject>, EbpfguardError> {
+        Ok(self
+            .map
+            .keys()
+            .map(|res| res.map(|key| key.subject))
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Returns access to the default actions of a single hook on the level of
+    /// map entries.
+    pub(crate) fn hook(&mut self, hook: policy::Hook) -> HookDefaults<'_> {
+        HookDefaults {
+            map: &mut self.map,
+            hook,
+        }
+    }
+}
+
+/// Default actions of a single hook. Every subject has at most one entry,
+/// allowing or denying everything.
+pub(crate) struct HookDefaults<'a> {
+    map: &'a mut HashMap<MapData, ebpf_policy::DefaultKey, u8>,
+    hook: policy::Hook,
+}
+
+impl HookDefaults<'_> {
+    fn key(&self, subject: Subject) -> ebpf_policy::DefaultKey {
+        ebpf_policy::DefaultKey::new(subject, self.hook.id())
+    }
+
+    fn action(&self, subject: Subject) -> Result<Option<policy::Action>, EbpfguardError> {
+        let flags = found(self.map.get(&self.key(subject), 0))?;
+        Ok(flags.map(policy::Action::from_flags))
+    }
+}
+
+impl PolicyMaps for HookDefaults<'_> {
+    const HOOK: &'static str = "default";
+
+    type Object = ();
+
+    fn describe(_: &()) -> String {
+        "default action".to_owned()
+    }
+
+    fn entries(&self) -> Result<Entries<()>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for res in self.map.iter() {
+            let (key, flags) = res?;
+            if key.hook != self.hook.id() {
+                continue;
+            }
+            let allow = policy::Action::from_flags(flags) == policy::Action::Allow;
+            entries
+                .entry(key.subject)
+                .or_default()
+                .insert_loaded((), allow, flags);
+        }
+
+        Ok(entries)
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        _: &(),
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        Ok(self.action(subject)? == Some(action(allow)))
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        _: &(),
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        let key = self.key(subject);
+        self.map.insert(key, action(allow).flags(mode), 0)?;
+        Ok(())
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        _: &(),
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        // Allowing and denying entries share the key. When the action of a
+        // subject changes, the new entry replaces the old one before the old
+        // one is removed, so only an entry with the same action is removed.
+        if self.contains_entry(subject, &(), allow)? {
+            self.map.remove(&self.key(subject))?;
+        }
+        Ok(())
+    }
+}
+
+fn action(allow: bool) -> policy::Action {
+    if allow {
+        policy::Action::Allow
+    } else {
+        policy::Action::Deny
+    }
+}
@@ -1,61 +1,853 @@
 use aya::{
-    maps::{AsyncPerfEventArray, HashMap, MapData},
+    maps::{
+        lpm_trie::{Key, LpmTrie},
+        Array, AsyncPerfEventArray, HashMap, MapData,
+    },
     programs::lsm::LsmLink,
 };
-use ebpfguard_common::{alerts as ebpf_alerts, policy as ebpf_policy};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Component, Path, PathBuf},
+};
+
+use ebpfguard_common::{
+    alerts as ebpf_alerts,
+    consts::{
+        ACCESS_ALL, DEFAULT_DIR_DEPTH, FILE_OPEN_ALLOW_TRUNCATED, FILE_OPEN_PREFIXES,
+        FILE_PREFIX_MAX, INODE_WILDCARD, MAX_DIR_DEPTH, SUBJECT_DIRS, SUBJECT_PREFIXES,
+    },
+    inode::Inode,
+    policy::{self as ebpf_policy, SUBJECT_PREFIX_LEN},
+    subject::Subject,
+};
 use tokio::sync::mpsc::Receiver;
 
-use crate::{alerts, error::EbpfguardError, policy};
+use crate::{
+    alerts,
+    error::EbpfguardError,
+    fs,
+    policy::{self, inode::InodeSubjectMap, Access, PathMatch, Paths, PolicyMode},
+    watch::PathWatcher,
+};
+
+use super::{
+    found, perf_array_alerts,
+    reconcile::{Diff, Entries, Phase, PolicyMaps, SubjectEntries},
+    remove_keys, SubjectMap,
+};
+
+/// Path of a `file_open` map entry.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum FilePath {
+    /// Inode of a file or a directory, or [`INODE_WILDCARD`] for all paths.
+    Inode(Inode),
+    /// Absolute path prefix, see [`prefix`].
+    Prefix(PathBuf),
+}
+
+impl FilePath {
+    pub(crate) fn inode(&self) -> Option<Inode> {
+        match self {
+            FilePath::Inode(inode) => Some(*inode),
+            FilePath::Prefix(_) => None,
+        }
+    }
+
+    fn matching(&self) -> PathMatch {
+        match self {
+            FilePath::Inode(_) => PathMatch::Inode,
+            FilePath::Prefix(_) => PathMatch::Prefix,
+        }
+    }
+}
+
+/// Object of `file_open` policies: a path (or all paths) and a kind of access
+/// to it. All kinds of access of a path share the map entry, they are stored
+/// as flags in its value.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FileTarget {
+    pub(crate) path: FilePath,
+    pub(crate) access: Access,
+}
+
+/// Normalizes a path of a prefix policy. It has to be absolute and fit into
+/// the map keys, `.` components and trailing slashes are dropped.
+pub(crate) fn prefix(path: &Path) -> Result<PathBuf, EbpfguardError> {
+    let prefix = path.components().collect::<PathBuf>();
+    let bytes = prefix.as_os_str().as_bytes();
+    // Entries of a path take one byte more than the path, see
+    // `prefix_keys`.
+    if !path.is_absolute()
+        || path.components().any(|c| c == Component::ParentDir)
+        || bytes.len() >= FILE_PREFIX_MAX
+        || bytes.contains(&0)
+    {
+        return Err(EbpfguardError::InvalidPathPrefix {
+            path: path.to_owned(),
+            max: FILE_PREFIX_MAX - 1,
+        });
+    }
+    Ok(prefix)
+}
+
+/// Returns the keys of the prefix map entries of the path, with the numbers
+/// of their path bytes: the path followed by a NUL byte, matching the path
+/// itself, and the path followed by `/`, matching everything under it. The
+/// root directory has a single entry, matching all paths.
+fn prefix_keys(subject: Subject, prefix: &Path) -> Vec<(Key<ebpf_policy::FilePrefixKey>, u16)> {
+    let bytes = prefix.as_os_str().as_bytes();
+    let suffixes: &[&[u8]] = if bytes == b"/" {
+        &[b""]
+    } else {
+        &[b"\0", b"/"]
+    };
+    suffixes
+        .iter()
+        .map(|suffix| {
+            let len = bytes.len() + suffix.len();
+            let mut path = [0; FILE_PREFIX_MAX];
+            path[..bytes.len()].copy_from_slice(bytes);
+            path[bytes.len()..len].copy_from_slice(suffix);
+            let key = Key::new(
+                ebpf_policy::FilePrefixKey::prefix_len(len),
+                ebpf_policy::FilePrefixKey::new(subject, path),
+            );
+            (key, len as u16)
+        })
+        .collect()
+}
+
+/// Returns the subject and the path prefix of a prefix map entry.
+fn prefix_target(key: &Key<ebpf_policy::FilePrefixKey>) -> (Subject, PathBuf) {
+    let data = key.data();
+    let len = ((key.prefix_len() - SUBJECT_PREFIX_LEN) / 8) as usize;
+    let path = &data.path[..len.min(FILE_PREFIX_MAX)];
+    let path = match path {
+        [b'/'] => path,
+        [path @ .., b'\0' | b'/'] => path,
+        path => path,
+    };
+    (data.subject, PathBuf::from(OsStr::from_bytes(path)))
+}
+
+/// Settings of `file_open` policies, shared by all processes managing the
+/// same maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileOpenSettings {
+    /// Number of parent directories of an opened file checked for inode
+    /// policies, between 1 and [`MAX_DIR_DEPTH`].
+    pub max_depth: u32,
+    /// Deny access which can't be decided because the file has more parent
+    /// directories than `max_depth` (or its path can't be resolved for
+    /// prefix policies) and the subject has directory (or prefix) entries.
+    /// Otherwise such access is alerted, but allowed, unless the entry of
+    /// the subject for all paths denies it. Enabled by default.
+    pub deny_truncated: bool,
+}
+
+impl Default for FileOpenSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_DIR_DEPTH,
+            deny_truncated: true,
+        }
+    }
+}
+
+/// Path of a policy, which is a glob pattern or a literal path matching only
+/// itself, together with the inodes it currently resolves to.
+#[derive(Clone, Debug)]
+pub(crate) struct WatchedPath {
+    pattern: PathBuf,
+    access: Vec<Access>,
+    allow: bool,
+    inodes: BTreeSet<Inode>,
+}
+
+/// Paths of all policies added for a subject.
+#[derive(Debug, Default)]
+pub(crate) struct WatchedPaths {
+    pub(crate) mode: PolicyMode,
+    pub(crate) paths: Vec<WatchedPath>,
+}
+
+impl WatchedPaths {
+    /// Returns the map entries the paths currently resolve to.
+    fn entries(&self) -> SubjectEntries<FileTarget> {
+        let mut entries = SubjectEntries {
+            mode: self.mode,
+            ..Default::default()
+        };
+        for path in &self.paths {
+            for inode in &path.inodes {
+                for access in &path.access {
+                    let target = FileTarget {
+                        path: FilePath::Inode(*inode),
+                        access: *access,
+                    };
+                    entries.insert(target, path.allow);
+                }
+            }
+        }
+        entries
+    }
+}
+
+/// Objects of the maps resolved from a policy.
+#[derive(Default)]
+pub(crate) struct Targets {
+    pub(crate) allow: Vec<FileTarget>,
+    pub(crate) deny: Vec<FileTarget>,
+    /// Paths which have to be expanded again when files are created,
+    /// renamed or deleted.
+    pub(crate) watched: Vec<WatchedPath>,
+}
+
+impl Targets {
+    fn push(&mut self, path: FilePath, access: &[Access], allow: bool) {
+        let targets = if allow {
+            &mut self.allow
+        } else {
+            &mut self.deny
+        };
+        targets.extend(access.iter().map(|access| FileTarget {
+            path: path.clone(),
+            access: *access,
+        }));
+    }
+}
 
-use super::{perf_array_alerts, INODE_SUBJECT_MAP};
+/// Resolves the paths of the policy to the objects of the maps, one for
+/// every kind of access a path is allowed or denied for. Paths of inode
+/// policies are expanded as glob patterns, the ones which don't exist
+/// resolve to no objects. Paths of prefix policies are taken literally.
+pub(crate) fn targets(
+    policy: policy::FileOpen,
+    map: &mut InodeSubjectMap,
+) -> Result<Targets, EbpfguardError> {
+    let mut targets = Targets::default();
 
+    let rules = [(Access::ALL.to_vec(), policy.allow, policy.deny)]
+        .into_iter()
+        .chain(
+            policy
+                .access_rules
+                .into_iter()
+                .map(|rule| (rule.access, rule.allow, rule.deny)),
+        );
+    for (access, allowed, denied) in rules {
+        for (paths, allow) in [(allowed, true), (denied, false)] {
+            let patterns = match paths {
+                Paths::All => {
+                    targets.push(FilePath::Inode(INODE_WILDCARD), &access, allow);
+                    continue;
+                }
+                Paths::Paths(patterns) => patterns,
+            };
+            for pattern in patterns {
+                if policy.matching == PathMatch::Prefix {
+                    targets.push(FilePath::Prefix(prefix(&pattern)?), &access, allow);
+                    continue;
+                }
+                let inodes = map.resolve_pattern(&pattern, FileOpen::HOOK)?;
+                for inode in &inodes {
+                    targets.push(FilePath::Inode(*inode), &access, allow);
+                }
+                targets.watched.push(WatchedPath {
+                    pattern,
+                    access: access.clone(),
+                    allow,
+                    inodes: inodes.into_iter().collect(),
+                });
+            }
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Policies for opening files.
+///
+/// Paths of inode policies are glob patterns (e.g. `/home/*/.ssh`). They are
+/// expanded when a policy is added, and again by
+/// [`update_paths`](Self::update_paths) when files are created, renamed or
+/// deleted, so paths which don't exist yet are enforced once they appear.
+/// Paths of prefix policies are stored as they are, see
+/// [`PathMatch::Prefix`].
 pub struct FileOpen {
     #[allow(dead_code)]
     pub(crate) program_link: Option<LsmLink>,
-    pub(crate) allowed_map: HashMap<MapData, u64, ebpf_policy::Paths>,
-    pub(crate) denied_map: HashMap<MapData, u64, ebpf_policy::Paths>,
+    pub(crate) allowed_map: HashMap<MapData, ebpf_policy::PathKey, u8>,
+    pub(crate) denied_map: HashMap<MapData, ebpf_policy::PathKey, u8>,
+    pub(crate) allowed_prefix_map:
+        LpmTrie<MapData, ebpf_policy::FilePrefixKey, ebpf_policy::FilePrefixValue>,
+    pub(crate) denied_prefix_map:
+        LpmTrie<MapData, ebpf_policy::FilePrefixKey, ebpf_policy::FilePrefixValue>,
+    pub(crate) settings_map: Array<MapData, ebpf_policy::FileOpenSettings>,
+    pub(crate) subject_flags_map: HashMap<MapData, Subject, u8>,
+    pub(crate) subjects: SubjectMap,
     pub(crate) perf_array: AsyncPerfEventArray<MapData>,
+    /// Paths of the policies added through this handle.
+    pub(crate) watched: BTreeMap<Subject, WatchedPaths>,
+    pub(crate) watcher: PathWatcher,
 }
 
 impl FileOpen {
+    /// Adds the policy. Paths are merged with the ones already allowed or
+    /// denied for the same subject.
     pub async fn add_policy(&mut self, policy: policy::FileOpen) -> Result<(), EbpfguardError> {
-        let bin_inode = {
-            let mut map = INODE_SUBJECT_MAP.lock().await;
-            map.resolve_path(policy.subject)?
+        policy::validate::validate(&[policy::Policy::FileOpen(policy.clone())], &[])?;
+
+        let subject = policy.subject.clone();
+        let mode = policy.mode;
+        let (subject_key, targets) = {
+            let mut map = self.subjects.lock().await;
+            let subject_key = map.resolve_subject(policy.subject.clone(), Self::HOOK)?;
+            (subject_key, targets(policy, &mut map)?)
+        };
+
+        let entries = SubjectEntries {
+            allow: targets.allow.into_iter().collect(),
+            deny: targets.deny.into_iter().collect(),
+            mode,
+        };
+        self.merge_entries(&subject, subject_key, entries)?;
+        self.update_subject_flags().await?;
+
+        let watched = self.watched.entry(subject_key).or_default();
+        watched.mode = mode;
+        watched.paths.extend(targets.watched);
+        self.update_watches()
+    }
+
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)?;
+        remove_keys(&mut self.subject_flags_map, |subject| {
+            *subject == subject_key
+        })?;
+        self.update_prefixes()?;
+
+        self.watched.remove(&subject_key);
+        self.update_watches()
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)?;
+        remove_keys(&mut self.subject_flags_map, |_| true)?;
+        self.update_prefixes()?;
+
+        self.watched.clear();
+        self.update_watches()
+    }
+
+    /// Returns the settings of `file_open` policies.
+    pub fn settings(&self) -> Result<FileOpenSettings, EbpfguardError> {
+        let settings = self.settings_map.get(&0, 0)?;
+        Ok(FileOpenSettings {
+            max_depth: match settings.max_depth {
+                0 => DEFAULT_DIR_DEPTH,
+                depth => depth.min(MAX_DIR_DEPTH),
+            },
+            deny_truncated: settings.flags & FILE_OPEN_ALLOW_TRUNCATED == 0,
+        })
+    }
+
+    /// Changes the settings of `file_open` policies. They apply to the
+    /// policies of all processes sharing the maps.
+    pub fn set_settings(&mut self, settings: FileOpenSettings) -> Result<(), EbpfguardError> {
+        if !(1..=MAX_DIR_DEPTH).contains(&settings.max_depth) {
+            return Err(EbpfguardError::InvalidDirDepth { max: MAX_DIR_DEPTH });
+        }
+        let current = self.settings_map.get(&0, 0)?;
+        let mut flags = current.flags & FILE_OPEN_PREFIXES;
+        if !settings.deny_truncated {
+            flags |= FILE_OPEN_ALLOW_TRUNCATED;
+        }
+        self.settings_map.set(
+            0,
+            ebpf_policy::FileOpenSettings::new(settings.max_depth, flags),
+            0,
+        )?;
+        Ok(())
+    }
+
+    /// Enables prefix lookups in the program if, and only if, there are any
+    /// prefix entries, so paths of opened files are resolved only when
+    /// needed.
+    fn update_prefixes(&mut self) -> Result<(), EbpfguardError> {
+        let empty = self.allowed_prefix_map.keys().next().is_none()
+            && self.denied_prefix_map.keys().next().is_none();
+        let settings = self.settings_map.get(&0, 0)?;
+        let flags = if empty {
+            settings.flags & !FILE_OPEN_PREFIXES
+        } else {
+            settings.flags | FILE_OPEN_PREFIXES
+        };
+        if flags != settings.flags {
+            let settings = ebpf_policy::FileOpenSettings::new(settings.max_depth, flags);
+            self.settings_map.set(0, settings, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Stores which subjects have directory or prefix entries, so the program
+    /// checks parent directories and prefixes of opened files only for them.
+    /// Inserted entries mark their subjects right away, this also drops the
+    /// marks which are not needed anymore.
+    pub(crate) async fn update_subject_flags(&mut self) -> Result<(), EbpfguardError> {
+        let mut flags = {
+            let mut map = self.subjects.lock().await;
+            inode_subject_flags([&self.allowed_map, &self.denied_map], &mut map)?
         };
+        for map in [&self.allowed_prefix_map, &self.denied_prefix_map] {
+            for res in map.keys() {
+                let subject = res?.data().subject;
+                *flags.entry(subject).or_default() |= SUBJECT_PREFIXES;
+            }
+        }
+        store_subject_flags(&mut self.subject_flags_map, &flags)
+    }
+
+    /// Waits until files matching the paths of the policies added through
+    /// this handle are created, renamed or deleted, then expands the paths
+    /// again and updates the maps. Returns the number of subjects whose
+    /// entries changed.
+    ///
+    /// Paths are kept up to date only while this method is being called,
+    /// e.g. in a `tokio::select!` loop next to receiving alerts. It's cancel
+    /// safe, changes which happen in the meantime are not lost. If no
+    /// policy has paths to watch, it never completes.
+    pub async fn update_paths(&mut self) -> Result<usize, EbpfguardError> {
+        self.watcher.changed().await?;
 
-        let allow: ebpf_policy::Paths = policy.allow.into();
-        let deny: ebpf_policy::Paths = policy.deny.into();
+        let mut current = Entries::new();
+        let mut desired = Entries::new();
+        {
+            let mut map = self.subjects.lock().await;
+            for (subject, watched) in self.watched.iter_mut() {
+                current.insert(*subject, watched.entries());
+                for path in &mut watched.paths {
+                    path.inodes = map
+                        .resolve_pattern(&path.pattern, Self::HOOK)?
+                        .into_iter()
+                        .collect();
+                }
 
-        self.allowed_map.insert(bin_inode, allow, 0)?;
-        self.denied_map.insert(bin_inode, deny, 0)?;
+                // A file can start matching both allowed and denied paths,
+                // e.g. after being renamed. Denying it is the safe choice.
+                let mut entries = watched.entries();
+                let conflicts = &entries.allow & &entries.deny;
+                for target in conflicts {
+                    log::warn!(
+                        "{} of {} is both allowed and denied, denying it",
+                        Self::describe(&target),
+                        map.policy_subject(*subject),
+                    );
+                    entries.allow.remove(&target);
+                }
+                desired.insert(*subject, entries);
+            }
+        }
+
+        let diff = Diff::new(current, desired);
+        for phase in Phase::ALL {
+            diff.apply(self, phase)?;
+        }
+        self.watcher.done();
+        self.update_watches()?;
+        self.update_subject_flags().await?;
 
+        Ok(diff.changes().len())
+    }
+
+    /// Watches the directories in which the watched paths can appear or
+    /// disappear.
+    pub(crate) fn update_watches(&mut self) -> Result<(), EbpfguardError> {
+        let mut dirs = BTreeSet::new();
+        for path in self.watched.values().flat_map(|watched| &watched.paths) {
+            dirs.extend(fs::pattern_dirs(&path.pattern)?);
+        }
+        self.watcher.watch(dirs.into_iter().collect())?;
         Ok(())
     }
 
     pub async fn list_policies(&self) -> Result<Vec<policy::FileOpen>, EbpfguardError> {
         let mut policies = Vec::new();
 
-        for res in self.allowed_map.iter() {
-            let (bin_inode, allow) = res?;
-            let deny = self.denied_map.get(&bin_inode, 0)?;
+        let mut map = self.subjects.lock().await;
+        for (subject_key, entries) in self.entries()? {
+            // Inode and prefix entries of a subject are listed as separate
+            // policies.
+            for matching in [PathMatch::Inode, PathMatch::Prefix] {
+                let allow = entries
+                    .allow
+                    .iter()
+                    .filter(|target| target.path.matching() == matching);
+                let deny = entries
+                    .deny
+                    .iter()
+                    .filter(|target| target.path.matching() == matching);
+                if allow.clone().next().is_none() && deny.clone().next().is_none() {
+                    continue;
+                }
 
-            let subject = {
-                let map = INODE_SUBJECT_MAP.lock().await;
-                map.resolve_inode(bin_inode)
-            };
+                // Paths are grouped by the kinds of access they are allowed
+                // or denied for. Paths with all of them go to `allow` and
+                // `deny`.
+                let mut rules: BTreeMap<Vec<Access>, (Vec<FilePath>, Vec<FilePath>)> =
+                    BTreeMap::new();
+                for (access, paths) in group_by_access(allow) {
+                    rules.entry(access).or_default().0 = paths;
+                }
+                for (access, paths) in group_by_access(deny) {
+                    rules.entry(access).or_default().1 = paths;
+                }
+                let (allow, deny) = rules.remove(Access::ALL.as_slice()).unwrap_or_default();
+                let subject = map.policy_subject(subject_key);
+                let mut paths = |paths: Vec<FilePath>| match matching {
+                    PathMatch::Inode => {
+                        map.resolve_inodes(paths.iter().filter_map(FilePath::inode).collect())
+                    }
+                    PathMatch::Prefix => Paths::Paths(
+                        paths
+                            .into_iter()
+                            .filter_map(|path| match path {
+                                FilePath::Prefix(prefix) => Some(prefix),
+                                FilePath::Inode(_) => None,
+                            })
+                            .collect(),
+                    ),
+                };
 
-            policies.push(policy::FileOpen {
-                subject,
-                allow: allow.into(),
-                deny: deny.into(),
-            });
+                policies.push(policy::FileOpen {
+                    subject,
+                    mode: entries.mode,
+                    allow: paths(allow),
+                    deny: paths(deny),
+                    matching,
+                    access_rules: rules
+                        .into_iter()
+                        .map(|(access, (allow, deny))| policy::AccessRule {
+                            access,
+                            allow: paths(allow),
+                            deny: paths(deny),
+                        })
+                        .collect(),
+                });
+            }
         }
 
         Ok(policies)
     }
 
     pub async fn alerts(&mut self) -> Result<Receiver<alerts::FileOpen>, EbpfguardError> {
-        perf_array_alerts::<ebpf_alerts::FileOpen, alerts::FileOpen>(&mut self.perf_array).await
+        perf_array_alerts::<ebpf_alerts::FileOpen, alerts::FileOpen>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+/// Returns the flags of the subjects of the inode maps, see [`SUBJECT_DIRS`].
+pub(crate) fn inode_subject_flags(
+    maps: [&HashMap<MapData, ebpf_policy::PathKey, u8>; 2],
+    index: &mut InodeSubjectMap,
+) -> Result<BTreeMap<Subject, u8>, EbpfguardError> {
+    let mut flags = BTreeMap::new();
+    let mut dirs = BTreeMap::new();
+    for map in maps {
+        for res in map.keys() {
+            let key = res?;
+            if key.path == INODE_WILDCARD {
+                continue;
+            }
+            let dir = *dirs
+                .entry(key.path)
+                .or_insert_with(|| index.may_be_dir(key.path));
+            if dir {
+                *flags.entry(key.subject).or_default() |= SUBJECT_DIRS;
+            }
+        }
+    }
+    Ok(flags)
+}
+
+/// Replaces the flags of all subjects in the map.
+pub(crate) fn store_subject_flags(
+    map: &mut HashMap<MapData, Subject, u8>,
+    flags: &BTreeMap<Subject, u8>,
+) -> Result<(), EbpfguardError> {
+    remove_keys(map, |subject| !flags.contains_key(subject))?;
+    for (subject, flags) in flags {
+        map.insert(subject, flags, 0)?;
+    }
+    Ok(())
+}
+
+/// Adds the flag to the subject, so its entries are checked by the program
+/// as soon as they're inserted.
+pub(crate) fn add_subject_flag(
+    map: &mut HashMap<MapData, Subject, u8>,
+    subject: Subject,
+    flag: u8,
+) -> Result<(), EbpfguardError> {
+    let flags = found(map.get(&subject, 0))?.unwrap_or(0);
+    if flags & flag == 0 {
+        map.insert(subject, flags | flag, 0)?;
+    }
+    Ok(())
+}
+
+/// Groups paths by all kinds of access they have entries for.
+fn group_by_access<'a>(
+    targets: impl IntoIterator<Item = &'a FileTarget>,
+) -> BTreeMap<Vec<Access>, Vec<FilePath>> {
+    let mut paths: BTreeMap<&FilePath, Vec<Access>> = BTreeMap::new();
+    for target in targets {
+        paths.entry(&target.path).or_default().push(target.access);
+    }
+
+    let mut groups: BTreeMap<Vec<Access>, Vec<FilePath>> = BTreeMap::new();
+    for (path, access) in paths {
+        groups.entry(access).or_default().push(path.clone());
+    }
+    groups
+}
+
+impl FileOpen {
+    fn map(&self, allow: bool) -> &HashMap<MapData, ebpf_policy::PathKey, u8> {
+        if allow {
+            &self.allowed_map
+        } else {
+            &self.denied_map
+        }
+    }
+
+    fn prefix_map(
+        &self,
+        allow: bool,
+    ) -> &LpmTrie<MapData, ebpf_policy::FilePrefixKey, ebpf_policy::FilePrefixValue> {
+        if allow {
+            &self.allowed_prefix_map
+        } else {
+            &self.denied_prefix_map
+        }
+    }
+
+    /// Returns the flags of the entry for the path, or `None` if there is no
+    /// entry. Prefix entries match only if they were added for exactly the
+    /// same path.
+    fn flags(
+        &self,
+        subject: Subject,
+        path: &FilePath,
+        allow: bool,
+    ) -> Result<Option<u8>, EbpfguardError> {
+        match path {
+            FilePath::Inode(inode) => {
+                let res = self
+                    .map(allow)
+                    .get(&ebpf_policy::PathKey::new(subject, *inode), 0);
+                Ok(found(res)?)
+            }
+            FilePath::Prefix(prefix) => {
+                // All entries of a path share the flags.
+                let (key, len) = prefix_keys(subject, prefix).swap_remove(0);
+                let value = found(self.prefix_map(allow).get(&key, 0))?;
+                Ok(value
+                    .filter(|value| value.path_len == len)
+                    .map(|value| value.flags))
+            }
+        }
+    }
+
+    /// Stores the flags in the entry for the path, or removes the entry if
+    /// `flags` is `None`.
+    fn set_flags(
+        &mut self,
+        subject: Subject,
+        path: &FilePath,
+        allow: bool,
+        flags: Option<u8>,
+    ) -> Result<(), EbpfguardError> {
+        match path {
+            FilePath::Inode(inode) => {
+                // Any inode may be a directory until the flags are updated.
+                if flags.is_some() && *inode != INODE_WILDCARD {
+                    add_subject_flag(&mut self.subject_flags_map, subject, SUBJECT_DIRS)?;
+                }
+                let map = if allow {
+                    &mut self.allowed_map
+                } else {
+                    &mut self.denied_map
+                };
+                let key = ebpf_policy::PathKey::new(subject, *inode);
+                match flags {
+                    Some(flags) => map.insert(key, flags, 0)?,
+                    None => map.remove(&key)?,
+                }
+            }
+            FilePath::Prefix(prefix) => {
+                if flags.is_some() {
+                    add_subject_flag(&mut self.subject_flags_map, subject, SUBJECT_PREFIXES)?;
+                }
+                let map = if allow {
+                    &mut self.allowed_prefix_map
+                } else {
+                    &mut self.denied_prefix_map
+                };
+                for (key, len) in prefix_keys(subject, prefix) {
+                    match flags {
+                        Some(flags) => {
+                            let value = ebpf_policy::FilePrefixValue::new(len, flags);
+                            map.insert(&key, value, 0)?;
+                        }
+                        None => map.remove(&key)?,
+                    }
+                }
+                self.update_prefixes()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PolicyMaps for FileOpen {
+    const HOOK: &'static str = "file_open";
+
+    type Object = FileTarget;
+
+    fn describe(target: &FileTarget) -> String {
+        match &target.path {
+            FilePath::Inode(INODE_WILDCARD) => format!("{} access to all paths", target.access),
+            FilePath::Inode(inode) => format!("{} access to inode {inode}", target.access),
+            FilePath::Prefix(prefix) => {
+                format!(
+                    "{} access to paths under {}",
+                    target.access,
+                    prefix.display()
+                )
+            }
+        }
+    }
+
+    fn entries(&self) -> Result<Entries<FileTarget>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for allow in [true, false] {
+            for res in self.map(allow).iter() {
+                let (key, flags) = res?;
+                let subject_entries = entries.entry(key.subject).or_default();
+                for access in Access::from_flags(flags) {
+                    let target = FileTarget {
+                        path: FilePath::Inode(key.path),
+                        access,
+                    };
+                    subject_entries.insert_loaded(target, allow, flags);
+                }
+            }
+            for res in self.prefix_map(allow).iter() {
+                let (key, value) = res?;
+                let (subject, prefix) = prefix_target(&key);
+                let subject_entries = entries.entry(subject).or_default();
+                for access in Access::from_flags(value.flags) {
+                    let target = FileTarget {
+                        path: FilePath::Prefix(prefix.clone()),
+                        access,
+                    };
+                    subject_entries.insert_loaded(target, allow, value.flags);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &FileTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        let flags = self.flags(subject, &target.path, allow)?;
+        Ok(flags.is_some_and(|flags| Access::from_flags(flags).contains(&target.access)))
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &FileTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        let access = match self.flags(subject, &target.path, allow)? {
+            Some(flags) => entry_access(flags) | target.access.flag(),
+            None => target.access.flag(),
+        };
+        self.set_flags(subject, &target.path, allow, Some(mode.flags() | access))
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &FileTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        let Some(flags) = self.flags(subject, &target.path, allow)? else {
+            return Ok(());
+        };
+        let access = entry_access(flags) & !target.access.flag();
+        let flags = (access != 0).then_some((flags & !ACCESS_ALL) | access);
+        self.set_flags(subject, &target.path, allow, flags)
+    }
+}
+
+/// Returns the access flags of an entry. Entries without them apply to all
+/// kinds of access.
+fn entry_access(flags: u8) -> u8 {
+    match flags & ACCESS_ALL {
+        0 => ACCESS_ALL,
+        access => access,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ebpfguard_common::consts::CGROUP_WILDCARD;
+
+    #[test]
+    fn test_prefix_keys() {
+        assert_eq!(
+            prefix(Path::new("/var/lib/./docker/")).unwrap(),
+            Path::new("/var/lib/docker")
+        );
+        assert!(prefix(Path::new("var/lib")).is_err());
+        assert!(prefix(Path::new("/var/../etc")).is_err());
+
+        let subject = Subject::new(Inode::new(0, 1), CGROUP_WILDCARD);
+        let keys = prefix_keys(subject, Path::new("/var/lib"));
+        assert_eq!(keys.iter().map(|(_, len)| *len).collect::<Vec<_>>(), [9, 9]);
+        for (key, _) in &keys {
+            assert_eq!(prefix_target(key), (subject, PathBuf::from("/var/lib")));
+        }
+
+        let keys = prefix_keys(subject, Path::new("/"));
+        assert_eq!(keys.len(), 1);
+        assert_eq!(prefix_target(&keys[0].0), (subject, PathBuf::from("/")));
     }
 }
@@ -0,0 +1,212 @@
+use aya::{
+    maps::{Array, AsyncPerfEventArray, MapData},
+    programs::lsm::LsmLink,
+};
+use ebpfguard_common::{
+    alerts as ebpf_alerts,
+    consts::{INET_CONN_REQUEST_BINARY_SUBJECTS, INET_CONN_REQUEST_DENY_UNKNOWN, INODE_WILDCARD},
+    subject::Subject,
+};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{alerts, error::EbpfguardError, policy};
+
+use super::{
+    perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    socket_connect::{connect_rules, targets, ConnectMaps, ConnectTarget},
+    SubjectMap,
+};
+
+/// Settings of `inet_conn_request` policies, shared by all processes
+/// managing the same maps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InetConnRequestSettings {
+    /// Deny connections to sockets whose listening process is not known,
+    /// if they're not denied already and any policy has a binary subject.
+    /// Otherwise such connections are alerted, but allowed.
+    pub deny_unknown_listeners: bool,
+}
+
+/// Policies for incoming TCP connections, matched by the address of the peer
+/// and the local port. The subject is the process which started listening
+/// on the socket, recorded by a separate program attached to `socket_listen`
+/// LSM hook, for up to
+/// [`MAX_LISTENER_ENTRIES`](ebpfguard_common::consts::MAX_LISTENER_ENTRIES)
+/// sockets. The least recently used records are evicted when there are
+/// more.
+///
+/// The listening process of sockets which were listening before the hook
+/// was attached, or whose records were evicted, is not known. Connections
+/// to them are matched by policies for their cgroup, user, group and all
+/// processes. If any policy has a binary subject, the connections they
+/// allow are alerted, or denied with
+/// [`InetConnRequestSettings::deny_unknown_listeners`].
+pub struct InetConnRequest {
+    #[allow(dead_code)]
+    pub(crate) program_link: Option<LsmLink>,
+    #[allow(dead_code)]
+    pub(crate) listen_program_link: Option<LsmLink>,
+    pub(crate) maps: ConnectMaps,
+    pub(crate) settings_map: Array<MapData, u8>,
+    pub(crate) subjects: SubjectMap,
+    pub(crate) perf_array: AsyncPerfEventArray<MapData>,
+}
+
+impl InetConnRequest {
+    /// Adds the policy. Networks and port rules are merged with the ones
+    /// already allowed or denied for the same subject.
+    pub async fn add_policy(
+        &mut self,
+        policy: policy::InetConnRequest,
+    ) -> Result<(), EbpfguardError> {
+        policy::validate::validate(&[policy::Policy::InetConnRequest(policy.clone())], &[])?;
+
+        let (allow, deny) = targets(&policy.allow, &policy.deny, &policy.port_rules)?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
+        };
+
+        let entries = SubjectEntries {
+            allow: allow.into_iter().collect(),
+            deny: deny.into_iter().collect(),
+            mode: policy.mode,
+        };
+        self.merge_entries(&policy.subject, subject_key, entries)?;
+        self.update_binary_subjects()
+    }
+
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)?;
+        self.update_binary_subjects()
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)?;
+        self.update_binary_subjects()
+    }
+
+    /// Returns the settings of `inet_conn_request` policies.
+    pub fn settings(&self) -> Result<InetConnRequestSettings, EbpfguardError> {
+        let flags = self.settings_map.get(&0, 0)?;
+        Ok(InetConnRequestSettings {
+            deny_unknown_listeners: flags & INET_CONN_REQUEST_DENY_UNKNOWN != 0,
+        })
+    }
+
+    /// Changes the settings of `inet_conn_request` policies. They apply to
+    /// the policies of all processes sharing the maps.
+    pub fn set_settings(
+        &mut self,
+        settings: InetConnRequestSettings,
+    ) -> Result<(), EbpfguardError> {
+        let mut flags = self.settings_map.get(&0, 0)? & !INET_CONN_REQUEST_DENY_UNKNOWN;
+        if settings.deny_unknown_listeners {
+            flags |= INET_CONN_REQUEST_DENY_UNKNOWN;
+        }
+        self.settings_map.set(0, flags, 0)?;
+        Ok(())
+    }
+
+    /// Stores whether any policy has a binary subject, so the program alerts
+    /// connections to sockets whose listening process is not known only
+    /// when a per-binary policy could apply to them.
+    pub(crate) fn update_binary_subjects(&mut self) -> Result<(), EbpfguardError> {
+        let binary_subjects = self
+            .entries()?
+            .keys()
+            .any(|subject| subject.binary != INODE_WILDCARD);
+        let mut flags = self.settings_map.get(&0, 0)? & !INET_CONN_REQUEST_BINARY_SUBJECTS;
+        if binary_subjects {
+            flags |= INET_CONN_REQUEST_BINARY_SUBJECTS;
+        }
+        self.settings_map.set(0, flags, 0)?;
+        Ok(())
+    }
+
+    /// Lists the policies, see [`connect_rules`].
+    pub async fn list_policies(&self) -> Result<Vec<policy::InetConnRequest>, EbpfguardError> {
+        let mut policies = Vec::new();
+
+        for (subject_key, entries) in self.entries()? {
+            let subject = {
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
+            };
+
+            let mode = entries.mode;
+            let (allow, deny, port_rules) = connect_rules(entries);
+            policies.push(policy::InetConnRequest {
+                subject,
+                mode,
+                allow,
+                deny,
+                port_rules,
+            });
+        }
+
+        Ok(policies)
+    }
+
+    pub async fn alerts(&mut self) -> Result<Receiver<alerts::InetConnRequest>, EbpfguardError> {
+        perf_array_alerts::<ebpf_alerts::InetConnRequest, alerts::InetConnRequest>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+impl PolicyMaps for InetConnRequest {
+    const HOOK: &'static str = "inet_conn_request";
+
+    type Object = ConnectTarget;
+
+    fn describe(target: &ConnectTarget) -> String {
+        target.to_string()
+    }
+
+    fn entries(&self) -> Result<Entries<ConnectTarget>, EbpfguardError> {
+        self.maps.entries()
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        self.maps.contains_entry(subject, target, allow)
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.insert_entry(subject, target, allow, mode)
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.remove_entry(subject, target, allow)
+    }
+}
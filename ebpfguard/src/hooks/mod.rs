@@ -1,12 +1,17 @@
-use std::fmt::Debug;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    path::PathBuf,
+    sync::Arc,
+};
 
 use aya::{
-    maps::{AsyncPerfEventArray, MapData},
+    maps::{AsyncPerfEventArray, HashMap, MapData, MapError},
     util::online_cpus,
+    Pod,
 };
 use bytes::BytesMut;
 use ebpfguard_common::alerts as ebpf_alerts;
-use once_cell::sync::Lazy;
 use tokio::{
     sync::{
         mpsc::{self, Receiver},
@@ -18,44 +23,83 @@ use tokio::{
 use crate::{alerts, error::EbpfguardError, policy, InodeSubjectMap};
 
 pub mod bprm_check_security;
+pub mod defaults;
 pub mod file_open;
+pub mod inet_conn_request;
+pub mod reconcile;
 pub mod sb_mount;
 pub mod sb_remount;
 pub mod sb_umount;
 pub mod socket_bind;
 pub mod socket_connect;
+pub mod socket_connect_unix;
+pub mod socket_create;
+pub mod socket_listen;
 pub mod task_fix_setuid;
 
 use bprm_check_security::BprmCheckSecurity;
+use defaults::{Defaults, HookDefaults};
 use file_open::FileOpen;
+use inet_conn_request::InetConnRequest;
+use reconcile::{
+    check_conflicts, Change, Diff, Entries, Modes, Phase, PolicyMaps, ReconcileReport,
+};
 use sb_mount::SbMount;
+use sb_remount::SbRemount;
+use sb_umount::SbUmount;
 use socket_bind::SocketBind;
 use socket_connect::SocketConnect;
+use socket_connect_unix::SocketConnectUnix;
+use socket_create::SocketCreate;
+use socket_listen::SocketListen;
 use task_fix_setuid::TaskFixSetuid;
 
-static INODE_SUBJECT_MAP: Lazy<Mutex<InodeSubjectMap>> =
-    Lazy::new(|| Mutex::new(InodeSubjectMap::default()));
+/// Index of inodes used in policies, shared by all hook handles of a
+/// [`PolicyManager`](crate::PolicyManager).
+pub(crate) type SubjectMap = Arc<Mutex<InodeSubjectMap>>;
 
 pub struct All {
+    pub defaults: Defaults,
     pub bprm_check_security: BprmCheckSecurity,
     pub file_open: FileOpen,
+    pub inet_conn_request: InetConnRequest,
     pub sb_mount: SbMount,
-    pub sb_remount: sb_remount::SbRemount,
-    pub sb_umount: sb_umount::SbUmount,
+    pub sb_remount: SbRemount,
+    pub sb_umount: SbUmount,
     pub socket_bind: SocketBind,
     pub socket_connect: SocketConnect,
+    pub socket_connect_unix: SocketConnectUnix,
+    pub socket_create: SocketCreate,
+    pub socket_listen: SocketListen,
     pub task_fix_setuid: TaskFixSetuid,
+    pub(crate) subjects: SubjectMap,
 }
 
 impl All {
+    /// Adds the policy to the hook it belongs to. Fails with
+    /// [`EbpfguardError::InvalidPolicies`] if
+    /// [`validate`](policy::validate::validate) reports problems within the
+    /// policy.
     pub async fn add_policy(&mut self, policy: policy::Policy) -> Result<(), EbpfguardError> {
         match policy {
+            policy::Policy::Default(policy) => self.defaults.add_policy(policy).await?,
+            policy::Policy::BprmCheckSecurity(policy) => {
+                self.bprm_check_security.add_policy(policy).await?
+            }
             policy::Policy::FileOpen(policy) => self.file_open.add_policy(policy).await?,
+            policy::Policy::InetConnRequest(policy) => {
+                self.inet_conn_request.add_policy(policy).await?
+            }
             policy::Policy::SbMount(policy) => self.sb_mount.add_policy(policy).await?,
             policy::Policy::SbRemount(policy) => self.sb_remount.add_policy(policy).await?,
             policy::Policy::SbUmount(policy) => self.sb_umount.add_policy(policy).await?,
             policy::Policy::SocketBind(policy) => self.socket_bind.add_policy(policy).await?,
             policy::Policy::SocketConnect(policy) => self.socket_connect.add_policy(policy).await?,
+            policy::Policy::SocketConnectUnix(policy) => {
+                self.socket_connect_unix.add_policy(policy).await?
+            }
+            policy::Policy::SocketCreate(policy) => self.socket_create.add_policy(policy).await?,
+            policy::Policy::SocketListen(policy) => self.socket_listen.add_policy(policy).await?,
             policy::Policy::TaskFixSetuid(policy) => {
                 self.task_fix_setuid.add_policy(policy).await?
             }
@@ -63,20 +107,435 @@ impl All {
 
         Ok(())
     }
+
+    /// Brings the maps of all hooks to the state described by the given
+    /// policies. Policies which are loaded but not present in `policies` are
+    /// removed. Changes are applied in an order which never makes the
+    /// enforcement more permissive than both the current and the desired
+    /// state. Fails with [`EbpfguardError::InvalidPolicies`], without
+    /// modifying the maps, if [`validate`](policy::validate::validate)
+    /// reports problems in the policies.
+    pub async fn reconcile(
+        &mut self,
+        policies: Vec<policy::Policy>,
+    ) -> Result<ReconcileReport, EbpfguardError> {
+        policy::validate::validate(&policies, &[])?;
+
+        let mut defaults: BTreeMap<policy::Hook, Entries<()>> = BTreeMap::new();
+        let mut bprm_check_security = Entries::new();
+        let mut file_open = Entries::new();
+        let mut file_open_watched: BTreeMap<_, file_open::WatchedPaths> = BTreeMap::new();
+        let mut inet_conn_request = Entries::new();
+        let mut sb_mount = Entries::new();
+        let mut sb_remount = Entries::new();
+        let mut sb_umount = Entries::new();
+        let mut socket_bind = Entries::new();
+        let mut socket_connect = Entries::new();
+        let mut socket_connect_unix = Entries::new();
+        let mut socket_create = Entries::new();
+        let mut socket_listen = Entries::new();
+        let mut task_fix_setuid = Entries::new();
+
+        {
+            let mut map = self.subjects.lock().await;
+            let mut modes = Modes::default();
+            for policy in policies {
+                match policy {
+                    policy::Policy::Default(policy) => {
+                        let hook = policy.hook.default_name();
+                        let subject = map.resolve_subject(policy.subject, hook)?;
+                        let entries = defaults
+                            .entry(policy.hook)
+                            .or_default()
+                            .entry(subject)
+                            .or_default();
+                        entries.mode = modes.check(hook, subject, policy.mode, &mut map)?;
+                        entries.insert((), policy.action == policy::Action::Allow);
+                    }
+                    policy::Policy::BprmCheckSecurity(policy) => {
+                        let subject =
+                            map.resolve_subject(policy.subject, BprmCheckSecurity::HOOK)?;
+                        let entries = bprm_check_security.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(BprmCheckSecurity::HOOK, subject, policy.mode, &mut map)?;
+                        entries
+                            .allow
+                            .extend(map.resolve_paths(policy.allow, BprmCheckSecurity::HOOK)?);
+                        entries
+                            .deny
+                            .extend(map.resolve_paths(policy.deny, BprmCheckSecurity::HOOK)?);
+                    }
+                    policy::Policy::FileOpen(policy) => {
+                        let subject =
+                            map.resolve_subject(policy.subject.clone(), FileOpen::HOOK)?;
+                        let mode = modes.check(FileOpen::HOOK, subject, policy.mode, &mut map)?;
+                        let targets = file_open::targets(policy, &mut map)?;
+                        let entries = file_open.entry(subject).or_default();
+                        entries.mode = mode;
+                        entries.allow.extend(targets.allow);
+                        entries.deny.extend(targets.deny);
+                        let watched = file_open_watched.entry(subject).or_default();
+                        watched.mode = mode;
+                        watched.paths.extend(targets.watched);
+                    }
+                    policy::Policy::InetConnRequest(policy) => {
+                        let (allow, deny) = socket_connect::targets(
+                            &policy.allow,
+                            &policy.deny,
+                            &policy.port_rules,
+                        )?;
+                        let subject = map.resolve_subject(policy.subject, InetConnRequest::HOOK)?;
+                        let entries = inet_conn_request.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(InetConnRequest::HOOK, subject, policy.mode, &mut map)?;
+                        entries.allow.extend(allow);
+                        entries.deny.extend(deny);
+                    }
+                    policy::Policy::SbMount(policy) => {
+                        let subject = map.resolve_subject(policy.subject, SbMount::HOOK)?;
+                        let entries = sb_mount.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SbMount::HOOK, subject, policy.mode, &mut map)?;
+                        entries.insert((), policy.allow);
+                    }
+                    policy::Policy::SbRemount(policy) => {
+                        let subject = map.resolve_subject(policy.subject, SbRemount::HOOK)?;
+                        let entries = sb_remount.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SbRemount::HOOK, subject, policy.mode, &mut map)?;
+                        entries.insert((), policy.allow);
+                    }
+                    policy::Policy::SbUmount(policy) => {
+                        let subject = map.resolve_subject(policy.subject, SbUmount::HOOK)?;
+                        let entries = sb_umount.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SbUmount::HOOK, subject, policy.mode, &mut map)?;
+                        entries.insert((), policy.allow);
+                    }
+                    policy::Policy::SocketBind(policy) => {
+                        let (allow, deny) = socket_bind::targets(
+                            &policy.allow,
+                            &policy.deny,
+                            &policy.address_rules,
+                        )?;
+                        let subject = map.resolve_subject(policy.subject, SocketBind::HOOK)?;
+                        let entries = socket_bind.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SocketBind::HOOK, subject, policy.mode, &mut map)?;
+                        entries.allow.extend(allow);
+                        entries.deny.extend(deny);
+                    }
+                    policy::Policy::SocketConnect(policy) => {
+                        let (allow, deny) = socket_connect::targets(
+                            &policy.allow,
+                            &policy.deny,
+                            &policy.port_rules,
+                        )?;
+                        let subject = map.resolve_subject(policy.subject, SocketConnect::HOOK)?;
+                        let entries = socket_connect.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SocketConnect::HOOK, subject, policy.mode, &mut map)?;
+                        entries.allow.extend(allow);
+                        entries.deny.extend(deny);
+                    }
+                    policy::Policy::SocketConnectUnix(policy) => {
+                        let subject =
+                            map.resolve_subject(policy.subject, SocketConnectUnix::HOOK)?;
+                        let entries = socket_connect_unix.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SocketConnectUnix::HOOK, subject, policy.mode, &mut map)?;
+                        entries.allow.extend(policy.allow.into_sun_paths()?);
+                        entries.deny.extend(policy.deny.into_sun_paths()?);
+                    }
+                    policy::Policy::SocketCreate(policy) => {
+                        let subject = map.resolve_subject(policy.subject, SocketCreate::HOOK)?;
+                        let entries = socket_create.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SocketCreate::HOOK, subject, policy.mode, &mut map)?;
+                        entries.allow.extend(policy.allow.to_ids());
+                        entries.deny.extend(policy.deny.to_ids());
+                    }
+                    policy::Policy::SocketListen(policy) => {
+                        let (allow, deny) = socket_bind::targets(
+                            &policy.allow,
+                            &policy.deny,
+                            &policy.address_rules,
+                        )?;
+                        let subject = map.resolve_subject(policy.subject, SocketListen::HOOK)?;
+                        let entries = socket_listen.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(SocketListen::HOOK, subject, policy.mode, &mut map)?;
+                        entries.allow.extend(allow);
+                        entries.deny.extend(deny);
+                    }
+                    policy::Policy::TaskFixSetuid(policy) => {
+                        let subject = map.resolve_subject(policy.subject, TaskFixSetuid::HOOK)?;
+                        let entries = task_fix_setuid.entry(subject).or_default();
+                        entries.mode =
+                            modes.check(TaskFixSetuid::HOOK, subject, policy.mode, &mut map)?;
+                        entries.insert((), policy.allow);
+                    }
+                }
+            }
+
+            for (hook, entries) in &defaults {
+                for (subject, entries) in entries {
+                    if !entries.allow.is_empty() && !entries.deny.is_empty() {
+                        return Err(EbpfguardError::PolicyConflict {
+                            hook: hook.default_name(),
+                            subject: map.policy_subject(*subject),
+                            object: HookDefaults::describe(&()),
+                        });
+                    }
+                }
+            }
+            check_conflicts::<BprmCheckSecurity>(&bprm_check_security, &mut map)?;
+            check_conflicts::<FileOpen>(&file_open, &mut map)?;
+            check_conflicts::<InetConnRequest>(&inet_conn_request, &mut map)?;
+            check_conflicts::<SbMount>(&sb_mount, &mut map)?;
+            check_conflicts::<SbRemount>(&sb_remount, &mut map)?;
+            check_conflicts::<SbUmount>(&sb_umount, &mut map)?;
+            check_conflicts::<SocketBind>(&socket_bind, &mut map)?;
+            check_conflicts::<SocketConnect>(&socket_connect, &mut map)?;
+            check_conflicts::<SocketConnectUnix>(&socket_connect_unix, &mut map)?;
+            check_conflicts::<SocketCreate>(&socket_create, &mut map)?;
+            check_conflicts::<SocketListen>(&socket_listen, &mut map)?;
+            check_conflicts::<TaskFixSetuid>(&task_fix_setuid, &mut map)?;
+        }
+
+        let mut default_diffs = Vec::new();
+        for hook in policy::Hook::ALL {
+            let current = self.defaults.hook(hook).entries()?;
+            let desired = defaults.remove(&hook).unwrap_or_default();
+            default_diffs.push((hook, Diff::new(current, desired)));
+        }
+        let bprm_check_security =
+            Diff::new(self.bprm_check_security.entries()?, bprm_check_security);
+        let file_open = Diff::new(self.file_open.entries()?, file_open);
+        let inet_conn_request = Diff::new(self.inet_conn_request.entries()?, inet_conn_request);
+        let sb_mount = Diff::new(self.sb_mount.entries()?, sb_mount);
+        let sb_remount = Diff::new(self.sb_remount.entries()?, sb_remount);
+        let sb_umount = Diff::new(self.sb_umount.entries()?, sb_umount);
+        let socket_bind = Diff::new(self.socket_bind.entries()?, socket_bind);
+        let socket_connect = Diff::new(self.socket_connect.entries()?, socket_connect);
+        let socket_connect_unix =
+            Diff::new(self.socket_connect_unix.entries()?, socket_connect_unix);
+        let socket_create = Diff::new(self.socket_create.entries()?, socket_create);
+        let socket_listen = Diff::new(self.socket_listen.entries()?, socket_listen);
+        let task_fix_setuid = Diff::new(self.task_fix_setuid.entries()?, task_fix_setuid);
+
+        // Every phase is applied to all hooks before moving to the next one,
+        // so no hook gets its permissions widened before all restrictions are
+        // in place.
+        for phase in Phase::ALL {
+            for (hook, diff) in &default_diffs {
+                diff.apply(&mut self.defaults.hook(*hook), phase)?;
+            }
+            bprm_check_security.apply(&mut self.bprm_check_security, phase)?;
+            file_open.apply(&mut self.file_open, phase)?;
+            inet_conn_request.apply(&mut self.inet_conn_request, phase)?;
+            sb_mount.apply(&mut self.sb_mount, phase)?;
+            sb_remount.apply(&mut self.sb_remount, phase)?;
+            sb_umount.apply(&mut self.sb_umount, phase)?;
+            socket_bind.apply(&mut self.socket_bind, phase)?;
+            socket_connect.apply(&mut self.socket_connect, phase)?;
+            socket_connect_unix.apply(&mut self.socket_connect_unix, phase)?;
+            socket_create.apply(&mut self.socket_create, phase)?;
+            socket_listen.apply(&mut self.socket_listen, phase)?;
+            task_fix_setuid.apply(&mut self.task_fix_setuid, phase)?;
+        }
+        self.file_open.watched = file_open_watched;
+        self.file_open.update_watches()?;
+        self.file_open.update_subject_flags().await?;
+        self.bprm_check_security.update_subject_flags().await?;
+        self.inet_conn_request.update_binary_subjects()?;
+
+        let mut map = self.subjects.lock().await;
+        let mut report = ReconcileReport::default();
+        let default_changes = default_diffs
+            .iter()
+            .map(|(hook, diff)| (hook.default_name(), diff.changes()));
+        for (hook, changes) in default_changes.chain([
+            (BprmCheckSecurity::HOOK, bprm_check_security.changes()),
+            (FileOpen::HOOK, file_open.changes()),
+            (InetConnRequest::HOOK, inet_conn_request.changes()),
+            (SbMount::HOOK, sb_mount.changes()),
+            (SbRemount::HOOK, sb_remount.changes()),
+            (SbUmount::HOOK, sb_umount.changes()),
+            (SocketBind::HOOK, socket_bind.changes()),
+            (SocketConnect::HOOK, socket_connect.changes()),
+            (SocketConnectUnix::HOOK, socket_connect_unix.changes()),
+            (SocketCreate::HOOK, socket_create.changes()),
+            (SocketListen::HOOK, socket_listen.changes()),
+            (TaskFixSetuid::HOOK, task_fix_setuid.changes()),
+        ]) {
+            for (subject, kind) in changes {
+                report.changes.push(Change {
+                    hook,
+                    subject: map.policy_subject(*subject),
+                    kind: *kind,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Removes policies and default actions of the given subject from all
+    /// hooks.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        self.defaults.remove_policy(subject).await?;
+        self.bprm_check_security.remove_policy(subject).await?;
+        self.file_open.remove_policy(subject).await?;
+        self.inet_conn_request.remove_policy(subject).await?;
+        self.sb_mount.remove_policy(subject).await?;
+        self.sb_remount.remove_policy(subject).await?;
+        self.sb_umount.remove_policy(subject).await?;
+        self.socket_bind.remove_policy(subject).await?;
+        self.socket_connect.remove_policy(subject).await?;
+        self.socket_connect_unix.remove_policy(subject).await?;
+        self.socket_create.remove_policy(subject).await?;
+        self.socket_listen.remove_policy(subject).await?;
+        self.task_fix_setuid.remove_policy(subject).await?;
+
+        Ok(())
+    }
+
+    /// Waits until files matching the paths of `file_open` policies are
+    /// created, renamed or deleted and updates the maps. See
+    /// [`FileOpen::update_paths`].
+    pub async fn update_paths(&mut self) -> Result<usize, EbpfguardError> {
+        self.file_open.update_paths().await
+    }
+
+    /// Scans the filesystem under the given roots for subjects and paths of
+    /// the loaded policies which can't be resolved to paths, and records them
+    /// in the inode index. Returns the number of newly resolved inodes.
+    pub async fn refresh_paths(&self, roots: &[PathBuf]) -> Result<usize, EbpfguardError> {
+        let mut subjects = BTreeSet::new();
+        let mut inodes = Vec::new();
+        for (subject, entries) in self.bprm_check_security.entries()? {
+            subjects.insert(subject);
+            inodes.extend(entries.allow);
+            inodes.extend(entries.deny);
+        }
+        for (subject, entries) in self.file_open.entries()? {
+            subjects.insert(subject);
+            inodes.extend(
+                entries
+                    .allow
+                    .iter()
+                    .filter_map(|target| target.path.inode()),
+            );
+            inodes.extend(entries.deny.iter().filter_map(|target| target.path.inode()));
+        }
+        subjects.extend(self.defaults.subjects()?);
+        subjects.extend(self.inet_conn_request.entries()?.into_keys());
+        subjects.extend(self.sb_mount.entries()?.into_keys());
+        subjects.extend(self.sb_remount.entries()?.into_keys());
+        subjects.extend(self.sb_umount.entries()?.into_keys());
+        subjects.extend(self.socket_bind.entries()?.into_keys());
+        subjects.extend(self.socket_connect.entries()?.into_keys());
+        subjects.extend(self.socket_connect_unix.entries()?.into_keys());
+        subjects.extend(self.socket_create.entries()?.into_keys());
+        subjects.extend(self.socket_listen.entries()?.into_keys());
+        subjects.extend(self.task_fix_setuid.entries()?.into_keys());
+
+        let mut map = self.subjects.lock().await;
+        for subject in subjects {
+            inodes.push(subject.binary);
+            if let Some(cgroup) = map.cgroup_inode(subject.cgroup) {
+                inodes.push(cgroup);
+            }
+        }
+        Ok(map.refresh(&inodes, roots))
+    }
+
+    /// Removes all policies and default actions from all hooks.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.defaults.clear()?;
+        self.bprm_check_security.clear()?;
+        self.file_open.clear()?;
+        self.inet_conn_request.clear()?;
+        self.sb_mount.clear()?;
+        self.sb_remount.clear()?;
+        self.sb_umount.clear()?;
+        self.socket_bind.clear()?;
+        self.socket_connect.clear()?;
+        self.socket_connect_unix.clear()?;
+        self.socket_create.clear()?;
+        self.socket_listen.clear()?;
+        self.task_fix_setuid.clear()?;
+
+        Ok(())
+    }
+}
+
+/// Turns a failed lookup of a missing key into `None`.
+pub(crate) fn found<V>(res: Result<V, MapError>) -> Result<Option<V>, MapError> {
+    match res {
+        Ok(value) => Ok(Some(value)),
+        Err(MapError::KeyNotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns the length of the object prefix of an LPM trie key, stored after
+/// the `base` bits of the subject (and protocol). Fails if it doesn't fit
+/// in the `max` bits of the object, e.g. because the entry is corrupted.
+pub(crate) fn object_prefix_len(
+    prefix_len: u32,
+    base: u32,
+    max: u32,
+) -> Result<u8, EbpfguardError> {
+    match prefix_len.checked_sub(base) {
+        Some(len) if len <= max => Ok(len as u8),
+        _ => Err(EbpfguardError::InvalidMapEntry(format!(
+            "prefix length {prefix_len} of a key with a {max}-bit object"
+        ))),
+    }
+}
+
+/// Removes all keys matching the predicate from the hash map.
+pub(crate) fn remove_keys<K, V, F>(
+    map: &mut HashMap<MapData, K, V>,
+    predicate: F,
+) -> Result<(), MapError>
+where
+    K: Pod,
+    V: Pod,
+    F: Fn(&K) -> bool,
+{
+    // Collect the keys first, removing elements while iterating over the map
+    // might skip some of them.
+    let keys = map
+        .keys()
+        .filter(|res| res.as_ref().map_or(true, &predicate))
+        .collect::<Result<Vec<_>, _>>()?;
+    for key in keys {
+        map.remove(&key)?;
+    }
+
+    Ok(())
 }
 
 pub async fn perf_array_alerts<E, U>(
     perf_array: &mut AsyncPerfEventArray<MapData>,
+    subjects: &SubjectMap,
 ) -> Result<Receiver<U>, EbpfguardError>
 where
     E: ebpf_alerts::Alert,
-    U: alerts::Alert + Debug + Send + From<E> + 'static,
+    U: alerts::Alert + alerts::ResolveAlert<E> + Debug + Send + 'static,
 {
     let (tx, rx) = mpsc::channel(32);
 
     let cpus = online_cpus()?;
     for cpu_id in cpus {
         let tx = tx.clone();
+        let subjects = subjects.clone();
         let mut buf = perf_array.open(cpu_id, None)?;
 
         task::spawn(async move {
@@ -87,9 +546,10 @@ where
                 let events = buf.read_events(&mut buffers).await.unwrap();
                 for buf in buffers.iter_mut().take(events.read) {
                     let alert: U = {
+                        let mut map = subjects.lock().await;
                         let ptr = buf.as_ptr() as *const E;
                         let alert = unsafe { ptr.read_unaligned() };
-                        alert.into()
+                        U::resolve(alert, &mut map)
                     };
                     tx.send(alert).await.unwrap();
                 }
@@ -0,0 +1,783 @@
+use std::{
+    collections::{btree_map, BTreeMap, BTreeSet},
+    fmt::{Display, Formatter},
+};
+
+use serde::Serialize;
+
+use ebpfguard_common::subject::Subject;
+
+use crate::{
+    error::EbpfguardError,
+    policy::{PolicyMode, PolicySubject},
+    InodeSubjectMap,
+};
+
+/// Map entries of a hook, grouped by subject.
+pub(crate) type Entries<O> = BTreeMap<Subject, SubjectEntries<O>>;
+
+/// Allowed and denied objects (paths, ports, networks etc.) of a single
+/// subject. All entries of a subject share the same mode.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SubjectEntries<O: Ord> {
+    pub(crate) allow: BTreeSet<O>,
+    pub(crate) deny: BTreeSet<O>,
+    pub(crate) mode: PolicyMode,
+}
+
+impl<O: Ord> Default for SubjectEntries<O> {
+    fn default() -> Self {
+        Self {
+            allow: BTreeSet::new(),
+            deny: BTreeSet::new(),
+            mode: PolicyMode::default(),
+        }
+    }
+}
+
+impl<O: Ord> SubjectEntries<O> {
+    pub(crate) fn insert(&mut self, object: O, allow: bool) {
+        if allow {
+            self.allow.insert(object);
+        } else {
+            self.deny.insert(object);
+        }
+    }
+
+    /// Records an entry loaded from the maps, with the flags stored in its
+    /// value.
+    pub(crate) fn insert_loaded(&mut self, object: O, allow: bool, flags: u8) {
+        self.insert(object, allow);
+        self.mode = PolicyMode::from_flags(flags);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Returns an object which is both allowed and denied, if any.
+    fn conflict(&self) -> Option<&O> {
+        self.allow.intersection(&self.deny).next()
+    }
+}
+
+/// Access to the allow and deny maps of a hook on the level of single
+/// entries.
+pub(crate) trait PolicyMaps {
+    /// Name of the hook.
+    const HOOK: &'static str;
+
+    /// Object the policies of the hook apply to.
+    type Object: Ord + Clone;
+
+    /// Returns a human readable description of the object.
+    fn describe(object: &Self::Object) -> String;
+
+    /// Returns all entries from the maps.
+    fn entries(&self) -> Result<Entries<Self::Object>, EbpfguardError>;
+
+    /// Returns `true` if exactly the given entry is present in the maps.
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        object: &Self::Object,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError>;
+
+    /// Inserts the entry, or updates its mode if it's already present.
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        object: &Self::Object,
+        allow: bool,
+        mode: PolicyMode,
+    ) -> Result<(), EbpfguardError>;
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        object: &Self::Object,
+        allow: bool,
+    ) -> Result<(), EbpfguardError>;
+
+    /// Adds entries of a subject to the maps. Entries which are already
+    /// loaded for the subject are kept, so repeated policies for the same
+    /// subject result in a union of their allow and deny lists. Fails with
+    /// [`EbpfguardError::PolicyConflict`], without modifying the maps, if
+    /// any object would end up being both allowed and denied, or with
+    /// [`EbpfguardError::ModeConflict`] if the subject already has entries in
+    /// a different mode.
+    fn merge_entries(
+        &mut self,
+        subject: &PolicySubject,
+        subject_key: Subject,
+        entries: SubjectEntries<Self::Object>,
+    ) -> Result<(), EbpfguardError> {
+        if let Some(object) = entries.conflict() {
+            return Err(conflict::<Self>(subject.clone(), object));
+        }
+        for object in &entries.allow {
+            if self.contains_entry(subject_key, object, false)? {
+                return Err(conflict::<Self>(subject.clone(), object));
+            }
+        }
+        for object in &entries.deny {
+            if self.contains_entry(subject_key, object, true)? {
+                return Err(conflict::<Self>(subject.clone(), object));
+            }
+        }
+
+        if let Some(current) = self.entries()?.remove(&subject_key) {
+            if current.mode != entries.mode {
+                return Err(EbpfguardError::ModeConflict {
+                    hook: Self::HOOK,
+                    subject: subject.clone(),
+                });
+            }
+        }
+
+        for object in &entries.deny {
+            self.insert_entry(subject_key, object, false, entries.mode)?;
+        }
+        for object in &entries.allow {
+            self.insert_entry(subject_key, object, true, entries.mode)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes all entries of the subjects matching the predicate. Allowing
+    /// entries of a subject are removed before the denying ones.
+    fn remove_entries<F>(&mut self, predicate: F) -> Result<(), EbpfguardError>
+    where
+        F: Fn(Subject) -> bool,
+    {
+        for (subject, entries) in self.entries()? {
+            if !predicate(subject) {
+                continue;
+            }
+            for object in &entries.allow {
+                self.remove_entry(subject, object, true)?;
+            }
+            for object in &entries.deny {
+                self.remove_entry(subject, object, false)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn conflict<M: PolicyMaps + ?Sized>(subject: PolicySubject, object: &M::Object) -> EbpfguardError {
+    EbpfguardError::PolicyConflict {
+        hook: M::HOOK,
+        subject,
+        object: M::describe(object),
+    }
+}
+
+/// Fails with [`EbpfguardError::PolicyConflict`] if any subject has an
+/// object which is both allowed and denied.
+pub(crate) fn check_conflicts<M: PolicyMaps>(
+    entries: &Entries<M::Object>,
+    subjects: &mut InodeSubjectMap,
+) -> Result<(), EbpfguardError> {
+    for (subject, subject_entries) in entries {
+        if let Some(object) = subject_entries.conflict() {
+            return Err(conflict::<M>(subjects.policy_subject(*subject), object));
+        }
+    }
+    Ok(())
+}
+
+/// Modes of the policies of subjects, collected while building the desired
+/// entries of all hooks. All entries of a subject share the same mode, so
+/// policies of one subject can't mix modes.
+#[derive(Default)]
+pub(crate) struct Modes(BTreeMap<(&'static str, Subject), PolicyMode>);
+
+impl Modes {
+    /// Records the mode of a policy of the subject in the hook and returns
+    /// it. Fails with [`EbpfguardError::ModeConflict`] if another policy of
+    /// the subject has a different mode.
+    pub(crate) fn check(
+        &mut self,
+        hook: &'static str,
+        subject: Subject,
+        mode: PolicyMode,
+        subjects: &mut InodeSubjectMap,
+    ) -> Result<PolicyMode, EbpfguardError> {
+        match self.0.entry((hook, subject)) {
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(mode);
+            }
+            btree_map::Entry::Occupied(entry) if *entry.get() != mode => {
+                return Err(EbpfguardError::ModeConflict {
+                    hook,
+                    subject: subjects.policy_subject(subject),
+                });
+            }
+            btree_map::Entry::Occupied(_) => {}
+        }
+        Ok(mode)
+    }
+}
+
+/// Phases of applying changes to the maps. They are ordered in a way that
+/// every intermediate state is at most as permissive as the initial or the
+/// desired one: restrictions are added first and permissions are removed
+/// before new permissions are added and old restrictions are lifted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Phase {
+    AddDenied,
+    RemoveAllowed,
+    AddAllowed,
+    RemoveDenied,
+}
+
+impl Phase {
+    pub(crate) const ALL: [Phase; 4] = [
+        Phase::AddDenied,
+        Phase::RemoveAllowed,
+        Phase::AddAllowed,
+        Phase::RemoveDenied,
+    ];
+}
+
+/// Difference between the current and the desired entries of a hook.
+#[derive(Debug)]
+pub(crate) struct Diff<O: Ord> {
+    add: Entries<O>,
+    remove: Entries<O>,
+    changes: Vec<(Subject, ChangeKind)>,
+}
+
+impl<O: Ord + Clone> Diff<O> {
+    pub(crate) fn new(current: Entries<O>, mut desired: Entries<O>) -> Self {
+        let mut add = Entries::new();
+        let mut remove = Entries::new();
+        let mut changes = Vec::new();
+
+        for (subject, current) in current {
+            let desired = desired.remove(&subject).unwrap_or_default();
+            let kind = if desired.is_empty() {
+                ChangeKind::Removed
+            } else {
+                ChangeKind::Updated
+            };
+            // Entries which are kept get rewritten when the mode changes.
+            let to_add = if desired.mode == current.mode {
+                SubjectEntries {
+                    allow: &desired.allow - &current.allow,
+                    deny: &desired.deny - &current.deny,
+                    mode: desired.mode,
+                }
+            } else {
+                SubjectEntries {
+                    allow: desired.allow.clone(),
+                    deny: desired.deny.clone(),
+                    mode: desired.mode,
+                }
+            };
+            let to_remove = SubjectEntries {
+                allow: &current.allow - &desired.allow,
+                deny: &current.deny - &desired.deny,
+                mode: current.mode,
+            };
+            if to_add.is_empty() && to_remove.is_empty() {
+                continue;
+            }
+            changes.push((subject, kind));
+            add.insert(subject, to_add);
+            remove.insert(subject, to_remove);
+        }
+
+        for (subject, desired) in desired {
+            if desired.is_empty() {
+                continue;
+            }
+            changes.push((subject, ChangeKind::Added));
+            add.insert(subject, desired);
+        }
+
+        Self {
+            add,
+            remove,
+            changes,
+        }
+    }
+
+    /// Applies the part of the diff which belongs to the given phase.
+    pub(crate) fn apply<M>(&self, maps: &mut M, phase: Phase) -> Result<(), EbpfguardError>
+    where
+        M: PolicyMaps<Object = O>,
+    {
+        let allow = matches!(phase, Phase::RemoveAllowed | Phase::AddAllowed);
+        let source = match phase {
+            Phase::AddDenied | Phase::AddAllowed => &self.add,
+            Phase::RemoveAllowed | Phase::RemoveDenied => &self.remove,
+        };
+
+        for (subject, subject_entries) in source {
+            let objects = if allow {
+                &subject_entries.allow
+            } else {
+                &subject_entries.deny
+            };
+            for object in objects {
+                match phase {
+                    Phase::AddDenied | Phase::AddAllowed => {
+                        maps.insert_entry(*subject, object, allow, subject_entries.mode)?
+                    }
+                    Phase::RemoveAllowed | Phase::RemoveDenied => {
+                        maps.remove_entry(*subject, object, allow)?
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn changes(&self) -> &[(Subject, ChangeKind)] {
+        &self.changes
+    }
+}
+
+/// Kind of change applied to policies of a subject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ChangeKind {
+    #[serde(rename = "added")]
+    Added,
+    #[serde(rename = "updated")]
+    Updated,
+    #[serde(rename = "removed")]
+    Removed,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeKind::Added => write!(f, "added"),
+            ChangeKind::Updated => write!(f, "updated"),
+            ChangeKind::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+/// Change applied to policies of a subject in a hook.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub hook: &'static str,
+    pub subject: PolicySubject,
+    pub kind: ChangeKind,
+}
+
+/// Report of changes applied by [`All::reconcile`](super::All::reconcile).
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ReconcileReport {
+    pub changes: Vec<Change>,
+}
+
+impl ReconcileReport {
+    /// Returns `true` if the policies were already in the desired state.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ebpfguard_common::{
+        consts::{CGROUP_WILDCARD, SUBJECT_WILDCARD},
+        inode::Inode,
+    };
+
+    fn key(ino: u64) -> Subject {
+        Subject::new(Inode::new(0, ino), CGROUP_WILDCARD)
+    }
+
+    fn entries(subjects: &[(u64, &[u16], &[u16])]) -> Entries<u16> {
+        subjects
+            .iter()
+            .map(|(subject, allow, deny)| {
+                (
+                    key(*subject),
+                    SubjectEntries {
+                        allow: allow.iter().copied().collect(),
+                        deny: deny.iter().copied().collect(),
+                        mode: PolicyMode::Enforce,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        loaded: Vec<(u64, u16, bool)>,
+        ops: Vec<(&'static str, u64, u16, bool)>,
+        modes: Vec<PolicyMode>,
+    }
+
+    impl PolicyMaps for Recorder {
+        const HOOK: &'static str = "recorder";
+
+        type Object = u16;
+
+        fn describe(port: &u16) -> String {
+            format!("port {port}")
+        }
+
+        fn entries(&self) -> Result<Entries<u16>, EbpfguardError> {
+            Ok(Entries::new())
+        }
+
+        fn contains_entry(
+            &self,
+            subject: Subject,
+            object: &u16,
+            allow: bool,
+        ) -> Result<bool, EbpfguardError> {
+            Ok(self.loaded.contains(&(subject.binary.ino, *object, allow)))
+        }
+
+        fn insert_entry(
+            &mut self,
+            subject: Subject,
+            object: &u16,
+            allow: bool,
+            mode: PolicyMode,
+        ) -> Result<(), EbpfguardError> {
+            self.ops
+                .push(("insert", subject.binary.ino, *object, allow));
+            self.modes.push(mode);
+            Ok(())
+        }
+
+        fn remove_entry(
+            &mut self,
+            subject: Subject,
+            object: &u16,
+            allow: bool,
+        ) -> Result<(), EbpfguardError> {
+            self.ops
+                .push(("remove", subject.binary.ino, *object, allow));
+            Ok(())
+        }
+    }
+
+    /// Allow and deny maps of a hook kept in memory, with the flags of the
+    /// entries as values.
+    struct Store<O> {
+        allowed: BTreeMap<(Subject, O), u8>,
+        denied: BTreeMap<(Subject, O), u8>,
+    }
+
+    impl<O: Ord + Clone> Store<O> {
+        fn new(subjects: &[(Subject, &[O], &[O])]) -> Self {
+            let mut store = Self {
+                allowed: BTreeMap::new(),
+                denied: BTreeMap::new(),
+            };
+            for (subject, allow, deny) in subjects {
+                for object in *allow {
+                    store.allowed.insert((*subject, object.clone()), 0);
+                }
+                for object in *deny {
+                    store.denied.insert((*subject, object.clone()), 0);
+                }
+            }
+            store
+        }
+
+        fn subjects(&self) -> BTreeSet<Subject> {
+            self.allowed
+                .keys()
+                .chain(self.denied.keys())
+                .map(|(subject, _)| *subject)
+                .collect()
+        }
+
+        fn map(&mut self, allow: bool) -> &mut BTreeMap<(Subject, O), u8> {
+            if allow {
+                &mut self.allowed
+            } else {
+                &mut self.denied
+            }
+        }
+    }
+
+    impl<O: Ord + Clone + std::fmt::Debug> PolicyMaps for Store<O> {
+        const HOOK: &'static str = "store";
+
+        type Object = O;
+
+        fn describe(object: &O) -> String {
+            format!("{object:?}")
+        }
+
+        fn entries(&self) -> Result<Entries<O>, EbpfguardError> {
+            let mut entries = Entries::new();
+            for (map, allow) in [(&self.allowed, true), (&self.denied, false)] {
+                for ((subject, object), flags) in map {
+                    entries.entry(*subject).or_default().insert_loaded(
+                        object.clone(),
+                        allow,
+                        *flags,
+                    );
+                }
+            }
+            Ok(entries)
+        }
+
+        fn contains_entry(
+            &self,
+            subject: Subject,
+            object: &O,
+            allow: bool,
+        ) -> Result<bool, EbpfguardError> {
+            let map = if allow { &self.allowed } else { &self.denied };
+            Ok(map.contains_key(&(subject, object.clone())))
+        }
+
+        fn insert_entry(
+            &mut self,
+            subject: Subject,
+            object: &O,
+            allow: bool,
+            mode: PolicyMode,
+        ) -> Result<(), EbpfguardError> {
+            self.map(allow)
+                .insert((subject, object.clone()), mode.flags());
+            Ok(())
+        }
+
+        fn remove_entry(
+            &mut self,
+            subject: Subject,
+            object: &O,
+            allow: bool,
+        ) -> Result<(), EbpfguardError> {
+            self.map(allow).remove(&(subject, object.clone()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let current = entries(&[(1, &[80], &[0]), (2, &[0], &[22]), (3, &[443], &[])]);
+        let desired = entries(&[(1, &[80], &[0]), (2, &[0], &[22, 23]), (4, &[8080], &[])]);
+        let diff = Diff::new(current, desired);
+        assert_eq!(
+            diff.changes(),
+            &[
+                (key(2), ChangeKind::Updated),
+                (key(3), ChangeKind::Removed),
+                (key(4), ChangeKind::Added)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_apply_order() {
+        let current = entries(&[(0, &[0], &[22])]);
+        let desired = entries(&[(0, &[80], &[0])]);
+        let diff = Diff::new(current, desired);
+
+        let mut recorder = Recorder::default();
+        for phase in Phase::ALL {
+            diff.apply(&mut recorder, phase).unwrap();
+        }
+        assert_eq!(
+            recorder.ops,
+            vec![
+                ("insert", 0, 0, false),
+                ("remove", 0, 0, true),
+                ("insert", 0, 80, true),
+                ("remove", 0, 22, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_mode_change() {
+        let current = entries(&[(1, &[80], &[0])]);
+        let mut desired = entries(&[(1, &[80, 443], &[0])]);
+        desired.get_mut(&key(1)).unwrap().mode = PolicyMode::Audit;
+        let diff = Diff::new(current, desired);
+        assert_eq!(diff.changes(), &[(key(1), ChangeKind::Updated)]);
+
+        // Entries which are kept are rewritten with the new mode.
+        let mut recorder = Recorder::default();
+        for phase in Phase::ALL {
+            diff.apply(&mut recorder, phase).unwrap();
+        }
+        assert_eq!(
+            recorder.ops,
+            vec![
+                ("insert", 1, 0, false),
+                ("insert", 1, 80, true),
+                ("insert", 1, 443, true),
+            ]
+        );
+        assert!(recorder.modes.iter().all(|mode| *mode == PolicyMode::Audit));
+    }
+
+    #[test]
+    fn test_merge_entries() {
+        let mut recorder = Recorder {
+            loaded: vec![(1, 22, false)],
+            ..Default::default()
+        };
+        let subject = PolicySubject::Binary("/usr/bin/foo".into());
+
+        let entries = SubjectEntries {
+            allow: [80, 443].into(),
+            deny: [23].into(),
+            mode: PolicyMode::Enforce,
+        };
+        recorder.merge_entries(&subject, key(1), entries).unwrap();
+        assert_eq!(
+            recorder.ops,
+            vec![
+                ("insert", 1, 23, false),
+                ("insert", 1, 80, true),
+                ("insert", 1, 443, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_entries_conflict() {
+        let mut recorder = Recorder {
+            loaded: vec![(1, 22, false)],
+            ..Default::default()
+        };
+        let subject = PolicySubject::Binary("/usr/bin/foo".into());
+
+        let entries = SubjectEntries {
+            allow: [22, 80].into(),
+            deny: [].into(),
+            mode: PolicyMode::Enforce,
+        };
+        let err = recorder
+            .merge_entries(&subject, key(1), entries)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            EbpfguardError::PolicyConflict { hook: "recorder", ref object, .. } if object == "port 22"
+        ));
+        assert!(recorder.ops.is_empty());
+
+        let entries = SubjectEntries {
+            allow: [8080].into(),
+            deny: [8080].into(),
+            mode: PolicyMode::Enforce,
+        };
+        assert!(recorder.merge_entries(&subject, key(2), entries).is_err());
+        assert!(recorder.ops.is_empty());
+    }
+
+    #[test]
+    fn test_merge_entries_mode_conflict() {
+        let mut store = Store::new(&[(key(1), &[80], &[22])]);
+        let subject = PolicySubject::Binary("/usr/bin/foo".into());
+
+        let entries = SubjectEntries {
+            allow: [443].into(),
+            deny: [].into(),
+            mode: PolicyMode::Audit,
+        };
+        let err = store.merge_entries(&subject, key(1), entries).unwrap_err();
+        assert!(matches!(
+            err,
+            EbpfguardError::ModeConflict { hook: "store", .. }
+        ));
+        // Entries of the subject stay enforced.
+        assert_eq!(store.entries().unwrap()[&key(1)].mode, PolicyMode::Enforce);
+        assert!(!store.contains_entry(key(1), &443, true).unwrap());
+
+        let entries = SubjectEntries {
+            allow: [443].into(),
+            deny: [].into(),
+            mode: PolicyMode::Audit,
+        };
+        store.merge_entries(&subject, key(2), entries).unwrap();
+        assert_eq!(
+            store.allowed.get(&(key(2), 443)),
+            Some(&PolicyMode::Audit.flags())
+        );
+    }
+
+    #[test]
+    fn test_remove_entries() {
+        let mut store = Store::new(&[
+            (key(1), &[80], &[22]),
+            (key(2), &[443], &[23, 25]),
+            (key(3), &[], &[0]),
+        ]);
+        store.remove_entries(|subject| subject == key(2)).unwrap();
+
+        assert_eq!(store.subjects(), [key(1), key(3)].into());
+        assert_eq!(
+            store.entries().unwrap(),
+            entries(&[(1, &[80], &[22]), (3, &[], &[0])])
+        );
+    }
+
+    #[test]
+    fn test_remove_entries_wildcard() {
+        let mut store = Store::new(&[(SUBJECT_WILDCARD, &[80], &[22]), (key(1), &[443], &[])]);
+
+        let subjects = InodeSubjectMap::default();
+        let subject_key = subjects.subject(&PolicySubject::All).unwrap();
+        assert_eq!(subject_key, SUBJECT_WILDCARD);
+        store
+            .remove_entries(|subject| subject == subject_key)
+            .unwrap();
+
+        assert_eq!(store.subjects(), [key(1)].into());
+    }
+
+    #[test]
+    fn test_remove_entries_deleted_binary() {
+        let binary = std::env::temp_dir().join(format!("ebpfguard-remove-{}", std::process::id()));
+        std::fs::write(&binary, "").unwrap();
+        let subject = PolicySubject::Binary(binary.clone());
+
+        let mut subjects = InodeSubjectMap::default();
+        let subject_key = subjects.resolve_subject(subject.clone(), "store").unwrap();
+        let mut store = Store::new(&[(subject_key, &[80], &[22]), (key(1), &[443], &[])]);
+
+        // The subject of a deleted binary is found by its indexed path.
+        std::fs::remove_file(&binary).unwrap();
+        assert_eq!(subjects.subject(&subject).unwrap(), subject_key);
+        store
+            .remove_entries(|subject| subject == subject_key)
+            .unwrap();
+
+        assert_eq!(store.subjects(), [key(1)].into());
+    }
+
+    #[test]
+    fn test_remove_all_entries() {
+        let mut ports = Store::new(&[
+            (SUBJECT_WILDCARD, &[80], &[22]),
+            (key(1), &[443], &[23]),
+            (key(2), &[], &[0]),
+        ]);
+        // Default actions allow or deny everything.
+        let mut defaults = Store::new(&[(SUBJECT_WILDCARD, &[()], &[]), (key(1), &[], &[()])]);
+
+        ports.remove_entries(|_| true).unwrap();
+        defaults.remove_entries(|_| true).unwrap();
+
+        assert!(ports.allowed.is_empty());
+        assert!(ports.denied.is_empty());
+        assert!(defaults.allowed.is_empty());
+        assert!(defaults.denied.is_empty());
+    }
+}
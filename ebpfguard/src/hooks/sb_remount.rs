@@ -2,64 +2,93 @@ use aya::{
     maps::{AsyncPerfEventArray, HashMap, MapData},
     programs::lsm::LsmLink,
 };
-use ebpfguard_common::alerts as ebpf_alerts;
+use ebpfguard_common::{alerts as ebpf_alerts, subject::Subject};
 use tokio::sync::mpsc::Receiver;
 
 use crate::{alerts, error::EbpfguardError, policy};
 
-use super::{perf_array_alerts, INODE_SUBJECT_MAP};
+use super::{
+    found, perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    SubjectMap,
+};
 
 pub struct SbRemount {
     #[allow(dead_code)]
     pub(crate) program_link: Option<LsmLink>,
-    pub(crate) allowed_map: HashMap<MapData, u64, u8>,
-    pub(crate) denied_map: HashMap<MapData, u64, u8>,
+    pub(crate) allowed_map: HashMap<MapData, Subject, u8>,
+    pub(crate) denied_map: HashMap<MapData, Subject, u8>,
+    pub(crate) subjects: SubjectMap,
     pub(crate) perf_array: AsyncPerfEventArray<MapData>,
 }
 
 impl SbRemount {
+    /// Adds the policy. Fails with [`EbpfguardError::PolicyConflict`] if
+    /// the subject already has a policy with the opposite action, which has
+    /// to be removed first.
     pub async fn add_policy(&mut self, policy: policy::SbRemount) -> Result<(), EbpfguardError> {
-        let bin_inode = {
-            let mut map = INODE_SUBJECT_MAP.lock().await;
-            map.resolve_path(policy.subject)?
+        policy::validate::validate(&[policy::Policy::SbRemount(policy.clone())], &[])?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
         };
 
-        if policy.allow {
-            self.allowed_map.insert(bin_inode, 0, 0)?;
-        } else {
-            self.denied_map.insert(bin_inode, 0, 0)?;
-        }
+        let mut entries = SubjectEntries {
+            mode: policy.mode,
+            ..Default::default()
+        };
+        entries.insert((), policy.allow);
+        self.merge_entries(&policy.subject, subject_key, entries)
+    }
 
-        Ok(())
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)
     }
 
     pub async fn list_policies(&self) -> Result<Vec<policy::SbRemount>, EbpfguardError> {
         let mut policies = Vec::new();
 
         for res in self.allowed_map.iter() {
-            let (bin_inode, _) = res?;
+            let (subject_key, flags) = res?;
 
             let subject = {
-                let map = INODE_SUBJECT_MAP.lock().await;
-                map.resolve_inode(bin_inode)
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
             };
 
             policies.push(policy::SbRemount {
                 subject,
+                mode: policy::PolicyMode::from_flags(flags),
                 allow: true,
             });
         }
 
         for res in self.denied_map.iter() {
-            let (bin_inode, _) = res?;
+            let (subject_key, flags) = res?;
 
             let subject = {
-                let map = INODE_SUBJECT_MAP.lock().await;
-                map.resolve_inode(bin_inode)
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
             };
 
             policies.push(policy::SbRemount {
                 subject,
+                mode: policy::PolicyMode::from_flags(flags),
                 allow: false,
             });
         }
@@ -68,6 +97,86 @@ impl SbRemount {
     }
 
     pub async fn alerts(&mut self) -> Result<Receiver<alerts::SbRemount>, EbpfguardError> {
-        perf_array_alerts::<ebpf_alerts::SbRemount, alerts::SbRemount>(&mut self.perf_array).await
+        perf_array_alerts::<ebpf_alerts::SbRemount, alerts::SbRemount>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+impl PolicyMaps for SbRemount {
+    const HOOK: &'static str = "sb_remount";
+
+    type Object = ();
+
+    fn describe(_: &()) -> String {
+        "remount".to_owned()
+    }
+
+    fn entries(&self) -> Result<Entries<()>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for res in self.allowed_map.iter() {
+            let (subject, flags) = res?;
+            entries
+                .entry(subject)
+                .or_default()
+                .insert_loaded((), true, flags);
+        }
+        for res in self.denied_map.iter() {
+            let (subject, flags) = res?;
+            entries
+                .entry(subject)
+                .or_default()
+                .insert_loaded((), false, flags);
+        }
+
+        Ok(entries)
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        _: &(),
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        let map = if allow {
+            &self.allowed_map
+        } else {
+            &self.denied_map
+        };
+        Ok(found(map.get(&subject, 0))?.is_some())
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        _: &(),
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.insert(subject, mode.flags(), 0)?;
+        Ok(())
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        _: &(),
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.remove(&subject)?;
+        Ok(())
     }
 }
@@ -1,55 +1,109 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
 use aya::{
-    maps::{AsyncPerfEventArray, HashMap, MapData},
+    maps::{lpm_trie::Key, AsyncPerfEventArray, LpmTrie, MapData},
     programs::lsm::LsmLink,
 };
-use ebpfguard_common::{alerts as ebpf_alerts, policy as ebpf_policy};
-
+use ebpfguard_common::{
+    alerts as ebpf_alerts,
+    policy::{self as ebpf_policy, SUBJECT_PREFIX_LEN},
+    subject::Subject,
+};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use tokio::sync::mpsc::Receiver;
 
 use crate::{alerts, error::EbpfguardError, policy};
 
-use super::{perf_array_alerts, INODE_SUBJECT_MAP};
+use super::{
+    found, object_prefix_len, perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    SubjectMap,
+};
 
 pub struct SocketBind {
     #[allow(dead_code)]
     pub(crate) program_link: Option<LsmLink>,
-    pub(crate) allowed_map: HashMap<MapData, u64, ebpf_policy::Ports>,
-    pub(crate) denied_map: HashMap<MapData, u64, ebpf_policy::Ports>,
+    pub(crate) maps: PortMaps,
+    pub(crate) subjects: SubjectMap,
     pub(crate) perf_array: AsyncPerfEventArray<MapData>,
 }
 
+/// LPM tries with the ports allowed and denied on all addresses and on IPv4
+/// and IPv6 networks, used by the hooks matching sockets by their local
+/// address.
+pub(crate) struct PortMaps {
+    pub(crate) allowed_map: LpmTrie<MapData, ebpf_policy::PortKey, ebpf_policy::PrefixValue>,
+    pub(crate) denied_map: LpmTrie<MapData, ebpf_policy::PortKey, ebpf_policy::PrefixValue>,
+    pub(crate) allowed_map_v4: LpmTrie<MapData, ebpf_policy::Ipv4PortKey, ebpf_policy::PrefixValue>,
+    pub(crate) denied_map_v4: LpmTrie<MapData, ebpf_policy::Ipv4PortKey, ebpf_policy::PrefixValue>,
+    pub(crate) allowed_map_v6: LpmTrie<MapData, ebpf_policy::Ipv6PortKey, ebpf_policy::PrefixValue>,
+    pub(crate) denied_map_v6: LpmTrie<MapData, ebpf_policy::Ipv6PortKey, ebpf_policy::PrefixValue>,
+}
+
 impl SocketBind {
+    /// Adds the policy. Ports and address rules are merged with the ones
+    /// already allowed or denied for the same subject. Ranges are stored as
+    /// aligned blocks of ports.
     pub async fn add_policy(&mut self, policy: policy::SocketBind) -> Result<(), EbpfguardError> {
-        let bin_inode = {
-            let mut map = INODE_SUBJECT_MAP.lock().await;
-            map.resolve_path(policy.subject)?
+        policy::validate::validate(&[policy::Policy::SocketBind(policy.clone())], &[])?;
+
+        let (allow, deny) = targets(&policy.allow, &policy.deny, &policy.address_rules)?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
         };
 
-        let allow: ebpf_policy::Ports = policy.allow.into();
-        let deny: ebpf_policy::Ports = policy.deny.into();
+        let entries = SubjectEntries {
+            allow: allow.into_iter().collect(),
+            deny: deny.into_iter().collect(),
+            mode: policy.mode,
+        };
+        self.merge_entries(&policy.subject, subject_key, entries)
+    }
 
-        self.allowed_map.insert(bin_inode, allow, 0)?;
-        self.denied_map.insert(bin_inode, deny, 0)?;
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
 
-        Ok(())
+        self.remove_entries(|subject| subject == subject_key)
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)
     }
 
+    /// Lists the policies. Adjacent blocks of ports are merged back into
+    /// ranges and networks with the same ports are listed in one address
+    /// rule, see [`port_rules`].
     pub async fn list_policies(&self) -> Result<Vec<policy::SocketBind>, EbpfguardError> {
         let mut policies = Vec::new();
 
-        for res in self.allowed_map.iter() {
-            let (bin_inode, allow) = res?;
-            let deny = self.denied_map.get(&bin_inode, 0)?;
-
+        for (subject_key, entries) in self.entries()? {
             let subject = {
-                let map = INODE_SUBJECT_MAP.lock().await;
-                map.resolve_inode(bin_inode)
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
             };
 
+            let mode = entries.mode;
+            let (allow, deny, address_rules) = port_rules(entries);
             policies.push(policy::SocketBind {
                 subject,
-                allow: allow.into(),
-                deny: deny.into(),
+                mode,
+                allow,
+                deny,
+                address_rules,
             });
         }
 
@@ -57,6 +111,400 @@ impl SocketBind {
     }
 
     pub async fn alerts(&mut self) -> Result<Receiver<alerts::SocketBind>, EbpfguardError> {
-        perf_array_alerts::<ebpf_alerts::SocketBind, alerts::SocketBind>(&mut self.perf_array).await
+        perf_array_alerts::<ebpf_alerts::SocketBind, alerts::SocketBind>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+/// Object of the `socket_bind` map entries: an aligned block of ports, on
+/// all addresses or on the given network. Networks wider than a single
+/// address are stored only with all ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct BindTarget {
+    pub(crate) network: Option<IpNet>,
+    pub(crate) ports: policy::PortRange,
+}
+
+impl BindTarget {
+    /// Returns the targets of the ports on all addresses.
+    pub(crate) fn all_addresses(ports: &policy::Ports) -> Vec<BindTarget> {
+        ports
+            .clone()
+            .into_blocks()
+            .into_iter()
+            .map(|ports| BindTarget {
+                network: None,
+                ports,
+            })
+            .collect()
+    }
+
+    /// Returns the targets of the ports on the addresses. Fails with
+    /// [`EbpfguardError::UnsupportedPortRange`] if a range of ports is
+    /// combined with a network.
+    pub(crate) fn addresses(
+        addresses: &policy::Addresses,
+        ports: &policy::Ports,
+    ) -> Result<Vec<BindTarget>, EbpfguardError> {
+        let mut targets = Vec::new();
+        for network in addresses.clone().into_networks() {
+            for ports in ports.clone().into_ranges() {
+                if network.prefix_len() != network.max_prefix_len()
+                    && ports != policy::PortRange::ALL
+                {
+                    return Err(EbpfguardError::UnsupportedPortRange { network, ports });
+                }
+                targets.extend(ports.blocks().into_iter().map(|ports| BindTarget {
+                    network: Some(network),
+                    ports,
+                }));
+            }
+        }
+        Ok(targets)
+    }
+
+    /// Returns the prefix length of the entry, without the subject.
+    pub(crate) fn prefix_len(&self) -> u8 {
+        match self.network {
+            None => self.ports.prefix_len(),
+            Some(network) if network.prefix_len() == network.max_prefix_len() => {
+                network.prefix_len() + self.ports.prefix_len()
+            }
+            Some(network) => network.prefix_len(),
+        }
+    }
+}
+
+impl Display for BindTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.ports == policy::PortRange::ALL {
+            write!(f, "all ports")?;
+        } else if self.ports.is_single() {
+            write!(f, "port {}", self.ports.start)?;
+        } else {
+            write!(f, "ports {}-{}", self.ports.start, self.ports.end)?;
+        }
+        match self.network {
+            Some(network) if network.prefix_len() == network.max_prefix_len() => {
+                write!(f, " on {}", network.addr())
+            }
+            Some(network) => write!(f, " on {network}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the allowed and denied targets of the ports and address rules of
+/// a policy.
+pub(crate) fn targets(
+    allow: &policy::Ports,
+    deny: &policy::Ports,
+    address_rules: &[policy::AddressRule],
+) -> Result<(Vec<BindTarget>, Vec<BindTarget>), EbpfguardError> {
+    let mut allowed = BindTarget::all_addresses(allow);
+    let mut denied = BindTarget::all_addresses(deny);
+    for rule in address_rules {
+        allowed.extend(BindTarget::addresses(&rule.addresses, &rule.allow)?);
+        denied.extend(BindTarget::addresses(&rule.addresses, &rule.deny)?);
+    }
+    Ok((allowed, denied))
+}
+
+/// Returns the ports allowed and denied on all addresses and the address
+/// rules of the entries of a subject. Adjacent blocks of ports are merged
+/// back into ranges and networks with the same ports are listed in one
+/// address rule.
+pub(crate) fn port_rules(
+    entries: SubjectEntries<BindTarget>,
+) -> (policy::Ports, policy::Ports, Vec<policy::AddressRule>) {
+    let mut allow = Vec::new();
+    let mut deny = Vec::new();
+    let mut networks = BTreeMap::new();
+    for (targets, allowed) in [(entries.allow, true), (entries.deny, false)] {
+        for target in targets {
+            let ports = match target.network {
+                None if allowed => &mut allow,
+                None => &mut deny,
+                Some(network) => {
+                    let (allow, deny) = networks
+                        .entry(network)
+                        .or_insert_with(|| (Vec::new(), Vec::new()));
+                    if allowed {
+                        allow
+                    } else {
+                        deny
+                    }
+                }
+            };
+            ports.push(target.ports);
+        }
+    }
+
+    let mut address_rules: Vec<(policy::Ports, policy::Ports, Vec<IpNet>)> = Vec::new();
+    for (network, (allow, deny)) in networks {
+        let allow = policy::Ports::from_ranges(allow);
+        let deny = policy::Ports::from_ranges(deny);
+        match address_rules
+            .iter_mut()
+            .find(|(a, d, _)| *a == allow && *d == deny)
+        {
+            Some((_, _, networks)) => networks.push(network),
+            None => address_rules.push((allow, deny, vec![network])),
+        }
+    }
+
+    (
+        policy::Ports::from_ranges(allow),
+        policy::Ports::from_ranges(deny),
+        address_rules
+            .into_iter()
+            .map(|(allow, deny, networks)| policy::AddressRule {
+                addresses: policy::Addresses::from_networks(networks),
+                allow,
+                deny,
+            })
+            .collect(),
+    )
+}
+
+impl PortMaps {
+    pub(crate) fn entries(&self) -> Result<Entries<BindTarget>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for (map, allow) in [(&self.allowed_map, true), (&self.denied_map, false)] {
+            for res in map.iter() {
+                let (key, value) = res?;
+                let (subject_key, target) = target(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, value.flags);
+            }
+        }
+        for (map, allow) in [(&self.allowed_map_v4, true), (&self.denied_map_v4, false)] {
+            for res in map.iter() {
+                let (key, value) = res?;
+                let (subject_key, target) = target_v4(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, value.flags);
+            }
+        }
+        for (map, allow) in [(&self.allowed_map_v6, true), (&self.denied_map_v6, false)] {
+            for res in map.iter() {
+                let (key, value) = res?;
+                let (subject_key, target) = target_v6(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, value.flags);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub(crate) fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        // Lookups in LPM tries return the longest matching prefix, so the
+        // stored prefix length has to be compared to find an exact match.
+        let value = match (target.network, allow) {
+            (None, true) => found(self.allowed_map.get(&key(subject, target), 0))?,
+            (None, false) => found(self.denied_map.get(&key(subject, target), 0))?,
+            (Some(IpNet::V4(network)), true) => found(
+                self.allowed_map_v4
+                    .get(&key_v4(subject, &network, target), 0),
+            )?,
+            (Some(IpNet::V4(network)), false) => found(
+                self.denied_map_v4
+                    .get(&key_v4(subject, &network, target), 0),
+            )?,
+            (Some(IpNet::V6(network)), true) => found(
+                self.allowed_map_v6
+                    .get(&key_v6(subject, &network, target), 0),
+            )?,
+            (Some(IpNet::V6(network)), false) => found(
+                self.denied_map_v6
+                    .get(&key_v6(subject, &network, target), 0),
+            )?,
+        };
+        Ok(value.map(|value| value.prefix_len) == Some(target.prefix_len()))
+    }
+
+    pub(crate) fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        let value = ebpf_policy::PrefixValue::new(target.prefix_len(), mode.flags());
+        match (target.network, allow) {
+            (None, true) => self.allowed_map.insert(&key(subject, target), value, 0)?,
+            (None, false) => self.denied_map.insert(&key(subject, target), value, 0)?,
+            (Some(IpNet::V4(network)), true) => {
+                self.allowed_map_v4
+                    .insert(&key_v4(subject, &network, target), value, 0)?
+            }
+            (Some(IpNet::V4(network)), false) => {
+                self.denied_map_v4
+                    .insert(&key_v4(subject, &network, target), value, 0)?
+            }
+            (Some(IpNet::V6(network)), true) => {
+                self.allowed_map_v6
+                    .insert(&key_v6(subject, &network, target), value, 0)?
+            }
+            (Some(IpNet::V6(network)), false) => {
+                self.denied_map_v6
+                    .insert(&key_v6(subject, &network, target), value, 0)?
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        match (target.network, allow) {
+            (None, true) => self.allowed_map.remove(&key(subject, target))?,
+            (None, false) => self.denied_map.remove(&key(subject, target))?,
+            (Some(IpNet::V4(network)), true) => self
+                .allowed_map_v4
+                .remove(&key_v4(subject, &network, target))?,
+            (Some(IpNet::V4(network)), false) => self
+                .denied_map_v4
+                .remove(&key_v4(subject, &network, target))?,
+            (Some(IpNet::V6(network)), true) => self
+                .allowed_map_v6
+                .remove(&key_v6(subject, &network, target))?,
+            (Some(IpNet::V6(network)), false) => self
+                .denied_map_v6
+                .remove(&key_v6(subject, &network, target))?,
+        }
+        Ok(())
+    }
+}
+
+impl PolicyMaps for SocketBind {
+    const HOOK: &'static str = "socket_bind";
+
+    type Object = BindTarget;
+
+    fn describe(target: &BindTarget) -> String {
+        target.to_string()
     }
+
+    fn entries(&self) -> Result<Entries<BindTarget>, EbpfguardError> {
+        self.maps.entries()
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        self.maps.contains_entry(subject, target, allow)
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.insert_entry(subject, target, allow, mode)
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.remove_entry(subject, target, allow)
+    }
+}
+
+fn key(subject_key: Subject, target: &BindTarget) -> Key<ebpf_policy::PortKey> {
+    Key::new(
+        SUBJECT_PREFIX_LEN + u32::from(target.prefix_len()),
+        ebpf_policy::PortKey::new(subject_key, target.ports.start),
+    )
+}
+
+fn key_v4(
+    subject_key: Subject,
+    network: &Ipv4Net,
+    target: &BindTarget,
+) -> Key<ebpf_policy::Ipv4PortKey> {
+    Key::new(
+        SUBJECT_PREFIX_LEN + u32::from(target.prefix_len()),
+        ebpf_policy::Ipv4PortKey::new(subject_key, network.network().octets(), target.ports.start),
+    )
+}
+
+fn key_v6(
+    subject_key: Subject,
+    network: &Ipv6Net,
+    target: &BindTarget,
+) -> Key<ebpf_policy::Ipv6PortKey> {
+    Key::new(
+        SUBJECT_PREFIX_LEN + u32::from(target.prefix_len()),
+        ebpf_policy::Ipv6PortKey::new(subject_key, network.network().octets(), target.ports.start),
+    )
+}
+
+fn target(key: &Key<ebpf_policy::PortKey>) -> Result<(Subject, BindTarget), EbpfguardError> {
+    let data = key.data();
+    let prefix_len = object_prefix_len(key.prefix_len(), SUBJECT_PREFIX_LEN, 16)?;
+    let start = u16::from_be_bytes(data.port);
+    let target = BindTarget {
+        network: None,
+        ports: policy::PortRange::from_prefix(start, prefix_len),
+    };
+    Ok((data.subject, target))
+}
+
+fn target_v4(key: &Key<ebpf_policy::Ipv4PortKey>) -> Result<(Subject, BindTarget), EbpfguardError> {
+    let data = key.data();
+    let prefix_len = object_prefix_len(key.prefix_len(), SUBJECT_PREFIX_LEN, 32 + 16)?;
+    let addr = Ipv4Addr::from(data.addr);
+    let network = Ipv4Net::new(addr, prefix_len.min(32))
+        .map_err(|e| EbpfguardError::InvalidMapEntry(e.to_string()))?;
+    let target = BindTarget {
+        network: Some(IpNet::V4(network)),
+        ports: address_ports(data.port, prefix_len.saturating_sub(32)),
+    };
+    Ok((data.subject, target))
+}
+
+fn target_v6(key: &Key<ebpf_policy::Ipv6PortKey>) -> Result<(Subject, BindTarget), EbpfguardError> {
+    let data = key.data();
+    let prefix_len = object_prefix_len(key.prefix_len(), SUBJECT_PREFIX_LEN, 128 + 16)?;
+    let addr = Ipv6Addr::from(data.addr);
+    let network = Ipv6Net::new(addr, prefix_len.min(128))
+        .map_err(|e| EbpfguardError::InvalidMapEntry(e.to_string()))?;
+    let target = BindTarget {
+        network: Some(IpNet::V6(network)),
+        ports: address_ports(data.port, prefix_len.saturating_sub(128)),
+    };
+    Ok((data.subject, target))
+}
+
+/// Returns the block of ports stored after a full address.
+fn address_ports(port: [u8; 2], prefix_len: u8) -> policy::PortRange {
+    policy::PortRange::from_prefix(u16::from_be_bytes(port), prefix_len)
 }
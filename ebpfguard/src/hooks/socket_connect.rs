@@ -1,113 +1,619 @@
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    net::{Ipv4Addr, Ipv6Addr},
+};
 
 use aya::{
-    maps::{AsyncPerfEventArray, HashMap, MapData},
+    maps::{lpm_trie::Key, AsyncPerfEventArray, HashMap, LpmTrie, MapData},
     programs::lsm::LsmLink,
 };
 use ebpfguard_common::{
     alerts as ebpf_alerts,
-    policy::{self as ebpf_policy, IpAddrs},
+    consts::{AF_INET, AF_INET6},
+    policy::{self as ebpf_policy, PROTOCOL_PREFIX_LEN},
+    subject::Subject,
 };
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use tokio::sync::mpsc::Receiver;
 
 use crate::{alerts, error::EbpfguardError, policy};
 
-use super::{perf_array_alerts, INODE_SUBJECT_MAP};
+use super::{
+    found, object_prefix_len, perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    socket_bind::BindTarget,
+    SubjectMap,
+};
 
 pub struct SocketConnect {
     #[allow(dead_code)]
     pub(crate) program_link: Option<LsmLink>,
-    pub(crate) allowed_map_v4: HashMap<MapData, u64, ebpf_policy::Ipv4Addrs>,
-    pub(crate) denied_map_v4: HashMap<MapData, u64, ebpf_policy::Ipv4Addrs>,
-    pub(crate) allowed_map_v6: HashMap<MapData, u64, ebpf_policy::Ipv6Addrs>,
-    pub(crate) denied_map_v6: HashMap<MapData, u64, ebpf_policy::Ipv6Addrs>,
+    pub(crate) maps: ConnectMaps,
+    pub(crate) subjects: SubjectMap,
     pub(crate) perf_array: AsyncPerfEventArray<MapData>,
 }
 
+/// LPM tries with the ports allowed and denied on all addresses and on IPv4
+/// and IPv6 networks for each protocol, and hash maps with the address
+/// families allowed and denied as a whole, used by the hooks matching
+/// connections by the address of the other end.
+pub(crate) struct ConnectMaps {
+    pub(crate) allowed_map:
+        LpmTrie<MapData, ebpf_policy::ProtocolPortKey, ebpf_policy::PrefixValue>,
+    pub(crate) denied_map: LpmTrie<MapData, ebpf_policy::ProtocolPortKey, ebpf_policy::PrefixValue>,
+    pub(crate) allowed_map_v4: LpmTrie<MapData, ebpf_policy::Ipv4Key, ebpf_policy::PrefixValue>,
+    pub(crate) denied_map_v4: LpmTrie<MapData, ebpf_policy::Ipv4Key, ebpf_policy::PrefixValue>,
+    pub(crate) allowed_map_v6: LpmTrie<MapData, ebpf_policy::Ipv6Key, ebpf_policy::PrefixValue>,
+    pub(crate) denied_map_v6: LpmTrie<MapData, ebpf_policy::Ipv6Key, ebpf_policy::PrefixValue>,
+    pub(crate) allowed_map_all: HashMap<MapData, ebpf_policy::FamilyKey, u8>,
+    pub(crate) denied_map_all: HashMap<MapData, ebpf_policy::FamilyKey, u8>,
+}
+
 impl SocketConnect {
+    /// Adds the policy. Networks and port rules are merged with the ones
+    /// already allowed or denied for the same subject.
     pub async fn add_policy(
         &mut self,
         policy: policy::SocketConnect,
     ) -> Result<(), EbpfguardError> {
-        let bin_inode = {
-            let mut map = INODE_SUBJECT_MAP.lock().await;
-            map.resolve_path(policy.subject)?
+        policy::validate::validate(&[policy::Policy::SocketConnect(policy.clone())], &[])?;
+
+        let (allow, deny) = targets(&policy.allow, &policy.deny, &policy.port_rules)?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
         };
 
-        let (allow_v4, allow_v6) = policy.allow.into_ebpf();
-        let (deny_v4, deny_v6) = policy.deny.into_ebpf();
+        let entries = SubjectEntries {
+            allow: allow.into_iter().collect(),
+            deny: deny.into_iter().collect(),
+            mode: policy.mode,
+        };
+        self.merge_entries(&policy.subject, subject_key, entries)
+    }
 
-        self.allowed_map_v4.insert(bin_inode, allow_v4, 0)?;
-        self.denied_map_v4.insert(bin_inode, deny_v4, 0)?;
-        self.allowed_map_v6.insert(bin_inode, allow_v6, 0)?;
-        self.denied_map_v6.insert(bin_inode, deny_v6, 0)?;
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
 
-        Ok(())
+        self.remove_entries(|subject| subject == subject_key)
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)
     }
 
+    /// Lists the policies, see [`connect_rules`].
     pub async fn list_policies(&self) -> Result<Vec<policy::SocketConnect>, EbpfguardError> {
         let mut policies = Vec::new();
 
-        for res in self.allowed_map_v4.iter() {
-            let (bin_inode, allow_v4) = res?;
-            let deny_v4 = self.denied_map_v4.get(&bin_inode, 0)?;
-            let allow_v6 = self.allowed_map_v6.get(&bin_inode, 0)?;
-            let deny_v6 = self.denied_map_v6.get(&bin_inode, 0)?;
-
+        for (subject_key, entries) in self.entries()? {
             let subject = {
-                let map = INODE_SUBJECT_MAP.lock().await;
-                map.resolve_inode(bin_inode)
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
             };
 
-            let allow = if allow_v4.all() && allow_v6.all() {
-                policy::Addresses::All
-            } else {
-                let mut addrs = Vec::new();
-                for addr in allow_v4.addrs.iter() {
-                    if *addr == 0 {
-                        break;
+            let mode = entries.mode;
+            let (allow, deny, port_rules) = connect_rules(entries);
+            policies.push(policy::SocketConnect {
+                subject,
+                mode,
+                allow,
+                deny,
+                port_rules,
+            });
+        }
+
+        Ok(policies)
+    }
+
+    pub async fn alerts(&mut self) -> Result<Receiver<alerts::SocketConnect>, EbpfguardError> {
+        perf_array_alerts::<ebpf_alerts::SocketConnect, alerts::SocketConnect>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+/// Object of the `socket_connect` map entries: an aligned block of ports for
+/// the protocol, on all addresses or on the given network. Networks wider
+/// than a single address are stored only with all ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ConnectTarget {
+    pub(crate) protocol: policy::Protocol,
+    pub(crate) network: Option<IpNet>,
+    pub(crate) ports: policy::PortRange,
+}
+
+impl ConnectTarget {
+    /// Returns the targets of the networks on all ports and protocols.
+    pub(crate) fn networks(addresses: &policy::Addresses) -> Vec<ConnectTarget> {
+        addresses
+            .clone()
+            .into_networks()
+            .into_iter()
+            .map(|network| ConnectTarget {
+                protocol: policy::Protocol::All,
+                network: Some(network),
+                ports: policy::PortRange::ALL,
+            })
+            .collect()
+    }
+
+    /// Returns the targets of the ports in the port rule. Fails with
+    /// [`EbpfguardError::UnsupportedPortRange`] if a range of ports is
+    /// combined with a network.
+    pub(crate) fn port_rule(
+        rule: &policy::PortRule,
+        ports: &policy::Ports,
+    ) -> Result<Vec<ConnectTarget>, EbpfguardError> {
+        let targets = match rule.addresses {
+            policy::Addresses::All => BindTarget::all_addresses(ports),
+            ref addresses => BindTarget::addresses(addresses, ports)?,
+        };
+        Ok(targets
+            .into_iter()
+            .map(|target| ConnectTarget {
+                protocol: rule.protocol,
+                network: target.network,
+                ports: target.ports,
+            })
+            .collect())
+    }
+
+    /// Returns the prefix length of the entry, without the subject and the
+    /// protocol.
+    fn prefix_len(&self) -> u8 {
+        BindTarget {
+            network: self.network,
+            ports: self.ports,
+        }
+        .prefix_len()
+    }
+}
+
+impl Display for ConnectTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.protocol.is_all() {
+            write!(f, "{} ", self.protocol)?;
+        }
+        match self.network {
+            Some(network) if self.ports == policy::PortRange::ALL => {
+                write!(f, "network {network}")
+            }
+            network => BindTarget {
+                network,
+                ports: self.ports,
+            }
+            .fmt(f),
+        }
+    }
+}
+
+/// Returns the allowed and denied targets of the addresses and port rules of
+/// a policy.
+pub(crate) fn targets(
+    allow: &policy::Addresses,
+    deny: &policy::Addresses,
+    port_rules: &[policy::PortRule],
+) -> Result<(Vec<ConnectTarget>, Vec<ConnectTarget>), EbpfguardError> {
+    let mut allow = ConnectTarget::networks(allow);
+    let mut deny = ConnectTarget::networks(deny);
+    for rule in port_rules {
+        allow.extend(ConnectTarget::port_rule(rule, &rule.allow)?);
+        deny.extend(ConnectTarget::port_rule(rule, &rule.deny)?);
+    }
+    Ok((allow, deny))
+}
+
+/// Returns the addresses allowed and denied on all ports and protocols and
+/// the port rules of the entries of a subject. Networks with the same
+/// protocol and ports are merged into one port rule.
+pub(crate) fn connect_rules(
+    entries: SubjectEntries<ConnectTarget>,
+) -> (policy::Addresses, policy::Addresses, Vec<policy::PortRule>) {
+    let mut allow = Vec::new();
+    let mut deny = Vec::new();
+    let mut rules = BTreeMap::new();
+    for (targets, allowed) in [(entries.allow, true), (entries.deny, false)] {
+        for target in targets {
+            match target.network {
+                Some(network)
+                    if target.protocol == policy::Protocol::All
+                        && target.ports == policy::PortRange::ALL =>
+                {
+                    if allowed {
+                        allow.push(network)
+                    } else {
+                        deny.push(network)
                     }
-                    addrs.push(IpAddr::V4(Ipv4Addr::from(addr.to_owned())));
                 }
-                for addr in allow_v6.addrs.iter() {
-                    if *addr == [0u8; 16] {
-                        break;
+                network => {
+                    let (allow, deny) = rules
+                        .entry((target.protocol, network))
+                        .or_insert_with(|| (Vec::new(), Vec::new()));
+                    if allowed {
+                        allow.push(target.ports)
+                    } else {
+                        deny.push(target.ports)
                     }
-                    addrs.push(IpAddr::V6(Ipv6Addr::from(addr.to_owned())));
                 }
-                policy::Addresses::Addresses(addrs)
+            }
+        }
+    }
+
+    let mut port_rules: Vec<(policy::Protocol, policy::Ports, policy::Ports, Vec<IpNet>)> =
+        Vec::new();
+    for ((protocol, network), (allow, deny)) in rules {
+        let allow = policy::Ports::from_ranges(allow);
+        let deny = policy::Ports::from_ranges(deny);
+        let Some(network) = network else {
+            port_rules.push((protocol, allow, deny, Vec::new()));
+            continue;
+        };
+        match port_rules.iter_mut().find(|(p, a, d, networks)| {
+            *p == protocol && *a == allow && *d == deny && !networks.is_empty()
+        }) {
+            Some((_, _, _, networks)) => networks.push(network),
+            None => port_rules.push((protocol, allow, deny, vec![network])),
+        }
+    }
+
+    (
+        policy::Addresses::from_networks(allow),
+        policy::Addresses::from_networks(deny),
+        port_rules
+            .into_iter()
+            .map(|(protocol, allow, deny, networks)| policy::PortRule {
+                protocol,
+                addresses: rule_addresses(networks),
+                allow,
+                deny,
+            })
+            .collect(),
+    )
+}
+
+/// Returns the addresses of a port rule with the given networks. Unlike
+/// [`policy::Addresses::from_networks`], never returns all addresses for
+/// networks with prefix length 0, which are stored differently.
+fn rule_addresses(networks: Vec<IpNet>) -> policy::Addresses {
+    if networks.is_empty() {
+        return policy::Addresses::All;
+    }
+    match policy::Addresses::from_networks(networks.clone()) {
+        policy::Addresses::All => policy::Addresses::Networks(networks),
+        addresses => addresses,
+    }
+}
+
+impl ConnectMaps {
+    pub(crate) fn entries(&self) -> Result<Entries<ConnectTarget>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for (map, allow) in [(&self.allowed_map, true), (&self.denied_map, false)] {
+            for res in map.iter() {
+                let (key, value) = res?;
+                let (subject_key, target) = target(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, value.flags);
+            }
+        }
+        for (map, allow) in [(&self.allowed_map_v4, true), (&self.denied_map_v4, false)] {
+            for res in map.iter() {
+                let (key, value) = res?;
+                let (subject_key, target) = target_v4(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, value.flags);
+            }
+        }
+        for (map, allow) in [(&self.allowed_map_v6, true), (&self.denied_map_v6, false)] {
+            for res in map.iter() {
+                let (key, value) = res?;
+                let (subject_key, target) = target_v6(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, value.flags);
+            }
+        }
+        for (map, allow) in [(&self.allowed_map_all, true), (&self.denied_map_all, false)] {
+            for res in map.iter() {
+                let (key, flags) = res?;
+                let (subject_key, target) = family_target(&key)?;
+                entries
+                    .entry(subject_key)
+                    .or_default()
+                    .insert_loaded(target, allow, flags);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub(crate) fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        if let Some(key) = family_key(subject, target) {
+            let map = if allow {
+                &self.allowed_map_all
+            } else {
+                &self.denied_map_all
             };
-            let deny = if deny_v4.all() && deny_v6.all() {
-                policy::Addresses::All
+            return Ok(found(map.get(&key, 0))?.is_some());
+        }
+        // Lookups in LPM tries return the longest matching prefix, so the
+        // stored prefix length has to be compared to find an exact match.
+        let value = match (target.network, allow) {
+            (None, true) => found(self.allowed_map.get(&key(subject, target), 0))?,
+            (None, false) => found(self.denied_map.get(&key(subject, target), 0))?,
+            (Some(IpNet::V4(network)), true) => found(
+                self.allowed_map_v4
+                    .get(&key_v4(subject, &network, target), 0),
+            )?,
+            (Some(IpNet::V4(network)), false) => found(
+                self.denied_map_v4
+                    .get(&key_v4(subject, &network, target), 0),
+            )?,
+            (Some(IpNet::V6(network)), true) => found(
+                self.allowed_map_v6
+                    .get(&key_v6(subject, &network, target), 0),
+            )?,
+            (Some(IpNet::V6(network)), false) => found(
+                self.denied_map_v6
+                    .get(&key_v6(subject, &network, target), 0),
+            )?,
+        };
+        Ok(value.map(|value| value.prefix_len) == Some(target.prefix_len()))
+    }
+
+    pub(crate) fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        if let Some(key) = family_key(subject, target) {
+            let map = if allow {
+                &mut self.allowed_map_all
             } else {
-                let mut addrs = Vec::new();
-                for addr in deny_v4.addrs.iter() {
-                    if *addr == 0 {
-                        break;
-                    }
-                    addrs.push(IpAddr::V4(Ipv4Addr::from(addr.to_owned())));
-                }
-                for addr in deny_v6.addrs.iter() {
-                    if *addr == [0u8; 16] {
-                        break;
-                    }
-                    addrs.push(IpAddr::V6(Ipv6Addr::from(addr.to_owned())));
-                }
-                policy::Addresses::Addresses(addrs)
+                &mut self.denied_map_all
             };
+            map.insert(key, mode.flags(), 0)?;
+            return Ok(());
+        }
+        let value = ebpf_policy::PrefixValue::new(target.prefix_len(), mode.flags());
+        match (target.network, allow) {
+            (None, true) => self.allowed_map.insert(&key(subject, target), value, 0)?,
+            (None, false) => self.denied_map.insert(&key(subject, target), value, 0)?,
+            (Some(IpNet::V4(network)), true) => {
+                self.allowed_map_v4
+                    .insert(&key_v4(subject, &network, target), value, 0)?
+            }
+            (Some(IpNet::V4(network)), false) => {
+                self.denied_map_v4
+                    .insert(&key_v4(subject, &network, target), value, 0)?
+            }
+            (Some(IpNet::V6(network)), true) => {
+                self.allowed_map_v6
+                    .insert(&key_v6(subject, &network, target), value, 0)?
+            }
+            (Some(IpNet::V6(network)), false) => {
+                self.denied_map_v6
+                    .insert(&key_v6(subject, &network, target), value, 0)?
+            }
+        }
+        Ok(())
+    }
 
-            policies.push(policy::SocketConnect {
-                subject,
-                allow,
-                deny,
-            });
+    pub(crate) fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        if let Some(key) = family_key(subject, target) {
+            let map = if allow {
+                &mut self.allowed_map_all
+            } else {
+                &mut self.denied_map_all
+            };
+            map.remove(&key)?;
+            return Ok(());
+        }
+        match (target.network, allow) {
+            (None, true) => self.allowed_map.remove(&key(subject, target))?,
+            (None, false) => self.denied_map.remove(&key(subject, target))?,
+            (Some(IpNet::V4(network)), true) => self
+                .allowed_map_v4
+                .remove(&key_v4(subject, &network, target))?,
+            (Some(IpNet::V4(network)), false) => self
+                .denied_map_v4
+                .remove(&key_v4(subject, &network, target))?,
+            (Some(IpNet::V6(network)), true) => self
+                .allowed_map_v6
+                .remove(&key_v6(subject, &network, target))?,
+            (Some(IpNet::V6(network)), false) => self
+                .denied_map_v6
+                .remove(&key_v6(subject, &network, target))?,
         }
+        Ok(())
+    }
+}
 
-        Ok(policies)
+impl PolicyMaps for SocketConnect {
+    const HOOK: &'static str = "socket_connect";
+
+    type Object = ConnectTarget;
+
+    fn describe(target: &ConnectTarget) -> String {
+        target.to_string()
     }
 
-    pub async fn alerts(&mut self) -> Result<Receiver<alerts::SocketConnect>, EbpfguardError> {
-        perf_array_alerts::<ebpf_alerts::SocketConnect, alerts::SocketConnect>(&mut self.perf_array)
-            .await
+    fn entries(&self) -> Result<Entries<ConnectTarget>, EbpfguardError> {
+        self.maps.entries()
     }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        self.maps.contains_entry(subject, target, allow)
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.insert_entry(subject, target, allow, mode)
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &ConnectTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.remove_entry(subject, target, allow)
+    }
+}
+
+fn key(subject_key: Subject, target: &ConnectTarget) -> Key<ebpf_policy::ProtocolPortKey> {
+    Key::new(
+        PROTOCOL_PREFIX_LEN + u32::from(target.prefix_len()),
+        ebpf_policy::ProtocolPortKey::new(subject_key, target.protocol.id(), target.ports.start),
+    )
+}
+
+fn key_v4(
+    subject_key: Subject,
+    network: &Ipv4Net,
+    target: &ConnectTarget,
+) -> Key<ebpf_policy::Ipv4Key> {
+    Key::new(
+        PROTOCOL_PREFIX_LEN + u32::from(target.prefix_len()),
+        ebpf_policy::Ipv4Key::new(
+            subject_key,
+            target.protocol.id(),
+            network.network().octets(),
+            target.ports.start,
+        ),
+    )
+}
+
+fn key_v6(
+    subject_key: Subject,
+    network: &Ipv6Net,
+    target: &ConnectTarget,
+) -> Key<ebpf_policy::Ipv6Key> {
+    Key::new(
+        PROTOCOL_PREFIX_LEN + u32::from(target.prefix_len()),
+        ebpf_policy::Ipv6Key::new(
+            subject_key,
+            target.protocol.id(),
+            network.network().octets(),
+            target.ports.start,
+        ),
+    )
+}
+
+/// Returns the key of the target in the maps with rules for all addresses of
+/// a family, or `None` if it's a rule for a narrower network or for ports.
+fn family_key(subject_key: Subject, target: &ConnectTarget) -> Option<ebpf_policy::FamilyKey> {
+    let family = match target.network? {
+        IpNet::V4(network) if network.prefix_len() == 0 => AF_INET,
+        IpNet::V6(network) if network.prefix_len() == 0 => AF_INET6,
+        _ => return None,
+    };
+    Some(ebpf_policy::FamilyKey::new(
+        subject_key,
+        family,
+        target.protocol.id(),
+    ))
+}
+
+fn family_target(key: &ebpf_policy::FamilyKey) -> Result<(Subject, ConnectTarget), EbpfguardError> {
+    let network = match key.family {
+        AF_INET => IpNet::V4(Ipv4Net::new(Ipv4Addr::UNSPECIFIED, 0).unwrap()),
+        _ => IpNet::V6(Ipv6Net::new(Ipv6Addr::UNSPECIFIED, 0).unwrap()),
+    };
+    let target = ConnectTarget {
+        protocol: protocol(key.protocol)?,
+        network: Some(network),
+        ports: policy::PortRange::ALL,
+    };
+    Ok((key.subject, target))
+}
+
+fn target(
+    key: &Key<ebpf_policy::ProtocolPortKey>,
+) -> Result<(Subject, ConnectTarget), EbpfguardError> {
+    let data = key.data();
+    let prefix_len = object_prefix_len(key.prefix_len(), PROTOCOL_PREFIX_LEN, 16)?;
+    let target = ConnectTarget {
+        protocol: protocol(data.protocol)?,
+        network: None,
+        ports: policy::PortRange::from_prefix(u16::from_be_bytes(data.port), prefix_len),
+    };
+    Ok((data.subject, target))
+}
+
+fn target_v4(key: &Key<ebpf_policy::Ipv4Key>) -> Result<(Subject, ConnectTarget), EbpfguardError> {
+    let data = key.data();
+    let prefix_len = object_prefix_len(key.prefix_len(), PROTOCOL_PREFIX_LEN, 32 + 16)?;
+    let network = Ipv4Net::new(Ipv4Addr::from(data.addr), prefix_len.min(32))
+        .map_err(|e| EbpfguardError::InvalidMapEntry(e.to_string()))?;
+    let target = ConnectTarget {
+        protocol: protocol(data.protocol)?,
+        network: Some(IpNet::V4(network)),
+        ports: policy::PortRange::from_prefix(
+            u16::from_be_bytes(data.port),
+            prefix_len.saturating_sub(32),
+        ),
+    };
+    Ok((data.subject, target))
+}
+
+fn target_v6(key: &Key<ebpf_policy::Ipv6Key>) -> Result<(Subject, ConnectTarget), EbpfguardError> {
+    let data = key.data();
+    let prefix_len = object_prefix_len(key.prefix_len(), PROTOCOL_PREFIX_LEN, 128 + 16)?;
+    let network = Ipv6Net::new(Ipv6Addr::from(data.addr), prefix_len.min(128))
+        .map_err(|e| EbpfguardError::InvalidMapEntry(e.to_string()))?;
+    let target = ConnectTarget {
+        protocol: protocol(data.protocol)?,
+        network: Some(IpNet::V6(network)),
+        ports: policy::PortRange::from_prefix(
+            u16::from_be_bytes(data.port),
+            prefix_len.saturating_sub(128),
+        ),
+    };
+    Ok((data.subject, target))
+}
+
+fn protocol(id: u8) -> Result<policy::Protocol, EbpfguardError> {
+    policy::Protocol::from_id(id)
+        .ok_or_else(|| EbpfguardError::InvalidMapEntry(format!("unknown protocol {id}")))
 }
@@ -0,0 +1,188 @@
+use aya::{
+    maps::{AsyncPerfEventArray, HashMap, MapData},
+    programs::lsm::LsmLink,
+};
+use ebpfguard_common::{
+    alerts as ebpf_alerts,
+    consts::{UNIX_PATH_MAX, UNIX_PATH_WILDCARD},
+    policy as ebpf_policy,
+    subject::Subject,
+};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{alerts, error::EbpfguardError, policy};
+
+use super::{
+    found, perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    SubjectMap,
+};
+
+/// Policies for connecting to Unix domain sockets. They are enforced by a
+/// separate program attached to `socket_connect` LSM hook, next to the one
+/// enforcing [`SocketConnect`](super::socket_connect::SocketConnect)
+/// policies.
+pub struct SocketConnectUnix {
+    #[allow(dead_code)]
+    pub(crate) program_link: Option<LsmLink>,
+    pub(crate) allowed_map: HashMap<MapData, ebpf_policy::UnixPathKey, u8>,
+    pub(crate) denied_map: HashMap<MapData, ebpf_policy::UnixPathKey, u8>,
+    pub(crate) subjects: SubjectMap,
+    pub(crate) perf_array: AsyncPerfEventArray<MapData>,
+}
+
+impl SocketConnectUnix {
+    /// Adds the policy. Paths are merged with the ones already allowed or
+    /// denied for the same subject.
+    pub async fn add_policy(
+        &mut self,
+        policy: policy::SocketConnectUnix,
+    ) -> Result<(), EbpfguardError> {
+        policy::validate::validate(&[policy::Policy::SocketConnectUnix(policy.clone())], &[])?;
+
+        let allow = policy.allow.into_sun_paths()?;
+        let deny = policy.deny.into_sun_paths()?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
+        };
+
+        let entries = SubjectEntries {
+            allow: allow.into_iter().collect(),
+            deny: deny.into_iter().collect(),
+            mode: policy.mode,
+        };
+        self.merge_entries(&policy.subject, subject_key, entries)
+    }
+
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<policy::SocketConnectUnix>, EbpfguardError> {
+        let mut policies = Vec::new();
+
+        for (subject_key, entries) in self.entries()? {
+            let subject = {
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
+            };
+
+            policies.push(policy::SocketConnectUnix {
+                subject,
+                mode: entries.mode,
+                allow: policy::UnixPaths::from_sun_paths(entries.allow.into_iter().collect()),
+                deny: policy::UnixPaths::from_sun_paths(entries.deny.into_iter().collect()),
+            });
+        }
+
+        Ok(policies)
+    }
+
+    pub async fn alerts(&mut self) -> Result<Receiver<alerts::SocketConnectUnix>, EbpfguardError> {
+        perf_array_alerts::<ebpf_alerts::SocketConnectUnix, alerts::SocketConnectUnix>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+impl PolicyMaps for SocketConnectUnix {
+    const HOOK: &'static str = "socket_connect_unix";
+
+    type Object = [u8; UNIX_PATH_MAX];
+
+    fn describe(path: &[u8; UNIX_PATH_MAX]) -> String {
+        match *path {
+            UNIX_PATH_WILDCARD => "all sockets".to_owned(),
+            ref path => format!("socket {}", policy::UnixPath::from_sun_path(path)),
+        }
+    }
+
+    fn entries(&self) -> Result<Entries<[u8; UNIX_PATH_MAX]>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for res in self.allowed_map.iter() {
+            let (key, flags) = res?;
+            entries
+                .entry(key.subject)
+                .or_default()
+                .insert_loaded(key.path, true, flags);
+        }
+        for res in self.denied_map.iter() {
+            let (key, flags) = res?;
+            entries
+                .entry(key.subject)
+                .or_default()
+                .insert_loaded(key.path, false, flags);
+        }
+
+        Ok(entries)
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        path: &[u8; UNIX_PATH_MAX],
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        let map = if allow {
+            &self.allowed_map
+        } else {
+            &self.denied_map
+        };
+        let res = map.get(&ebpf_policy::UnixPathKey::new(subject, *path), 0);
+        Ok(found(res)?.is_some())
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        path: &[u8; UNIX_PATH_MAX],
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.insert(
+            ebpf_policy::UnixPathKey::new(subject, *path),
+            mode.flags(),
+            0,
+        )?;
+        Ok(())
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        path: &[u8; UNIX_PATH_MAX],
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.remove(&ebpf_policy::UnixPathKey::new(subject, *path))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use aya::{
+    maps::{AsyncPerfEventArray, HashMap, MapData},
+    programs::lsm::LsmLink,
+};
+use ebpfguard_common::{alerts as ebpf_alerts, policy as ebpf_policy, subject::Subject};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{alerts, error::EbpfguardError, policy};
+
+use super::{
+    found, perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    SubjectMap,
+};
+
+/// Policies for creating sockets, matched by the family, the type and the
+/// protocol given to `socket`. Objects are stored as these three numbers,
+/// see [`policy::Sockets::to_ids`].
+pub struct SocketCreate {
+    #[allow(dead_code)]
+    pub(crate) program_link: Option<LsmLink>,
+    pub(crate) allowed_map: HashMap<MapData, ebpf_policy::SocketKey, u8>,
+    pub(crate) denied_map: HashMap<MapData, ebpf_policy::SocketKey, u8>,
+    pub(crate) subjects: SubjectMap,
+    pub(crate) perf_array: AsyncPerfEventArray<MapData>,
+}
+
+impl SocketCreate {
+    /// Adds the policy. Sockets are merged with the ones already allowed or
+    /// denied for the same subject.
+    pub async fn add_policy(&mut self, policy: policy::SocketCreate) -> Result<(), EbpfguardError> {
+        policy::validate::validate(&[policy::Policy::SocketCreate(policy.clone())], &[])?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
+        };
+
+        let entries = SubjectEntries {
+            allow: policy.allow.to_ids().into_iter().collect(),
+            deny: policy.deny.to_ids().into_iter().collect(),
+            mode: policy.mode,
+        };
+        self.merge_entries(&policy.subject, subject_key, entries)
+    }
+
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<policy::SocketCreate>, EbpfguardError> {
+        let mut policies = Vec::new();
+
+        for (subject_key, entries) in self.entries()? {
+            let subject = {
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
+            };
+
+            policies.push(policy::SocketCreate {
+                subject,
+                mode: entries.mode,
+                allow: policy::Sockets::from_ids(entries.allow.into_iter().collect())?,
+                deny: policy::Sockets::from_ids(entries.deny.into_iter().collect())?,
+            });
+        }
+
+        Ok(policies)
+    }
+
+    pub async fn alerts(&mut self) -> Result<Receiver<alerts::SocketCreate>, EbpfguardError> {
+        perf_array_alerts::<ebpf_alerts::SocketCreate, alerts::SocketCreate>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+impl PolicyMaps for SocketCreate {
+    const HOOK: &'static str = "socket_create";
+
+    type Object = [u32; 3];
+
+    fn describe(ids: &[u32; 3]) -> String {
+        match policy::Sockets::from_ids(vec![*ids]) {
+            Ok(policy::Sockets::All) => "all sockets".to_owned(),
+            Ok(policy::Sockets::Sockets(kinds)) => format!("{} sockets", kinds[0]),
+            Err(_) => format!("sockets {ids:?}"),
+        }
+    }
+
+    fn entries(&self) -> Result<Entries<[u32; 3]>, EbpfguardError> {
+        let mut entries = Entries::new();
+
+        for res in self.allowed_map.iter() {
+            let (key, flags) = res?;
+            entries.entry(key.subject).or_default().insert_loaded(
+                [key.family, key.socket_type, key.protocol],
+                true,
+                flags,
+            );
+        }
+        for res in self.denied_map.iter() {
+            let (key, flags) = res?;
+            entries.entry(key.subject).or_default().insert_loaded(
+                [key.family, key.socket_type, key.protocol],
+                false,
+                flags,
+            );
+        }
+
+        Ok(entries)
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        ids: &[u32; 3],
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        let map = if allow {
+            &self.allowed_map
+        } else {
+            &self.denied_map
+        };
+        let res = map.get(&socket_key(subject, ids), 0);
+        Ok(found(res)?.is_some())
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        ids: &[u32; 3],
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.insert(socket_key(subject, ids), mode.flags(), 0)?;
+        Ok(())
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        ids: &[u32; 3],
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        let map = if allow {
+            &mut self.allowed_map
+        } else {
+            &mut self.denied_map
+        };
+        map.remove(&socket_key(subject, ids))?;
+        Ok(())
+    }
+}
+
+fn socket_key(
+    subject: Subject,
+    &[family, socket_type, protocol]: &[u32; 3],
+) -> ebpf_policy::SocketKey {
+    ebpf_policy::SocketKey::new(subject, family, socket_type, protocol)
+}
@@ -0,0 +1,141 @@
+use aya::{
+    maps::{AsyncPerfEventArray, MapData},
+    programs::lsm::LsmLink,
+};
+use ebpfguard_common::{alerts as ebpf_alerts, subject::Subject};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{alerts, error::EbpfguardError, policy};
+
+use super::{
+    perf_array_alerts,
+    reconcile::{Entries, PolicyMaps, SubjectEntries},
+    socket_bind::{port_rules, targets, BindTarget, PortMaps},
+    SubjectMap,
+};
+
+/// Policies for listening on sockets. Sockets are matched by their local
+/// address and port, with the same rules as
+/// [`SocketBind`](super::socket_bind::SocketBind) policies.
+pub struct SocketListen {
+    #[allow(dead_code)]
+    pub(crate) program_link: Option<LsmLink>,
+    pub(crate) maps: PortMaps,
+    pub(crate) subjects: SubjectMap,
+    pub(crate) perf_array: AsyncPerfEventArray<MapData>,
+}
+
+impl SocketListen {
+    /// Adds the policy. Ports and address rules are merged with the ones
+    /// already allowed or denied for the same subject. Ranges are stored as
+    /// aligned blocks of ports.
+    pub async fn add_policy(&mut self, policy: policy::SocketListen) -> Result<(), EbpfguardError> {
+        policy::validate::validate(&[policy::Policy::SocketListen(policy.clone())], &[])?;
+
+        let (allow, deny) = targets(&policy.allow, &policy.deny, &policy.address_rules)?;
+
+        let subject_key = {
+            let mut map = self.subjects.lock().await;
+            map.resolve_subject(policy.subject.clone(), Self::HOOK)?
+        };
+
+        let entries = SubjectEntries {
+            allow: allow.into_iter().collect(),
+            deny: deny.into_iter().collect(),
+            mode: policy.mode,
+        };
+        self.merge_entries(&policy.subject, subject_key, entries)
+    }
+
+    /// Removes policies of the given subject.
+    pub async fn remove_policy(
+        &mut self,
+        subject: &policy::PolicySubject,
+    ) -> Result<(), EbpfguardError> {
+        let subject_key = {
+            let map = self.subjects.lock().await;
+            map.subject(subject)?
+        };
+
+        self.remove_entries(|subject| subject == subject_key)
+    }
+
+    /// Removes all policies.
+    pub fn clear(&mut self) -> Result<(), EbpfguardError> {
+        self.remove_entries(|_| true)
+    }
+
+    /// Lists the policies, see [`port_rules`].
+    pub async fn list_policies(&self) -> Result<Vec<policy::SocketListen>, EbpfguardError> {
+        let mut policies = Vec::new();
+
+        for (subject_key, entries) in self.entries()? {
+            let subject = {
+                let mut map = self.subjects.lock().await;
+                map.policy_subject(subject_key)
+            };
+
+            let mode = entries.mode;
+            let (allow, deny, address_rules) = port_rules(entries);
+            policies.push(policy::SocketListen {
+                subject,
+                mode,
+                allow,
+                deny,
+                address_rules,
+            });
+        }
+
+        Ok(policies)
+    }
+
+    pub async fn alerts(&mut self) -> Result<Receiver<alerts::SocketListen>, EbpfguardError> {
+        perf_array_alerts::<ebpf_alerts::SocketListen, alerts::SocketListen>(
+            &mut self.perf_array,
+            &self.subjects,
+        )
+        .await
+    }
+}
+
+impl PolicyMaps for SocketListen {
+    const HOOK: &'static str = "socket_listen";
+
+    type Object = BindTarget;
+
+    fn describe(target: &BindTarget) -> String {
+        target.to_string()
+    }
+
+    fn entries(&self) -> Result<Entries<BindTarget>, EbpfguardError> {
+        self.maps.entries()
+    }
+
+    fn contains_entry(
+        &self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+    ) -> Result<bool, EbpfguardError> {
+        self.maps.contains_entry(subject, target, allow)
+    }
+
+    fn insert_entry(
+        &mut self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+        mode: policy::PolicyMode,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.insert_entry(subject, target, allow, mode)
+    }
+
+    fn remove_entry(
+        &mut self,
+        subject: Subject,
+        target: &BindTarget,
+        allow: bool,
+    ) -> Result<(), EbpfguardError> {
+        self.maps.remove_entry(subject, target, allow)
+    }
+}
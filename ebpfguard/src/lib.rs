@@ -12,6 +12,7 @@ pub mod fs;
 pub mod hooks;
 pub mod manager;
 pub mod policy;
+pub(crate) mod watch;
 
 pub use manager::PolicyManager;
 pub use policy::inode::InodeSubjectMap;
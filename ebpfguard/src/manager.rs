@@ -1,22 +1,43 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use aya::{
     include_bytes_aligned,
     programs::{lsm::LsmLink, Lsm},
     Bpf, BpfLoader, Btf,
 };
+use ebpfguard_common::inode::Inode;
+use tokio::sync::Mutex;
 
 use crate::{
     error::EbpfguardError,
     hooks::{
-        bprm_check_security::BprmCheckSecurity, file_open::FileOpen, sb_mount::SbMount,
-        sb_remount::SbRemount, sb_umount::SbUmount, socket_bind::SocketBind,
-        socket_connect::SocketConnect, task_fix_setuid::TaskFixSetuid, All,
+        bprm_check_security::BprmCheckSecurity,
+        defaults::Defaults,
+        file_open::FileOpen,
+        inet_conn_request::InetConnRequest,
+        sb_mount::SbMount,
+        sb_remount::SbRemount,
+        sb_umount::SbUmount,
+        socket_bind::{PortMaps, SocketBind},
+        socket_connect::{ConnectMaps, SocketConnect},
+        socket_connect_unix::SocketConnectUnix,
+        socket_create::SocketCreate,
+        socket_listen::SocketListen,
+        task_fix_setuid::TaskFixSetuid,
+        All, SubjectMap,
     },
+    policy::inode::InodeEntry,
+    watch::PathWatcher,
+    InodeSubjectMap,
 };
 
 pub struct PolicyManager {
     bpf: Bpf,
+    subjects: SubjectMap,
 }
 
 impl PolicyManager {
@@ -60,63 +81,106 @@ impl PolicyManager {
         }
 
         #[cfg(debug_assertions)]
-        let bpf = BpfLoader::new()
+        let mut bpf = BpfLoader::new()
             .map_pin_path(&bpf_path)
             .load(include_bytes_aligned!(
                 "../../ebpfguard-ebpf/ebpfguard.debug.obj"
             ))?;
         #[cfg(not(debug_assertions))]
-        let bpf = BpfLoader::new()
+        let mut bpf = BpfLoader::new()
             .map_pin_path(&bpf_path)
             .load(include_bytes_aligned!(
                 "../../ebpfguard-ebpf/ebpfguard.release.obj"
             ))?;
 
-        Ok(Self { bpf })
+        let store = bpf.take_map("INODE_INFO").unwrap().try_into()?;
+        let subjects = Arc::new(Mutex::new(InodeSubjectMap::new(store)?));
+
+        Ok(Self { bpf, subjects })
+    }
+
+    /// Returns the paths and metadata of the inodes used in the policies,
+    /// including the ones added by other processes managing the same maps.
+    pub async fn inodes(&self) -> Result<Vec<(Inode, InodeEntry)>, EbpfguardError> {
+        let mut map = self.subjects.lock().await;
+        map.entries()
     }
 
     /// Attaches and returns a handle to all LSM hooks.
     pub fn attach_all(&mut self) -> Result<All, EbpfguardError> {
+        let defaults = self.manage_defaults()?;
         let bprm_check_security = self.attach_bprm_check_security()?;
         let file_open = self.attach_file_open()?;
+        let inet_conn_request = self.attach_inet_conn_request()?;
         let sb_mount = self.attach_sb_mount()?;
         let sb_remount = self.attach_sb_remount()?;
         let sb_umount = self.attach_sb_umount()?;
         let socket_bind = self.attach_socket_bind()?;
         let socket_connect = self.attach_socket_connect()?;
+        let socket_connect_unix = self.attach_socket_connect_unix()?;
+        let socket_create = self.attach_socket_create()?;
+        let socket_listen = self.attach_socket_listen()?;
         let task_fix_setuid = self.attach_task_fix_setuid()?;
 
         Ok(All {
+            defaults,
             bprm_check_security,
             file_open,
+            inet_conn_request,
             sb_mount,
             sb_remount,
             sb_umount,
             socket_bind,
             socket_connect,
+            socket_connect_unix,
+            socket_create,
+            socket_listen,
             task_fix_setuid,
+            subjects: self.subjects.clone(),
         })
     }
 
     pub fn manage_all(&mut self) -> Result<All, EbpfguardError> {
+        let defaults = self.manage_defaults()?;
         let bprm_check_security = self.manage_bprm_check_security()?;
         let file_open = self.manage_file_open()?;
+        let inet_conn_request = self.manage_inet_conn_request()?;
         let sb_mount = self.manage_sb_mount()?;
         let sb_remount = self.manage_sb_remount()?;
         let sb_umount = self.manage_sb_umount()?;
         let socket_bind = self.manage_socket_bind()?;
         let socket_connect = self.manage_socket_connect()?;
+        let socket_connect_unix = self.manage_socket_connect_unix()?;
+        let socket_create = self.manage_socket_create()?;
+        let socket_listen = self.manage_socket_listen()?;
         let task_fix_setuid = self.manage_task_fix_setuid()?;
 
         Ok(All {
+            defaults,
             bprm_check_security,
             file_open,
+            inet_conn_request,
             sb_mount,
             sb_remount,
             sb_umount,
             socket_bind,
             socket_connect,
+            socket_connect_unix,
+            socket_create,
+            socket_listen,
             task_fix_setuid,
+            subjects: self.subjects.clone(),
+        })
+    }
+
+    /// Returns a handle to the default actions of all hooks. They don't
+    /// belong to any program, so there is nothing to attach.
+    pub fn manage_defaults(&mut self) -> Result<Defaults, EbpfguardError> {
+        let map = self.bpf.take_map("DEFAULT_ACTIONS").unwrap().try_into()?;
+
+        Ok(Defaults {
+            map,
+            subjects: self.subjects.clone(),
         })
     }
 
@@ -129,6 +193,26 @@ impl PolicyManager {
     }
 
     pub fn manage_bprm_check_security(&mut self) -> Result<BprmCheckSecurity, EbpfguardError> {
+        let allowed_map = self
+            .bpf
+            .take_map("ALLOWED_BPRM_CHECK_SECURITY")
+            .unwrap()
+            .try_into()?;
+        let denied_map = self
+            .bpf
+            .take_map("DENIED_BPRM_CHECK_SECURITY")
+            .unwrap()
+            .try_into()?;
+        let subject_flags_map = self
+            .bpf
+            .take_map("BPRM_CHECK_SECURITY_SUBJECT_FLAGS")
+            .unwrap()
+            .try_into()?;
+        let settings_map = self
+            .bpf
+            .take_map("BPRM_CHECK_SECURITY_SETTINGS")
+            .unwrap()
+            .try_into()?;
         let perf_array = self
             .bpf
             .take_map("ALERT_BPRM_CHECK_SECURITY")
@@ -137,6 +221,11 @@ impl PolicyManager {
 
         Ok(BprmCheckSecurity {
             program_link: None,
+            allowed_map,
+            denied_map,
+            subject_flags_map,
+            settings_map,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
@@ -152,13 +241,40 @@ impl PolicyManager {
     pub fn manage_file_open(&mut self) -> Result<FileOpen, EbpfguardError> {
         let allowed_map = self.bpf.take_map("ALLOWED_FILE_OPEN").unwrap().try_into()?;
         let denied_map = self.bpf.take_map("DENIED_FILE_OPEN").unwrap().try_into()?;
+        let allowed_prefix_map = self
+            .bpf
+            .take_map("ALLOWED_FILE_OPEN_PREFIX")
+            .unwrap()
+            .try_into()?;
+        let denied_prefix_map = self
+            .bpf
+            .take_map("DENIED_FILE_OPEN_PREFIX")
+            .unwrap()
+            .try_into()?;
+        let settings_map = self
+            .bpf
+            .take_map("FILE_OPEN_SETTINGS")
+            .unwrap()
+            .try_into()?;
+        let subject_flags_map = self
+            .bpf
+            .take_map("FILE_OPEN_SUBJECT_FLAGS")
+            .unwrap()
+            .try_into()?;
         let perf_array = self.bpf.take_map("ALERT_FILE_OPEN").unwrap().try_into()?;
 
         Ok(FileOpen {
             program_link: None,
             allowed_map,
             denied_map,
+            allowed_prefix_map,
+            denied_prefix_map,
+            settings_map,
+            subject_flags_map,
+            subjects: self.subjects.clone(),
             perf_array,
+            watched: BTreeMap::new(),
+            watcher: PathWatcher::default(),
         })
     }
 
@@ -191,6 +307,7 @@ impl PolicyManager {
             program_link: None,
             allowed_map,
             denied_map,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
@@ -212,6 +329,7 @@ impl PolicyManager {
             program_link: None,
             allowed_map,
             denied_map,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
@@ -237,6 +355,7 @@ impl PolicyManager {
             program_link: None,
             allowed_map,
             denied_map,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
@@ -258,6 +377,7 @@ impl PolicyManager {
             program_link: None,
             allowed_map,
             denied_map,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
@@ -271,26 +391,59 @@ impl PolicyManager {
     }
 
     pub fn manage_socket_bind(&mut self) -> Result<SocketBind, EbpfguardError> {
-        let allowed_map = self
-            .bpf
-            .take_map("ALLOWED_SOCKET_BIND")
-            .unwrap()
-            .try_into()?;
-        let denied_map = self
+        let maps = self.port_maps("SOCKET_BIND")?;
+        let perf_array = self.bpf.take_map("ALERT_SOCKET_BIND").unwrap().try_into()?;
+
+        Ok(SocketBind {
+            program_link: None,
+            maps,
+            subjects: self.subjects.clone(),
+            perf_array,
+        })
+    }
+
+    pub fn attach_socket_listen(&mut self) -> Result<SocketListen, EbpfguardError> {
+        let mut socket_listen = self.manage_socket_listen()?;
+        let program_link = self.attach_program("socket_listen")?;
+        socket_listen.program_link = Some(program_link);
+
+        Ok(socket_listen)
+    }
+
+    pub fn manage_socket_listen(&mut self) -> Result<SocketListen, EbpfguardError> {
+        let maps = self.port_maps("SOCKET_LISTEN")?;
+        let perf_array = self
             .bpf
-            .take_map("DENIED_SOCKET_BIND")
+            .take_map("ALERT_SOCKET_LISTEN")
             .unwrap()
             .try_into()?;
-        let perf_array = self.bpf.take_map("ALERT_SOCKET_BIND").unwrap().try_into()?;
 
-        Ok(SocketBind {
+        Ok(SocketListen {
             program_link: None,
-            allowed_map,
-            denied_map,
+            maps,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
 
+    /// Takes the port maps of a hook, named after it (e.g. `SOCKET_BIND` for
+    /// `ALLOWED_SOCKET_BIND`, `DENIED_SOCKET_BIND_V4` and so on).
+    fn port_maps(&mut self, name: &str) -> Result<PortMaps, EbpfguardError> {
+        let mut take = |prefix: &str, suffix: &str| {
+            self.bpf
+                .take_map(&format!("{prefix}_{name}{suffix}"))
+                .unwrap()
+        };
+        Ok(PortMaps {
+            allowed_map: take("ALLOWED", "").try_into()?,
+            denied_map: take("DENIED", "").try_into()?,
+            allowed_map_v4: take("ALLOWED", "_V4").try_into()?,
+            denied_map_v4: take("DENIED", "_V4").try_into()?,
+            allowed_map_v6: take("ALLOWED", "_V6").try_into()?,
+            denied_map_v6: take("DENIED", "_V6").try_into()?,
+        })
+    }
+
     pub fn attach_socket_connect(&mut self) -> Result<SocketConnect, EbpfguardError> {
         let mut socket_connect = self.manage_socket_connect()?;
         let program_link = self.attach_program("socket_connect")?;
@@ -300,46 +453,156 @@ impl PolicyManager {
     }
 
     pub fn manage_socket_connect(&mut self) -> Result<SocketConnect, EbpfguardError> {
-        let allowed_map_v4 = self
+        let maps = self.connect_maps("SOCKET_CONNECT")?;
+        let perf_array = self
+            .bpf
+            .take_map("ALERT_SOCKET_CONNECT")
+            .unwrap()
+            .try_into()?;
+
+        Ok(SocketConnect {
+            program_link: None,
+            maps,
+            subjects: self.subjects.clone(),
+            perf_array,
+        })
+    }
+
+    /// Attaches the `inet_conn_request` program together with the program
+    /// recording the subjects of listening sockets, which is attached to
+    /// `socket_listen` LSM hook.
+    pub fn attach_inet_conn_request(&mut self) -> Result<InetConnRequest, EbpfguardError> {
+        let mut inet_conn_request = self.manage_inet_conn_request()?;
+        let listen_program_link =
+            self.attach_program_to("inet_conn_request_listen", "socket_listen")?;
+        inet_conn_request.listen_program_link = Some(listen_program_link);
+        let program_link = self.attach_program("inet_conn_request")?;
+        inet_conn_request.program_link = Some(program_link);
+
+        Ok(inet_conn_request)
+    }
+
+    pub fn manage_inet_conn_request(&mut self) -> Result<InetConnRequest, EbpfguardError> {
+        let maps = self.connect_maps("INET_CONN_REQUEST")?;
+        let settings_map = self
             .bpf
-            .take_map("ALLOWED_SOCKET_CONNECT_V4")
+            .take_map("INET_CONN_REQUEST_SETTINGS")
             .unwrap()
             .try_into()?;
-        let denied_map_v4 = self
+        let perf_array = self
             .bpf
-            .take_map("DENIED_SOCKET_CONNECT_V4")
+            .take_map("ALERT_INET_CONN_REQUEST")
             .unwrap()
             .try_into()?;
-        let allowed_map_v6 = self
+
+        Ok(InetConnRequest {
+            program_link: None,
+            listen_program_link: None,
+            maps,
+            settings_map,
+            subjects: self.subjects.clone(),
+            perf_array,
+        })
+    }
+
+    /// Takes the connect maps of a hook, named after it (e.g.
+    /// `SOCKET_CONNECT` for `ALLOWED_SOCKET_CONNECT`,
+    /// `DENIED_SOCKET_CONNECT_V4`, `ALLOWED_SOCKET_CONNECT_ALL` and so on).
+    fn connect_maps(&mut self, name: &str) -> Result<ConnectMaps, EbpfguardError> {
+        let mut take = |prefix: &str, suffix: &str| {
+            self.bpf
+                .take_map(&format!("{prefix}_{name}{suffix}"))
+                .unwrap()
+        };
+        Ok(ConnectMaps {
+            allowed_map: take("ALLOWED", "").try_into()?,
+            denied_map: take("DENIED", "").try_into()?,
+            allowed_map_v4: take("ALLOWED", "_V4").try_into()?,
+            denied_map_v4: take("DENIED", "_V4").try_into()?,
+            allowed_map_v6: take("ALLOWED", "_V6").try_into()?,
+            denied_map_v6: take("DENIED", "_V6").try_into()?,
+            allowed_map_all: take("ALLOWED", "_ALL").try_into()?,
+            denied_map_all: take("DENIED", "_ALL").try_into()?,
+        })
+    }
+
+    pub fn attach_socket_connect_unix(&mut self) -> Result<SocketConnectUnix, EbpfguardError> {
+        let mut socket_connect_unix = self.manage_socket_connect_unix()?;
+        let program_link = self.attach_program_to("socket_connect_unix", "socket_connect")?;
+        socket_connect_unix.program_link = Some(program_link);
+
+        Ok(socket_connect_unix)
+    }
+
+    pub fn manage_socket_connect_unix(&mut self) -> Result<SocketConnectUnix, EbpfguardError> {
+        let allowed_map = self
             .bpf
-            .take_map("ALLOWED_SOCKET_CONNECT_V6")
+            .take_map("ALLOWED_SOCKET_CONNECT_UNIX")
             .unwrap()
             .try_into()?;
-        let denied_map_v6 = self
+        let denied_map = self
             .bpf
-            .take_map("DENIED_SOCKET_CONNECT_V6")
+            .take_map("DENIED_SOCKET_CONNECT_UNIX")
             .unwrap()
             .try_into()?;
         let perf_array = self
             .bpf
-            .take_map("ALERT_SOCKET_CONNECT")
+            .take_map("ALERT_SOCKET_CONNECT_UNIX")
             .unwrap()
             .try_into()?;
 
-        Ok(SocketConnect {
+        Ok(SocketConnectUnix {
             program_link: None,
-            allowed_map_v4,
-            denied_map_v4,
-            allowed_map_v6,
-            denied_map_v6,
+            allowed_map,
+            denied_map,
+            subjects: self.subjects.clone(),
+            perf_array,
+        })
+    }
+
+    pub fn attach_socket_create(&mut self) -> Result<SocketCreate, EbpfguardError> {
+        let mut socket_create = self.manage_socket_create()?;
+        let program_link = self.attach_program("socket_create")?;
+        socket_create.program_link = Some(program_link);
+
+        Ok(socket_create)
+    }
+
+    pub fn manage_socket_create(&mut self) -> Result<SocketCreate, EbpfguardError> {
+        let allowed_map = self
+            .bpf
+            .take_map("ALLOWED_SOCKET_CREATE")
+            .unwrap()
+            .try_into()?;
+        let denied_map = self
+            .bpf
+            .take_map("DENIED_SOCKET_CREATE")
+            .unwrap()
+            .try_into()?;
+        let perf_array = self
+            .bpf
+            .take_map("ALERT_SOCKET_CREATE")
+            .unwrap()
+            .try_into()?;
+
+        Ok(SocketCreate {
+            program_link: None,
+            allowed_map,
+            denied_map,
+            subjects: self.subjects.clone(),
             perf_array,
         })
     }
 
     fn attach_program(&mut self, name: &str) -> Result<LsmLink, EbpfguardError> {
+        self.attach_program_to(name, name)
+    }
+
+    /// Attaches the program with the given name to the given LSM hook.
+    fn attach_program_to(&mut self, name: &str, hook: &str) -> Result<LsmLink, EbpfguardError> {
         let btf = Btf::from_sys_fs()?;
         let program: &mut Lsm = self.bpf.program_mut(name).unwrap().try_into()?;
-        program.load(name, &btf)?;
+        program.load(hook, &btf)?;
         let link_id = program.attach()?;
         let link = program.take_link(link_id)?;
 
@@ -0,0 +1,117 @@
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::EbpfguardError;
+
+const PASSWD: &str = "/etc/passwd";
+const GROUP: &str = "/etc/group";
+
+/// User or group, given either by name or by numeric ID. Names are resolved
+/// through `/etc/passwd` and `/etc/group`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Account {
+    Id(u32),
+    Name(String),
+}
+
+impl Display for Account {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Account::Id(id) => write!(f, "{id}"),
+            Account::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl Account {
+    /// Resolves the account to a user ID.
+    pub fn uid(&self) -> Result<u32, EbpfguardError> {
+        self.id(PASSWD, "user")
+    }
+
+    /// Resolves the account to a group ID.
+    pub fn gid(&self) -> Result<u32, EbpfguardError> {
+        self.id(GROUP, "group")
+    }
+
+    /// Returns the user with the given ID, by name if it's known.
+    pub fn from_uid(uid: u32) -> Self {
+        Self::from_id(PASSWD, uid)
+    }
+
+    /// Returns the group with the given ID, by name if it's known.
+    pub fn from_gid(gid: u32) -> Self {
+        Self::from_id(GROUP, gid)
+    }
+
+    fn id(&self, db: &str, kind: &'static str) -> Result<u32, EbpfguardError> {
+        match self {
+            Account::Id(id) => Ok(*id),
+            Account::Name(name) => {
+                let contents = fs::read_to_string(db)?;
+                find_id(&contents, name).ok_or_else(|| EbpfguardError::UnknownAccount {
+                    kind,
+                    name: name.to_owned(),
+                })
+            }
+        }
+    }
+
+    fn from_id(db: &str, id: u32) -> Self {
+        fs::read_to_string(db)
+            .ok()
+            .and_then(|contents| find_name(&contents, id).map(str::to_owned))
+            .map_or(Account::Id(id), Account::Name)
+    }
+}
+
+/// Returns the names and IDs from the contents of `/etc/passwd` or
+/// `/etc/group`. Both keep the ID in the third field.
+fn entries(contents: &str) -> impl Iterator<Item = (&str, u32)> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((name, id))
+        })
+}
+
+fn find_id(contents: &str, name: &str) -> Option<u32> {
+    entries(contents)
+        .find(|(n, _)| *n == name)
+        .map(|(_, id)| id)
+}
+
+fn find_name(contents: &str, id: u32) -> Option<&str> {
+    entries(contents).find(|(_, i)| *i == id).map(|(n, _)| n)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_entries() {
+        let passwd = "\
+# comment
+root:x:0:0:root:/root:/bin/bash
+www-data:x:33:33:www-data:/var/www:/usr/sbin/nologin
+broken
+";
+        assert_eq!(find_id(passwd, "www-data"), Some(33));
+        assert_eq!(find_id(passwd, "nobody"), None);
+        assert_eq!(find_name(passwd, 0), Some("root"));
+        assert_eq!(find_name(passwd, 1000), None);
+
+        let group = "wheel:x:10:alice,bob\n";
+        assert_eq!(find_id(group, "wheel"), Some(10));
+    }
+}
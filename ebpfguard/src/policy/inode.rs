@@ -1,34 +1,560 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs as std_fs,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::{error::EbpfguardError, fs};
+use aya::maps::{HashMap as BpfHashMap, MapData};
+use ebpfguard_common::{
+    consts::{CGROUP_WILDCARD, GID_WILDCARD, INODE_WILDCARD, SUBJECT_WILDCARD, UID_WILDCARD},
+    inode::{Inode, InodeInfo},
+    subject::Subject,
+};
 
-use super::PolicySubject;
+use crate::{error::EbpfguardError, fs, hooks::found};
 
+use super::{account::Account, Paths, PolicySubject};
+
+/// Maximum number of directory entries visited by a single filesystem scan.
+const MAX_SCAN_ENTRIES: usize = 65536;
+
+/// Mount point of the cgroup v2 hierarchy.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Metadata of an indexed inode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InodeEntry {
+    /// Path which the inode was resolved from.
+    pub path: PathBuf,
+    /// Name of the policy which added the inode (e.g. `file_open`). `None`
+    /// for inodes found by scanning the filesystem or inspecting processes.
+    pub policy: Option<String>,
+    /// Time when the inode was added.
+    pub added: SystemTime,
+}
+
+impl InodeEntry {
+    fn new(path: PathBuf, policy: Option<&str>) -> Self {
+        Self {
+            path,
+            policy: policy.map(str::to_owned),
+            added: SystemTime::now(),
+        }
+    }
+
+    fn to_info(&self) -> Option<InodeInfo> {
+        let added = self
+            .added
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        InodeInfo::new(
+            self.path.as_os_str().as_bytes(),
+            self.policy.as_deref().unwrap_or_default().as_bytes(),
+            added,
+        )
+    }
+}
+
+impl From<&InodeInfo> for InodeEntry {
+    fn from(info: &InodeInfo) -> Self {
+        let policy = match info.policy() {
+            [] => None,
+            policy => Some(String::from_utf8_lossy(policy).into_owned()),
+        };
+        Self {
+            path: PathBuf::from(OsStr::from_bytes(info.path())),
+            policy,
+            added: UNIX_EPOCH + Duration::from_secs(info.added),
+        }
+    }
+}
+
+/// Index of inodes and the paths they were resolved from.
+///
+/// The index is seeded with subjects and objects of added policies and can be
+/// refreshed by scanning the filesystem. When it's backed by the pinned
+/// `INODE_INFO` map, every new entry is stored there, so all processes
+/// managing the same maps resolve inodes to the same paths.
 #[derive(Default)]
 pub struct InodeSubjectMap {
-    map: HashMap<u64, PathBuf>,
+    map: HashMap<Inode, InodeEntry>,
+    store: Option<BpfHashMap<MapData, Inode, InodeInfo>>,
+    cgroup_dev: Option<u64>,
 }
 
 impl InodeSubjectMap {
-    pub fn resolve_path(&mut self, subject: PolicySubject) -> Result<u64, EbpfguardError> {
+    /// Creates the index backed by the given BPF map, loading the entries
+    /// which are already stored in it.
+    pub fn new(store: BpfHashMap<MapData, Inode, InodeInfo>) -> Result<Self, EbpfguardError> {
+        let mut map = HashMap::new();
+        for res in store.iter() {
+            let (inode, info) = res?;
+            map.insert(inode, InodeEntry::from(&info));
+        }
+
+        Ok(Self {
+            map,
+            store: Some(store),
+            cgroup_dev: None,
+        })
+    }
+
+    /// Returns the metadata of all indexed inodes, sorted by path. Entries
+    /// added by other processes are loaded from the BPF map first.
+    pub fn entries(&mut self) -> Result<Vec<(Inode, InodeEntry)>, EbpfguardError> {
+        if let Some(store) = &self.store {
+            for res in store.iter() {
+                let (inode, info) = res?;
+                self.map
+                    .entry(inode)
+                    .or_insert_with(|| InodeEntry::from(&info));
+            }
+        }
+
+        let mut entries = self
+            .map
+            .iter()
+            .map(|(inode, entry)| (*inode, entry.clone()))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// Returns the metadata of the given inode. Inodes which were added by
+    /// other processes are loaded from the BPF map.
+    pub fn entry(&mut self, inode: Inode) -> Option<&InodeEntry> {
+        if !self.map.contains_key(&inode) {
+            let info = self
+                .store
+                .as_ref()
+                .and_then(|store| found(store.get(&inode, 0)).ok().flatten())?;
+            self.map.insert(inode, InodeEntry::from(&info));
+        }
+        self.map.get(&inode)
+    }
+
+    /// Records the path of the given inode. Entries which already have the
+    /// same path are left untouched.
+    fn insert(&mut self, inode: Inode, path: PathBuf, policy: Option<&str>) {
+        if self.entry(inode).map(|entry| &entry.path) == Some(&path) {
+            return;
+        }
+
+        let entry = InodeEntry::new(path, policy);
+        if let Some(store) = self.store.as_mut() {
+            // Failing to store the path doesn't affect enforcement, only
+            // the resolution of the inode in other processes.
+            match entry.to_info() {
+                Some(info) => {
+                    if let Err(e) = store.insert(inode, info, 0) {
+                        log::warn!("failed to store the path of inode {inode}: {e}");
+                    }
+                }
+                None => log::warn!("path {} is too long to be stored", entry.path.display()),
+            }
+        }
+        self.map.insert(inode, entry);
+    }
+
+    /// Returns the indexed path of the given inode. Entries whose path now
+    /// points to a different file (e.g. after the inode was reused) are
+    /// ignored. Entries of removed files are still returned, since policies
+    /// can outlive them.
+    pub fn path(&mut self, inode: Inode) -> Option<PathBuf> {
+        let path = self.entry(inode)?.path.clone();
+        match fs::inode(&path) {
+            Ok(current) if current != inode => None,
+            _ => Some(path),
+        }
+    }
+
+    /// Returns `false` if the given inode is known not to be a directory,
+    /// i.e. its indexed path still points to it and isn't a directory.
+    /// Inodes which can't be checked are assumed to be directories.
+    pub(crate) fn may_be_dir(&mut self, inode: Inode) -> bool {
+        let Some(path) = self.path(inode) else {
+            return true;
+        };
+        std_fs::metadata(path).map_or(true, |metadata| metadata.is_dir())
+    }
+
+    /// Resolves the subject to the inodes of its binary and cgroup and
+    /// records them in the index, as added by the given policy.
+    pub fn resolve_subject(
+        &mut self,
+        subject: PolicySubject,
+        policy: &str,
+    ) -> Result<Subject, EbpfguardError> {
+        match subject {
+            PolicySubject::Binary(binary) => Ok(Subject::new(
+                self.resolve_path(binary, policy)?,
+                CGROUP_WILDCARD,
+            )),
+            PolicySubject::Cgroup(cgroup) => Ok(Subject::new(
+                INODE_WILDCARD,
+                self.resolve_path(cgroup, policy)?.ino,
+            )),
+            PolicySubject::CgroupBinary { cgroup, binary } => Ok(Subject::new(
+                self.resolve_path(binary, policy)?,
+                self.resolve_path(cgroup, policy)?.ino,
+            )),
+            PolicySubject::User(user) => Ok(Subject::user(user.uid()?)),
+            PolicySubject::Group(group) => Ok(Subject::group(group.gid()?)),
+            PolicySubject::All => Ok(SUBJECT_WILDCARD),
+        }
+    }
+
+    fn resolve_path(&mut self, path: PathBuf, policy: &str) -> Result<Inode, EbpfguardError> {
+        let inode = fs::inode(&path)?;
+        self.insert(inode, path, Some(policy));
+        Ok(inode)
+    }
+
+    /// Resolves the paths to inodes and records them in the index, as added
+    /// by the given policy.
+    pub fn resolve_paths(
+        &mut self,
+        paths: Paths,
+        policy: &str,
+    ) -> Result<Vec<Inode>, EbpfguardError> {
+        let paths = match paths {
+            Paths::All => return Ok(vec![INODE_WILDCARD]),
+            Paths::Paths(paths) => paths,
+        };
+
+        let mut inodes = Vec::with_capacity(paths.len());
+        for path in paths {
+            let inode = fs::inode(&path)?;
+            self.insert(inode, path, Some(policy));
+            inodes.push(inode);
+        }
+
+        Ok(inodes)
+    }
+
+    /// Expands the glob pattern to the existing paths matching it, resolves
+    /// them to inodes and records them in the index, as added by the given
+    /// policy. Paths which don't exist are skipped.
+    pub fn resolve_pattern(
+        &mut self,
+        pattern: &Path,
+        policy: &str,
+    ) -> Result<Vec<Inode>, EbpfguardError> {
+        let mut inodes = Vec::new();
+        for path in fs::expand(pattern)? {
+            // Dangling symlinks and files removed after expanding the
+            // pattern have no inode to enforce on.
+            if let Ok(inode) = fs::inode(&path) {
+                self.insert(inode, path, Some(policy));
+                inodes.push(inode);
+            }
+        }
+
+        Ok(inodes)
+    }
+
+    /// Returns the key of the given subject without registering it. Paths
+    /// which were resolved before are looked up first, so subjects can be
+    /// found even if the binary or cgroup doesn't exist anymore.
+    pub fn subject(&self, subject: &PolicySubject) -> Result<Subject, EbpfguardError> {
         match subject {
-            PolicySubject::Binary(path) => {
-                let inode = fs::inode(&path)?;
-                self.map.insert(inode, path);
-                Ok(inode)
+            PolicySubject::Binary(binary) => Ok(Subject::new(self.inode(binary)?, CGROUP_WILDCARD)),
+            PolicySubject::Cgroup(cgroup) => {
+                Ok(Subject::new(INODE_WILDCARD, self.inode(cgroup)?.ino))
+            }
+            PolicySubject::CgroupBinary { cgroup, binary } => {
+                Ok(Subject::new(self.inode(binary)?, self.inode(cgroup)?.ino))
+            }
+            PolicySubject::User(user) => Ok(Subject::user(user.uid()?)),
+            PolicySubject::Group(group) => Ok(Subject::group(group.gid()?)),
+            PolicySubject::All => Ok(SUBJECT_WILDCARD),
+        }
+    }
+
+    /// Returns the inode recorded for the given path. A path can be recorded
+    /// for several inodes, e.g. after a binary was replaced by an upgrade.
+    /// The one the path currently points to is preferred, otherwise the most
+    /// recently added one is returned.
+    fn inode(&self, path: &Path) -> Result<Inode, EbpfguardError> {
+        let mut recorded: Vec<(Inode, SystemTime)> = self
+            .map
+            .iter()
+            .filter(|(_, entry)| entry.path == path)
+            .map(|(inode, entry)| (*inode, entry.added))
+            .collect();
+        for (inode, info) in self.store.iter().flat_map(|store| store.iter()).flatten() {
+            if info.path() == path.as_os_str().as_bytes() && !self.map.contains_key(&inode) {
+                recorded.push((inode, InodeEntry::from(&info).added));
             }
-            PolicySubject::All => Ok(0),
+        }
+
+        let current = fs::inode(path);
+        match current {
+            Ok(current) if recorded.iter().any(|(inode, _)| *inode == current) => Ok(current),
+            current => match recorded.into_iter().max_by_key(|(_, added)| *added) {
+                Some((inode, _)) => Ok(inode),
+                None => Ok(current?),
+            },
         }
     }
 
-    pub fn resolve_inode(&self, inode: u64) -> PolicySubject {
+    /// Resolves the key of a subject back to the paths of its binary and
+    /// cgroup, or to the name of its user or group.
+    pub fn policy_subject(&mut self, subject: Subject) -> PolicySubject {
+        if subject.uid != UID_WILDCARD {
+            return PolicySubject::User(Account::from_uid(subject.uid));
+        }
+        if subject.gid != GID_WILDCARD {
+            return PolicySubject::Group(Account::from_gid(subject.gid));
+        }
+        match (subject.binary, subject.cgroup) {
+            (INODE_WILDCARD, CGROUP_WILDCARD) => PolicySubject::All,
+            (binary, CGROUP_WILDCARD) => PolicySubject::Binary(self.path_or_inode(binary)),
+            (INODE_WILDCARD, cgroup) => PolicySubject::Cgroup(self.resolve_cgroup(cgroup)),
+            (binary, cgroup) => PolicySubject::CgroupBinary {
+                cgroup: self.resolve_cgroup(cgroup),
+                binary: self.path_or_inode(binary),
+            },
+        }
+    }
+
+    /// Returns the inode of the cgroup with the given ID. On cgroup v2, the
+    /// ID of a cgroup is the inode number of its directory.
+    pub fn cgroup_inode(&mut self, id: u64) -> Option<Inode> {
+        if id == CGROUP_WILDCARD {
+            return None;
+        }
+        if self.cgroup_dev.is_none() {
+            self.cgroup_dev = fs::inode(Path::new(CGROUP_ROOT))
+                .ok()
+                .map(|inode| inode.dev);
+        }
+        self.cgroup_dev.map(|dev| Inode::new(dev, id))
+    }
+
+    /// Resolves the cgroup with the given ID to its path. Cgroups missing in
+    /// the index are searched for under the cgroup filesystem.
+    pub fn resolve_cgroup(&mut self, id: u64) -> PathBuf {
+        let inode = match self.cgroup_inode(id) {
+            Some(inode) => inode,
+            None => return PathBuf::from(id.to_string()),
+        };
+        if self.path(inode).is_none() {
+            self.refresh(&[inode], &[PathBuf::from(CGROUP_ROOT)]);
+        }
+        self.path(inode)
+            .unwrap_or_else(|| PathBuf::from(id.to_string()))
+    }
+
+    /// Resolves the subject of an alert triggered by the process with the
+    /// given PID. Binaries missing in the index are looked up through
+    /// `/proc/<pid>/exe`.
+    pub fn resolve_process(&mut self, inode: Inode, pid: u32) -> PolicySubject {
+        if self.path(inode).is_none() {
+            let exe = PathBuf::from(format!("/proc/{pid}/exe"));
+            if let Ok(path) = std_fs::read_link(&exe) {
+                // The process might have exited and its PID reused, so
+                // make sure it's still the same binary.
+                if fs::inode(&exe).ok() == Some(inode) {
+                    self.insert(inode, path, None);
+                }
+            }
+        }
         match inode {
-            0 => PolicySubject::All,
-            _ => self
-                .map
-                .get(&inode)
-                .map(|p| PolicySubject::Binary(p.to_owned()))
-                .unwrap_or(PolicySubject::Binary(PathBuf::from(inode.to_string()))),
+            INODE_WILDCARD => PolicySubject::All,
+            _ => PolicySubject::Binary(self.path_or_inode(inode)),
+        }
+    }
+
+    /// Resolves the inodes to paths. Inodes missing in the index are searched
+    /// for under the indexed directories.
+    pub fn resolve_inodes(&mut self, inodes: Vec<Inode>) -> Paths {
+        if inodes.contains(&INODE_WILDCARD) {
+            return Paths::All;
+        }
+
+        let missing = inodes
+            .iter()
+            .filter(|inode| self.path(**inode).is_none())
+            .copied()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let roots = self.indexed_dirs();
+            self.refresh(&missing, &roots);
         }
+
+        Paths::Paths(
+            inodes
+                .into_iter()
+                .map(|inode| self.path_or_inode(inode))
+                .collect(),
+        )
+    }
+
+    /// Resolves an object inode, e.g. the file from a `file_open` alert.
+    /// Inodes missing in the index are searched for under the indexed
+    /// directories.
+    pub fn resolve_object(&mut self, inode: Inode) -> PathBuf {
+        if self.path(inode).is_none() {
+            let roots = self.indexed_dirs();
+            self.refresh(&[inode], &roots);
+        }
+        self.path_or_inode(inode)
+    }
+
+    /// Scans the filesystem under the given roots for the given inodes and
+    /// records the found paths. Returns the number of inodes found.
+    pub fn refresh(&mut self, inodes: &[Inode], roots: &[PathBuf]) -> usize {
+        let mut missing = HashSet::new();
+        for inode in inodes {
+            if *inode != INODE_WILDCARD && self.path(*inode).is_none() {
+                missing.insert(*inode);
+            }
+        }
+        let total = missing.len();
+
+        let mut visited = 0;
+        let mut dirs = roots.to_vec();
+        while let Some(dir) = dirs.pop() {
+            if missing.is_empty() || visited >= MAX_SCAN_ENTRIES {
+                break;
+            }
+            let entries = match std_fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                // Directories may disappear or be unreadable while scanning.
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                visited += 1;
+                let path = entry.path();
+                // Don't follow symlinks, they could lead to loops.
+                match entry.file_type() {
+                    Ok(file_type) if file_type.is_dir() => dirs.push(path.clone()),
+                    Ok(file_type) if file_type.is_symlink() => continue,
+                    _ => {}
+                }
+                if let Ok(inode) = fs::inode(&path) {
+                    if missing.remove(&inode) {
+                        self.insert(inode, path, None);
+                    }
+                }
+            }
+        }
+
+        total - missing.len()
+    }
+
+    /// Returns indexed paths which are existing directories.
+    fn indexed_dirs(&self) -> Vec<PathBuf> {
+        self.map
+            .values()
+            .filter(|entry| entry.path.is_dir())
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    fn path_or_inode(&mut self, inode: Inode) -> PathBuf {
+        self.path(inode)
+            .unwrap_or_else(|| PathBuf::from(inode.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_inodes() {
+        let dir = std::env::temp_dir().join(format!("ebpfguard-inode-{}", std::process::id()));
+        std_fs::create_dir_all(dir.join("nested")).unwrap();
+        let file = dir.join("nested").join("file");
+        std_fs::write(&file, "foo").unwrap();
+
+        let mut map = InodeSubjectMap::default();
+        let dir_inode = map
+            .resolve_paths(Paths::Paths(vec![dir.clone()]), "file_open")
+            .unwrap()[0];
+        let file_inode = fs::inode(&file).unwrap();
+
+        // The file is found by scanning the indexed directory.
+        assert_eq!(
+            map.resolve_inodes(vec![dir_inode, file_inode]),
+            Paths::Paths(vec![dir.clone(), file.clone()])
+        );
+        assert_eq!(
+            map.entry(dir_inode).unwrap().policy.as_deref(),
+            Some("file_open")
+        );
+        assert_eq!(map.entry(file_inode).unwrap().policy, None);
+        assert_eq!(
+            map.policy_subject(Subject::new(Inode::new(0, 1), CGROUP_WILDCARD)),
+            PolicySubject::Binary(PathBuf::from("0:0/1"))
+        );
+        assert_eq!(map.policy_subject(SUBJECT_WILDCARD), PolicySubject::All);
+
+        std_fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_subject_replaced_binary() {
+        let dir = std::env::temp_dir().join(format!("ebpfguard-replaced-{}", std::process::id()));
+        std_fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("binary");
+        // Replaced files are kept as links, so their inodes aren't reused.
+        let replace = |content: &str| {
+            if binary.exists() {
+                let old = dir.join(format!("binary.{}", fs::inode(&binary).unwrap().ino));
+                std_fs::hard_link(&binary, old).unwrap();
+            }
+            let tmp = dir.join("binary.tmp");
+            std_fs::write(&tmp, content).unwrap();
+            std_fs::rename(&tmp, &binary).unwrap();
+            fs::inode(&binary).unwrap()
+        };
+
+        let mut map = InodeSubjectMap::default();
+        let old = replace("old");
+        map.resolve_subject(PolicySubject::Binary(binary.clone()), "bprm_check_security")
+            .unwrap();
+        let new = replace("new");
+        map.resolve_subject(PolicySubject::Binary(binary.clone()), "bprm_check_security")
+            .unwrap();
+        assert_ne!(old, new);
+
+        // Both inodes are recorded for the path, the current one is chosen.
+        let subject = PolicySubject::Binary(binary.clone());
+        assert_eq!(
+            map.subject(&subject).unwrap(),
+            Subject::new(new, CGROUP_WILDCARD)
+        );
+
+        // Neither is current anymore, the most recently added one is chosen.
+        replace("newer");
+        assert_eq!(
+            map.subject(&subject).unwrap(),
+            Subject::new(new, CGROUP_WILDCARD)
+        );
+
+        std_fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_inode_info() {
+        let entry = InodeEntry {
+            path: PathBuf::from("/usr/bin/mount"),
+            policy: Some("sb_mount".to_owned()),
+            added: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        };
+        assert_eq!(InodeEntry::from(&entry.to_info().unwrap()), entry);
+
+        let entry = InodeEntry {
+            policy: None,
+            ..entry
+        };
+        assert_eq!(InodeEntry::from(&entry.to_info().unwrap()), entry);
     }
 }
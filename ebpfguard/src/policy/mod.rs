@@ -1,21 +1,52 @@
 use std::{
+    ffi::OsStr,
     fmt::{Display, Formatter},
     net::IpAddr,
+    os::unix::ffi::OsStrExt,
     path::PathBuf,
+    str::FromStr,
 };
 
-use ebpfguard_common::policy as ebpf_policy;
-use serde::{Deserialize, Serialize};
+use ebpfguard_common::{
+    consts::{
+        ACCESS_ALL, ACCESS_EXEC, ACCESS_READ, ACCESS_WRITE, DEFAULT_DENY, HOOK_BPRM_CHECK_SECURITY,
+        HOOK_FILE_OPEN, HOOK_INET_CONN_REQUEST, HOOK_SB_MOUNT, HOOK_SB_REMOUNT, HOOK_SB_UMOUNT,
+        HOOK_SOCKET_BIND, HOOK_SOCKET_CONNECT, HOOK_SOCKET_CONNECT_UNIX, HOOK_SOCKET_CREATE,
+        HOOK_SOCKET_LISTEN, HOOK_TASK_FIX_SETUID, INODE_WILDCARD, IPPROTO_TCP, IPPROTO_UDP,
+        POLICY_AUDIT, PROTOCOL_WILDCARD, SOCKET_FAMILY_WILDCARD, SOCKET_PROTOCOL_WILDCARD,
+        SOCKET_TYPE_WILDCARD, UNIX_PATH_MAX, UNIX_PATH_WILDCARD,
+    },
+    inode::Inode,
+};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{error::EbpfguardError, fs};
 
-use crate::fs;
+use self::account::Account;
 
+pub mod account;
 pub mod inode;
 pub mod reader;
+pub mod validate;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PolicySubject {
     #[serde(rename = "binary")]
     Binary(PathBuf),
+    /// All processes in the cgroup (v2) with the given path, e.g.
+    /// `/sys/fs/cgroup/system.slice/docker-<id>.scope`.
+    #[serde(rename = "cgroup")]
+    Cgroup(PathBuf),
+    /// Processes running the given binary in the given cgroup.
+    #[serde(rename = "cgroup_binary")]
+    CgroupBinary { cgroup: PathBuf, binary: PathBuf },
+    /// All processes running as the given user (real UID).
+    #[serde(rename = "user")]
+    User(Account),
+    /// All processes running with the given group (real GID).
+    #[serde(rename = "group")]
+    Group(Account),
     #[serde(rename = "all")]
     All,
 }
@@ -24,12 +55,18 @@ impl Display for PolicySubject {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             PolicySubject::Binary(path) => write!(f, "{}", path.display()),
+            PolicySubject::Cgroup(path) => write!(f, "cgroup {}", path.display()),
+            PolicySubject::CgroupBinary { cgroup, binary } => {
+                write!(f, "{} in cgroup {}", binary.display(), cgroup.display())
+            }
+            PolicySubject::User(user) => write!(f, "user {user}"),
+            PolicySubject::Group(group) => write!(f, "group {group}"),
             PolicySubject::All => write!(f, "all"),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Paths {
     #[serde(rename = "all")]
     All,
@@ -43,128 +80,991 @@ pub enum Paths {
 // lookup, while inode -> path resolution requires more complex per-filesystem
 // operations). Therefore, `Into` and `From` traits have to be implemented
 // separately.
-#[allow(clippy::from_over_into)]
-impl Into<ebpf_policy::Paths> for Paths {
-    fn into(self) -> ebpf_policy::Paths {
+impl Paths {
+    // Here, `from_inodes` doesn't resolve inodes to paths at all (the inode
+    // index does), so only `into_inodes` is fallible: paths which don't
+    // exist have no inode.
+
+    /// Resolves the paths to inodes. `Paths::All` is represented by
+    /// [`INODE_WILDCARD`]. Fails if any of the paths doesn't exist.
+    pub fn into_inodes(self) -> Result<Vec<Inode>, std::io::Error> {
         match self {
-            Paths::All => ebpf_policy::Paths {
-                paths: [0; ebpf_policy::MAX_PATHS],
-            },
-            Paths::Paths(paths) => {
-                let mut ebpf_paths = [0; ebpf_policy::MAX_PATHS];
-                for (i, path) in paths.iter().enumerate() {
-                    ebpf_paths[i] = fs::inode(path).unwrap();
-                }
-                ebpf_policy::Paths { paths: ebpf_paths }
-            }
+            Paths::All => Ok(vec![INODE_WILDCARD]),
+            Paths::Paths(paths) => paths.iter().map(fs::inode).collect(),
         }
     }
-}
 
-impl From<ebpf_policy::Paths> for Paths {
-    fn from(paths: ebpf_policy::Paths) -> Self {
-        if paths.paths[0] == 0 {
+    /// Converts the inodes to `Paths` without resolving them, every inode is
+    /// represented by its string form. Use
+    /// [`InodeSubjectMap::resolve_inodes`](inode::InodeSubjectMap::resolve_inodes)
+    /// to get the real paths.
+    pub fn from_inodes(inodes: Vec<Inode>) -> Self {
+        if inodes.contains(&INODE_WILDCARD) {
             Paths::All
         } else {
-            let mut paths_vec = Vec::new();
-            for inode in paths.paths.iter() {
-                if *inode == 0 {
-                    break;
-                }
-                // TODO(vadorovsky): Resolve inodes to paths properly.
-                paths_vec.push(PathBuf::from(inode.to_string()));
-            }
-            Paths::Paths(paths_vec)
+            Paths::Paths(
+                inodes
+                    .iter()
+                    .map(|inode| PathBuf::from(inode.to_string()))
+                    .collect(),
+            )
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Kind of access to a file in `file_open` policies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Access {
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "write")]
+    Write,
+    /// Opening a binary for execution.
+    #[serde(rename = "exec")]
+    Exec,
+}
+
+impl Access {
+    pub const ALL: [Access; 3] = [Access::Read, Access::Write, Access::Exec];
+
+    /// Returns the flag of the access used in the map values.
+    pub(crate) fn flag(&self) -> u8 {
+        match self {
+            Access::Read => ACCESS_READ,
+            Access::Write => ACCESS_WRITE,
+            Access::Exec => ACCESS_EXEC,
+        }
+    }
+
+    /// Returns the kinds of access with flags set in the given map value or
+    /// alert. Values without any of the flags apply to all kinds of access.
+    pub(crate) fn from_flags(flags: u8) -> Vec<Access> {
+        match flags & ACCESS_ALL {
+            0 => Access::ALL.to_vec(),
+            flags => Access::ALL
+                .into_iter()
+                .filter(|access| flags & access.flag() != 0)
+                .collect(),
+        }
+    }
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "write"),
+            Access::Exec => write!(f, "exec"),
+        }
+    }
+}
+
+/// How paths of `file_open` policies are matched.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum PathMatch {
+    /// Paths are glob patterns, resolved to the inodes of the matching files.
+    /// Directories match files under them up to the configured depth (see
+    /// [`FileOpenSettings`](crate::hooks::file_open::FileOpenSettings)).
+    #[default]
+    #[serde(rename = "inode")]
+    Inode,
+    /// Paths are prefixes of the paths of opened files, matched by whole
+    /// components and regardless of the depth. They don't have to exist and
+    /// are matched as seen from the root directory of the process opening
+    /// the file, so they follow bind mounts and aren't affected by inode
+    /// reuse.
+    #[serde(rename = "prefix")]
+    Prefix,
+}
+
+impl PathMatch {
+    pub fn is_inode(&self) -> bool {
+        *self == PathMatch::Inode
+    }
+}
+
+impl Display for PathMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathMatch::Inode => write!(f, "inode"),
+            PathMatch::Prefix => write!(f, "prefix"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Ports {
     #[serde(rename = "all")]
     All,
     #[serde(rename = "ports")]
     Ports(Vec<u16>),
+    #[serde(rename = "ranges")]
+    Ranges(Vec<PortRange>),
 }
 
-#[allow(clippy::from_over_into)]
-impl Into<ebpf_policy::Ports> for Ports {
-    fn into(self) -> ebpf_policy::Ports {
+impl Ports {
+    /// Returns the ports as a list of ranges. Single ports are represented
+    /// as ranges with one port.
+    pub fn into_ranges(self) -> Vec<PortRange> {
         match self {
-            Ports::All => ebpf_policy::Ports::new_all(),
-            Ports::Ports(ports) => {
-                let mut ebpf_ports = [0; ebpf_policy::MAX_PORTS];
-                for (i, port) in ports.iter().enumerate() {
-                    ebpf_ports[i] = *port;
-                }
-                ebpf_policy::Ports::new(ebpf_ports)
-            }
+            Ports::All => vec![PortRange::ALL],
+            Ports::Ports(ports) => ports.into_iter().map(PortRange::from).collect(),
+            Ports::Ranges(ranges) => ranges,
         }
     }
-}
 
-impl From<ebpf_policy::Ports> for Ports {
-    fn from(ports: ebpf_policy::Ports) -> Self {
-        if ports.all() {
+    /// Returns the ports as aligned blocks, the way they are stored in the
+    /// maps.
+    pub(crate) fn into_blocks(self) -> Vec<PortRange> {
+        self.into_ranges()
+            .iter()
+            .flat_map(PortRange::blocks)
+            .collect()
+    }
+
+    /// Builds ports from a list of ranges, merging the adjacent ones and
+    /// using the most specific variant which is able to represent them.
+    pub fn from_ranges(ranges: Vec<PortRange>) -> Self {
+        let ranges = PortRange::merge(ranges);
+        if ranges.contains(&PortRange::ALL) {
             Ports::All
+        } else if ranges.iter().all(PortRange::is_single) {
+            Ports::Ports(ranges.iter().map(|range| range.start).collect())
         } else {
-            let mut ports_vec = Vec::new();
-            for port in ports.ports.iter() {
-                if *port == 0 {
-                    break;
+            Ports::Ranges(ranges)
+        }
+    }
+}
+
+/// Inclusive range of ports. In YAML, it's written as a single port (`80`),
+/// a range (`8000-8999`) or all ports below the given one (`<1024`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub const ALL: PortRange = PortRange {
+        start: 0,
+        end: u16::MAX,
+    };
+
+    /// Creates the range, fails with [`EbpfguardError::InvalidPortRange`] if
+    /// `start` is greater than `end`.
+    pub fn new(start: u16, end: u16) -> Result<Self, EbpfguardError> {
+        if start > end {
+            return Err(EbpfguardError::InvalidPortRange(format!("{start}-{end}")));
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Splits the range into the smallest number of aligned blocks, i.e.
+    /// ranges which have a power of two length and start at its multiple.
+    /// Blocks are stored in the LPM tries as prefixes of ports.
+    pub(crate) fn blocks(&self) -> Vec<PortRange> {
+        let mut blocks = Vec::new();
+        let mut start = u32::from(self.start);
+        let end = u32::from(self.end);
+        while start <= end {
+            let mut len = 1 << start.trailing_zeros().min(16);
+            while start + len - 1 > end {
+                len >>= 1;
+            }
+            blocks.push(PortRange {
+                start: start as u16,
+                end: (start + len - 1) as u16,
+            });
+            start += len;
+        }
+        blocks
+    }
+
+    /// Returns the prefix length of an aligned block.
+    pub(crate) fn prefix_len(&self) -> u8 {
+        let len = u32::from(self.end - self.start) + 1;
+        16 - len.trailing_zeros() as u8
+    }
+
+    /// Returns the aligned block with the given first port and prefix length.
+    pub(crate) fn from_prefix(start: u16, prefix_len: u8) -> Self {
+        let len = 1u32 << (16 - u32::from(prefix_len));
+        PortRange {
+            start,
+            end: (u32::from(start) + len - 1) as u16,
+        }
+    }
+
+    /// Sorts the ranges and merges the overlapping and adjacent ones.
+    pub(crate) fn merge(mut ranges: Vec<PortRange>) -> Vec<PortRange> {
+        ranges.sort_unstable();
+        let mut merged: Vec<PortRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if u32::from(range.start) <= u32::from(last.end) + 1 => {
+                    last.end = last.end.max(range.end);
                 }
-                ports_vec.push(*port);
+                _ => merged.push(range),
             }
-            Ports::Ports(ports_vec)
         }
+        merged
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+impl From<u16> for PortRange {
+    fn from(port: u16) -> Self {
+        PortRange {
+            start: port,
+            end: port,
+        }
+    }
+}
+
+impl Display for PortRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_single() {
+            write!(f, "{}", self.start)
+        } else if self.start == 0 && self.end < u16::MAX {
+            write!(f, "<{}", self.end + 1)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl FromStr for PortRange {
+    type Err = EbpfguardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || EbpfguardError::InvalidPortRange(s.to_owned());
+        let port = |port: &str| port.trim().parse::<u16>().map_err(|_| invalid());
+
+        if let Some(end) = s.trim().strip_prefix('<') {
+            let end = port(end)?.checked_sub(1).ok_or_else(invalid)?;
+            return PortRange::new(0, end);
+        }
+        match s.split_once('-') {
+            Some((start, end)) => PortRange::new(port(start)?, port(end)?),
+            None => Ok(PortRange::from(port(s)?)),
+        }
+    }
+}
+
+impl Serialize for PortRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_single() {
+            serializer.serialize_u16(self.start)
+        } else {
+            serializer.collect_str(self)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PortRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Port(u16),
+            Range(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Port(port) => Ok(PortRange::from(port)),
+            Repr::Range(range) => range.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Addresses {
+    #[default]
     #[serde(rename = "all")]
     All,
     #[serde(rename = "addresses")]
     Addresses(Vec<IpAddr>),
+    #[serde(rename = "networks")]
+    Networks(Vec<IpNet>),
 }
 
 impl Addresses {
-    pub fn into_ebpf(self) -> (ebpf_policy::Ipv4Addrs, ebpf_policy::Ipv6Addrs) {
+    pub fn is_all(&self) -> bool {
+        *self == Addresses::All
+    }
+
+    /// Returns the addresses as a list of networks. Single addresses are
+    /// represented as networks with the full prefix length. IPv4-mapped IPv6
+    /// networks (within `::ffff:0:0/96`) are converted to IPv4 networks, the
+    /// programs match such addresses with IPv4 rules.
+    pub fn into_networks(self) -> Vec<IpNet> {
+        let networks: Vec<IpNet> = match self {
+            Addresses::All => vec![IpNet::V4(Ipv4Net::default()), IpNet::V6(Ipv6Net::default())],
+            Addresses::Addresses(addrs) => addrs.into_iter().map(IpNet::from).collect(),
+            Addresses::Networks(networks) => networks.into_iter().map(|n| n.trunc()).collect(),
+        };
+        networks.into_iter().map(unmap_ipv4).collect()
+    }
+
+    /// Builds addresses from a list of networks, using the most specific
+    /// variant which is able to represent them.
+    pub fn from_networks(mut networks: Vec<IpNet>) -> Self {
+        let all_v4 = networks.contains(&IpNet::V4(Ipv4Net::default()));
+        let all_v6 = networks.contains(&IpNet::V6(Ipv6Net::default()));
+        if all_v4 && all_v6 {
+            return Addresses::All;
+        }
+
+        networks.sort();
+        if networks
+            .iter()
+            .all(|n| n.prefix_len() == n.max_prefix_len())
+        {
+            Addresses::Addresses(networks.iter().map(|n| n.addr()).collect())
+        } else {
+            Addresses::Networks(networks)
+        }
+    }
+}
+
+/// Returns the IPv4 network embedded in an IPv4-mapped IPv6 network, or the
+/// network itself.
+fn unmap_ipv4(network: IpNet) -> IpNet {
+    let IpNet::V6(v6) = network else {
+        return network;
+    };
+    match (
+        v6.network().to_ipv4_mapped(),
+        v6.prefix_len().checked_sub(96),
+    ) {
+        (Some(addr), Some(prefix_len)) => Ipv4Net::new(addr, prefix_len)
+            .map(IpNet::V4)
+            .unwrap_or(network),
+        _ => network,
+    }
+}
+
+/// Transport protocol of the sockets a `socket_connect` rule applies to.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Protocol {
+    #[default]
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "tcp")]
+    Tcp,
+    #[serde(rename = "udp")]
+    Udp,
+}
+
+impl Protocol {
+    pub fn is_all(&self) -> bool {
+        *self == Protocol::All
+    }
+
+    /// Returns the protocol number used in the map keys.
+    pub(crate) fn id(&self) -> u8 {
         match self {
-            Addresses::All => (
-                ebpf_policy::Ipv4Addrs::new_all(),
-                ebpf_policy::Ipv6Addrs::new_all(),
-            ),
-            Addresses::Addresses(addrs) => {
-                let mut ebpf_addrs_v4 = [0; ebpf_policy::MAX_IPV4ADDRS];
-                let mut ebpf_addrs_v6 = [[0u8; 16]; ebpf_policy::MAX_IPV6ADDRS];
-                let mut i_v4 = 0;
-                let mut i_v6 = 0;
-                for addr in addrs.iter() {
-                    match addr {
-                        IpAddr::V4(ipv4) => {
-                            ebpf_addrs_v4[i_v4] = (*ipv4).into();
-                            i_v4 += 1;
-                        }
-                        IpAddr::V6(ipv6) => {
-                            ebpf_addrs_v6[i_v6] = ipv6.octets();
-                            i_v6 += 1;
-                        }
-                    }
-                }
-                (
-                    ebpf_policy::Ipv4Addrs::new(ebpf_addrs_v4),
-                    ebpf_policy::Ipv6Addrs::new(ebpf_addrs_v6),
-                )
-            }
+            Protocol::All => PROTOCOL_WILDCARD,
+            Protocol::Tcp => IPPROTO_TCP,
+            Protocol::Udp => IPPROTO_UDP,
+        }
+    }
+
+    /// Returns the protocol with the given number used in the map keys.
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            PROTOCOL_WILDCARD => Some(Protocol::All),
+            IPPROTO_TCP => Some(Protocol::Tcp),
+            IPPROTO_UDP => Some(Protocol::Udp),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::All => write!(f, "all"),
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnixPaths {
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "paths")]
+    Paths(Vec<UnixPath>),
+}
+
+impl UnixPaths {
+    /// Returns the paths the way they are stored in the maps. `UnixPaths::All`
+    /// is represented by [`UNIX_PATH_WILDCARD`].
+    pub(crate) fn into_sun_paths(self) -> Result<Vec<[u8; UNIX_PATH_MAX]>, EbpfguardError> {
+        match self {
+            UnixPaths::All => Ok(vec![UNIX_PATH_WILDCARD]),
+            UnixPaths::Paths(paths) => paths.iter().map(UnixPath::to_sun_path).collect(),
+        }
+    }
+
+    /// Builds paths from the way they are stored in the maps.
+    pub(crate) fn from_sun_paths(paths: Vec<[u8; UNIX_PATH_MAX]>) -> Self {
+        if paths.contains(&UNIX_PATH_WILDCARD) {
+            UnixPaths::All
+        } else {
+            UnixPaths::Paths(paths.iter().map(UnixPath::from_sun_path).collect())
+        }
+    }
+}
+
+/// Path of a Unix domain socket, either an absolute path of a socket file
+/// or a name in the abstract namespace. In YAML, abstract names are written
+/// with a leading `@` (e.g. `@/tmp/.X11-unix/X0`), the way `ss` shows them.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixPath {
+    Path(PathBuf),
+    /// Name in the abstract namespace, without the leading zero byte.
+    Abstract(String),
+}
+
+impl UnixPath {
+    /// Returns the path as `sun_path` padded with zeros. Fails with
+    /// [`EbpfguardError::InvalidUnixPath`] if the path is relative, empty,
+    /// contains a zero byte or doesn't fit into `sun_path`.
+    pub(crate) fn to_sun_path(&self) -> Result<[u8; UNIX_PATH_MAX], EbpfguardError> {
+        let invalid = || EbpfguardError::InvalidUnixPath(self.to_string());
+        let (offset, bytes) = match self {
+            UnixPath::Path(path) if path.is_absolute() => (0, path.as_os_str().as_bytes()),
+            UnixPath::Path(_) => return Err(invalid()),
+            UnixPath::Abstract(name) => (1, name.as_bytes()),
+        };
+        if bytes.is_empty() || bytes.contains(&0) || offset + bytes.len() > UNIX_PATH_MAX {
+            return Err(invalid());
+        }
+        let mut sun_path = [0; UNIX_PATH_MAX];
+        sun_path[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(sun_path)
+    }
+
+    /// Returns the path stored as `sun_path` padded with zeros.
+    pub(crate) fn from_sun_path(sun_path: &[u8; UNIX_PATH_MAX]) -> Self {
+        let end = sun_path
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+        match sun_path[..end].split_first() {
+            Some((0, name)) => UnixPath::Abstract(String::from_utf8_lossy(name).into_owned()),
+            _ => UnixPath::Path(PathBuf::from(OsStr::from_bytes(&sun_path[..end]))),
+        }
+    }
+}
+
+impl Display for UnixPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnixPath::Path(path) => write!(f, "{}", path.display()),
+            UnixPath::Abstract(name) => write!(f, "@{name}"),
+        }
+    }
+}
+
+impl FromStr for UnixPath {
+    type Err = EbpfguardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = match s.strip_prefix('@') {
+            Some(name) => UnixPath::Abstract(name.to_owned()),
+            None => UnixPath::Path(PathBuf::from(s)),
+        };
+        path.to_sun_path()?;
+        Ok(path)
+    }
+}
+
+impl Serialize for UnixPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnixPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Sockets a `socket_create` rule applies to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sockets {
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "sockets")]
+    Sockets(Vec<SocketKind>),
+}
+
+impl Sockets {
+    /// Returns the sockets the way they are stored in the maps, as the
+    /// family, the type and the protocol. `Sockets::All` is represented by
+    /// wildcards.
+    pub(crate) fn to_ids(&self) -> Vec<[u32; 3]> {
+        match self {
+            Sockets::All => vec![SocketKind::ALL_IDS],
+            Sockets::Sockets(kinds) => kinds.iter().map(SocketKind::ids).collect(),
+        }
+    }
+
+    /// Builds sockets from the way they are stored in the maps.
+    pub(crate) fn from_ids(ids: Vec<[u32; 3]>) -> Result<Self, EbpfguardError> {
+        if ids.contains(&SocketKind::ALL_IDS) {
+            Ok(Sockets::All)
+        } else {
+            Ok(Sockets::Sockets(
+                ids.into_iter()
+                    .map(SocketKind::from_ids)
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+    }
+}
+
+/// Sockets matched by the arguments of `socket`. `type` and `protocol`
+/// match all types and protocols when omitted. The protocol is compared
+/// with the one given to `socket`, so `0` matches only sockets created with
+/// the default protocol of the family and type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketKind {
+    pub family: SocketFamily,
+    #[serde(rename = "type", default, skip_serializing_if = "SocketType::is_all")]
+    pub socket_type: SocketType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<u32>,
+}
+
+impl SocketKind {
+    /// IDs of the map entries matching all sockets.
+    const ALL_IDS: [u32; 3] = [
+        SOCKET_FAMILY_WILDCARD,
+        SOCKET_TYPE_WILDCARD,
+        SOCKET_PROTOCOL_WILDCARD,
+    ];
+
+    /// Returns the family, the type and the protocol used in the map keys.
+    pub(crate) fn ids(&self) -> [u32; 3] {
+        [
+            self.family.id(),
+            self.socket_type.id(),
+            self.protocol.unwrap_or(SOCKET_PROTOCOL_WILDCARD),
+        ]
+    }
+
+    /// Returns the sockets with the given family, type and protocol used in
+    /// the map keys. Fails if the type is unknown.
+    pub(crate) fn from_ids(
+        [family, socket_type, protocol]: [u32; 3],
+    ) -> Result<Self, EbpfguardError> {
+        Ok(Self {
+            family: SocketFamily::from_id(family),
+            socket_type: SocketType::from_id(socket_type).ok_or_else(|| {
+                EbpfguardError::InvalidMapEntry(format!("unknown socket type {socket_type}"))
+            })?,
+            protocol: (protocol != SOCKET_PROTOCOL_WILDCARD).then_some(protocol),
+        })
+    }
+}
+
+impl Display for SocketKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.family)?;
+        if !self.socket_type.is_all() {
+            write!(f, " {}", self.socket_type)?;
+        }
+        if let Some(protocol) = self.protocol {
+            write!(f, " protocol {protocol}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Address family of a socket. Families without a name are written as
+/// numbers, e.g. `family: 36` for `AF_IEEE802154`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SocketFamily {
+    Unix,
+    Inet,
+    Inet6,
+    Key,
+    Netlink,
+    Packet,
+    Can,
+    Tipc,
+    Bluetooth,
+    Alg,
+    Vsock,
+    Xdp,
+    Other(u32),
+}
+
+impl SocketFamily {
+    const NAMES: [(SocketFamily, &'static str, u32); 12] = [
+        (SocketFamily::Unix, "unix", 1),
+        (SocketFamily::Inet, "inet", 2),
+        (SocketFamily::Inet6, "inet6", 10),
+        (SocketFamily::Key, "key", 15),
+        (SocketFamily::Netlink, "netlink", 16),
+        (SocketFamily::Packet, "packet", 17),
+        (SocketFamily::Can, "can", 29),
+        (SocketFamily::Tipc, "tipc", 30),
+        (SocketFamily::Bluetooth, "bluetooth", 31),
+        (SocketFamily::Alg, "alg", 38),
+        (SocketFamily::Vsock, "vsock", 40),
+        (SocketFamily::Xdp, "xdp", 44),
+    ];
+
+    fn name(&self) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .find(|(family, _, _)| family == self)
+            .map(|(_, name, _)| *name)
+    }
+
+    /// Returns the `AF_*` number used in the map keys.
+    pub(crate) fn id(&self) -> u32 {
+        match self {
+            SocketFamily::Other(id) => *id,
+            family => Self::NAMES
+                .iter()
+                .find(|(f, _, _)| f == family)
+                .map(|(_, _, id)| *id)
+                .expect("named family should have a number"),
+        }
+    }
+
+    /// Returns the family with the given `AF_*` number.
+    pub(crate) fn from_id(id: u32) -> Self {
+        Self::NAMES
+            .iter()
+            .find(|(_, _, i)| *i == id)
+            .map_or(SocketFamily::Other(id), |(family, _, _)| *family)
+    }
+}
+
+impl Display for SocketFamily {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "{}", self.id()),
+        }
+    }
+}
+
+impl Serialize for SocketFamily {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.name() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_u32(self.id()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SocketFamily {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Family {
+            Id(u32),
+            Name(String),
+        }
+
+        match Family::deserialize(deserializer)? {
+            Family::Id(id) => Ok(SocketFamily::from_id(id)),
+            Family::Name(name) => Self::NAMES
+                .iter()
+                .find(|(_, n, _)| *n == name)
+                .map(|(family, _, _)| *family)
+                .ok_or_else(|| serde::de::Error::custom(format!("unknown socket family: {name}"))),
+        }
+    }
+}
+
+/// Type of a socket.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum SocketType {
+    #[default]
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "stream")]
+    Stream,
+    #[serde(rename = "dgram")]
+    Dgram,
+    #[serde(rename = "raw")]
+    Raw,
+    #[serde(rename = "rdm")]
+    Rdm,
+    #[serde(rename = "seqpacket")]
+    Seqpacket,
+    #[serde(rename = "packet")]
+    Packet,
+}
+
+impl SocketType {
+    pub fn is_all(&self) -> bool {
+        *self == SocketType::All
+    }
+
+    /// Returns the `SOCK_*` number used in the map keys.
+    pub(crate) fn id(&self) -> u32 {
+        match self {
+            SocketType::All => SOCKET_TYPE_WILDCARD,
+            SocketType::Stream => 1,
+            SocketType::Dgram => 2,
+            SocketType::Raw => 3,
+            SocketType::Rdm => 4,
+            SocketType::Seqpacket => 5,
+            SocketType::Packet => 10,
+        }
+    }
+
+    /// Returns the type with the given `SOCK_*` number used in the map keys.
+    pub(crate) fn from_id(id: u32) -> Option<Self> {
+        match id {
+            SOCKET_TYPE_WILDCARD => Some(SocketType::All),
+            1 => Some(SocketType::Stream),
+            2 => Some(SocketType::Dgram),
+            3 => Some(SocketType::Raw),
+            4 => Some(SocketType::Rdm),
+            5 => Some(SocketType::Seqpacket),
+            10 => Some(SocketType::Packet),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SocketType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SocketType::All => write!(f, "all"),
+            SocketType::Stream => write!(f, "stream"),
+            SocketType::Dgram => write!(f, "dgram"),
+            SocketType::Raw => write!(f, "raw"),
+            SocketType::Rdm => write!(f, "rdm"),
+            SocketType::Seqpacket => write!(f, "seqpacket"),
+            SocketType::Packet => write!(f, "packet"),
+        }
+    }
+}
+
+/// Mode of a policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolicyMode {
+    /// Operations denied by the policy fail.
+    #[default]
+    #[serde(rename = "enforce")]
+    Enforce,
+    /// Operations denied by the policy are alerted, but allowed. Useful for
+    /// testing new rules before enforcing them.
+    #[serde(rename = "audit")]
+    Audit,
+}
+
+impl PolicyMode {
+    pub fn is_enforce(&self) -> bool {
+        *self == PolicyMode::Enforce
+    }
+
+    /// Returns the flags of the map entries of policies in this mode.
+    pub(crate) fn flags(&self) -> u8 {
+        match self {
+            PolicyMode::Enforce => 0,
+            PolicyMode::Audit => POLICY_AUDIT,
+        }
+    }
+
+    /// Returns the mode of the policy which added a map entry with the given
+    /// flags.
+    pub(crate) fn from_flags(flags: u8) -> Self {
+        if flags & POLICY_AUDIT != 0 {
+            PolicyMode::Audit
+        } else {
+            PolicyMode::Enforce
+        }
+    }
+}
+
+impl Display for PolicyMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyMode::Enforce => write!(f, "enforce"),
+            PolicyMode::Audit => write!(f, "audit"),
+        }
+    }
+}
+
+/// LSM hook with policies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Hook {
+    #[serde(rename = "bprm_check_security")]
+    BprmCheckSecurity,
+    #[serde(rename = "file_open")]
+    FileOpen,
+    #[serde(rename = "inet_conn_request")]
+    InetConnRequest,
+    #[serde(rename = "sb_mount")]
+    SbMount,
+    #[serde(rename = "sb_remount")]
+    SbRemount,
+    #[serde(rename = "sb_umount")]
+    SbUmount,
+    #[serde(rename = "socket_bind")]
+    SocketBind,
+    #[serde(rename = "socket_connect")]
+    SocketConnect,
+    #[serde(rename = "socket_connect_unix")]
+    SocketConnectUnix,
+    #[serde(rename = "socket_create")]
+    SocketCreate,
+    #[serde(rename = "socket_listen")]
+    SocketListen,
+    #[serde(rename = "task_fix_setuid")]
+    TaskFixSetuid,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 12] = [
+        Hook::BprmCheckSecurity,
+        Hook::FileOpen,
+        Hook::InetConnRequest,
+        Hook::SbMount,
+        Hook::SbRemount,
+        Hook::SbUmount,
+        Hook::SocketBind,
+        Hook::SocketConnect,
+        Hook::SocketConnectUnix,
+        Hook::SocketCreate,
+        Hook::SocketListen,
+        Hook::TaskFixSetuid,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::BprmCheckSecurity => "bprm_check_security",
+            Hook::FileOpen => "file_open",
+            Hook::InetConnRequest => "inet_conn_request",
+            Hook::SbMount => "sb_mount",
+            Hook::SbRemount => "sb_remount",
+            Hook::SbUmount => "sb_umount",
+            Hook::SocketBind => "socket_bind",
+            Hook::SocketConnect => "socket_connect",
+            Hook::SocketConnectUnix => "socket_connect_unix",
+            Hook::SocketCreate => "socket_create",
+            Hook::SocketListen => "socket_listen",
+            Hook::TaskFixSetuid => "task_fix_setuid",
+        }
+    }
+
+    /// Returns the name used for the default action of the hook in errors
+    /// and reports.
+    pub fn default_name(&self) -> &'static str {
+        match self {
+            Hook::BprmCheckSecurity => "bprm_check_security default",
+            Hook::FileOpen => "file_open default",
+            Hook::InetConnRequest => "inet_conn_request default",
+            Hook::SbMount => "sb_mount default",
+            Hook::SbRemount => "sb_remount default",
+            Hook::SbUmount => "sb_umount default",
+            Hook::SocketBind => "socket_bind default",
+            Hook::SocketConnect => "socket_connect default",
+            Hook::SocketConnectUnix => "socket_connect_unix default",
+            Hook::SocketCreate => "socket_create default",
+            Hook::SocketListen => "socket_listen default",
+            Hook::TaskFixSetuid => "task_fix_setuid default",
+        }
+    }
+
+    /// Returns the ID of the hook used in the default action map.
+    pub(crate) fn id(&self) -> u32 {
+        match self {
+            Hook::BprmCheckSecurity => HOOK_BPRM_CHECK_SECURITY,
+            Hook::FileOpen => HOOK_FILE_OPEN,
+            Hook::InetConnRequest => HOOK_INET_CONN_REQUEST,
+            Hook::SbMount => HOOK_SB_MOUNT,
+            Hook::SbRemount => HOOK_SB_REMOUNT,
+            Hook::SbUmount => HOOK_SB_UMOUNT,
+            Hook::SocketBind => HOOK_SOCKET_BIND,
+            Hook::SocketConnect => HOOK_SOCKET_CONNECT,
+            Hook::SocketConnectUnix => HOOK_SOCKET_CONNECT_UNIX,
+            Hook::SocketCreate => HOOK_SOCKET_CREATE,
+            Hook::SocketListen => HOOK_SOCKET_LISTEN,
+            Hook::TaskFixSetuid => HOOK_TASK_FIX_SETUID,
+        }
+    }
+}
+
+impl Display for Hook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Action applied to an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    #[serde(rename = "allow")]
+    Allow,
+    #[serde(rename = "deny")]
+    Deny,
+}
+
+impl Action {
+    /// Returns the flags of the default action map entries with this action
+    /// and mode.
+    pub(crate) fn flags(&self, mode: PolicyMode) -> u8 {
+        match self {
+            Action::Allow => mode.flags(),
+            Action::Deny => DEFAULT_DENY | mode.flags(),
+        }
+    }
+
+    pub(crate) fn from_flags(flags: u8) -> Self {
+        if flags & DEFAULT_DENY != 0 {
+            Action::Deny
+        } else {
+            Action::Allow
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Allow => write!(f, "allow"),
+            Action::Deny => write!(f, "deny"),
         }
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Policy {
+    #[serde(rename = "default")]
+    Default(DefaultAction),
+    #[serde(rename = "bprm_check_security")]
+    BprmCheckSecurity(BprmCheckSecurity),
     #[serde(rename = "file_open")]
     FileOpen(FileOpen),
+    #[serde(rename = "inet_conn_request")]
+    InetConnRequest(InetConnRequest),
     #[serde(rename = "sb_mount")]
     SbMount(SbMount),
     #[serde(rename = "sb_remount")]
@@ -175,52 +1075,238 @@ pub enum Policy {
     SocketBind(SocketBind),
     #[serde(rename = "socket_connect")]
     SocketConnect(SocketConnect),
+    #[serde(rename = "socket_connect_unix")]
+    SocketConnectUnix(SocketConnectUnix),
+    #[serde(rename = "socket_create")]
+    SocketCreate(SocketCreate),
+    #[serde(rename = "socket_listen")]
+    SocketListen(SocketListen),
     #[serde(rename = "task_fix_setuid")]
     TaskFixSetuid(TaskFixSetuid),
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+impl Policy {
+    pub fn mode(&self) -> PolicyMode {
+        match self {
+            Policy::Default(policy) => policy.mode,
+            Policy::BprmCheckSecurity(policy) => policy.mode,
+            Policy::FileOpen(policy) => policy.mode,
+            Policy::InetConnRequest(policy) => policy.mode,
+            Policy::SbMount(policy) => policy.mode,
+            Policy::SbRemount(policy) => policy.mode,
+            Policy::SbUmount(policy) => policy.mode,
+            Policy::SocketBind(policy) => policy.mode,
+            Policy::SocketConnect(policy) => policy.mode,
+            Policy::SocketConnectUnix(policy) => policy.mode,
+            Policy::SocketCreate(policy) => policy.mode,
+            Policy::SocketListen(policy) => policy.mode,
+            Policy::TaskFixSetuid(policy) => policy.mode,
+        }
+    }
+}
+
+/// Action applied to operations of the subject in the hook which don't match
+/// any policy. Policies of all subjects matching a process, even the less
+/// specific ones, take precedence over default actions. The default action
+/// of the most specific subject applies, operations are allowed if there is
+/// none.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefaultAction {
+    pub hook: Hook,
+    pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    pub action: Action,
+}
+
+/// Executables which the subject (the process calling `execve`) is allowed or
+/// denied to run. Directories match all executables under them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BprmCheckSecurity {
+    pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    pub allow: Paths,
+    pub deny: Paths,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileOpen {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    /// Paths allowed for all kinds of access.
     pub allow: Paths,
+    /// Paths denied for all kinds of access.
     pub deny: Paths,
+    /// How the paths of the policy are matched.
+    #[serde(default, skip_serializing_if = "PathMatch::is_inode")]
+    pub matching: PathMatch,
+    /// Rules for the given kinds of access. A path can't be both allowed and
+    /// denied for the same kind of access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub access_rules: Vec<AccessRule>,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Paths allowed and denied for the given kinds of access in a `file_open`
+/// policy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessRule {
+    pub access: Vec<Access>,
+    pub allow: Paths,
+    pub deny: Paths,
+}
+
+/// Peers which are allowed or denied to connect to TCP sockets the subject
+/// listens on. Addresses are the ones of the peer, ports in `port_rules` are
+/// the local ones. Rules are compared like in [`SocketConnect`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InetConnRequest {
+    pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    /// Peer addresses allowed on all local ports.
+    pub allow: Addresses,
+    /// Peer addresses denied on all local ports.
+    pub deny: Addresses,
+    /// Rules for connections to the given local ports.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub port_rules: Vec<PortRule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SbMount {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
     pub allow: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SbRemount {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
     pub allow: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SbUmount {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
     pub allow: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct SocketBind {
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketBind {
+    pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    /// Ports allowed on all addresses.
+    pub allow: Ports,
+    /// Ports denied on all addresses.
+    pub deny: Ports,
+    /// Rules for binding on the given addresses, which win over `allow` and
+    /// `deny`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address_rules: Vec<AddressRule>,
+}
+
+/// Ports allowed and denied on the given addresses in a `socket_bind` or
+/// `socket_listen` policy.
+/// Port ranges (other than all ports) can be used only with single addresses,
+/// not with networks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressRule {
+    pub addresses: Addresses,
+    pub allow: Ports,
+    pub deny: Ports,
+}
+
+/// Local ports and addresses the subject is allowed or denied to listen on.
+/// They are matched like in [`SocketBind`]. Sockets listening before being
+/// bound get an ephemeral port, they match only rules for all ports.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketListen {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    /// Ports allowed on all addresses.
     pub allow: Ports,
+    /// Ports denied on all addresses.
     pub deny: Ports,
+    /// Rules for listening on the given addresses, which win over `allow`
+    /// and `deny`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address_rules: Vec<AddressRule>,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SocketConnect {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    /// Addresses allowed on all ports and protocols.
     pub allow: Addresses,
+    /// Addresses denied on all ports and protocols.
     pub deny: Addresses,
+    /// Rules for connecting to the given ports. Rules for all addresses win
+    /// only over `all` addresses in `allow` and `deny`, rules for given
+    /// addresses are compared with `allow` and `deny` by the length of the
+    /// network (and of the ports, for single addresses).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub port_rules: Vec<PortRule>,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Ports allowed and denied for the given protocol and addresses in a
+/// `socket_connect` policy (destination ports) or an `inet_conn_request`
+/// policy (local ports of the listening socket). Port ranges (other than all
+/// ports) can be used only with all addresses or with single addresses, not
+/// with networks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRule {
+    #[serde(default, skip_serializing_if = "Protocol::is_all")]
+    pub protocol: Protocol,
+    #[serde(default, skip_serializing_if = "Addresses::is_all")]
+    pub addresses: Addresses,
+    pub allow: Ports,
+    pub deny: Ports,
+}
+
+/// Unix domain sockets the subject is allowed or denied to connect to,
+/// matched by the path given to `connect`. Relative paths and symlinks used
+/// by the connecting process are not resolved, so rules for single paths are
+/// best combined with denying (or allowing) `all` sockets.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketConnectUnix {
+    pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    pub allow: UnixPaths,
+    pub deny: UnixPaths,
+}
+
+/// Sockets the subject is allowed or denied to create, matched by the
+/// family, the type and the protocol given to `socket`. Sockets created by
+/// the kernel are not inspected. A rule for the family, the type and the
+/// protocol wins over the rules with fewer of them, e.g. denying `packet`
+/// sockets and allowing `packet` sockets of type `dgram` allows only the
+/// latter.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketCreate {
+    pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
+    pub allow: Sockets,
+    pub deny: Sockets,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TaskFixSetuid {
     pub subject: PolicySubject,
+    #[serde(default, skip_serializing_if = "PolicyMode::is_enforce")]
+    pub mode: PolicyMode,
     pub allow: bool,
 }
 
@@ -230,6 +1316,29 @@ mod test {
 
     use std::net::{Ipv4Addr, Ipv6Addr};
 
+    #[test]
+    fn test_bprm_check_security() {
+        let yaml = "
+- !bprm_check_security
+  subject: !cgroup /sys/fs/cgroup/system.slice/nginx.service
+  allow: !paths
+    - /usr/sbin/nginx
+  deny: all
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::BprmCheckSecurity(BprmCheckSecurity {
+                subject: PolicySubject::Cgroup(PathBuf::from(
+                    "/sys/fs/cgroup/system.slice/nginx.service"
+                )),
+                mode: PolicyMode::Enforce,
+                allow: Paths::Paths(vec![PathBuf::from("/usr/sbin/nginx")]),
+                deny: Paths::All,
+            })]
+        );
+    }
+
     #[test]
     fn test_file_open() {
         let yaml = "
@@ -250,20 +1359,105 @@ mod test {
             policy[0],
             Policy::FileOpen(FileOpen {
                 subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
                 allow: Paths::All,
-                deny: Paths::Paths(vec![PathBuf::from("/root/s3cr3tdir")])
+                deny: Paths::Paths(vec![PathBuf::from("/root/s3cr3tdir")]),
+                matching: PathMatch::Inode,
+                access_rules: vec![],
             })
         );
         assert_eq!(
             policy[1],
             Policy::FileOpen(FileOpen {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/myapp")),
+                mode: PolicyMode::Enforce,
                 allow: Paths::Paths(vec![PathBuf::from("/etc/myapp")]),
-                deny: Paths::All
+                deny: Paths::All,
+                matching: PathMatch::Inode,
+                access_rules: vec![],
             })
         );
     }
 
+    #[test]
+    fn test_file_open_prefix() {
+        let yaml = "
+- !file_open
+  subject: all
+  allow: all
+  deny: !paths
+  - /var/lib/docker/volumes
+  matching: prefix
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::FileOpen(FileOpen {
+                subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
+                allow: Paths::All,
+                deny: Paths::Paths(vec![PathBuf::from("/var/lib/docker/volumes")]),
+                matching: PathMatch::Prefix,
+                access_rules: vec![],
+            })]
+        );
+        assert_eq!(serde_yaml::to_string(&policy).unwrap().trim(), yaml.trim());
+    }
+
+    #[test]
+    fn test_file_open_access_rules() {
+        let yaml = "
+- !file_open
+  subject: !binary /usr/sbin/nginx
+  allow: !paths []
+  deny: all
+  access_rules:
+  - access:
+    - read
+    allow: !paths
+    - /etc/nginx
+    deny: !paths []
+  - access:
+    - write
+    - exec
+    allow: !paths []
+    deny: !paths
+    - /etc/nginx
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::FileOpen(FileOpen {
+                subject: PolicySubject::Binary(PathBuf::from("/usr/sbin/nginx")),
+                mode: PolicyMode::Enforce,
+                allow: Paths::Paths(vec![]),
+                deny: Paths::All,
+                matching: PathMatch::Inode,
+                access_rules: vec![
+                    AccessRule {
+                        access: vec![Access::Read],
+                        allow: Paths::Paths(vec![PathBuf::from("/etc/nginx")]),
+                        deny: Paths::Paths(vec![]),
+                    },
+                    AccessRule {
+                        access: vec![Access::Write, Access::Exec],
+                        allow: Paths::Paths(vec![]),
+                        deny: Paths::Paths(vec![PathBuf::from("/etc/nginx")]),
+                    },
+                ],
+            })]
+        );
+        assert_eq!(serde_yaml::to_string(&policy).unwrap().trim(), yaml.trim());
+
+        assert_eq!(Access::from_flags(ACCESS_READ), vec![Access::Read]);
+        assert_eq!(
+            Access::from_flags(ACCESS_WRITE | ACCESS_EXEC | POLICY_AUDIT),
+            vec![Access::Write, Access::Exec]
+        );
+        // Entries without access flags apply to all kinds of access.
+        assert_eq!(Access::from_flags(POLICY_AUDIT), Access::ALL.to_vec());
+    }
+
     #[test]
     fn test_sb_mount() {
         let yaml = "
@@ -280,6 +1474,7 @@ mod test {
             policy[0],
             Policy::SbMount(SbMount {
                 subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
                 allow: false
             })
         );
@@ -287,11 +1482,139 @@ mod test {
             policy[1],
             Policy::SbMount(SbMount {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/mount")),
+                mode: PolicyMode::Enforce,
                 allow: true
             })
         );
     }
 
+    #[test]
+    fn test_cgroup_subjects() {
+        let yaml = "
+- !sb_mount
+  subject: !cgroup /sys/fs/cgroup/system.slice/docker-abc.scope
+  allow: false
+- !sb_mount
+  subject: !cgroup_binary
+    cgroup: /sys/fs/cgroup/system.slice/docker-abc.scope
+    binary: /usr/bin/mount
+  allow: true
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        let cgroup = PathBuf::from("/sys/fs/cgroup/system.slice/docker-abc.scope");
+        assert_eq!(
+            policy,
+            vec![
+                Policy::SbMount(SbMount {
+                    subject: PolicySubject::Cgroup(cgroup.clone()),
+                    mode: PolicyMode::Enforce,
+                    allow: false
+                }),
+                Policy::SbMount(SbMount {
+                    subject: PolicySubject::CgroupBinary {
+                        cgroup,
+                        binary: PathBuf::from("/usr/bin/mount")
+                    },
+                    mode: PolicyMode::Enforce,
+                    allow: true
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_account_subjects() {
+        let yaml = "
+- !socket_bind
+  subject: !user www-data
+  allow: all
+  deny: !ports
+    - 80
+- !file_open
+  subject: !group 1000
+  allow: all
+  deny: !paths
+    - /etc/shadow
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![
+                Policy::SocketBind(SocketBind {
+                    subject: PolicySubject::User(Account::Name("www-data".to_owned())),
+                    mode: PolicyMode::Enforce,
+                    allow: Ports::All,
+                    deny: Ports::Ports(vec![80]),
+                    address_rules: vec![],
+                }),
+                Policy::FileOpen(FileOpen {
+                    subject: PolicySubject::Group(Account::Id(1000)),
+                    mode: PolicyMode::Enforce,
+                    allow: Paths::All,
+                    deny: Paths::Paths(vec![PathBuf::from("/etc/shadow")]),
+                    matching: PathMatch::Inode,
+                    access_rules: vec![],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_policy_mode() {
+        let yaml = "
+- !task_fix_setuid
+  subject: all
+  mode: audit
+  allow: false
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        let expected = Policy::TaskFixSetuid(TaskFixSetuid {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Audit,
+            allow: false,
+        });
+        assert_eq!(policy, vec![expected]);
+        assert_eq!(serde_yaml::to_string(&policy).unwrap().trim(), yaml.trim());
+    }
+
+    #[test]
+    fn test_default_action() {
+        let yaml = "
+- !default
+  hook: file_open
+  subject: all
+  action: allow
+- !default
+  hook: socket_connect
+  subject: !binary /usr/bin/curl
+  mode: audit
+  action: deny
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![
+                Policy::Default(DefaultAction {
+                    hook: Hook::FileOpen,
+                    subject: PolicySubject::All,
+                    mode: PolicyMode::Enforce,
+                    action: Action::Allow,
+                }),
+                Policy::Default(DefaultAction {
+                    hook: Hook::SocketConnect,
+                    subject: PolicySubject::Binary(PathBuf::from("/usr/bin/curl")),
+                    mode: PolicyMode::Audit,
+                    action: Action::Deny,
+                }),
+            ]
+        );
+        assert_eq!(serde_yaml::to_string(&policy).unwrap().trim(), yaml.trim());
+
+        let flags = Action::Deny.flags(PolicyMode::Audit);
+        assert_eq!(Action::from_flags(flags), Action::Deny);
+        assert_eq!(PolicyMode::from_flags(flags), PolicyMode::Audit);
+    }
+
     #[test]
     fn test_socket_bind() {
         let yaml = "
@@ -313,20 +1636,171 @@ mod test {
             policy[0],
             Policy::SocketBind(SocketBind {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/nginx")),
+                mode: PolicyMode::Enforce,
                 allow: Ports::Ports(vec![80, 443]),
-                deny: Ports::All
+                deny: Ports::All,
+                address_rules: vec![],
             })
         );
         assert_eq!(
             policy[1],
             Policy::SocketBind(SocketBind {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/python")),
+                mode: PolicyMode::Enforce,
                 allow: Ports::Ports(vec![8080]),
-                deny: Ports::All
+                deny: Ports::All,
+                address_rules: vec![],
             })
         );
     }
 
+    #[test]
+    fn test_ports_round_trip() {
+        let ports = Ports::Ports(vec![443, 80, 8080]);
+        assert_eq!(
+            Ports::from_ranges(ports.into_blocks()),
+            Ports::Ports(vec![80, 443, 8080])
+        );
+
+        // Adjacent ports are merged into ranges.
+        let ports = Ports::Ports((1000..1300).rev().collect());
+        assert_eq!(
+            Ports::from_ranges(ports.into_blocks()),
+            Ports::Ranges(vec![PortRange::new(1000, 1299).unwrap()])
+        );
+
+        assert_eq!(Ports::from_ranges(Ports::All.into_blocks()), Ports::All);
+    }
+
+    #[test]
+    fn test_address_rules() {
+        let yaml = "
+- !socket_bind
+  subject: !binary /usr/bin/redis-server
+  allow: !ports []
+  deny: all
+  address_rules:
+    - addresses: !addresses
+        - 127.0.0.1
+        - ::1
+      allow: !ports
+        - 6379
+      deny: !ports []
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::SocketBind(SocketBind {
+                subject: PolicySubject::Binary(PathBuf::from("/usr/bin/redis-server")),
+                mode: PolicyMode::Enforce,
+                allow: Ports::Ports(vec![]),
+                deny: Ports::All,
+                address_rules: vec![AddressRule {
+                    addresses: Addresses::Addresses(vec![
+                        IpAddr::from([127, 0, 0, 1]),
+                        IpAddr::from(Ipv6Addr::LOCALHOST),
+                    ]),
+                    allow: Ports::Ports(vec![6379]),
+                    deny: Ports::Ports(vec![]),
+                }],
+            })]
+        );
+        let serialized = serde_yaml::to_string(&policy).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<Vec<Policy>>(&serialized).unwrap(),
+            policy
+        );
+    }
+
+    #[test]
+    fn test_socket_listen() {
+        let yaml = "
+- !socket_listen
+  subject: !binary /usr/sbin/sshd
+  allow: !ports
+    - 22
+  deny: all
+  address_rules:
+    - addresses: !networks
+        - 10.0.0.0/8
+      allow: all
+      deny: !ports []
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::SocketListen(SocketListen {
+                subject: PolicySubject::Binary(PathBuf::from("/usr/sbin/sshd")),
+                mode: PolicyMode::Enforce,
+                allow: Ports::Ports(vec![22]),
+                deny: Ports::All,
+                address_rules: vec![AddressRule {
+                    addresses: Addresses::Networks(vec!["10.0.0.0/8".parse().unwrap()]),
+                    allow: Ports::All,
+                    deny: Ports::Ports(vec![]),
+                }],
+            })]
+        );
+        let serialized = serde_yaml::to_string(&policy).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<Vec<Policy>>(&serialized).unwrap(),
+            policy
+        );
+    }
+
+    #[test]
+    fn test_port_ranges() {
+        let yaml = "
+- !socket_bind
+  subject: all
+  allow: !ranges
+    - 80
+    - 8000-8999
+  deny: !ranges
+    - <1024
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::SocketBind(SocketBind {
+                subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
+                allow: Ports::Ranges(vec![
+                    PortRange::from(80),
+                    PortRange::new(8000, 8999).unwrap()
+                ]),
+                deny: Ports::Ranges(vec![PortRange::new(0, 1023).unwrap()]),
+                address_rules: vec![],
+            })]
+        );
+        let serialized = serde_yaml::to_string(&policy).unwrap();
+        assert!(serialized.contains("- 8000-8999"));
+        assert!(serialized.contains("- <1024"));
+        assert_eq!(
+            serde_yaml::from_str::<Vec<Policy>>(&serialized).unwrap(),
+            policy
+        );
+
+        assert!("1024-80".parse::<PortRange>().is_err());
+        assert!("<0".parse::<PortRange>().is_err());
+        assert!("http".parse::<PortRange>().is_err());
+
+        let blocks = PortRange::new(8000, 8999).unwrap().blocks();
+        assert_eq!(blocks.len(), 6);
+        assert_eq!(
+            PortRange::merge(blocks.clone()),
+            vec![PortRange::new(8000, 8999).unwrap()]
+        );
+        for block in blocks {
+            assert_eq!(
+                PortRange::from_prefix(block.start, block.prefix_len()),
+                block
+            );
+        }
+        assert_eq!(PortRange::ALL.blocks(), vec![PortRange::ALL]);
+        assert_eq!(PortRange::ALL.prefix_len(), 0);
+    }
+
     #[test]
     fn test_socket_connect() {
         let yaml = "
@@ -349,19 +1823,22 @@ mod test {
             policy[0],
             Policy::SocketConnect(SocketConnect {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/nginx")),
+                mode: PolicyMode::Enforce,
                 allow: Addresses::Addresses(vec![
                     IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
                     IpAddr::V6(Ipv6Addr::new(
                         0x2001, 0x0db8, 0x3333, 0x4444, 0x5555, 0x6666, 0x7777, 0x8888
                     ))
                 ]),
-                deny: Addresses::All
+                deny: Addresses::All,
+                port_rules: vec![],
             })
         );
         assert_eq!(
             policy[1],
             Policy::SocketConnect(SocketConnect {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/tomcat")),
+                mode: PolicyMode::Enforce,
                 allow: Addresses::All,
                 deny: Addresses::Addresses(vec![
                     IpAddr::V4(Ipv4Addr::new(172, 16, 0, 1)),
@@ -369,8 +1846,337 @@ mod test {
                         0x2001, 0x0db8, 0x3333, 0x4444, 0xCCCC, 0xDDDD, 0xEEEE, 0xFFFF
                     )),
                 ]),
+                port_rules: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_socket_connect_networks() {
+        let yaml = "
+- !socket_connect
+  subject: all
+  allow: all
+  deny: !networks
+    - 10.0.0.0/8
+    - 192.168.1.1/32
+    - fd00::/8
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(policy.len(), 1);
+        assert_eq!(
+            policy[0],
+            Policy::SocketConnect(SocketConnect {
+                subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
+                allow: Addresses::All,
+                deny: Addresses::Networks(vec![
+                    "10.0.0.0/8".parse().unwrap(),
+                    "192.168.1.1/32".parse().unwrap(),
+                    "fd00::/8".parse().unwrap(),
+                ]),
+                port_rules: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_socket_connect_port_rules() {
+        let yaml = "
+- !socket_connect
+  subject: !binary /usr/bin/curl
+  allow: !addresses []
+  deny: !addresses []
+  port_rules:
+    - protocol: tcp
+      allow: !ports
+        - 80
+        - 443
+      deny: all
+    - protocol: udp
+      addresses: !addresses
+        - 10.0.0.53
+      allow: !ports
+        - 53
+      deny: !ports []
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(policy.len(), 1);
+        assert_eq!(
+            policy[0],
+            Policy::SocketConnect(SocketConnect {
+                subject: PolicySubject::Binary(PathBuf::from("/usr/bin/curl")),
+                mode: PolicyMode::Enforce,
+                allow: Addresses::Addresses(vec![]),
+                deny: Addresses::Addresses(vec![]),
+                port_rules: vec![
+                    PortRule {
+                        protocol: Protocol::Tcp,
+                        addresses: Addresses::All,
+                        allow: Ports::Ports(vec![80, 443]),
+                        deny: Ports::All,
+                    },
+                    PortRule {
+                        protocol: Protocol::Udp,
+                        addresses: Addresses::Addresses(vec![IpAddr::V4(Ipv4Addr::new(
+                            10, 0, 0, 53
+                        ))]),
+                        allow: Ports::Ports(vec![53]),
+                        deny: Ports::Ports(vec![]),
+                    },
+                ],
+            })
+        );
+
+        let serialized = serde_yaml::to_string(&policy).unwrap();
+        assert!(!serialized.contains("protocol: all"));
+        assert_eq!(
+            serde_yaml::from_str::<Vec<Policy>>(&serialized).unwrap(),
+            policy
+        );
+    }
+
+    #[test]
+    fn test_inet_conn_request() {
+        let yaml = "
+- !inet_conn_request
+  subject: !binary /usr/bin/admin-server
+  allow: all
+  deny: !addresses []
+  port_rules:
+    - allow: !ports []
+      deny: !ports
+        - 9000
+    - addresses: !networks
+        - 10.1.0.0/16
+      allow: !ports
+        - 9000
+      deny: !ports []
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(
+            policy,
+            vec![Policy::InetConnRequest(InetConnRequest {
+                subject: PolicySubject::Binary(PathBuf::from("/usr/bin/admin-server")),
+                mode: PolicyMode::Enforce,
+                allow: Addresses::All,
+                deny: Addresses::Addresses(vec![]),
+                port_rules: vec![
+                    PortRule {
+                        protocol: Protocol::All,
+                        addresses: Addresses::All,
+                        allow: Ports::Ports(vec![]),
+                        deny: Ports::Ports(vec![9000]),
+                    },
+                    PortRule {
+                        protocol: Protocol::All,
+                        addresses: Addresses::Networks(vec!["10.1.0.0/16".parse().unwrap()]),
+                        allow: Ports::Ports(vec![9000]),
+                        deny: Ports::Ports(vec![]),
+                    },
+                ],
+            })]
+        );
+
+        let serialized = serde_yaml::to_string(&policy).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<Vec<Policy>>(&serialized).unwrap(),
+            policy
+        );
+    }
+
+    #[test]
+    fn test_addresses_networks_round_trip() {
+        let addresses = Addresses::Addresses(vec![
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0x0db8, 0, 0, 0, 0, 0, 1)),
+        ]);
+        let networks = addresses.into_networks();
+        assert_eq!(
+            Addresses::from_networks(networks),
+            Addresses::Addresses(vec![
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0x0db8, 0, 0, 0, 0, 0, 1)),
+            ])
+        );
+
+        let addresses = Addresses::Networks(vec![
+            "10.1.2.3/8".parse().unwrap(),
+            "fd00::/8".parse().unwrap(),
+        ]);
+        let networks = addresses.into_networks();
+        assert_eq!(
+            Addresses::from_networks(networks),
+            Addresses::Networks(vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "fd00::/8".parse().unwrap(),
+            ])
+        );
+
+        let networks = Addresses::All.into_networks();
+        assert_eq!(Addresses::from_networks(networks), Addresses::All);
+
+        let addresses = Addresses::Networks(vec![
+            "::ffff:10.0.0.0/104".parse().unwrap(),
+            "::ffff:192.168.1.1/128".parse().unwrap(),
+        ]);
+        assert_eq!(
+            addresses.into_networks(),
+            vec![
+                "10.0.0.0/8".parse::<IpNet>().unwrap(),
+                "192.168.1.1/32".parse::<IpNet>().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_socket_connect_unix() {
+        let yaml = "
+- !socket_connect_unix
+  subject: !binary /usr/bin/dockerd
+  allow: !paths
+  - /var/run/docker.sock
+  - '@/tmp/.X11-unix/X0'
+  deny: all
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(policy.len(), 1);
+        assert_eq!(
+            policy[0],
+            Policy::SocketConnectUnix(SocketConnectUnix {
+                subject: PolicySubject::Binary(PathBuf::from("/usr/bin/dockerd")),
+                mode: PolicyMode::Enforce,
+                allow: UnixPaths::Paths(vec![
+                    UnixPath::Path(PathBuf::from("/var/run/docker.sock")),
+                    UnixPath::Abstract("/tmp/.X11-unix/X0".to_owned()),
+                ]),
+                deny: UnixPaths::All,
+            })
+        );
+        assert_eq!(serde_yaml::to_string(&policy).unwrap().trim(), yaml.trim());
+
+        let yaml = "
+- !socket_connect_unix
+  subject: all
+  allow: !paths
+  - run/docker.sock
+  deny: all
+";
+        assert!(serde_yaml::from_str::<Vec<Policy>>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_socket_create() {
+        let yaml = "
+- !socket_create
+  subject: all
+  allow: !sockets
+  - family: packet
+    type: dgram
+  deny: !sockets
+  - family: packet
+  - family: inet
+    type: raw
+  - family: netlink
+    protocol: 4
+  - family: 36
+";
+        let policy = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        assert_eq!(policy.len(), 1);
+        assert_eq!(
+            policy[0],
+            Policy::SocketCreate(SocketCreate {
+                subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
+                allow: Sockets::Sockets(vec![SocketKind {
+                    family: SocketFamily::Packet,
+                    socket_type: SocketType::Dgram,
+                    protocol: None,
+                }]),
+                deny: Sockets::Sockets(vec![
+                    SocketKind {
+                        family: SocketFamily::Packet,
+                        socket_type: SocketType::All,
+                        protocol: None,
+                    },
+                    SocketKind {
+                        family: SocketFamily::Inet,
+                        socket_type: SocketType::Raw,
+                        protocol: None,
+                    },
+                    SocketKind {
+                        family: SocketFamily::Netlink,
+                        socket_type: SocketType::All,
+                        protocol: Some(4),
+                    },
+                    SocketKind {
+                        family: SocketFamily::Other(36),
+                        socket_type: SocketType::All,
+                        protocol: None,
+                    },
+                ]),
             })
         );
+        assert_eq!(serde_yaml::to_string(&policy).unwrap().trim(), yaml.trim());
+
+        let yaml = "
+- !socket_create
+  subject: all
+  allow: all
+  deny: !sockets
+  - family: inet7
+";
+        assert!(serde_yaml::from_str::<Vec<Policy>>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_sockets_round_trip() {
+        let sockets = Sockets::Sockets(vec![
+            SocketKind {
+                family: SocketFamily::Inet6,
+                socket_type: SocketType::Raw,
+                protocol: Some(58),
+            },
+            SocketKind {
+                family: SocketFamily::Vsock,
+                socket_type: SocketType::All,
+                protocol: None,
+            },
+        ]);
+        let ids = sockets.to_ids();
+        assert_eq!(ids[0], [10, 3, 58]);
+        assert_eq!(ids[1], [40, SOCKET_TYPE_WILDCARD, SOCKET_PROTOCOL_WILDCARD]);
+        assert_eq!(Sockets::from_ids(ids).unwrap(), sockets);
+
+        assert_eq!(
+            Sockets::from_ids(Sockets::All.to_ids()).unwrap(),
+            Sockets::All
+        );
+        assert!(matches!(
+            Sockets::from_ids(vec![[10, 7, 0]]),
+            Err(EbpfguardError::InvalidMapEntry(_))
+        ));
+        assert_eq!(SocketFamily::from_id(17), SocketFamily::Packet);
+        assert_eq!(SocketFamily::Other(36).to_string(), "36");
+    }
+
+    #[test]
+    fn test_unix_paths_round_trip() {
+        let paths = UnixPaths::Paths(vec![
+            UnixPath::Path(PathBuf::from("/var/run/docker.sock")),
+            UnixPath::Abstract("dbus-1".to_owned()),
+        ]);
+        let sun_paths = paths.clone().into_sun_paths().unwrap();
+        assert_eq!(&sun_paths[0][..21], b"/var/run/docker.sock\0");
+        assert_eq!(&sun_paths[1][..8], b"\0dbus-1\0");
+        assert_eq!(UnixPaths::from_sun_paths(sun_paths), paths);
+
+        let sun_paths = UnixPaths::All.into_sun_paths().unwrap();
+        assert_eq!(UnixPaths::from_sun_paths(sun_paths), UnixPaths::All);
+
+        let too_long = UnixPath::Path(PathBuf::from(format!("/{}", "a".repeat(UNIX_PATH_MAX))));
+        assert!(too_long.to_sun_path().is_err());
+        assert!(UnixPath::Abstract(String::new()).to_sun_path().is_err());
     }
 
     #[test]
@@ -389,6 +2195,7 @@ mod test {
             policy[0],
             Policy::TaskFixSetuid(TaskFixSetuid {
                 subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
                 allow: false
             })
         );
@@ -396,6 +2203,7 @@ mod test {
             policy[1],
             Policy::TaskFixSetuid(TaskFixSetuid {
                 subject: PolicySubject::Binary(PathBuf::from("/usr/bin/sudo")),
+                mode: PolicyMode::Enforce,
                 allow: true
             })
         );
@@ -1,12 +1,123 @@
-use std::{fs, path::Path};
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::error::EbpfguardError;
 
 use super::Policy;
 
+/// Location of a policy, used in validation errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// File the policy was read from.
+    pub file: Option<PathBuf>,
+    /// Index of the policy in the list.
+    pub index: usize,
+    /// Line on which the policy starts.
+    pub line: Option<u32>,
+}
+
+impl Location {
+    /// Returns the location of a policy which was not read from a file.
+    pub fn from_index(index: usize) -> Self {
+        Self {
+            file: None,
+            index,
+            line: None,
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}", file.display(), line),
+            (Some(file), None) => write!(f, "{} (policy {})", file.display(), self.index + 1),
+            (None, _) => write!(f, "policy {}", self.index + 1),
+        }
+    }
+}
+
 pub fn read_policies<P: AsRef<Path>>(path: P) -> Result<Vec<Policy>, EbpfguardError> {
     let path = path.as_ref();
     let yaml = fs::read_to_string(path)?;
     let policies = serde_yaml::from_str::<Vec<Policy>>(&yaml)?;
     Ok(policies)
 }
+
+/// Reads policies together with their locations in the file, which can be
+/// passed to [`validate`](super::validate::validate).
+pub fn read_policies_with_locations<P: AsRef<Path>>(
+    path: P,
+) -> Result<(Vec<Policy>, Vec<Location>), EbpfguardError> {
+    let path = path.as_ref();
+    let yaml = fs::read_to_string(path)?;
+    let policies = serde_yaml::from_str::<Vec<Policy>>(&yaml)?;
+
+    let mut lines = item_lines(&yaml);
+    if lines.len() != policies.len() {
+        lines.clear();
+    }
+    let locations = (0..policies.len())
+        .map(|index| Location {
+            file: Some(path.to_owned()),
+            index,
+            line: lines.get(index).copied(),
+        })
+        .collect();
+
+    Ok((policies, locations))
+}
+
+/// Returns the lines (counted from 1) on which the items of the top-level
+/// sequence start.
+fn item_lines(yaml: &str) -> Vec<u32> {
+    // serde_yaml doesn't expose positions of deserialized values, so the lines
+    // are found by looking for items of the top-level block sequence.
+    // Documents in the flow style (`[...]`) have no line information.
+    let mut indent = None;
+    let mut lines = Vec::new();
+
+    for (i, line) in yaml.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+            continue;
+        }
+        let is_item = trimmed == "-" || trimmed.starts_with("- ");
+        let line_indent = line.len() - trimmed.len();
+        match indent {
+            None if is_item => indent = Some(line_indent),
+            None => return Vec::new(),
+            Some(indent) if !is_item || line_indent != indent => continue,
+            Some(_) => {}
+        }
+        lines.push(i as u32 + 1);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_item_lines() {
+        let yaml = "
+# Deny mount for everyone.
+- !sb_mount
+  subject: all
+  allow: false
+
+- !socket_bind
+  subject: all
+  allow: !ports
+    - 8080
+  deny: all
+";
+        assert_eq!(item_lines(yaml), vec![3, 7]);
+        assert!(item_lines("[!sb_mount {subject: all, allow: false}]").is_empty());
+    }
+}
@@ -0,0 +1,1001 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ebpfguard_common::consts::{MAX_DEFAULT_ENTRIES, MAX_OBJECT_ENTRIES, MAX_SUBJECT_ENTRIES};
+use ipnet::IpNet;
+
+use crate::{
+    error::EbpfguardError,
+    fs,
+    hooks::{
+        bprm_check_security::BprmCheckSecurity,
+        defaults::HookDefaults,
+        file_open::{self, FileOpen},
+        inet_conn_request::InetConnRequest,
+        reconcile::PolicyMaps,
+        sb_mount::SbMount,
+        sb_remount::SbRemount,
+        sb_umount::SbUmount,
+        socket_bind::{BindTarget, SocketBind},
+        socket_connect::{ConnectTarget, SocketConnect},
+        socket_connect_unix::SocketConnectUnix,
+        socket_create::SocketCreate,
+        socket_listen::SocketListen,
+        task_fix_setuid::TaskFixSetuid,
+    },
+};
+
+use super::{
+    reader::Location, Access, Action, AddressRule, Addresses, Hook, PathMatch, Paths, Policy,
+    PolicyMode, PolicySubject, PortRange, PortRule, Ports, Sockets, UnixPaths,
+};
+
+/// Checks the policies before loading them. Reports:
+///
+/// * paths which don't exist, except for objects of `file_open` policies,
+///   which are enforced once they appear,
+/// * invalid glob patterns and path prefixes in `file_open` policies,
+/// * policies which don't fit into the maps,
+/// * objects which are both allowed and denied for the same subject,
+/// * subjects defined more than once for the same hook (`file_open` allows
+///   one policy of each [`PathMatch`] per subject),
+/// * policies of the same subject and hook in different modes,
+/// * rules which are never evaluated by the eBPF programs.
+///
+/// `locations` are used in the reported errors, policies without a location
+/// are referred to by their index. All problems are returned at once as
+/// [`EbpfguardError::InvalidPolicies`].
+pub fn validate(policies: &[Policy], locations: &[Location]) -> Result<(), EbpfguardError> {
+    let mut errors = Vec::new();
+    let mut hooks: BTreeMap<&'static str, HookRules> = BTreeMap::new();
+    let mut defaults = Vec::new();
+
+    for (index, policy) in policies.iter().enumerate() {
+        let location = locations
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Location::from_index(index));
+
+        let (hook, max_entries, subject, objects) = match policy {
+            Policy::Default(policy) => {
+                defaults.push((policy.hook, &policy.subject, location.clone()));
+                // All hooks share the map of default actions.
+                if defaults.len() == MAX_DEFAULT_ENTRIES as usize + 1 {
+                    errors.push(EbpfguardError::TooManyEntries {
+                        location: location.clone(),
+                        hook: "default",
+                        max: MAX_DEFAULT_ENTRIES,
+                    });
+                }
+                let object = Object {
+                    scope: Scope::Any,
+                    all: true,
+                    name: HookDefaults::describe(&()),
+                    access: None,
+                };
+                (
+                    policy.hook.default_name(),
+                    // Checked above for all hooks together.
+                    u32::MAX,
+                    &policy.subject,
+                    vec![(object, policy.action == Action::Allow)],
+                )
+            }
+            Policy::BprmCheckSecurity(policy) => {
+                check_paths(&[&policy.allow, &policy.deny], &location, &mut errors);
+                (
+                    BprmCheckSecurity::HOOK,
+                    MAX_OBJECT_ENTRIES,
+                    &policy.subject,
+                    objects(&policy.allow, &policy.deny, |p| paths(p, "all executables")),
+                )
+            }
+            Policy::FileOpen(policy) => {
+                let matching = policy.matching;
+                check_patterns(&[&policy.allow, &policy.deny], matching, &mut errors);
+                let mut objects = objects(&policy.allow, &policy.deny, |p| {
+                    file_paths(p, matching, &Access::ALL)
+                });
+                for rule in &policy.access_rules {
+                    check_patterns(&[&rule.allow, &rule.deny], matching, &mut errors);
+                    objects.extend(self::objects(&rule.allow, &rule.deny, |p| {
+                        file_paths(p, matching, &rule.access)
+                    }));
+                }
+                (FileOpen::HOOK, MAX_OBJECT_ENTRIES, &policy.subject, objects)
+            }
+            Policy::InetConnRequest(policy) => (
+                InetConnRequest::HOOK,
+                MAX_OBJECT_ENTRIES,
+                &policy.subject,
+                connect_objects::<InetConnRequest>(
+                    &policy.subject,
+                    &policy.allow,
+                    &policy.deny,
+                    &policy.port_rules,
+                    &location,
+                    &mut errors,
+                ),
+            ),
+            Policy::SbMount(policy) => (
+                SbMount::HOOK,
+                MAX_SUBJECT_ENTRIES,
+                &policy.subject,
+                operation::<SbMount>(policy.allow),
+            ),
+            Policy::SbRemount(policy) => (
+                SbRemount::HOOK,
+                MAX_SUBJECT_ENTRIES,
+                &policy.subject,
+                operation::<SbRemount>(policy.allow),
+            ),
+            Policy::SbUmount(policy) => (
+                SbUmount::HOOK,
+                MAX_SUBJECT_ENTRIES,
+                &policy.subject,
+                operation::<SbUmount>(policy.allow),
+            ),
+            Policy::SocketBind(policy) => (
+                SocketBind::HOOK,
+                MAX_OBJECT_ENTRIES,
+                &policy.subject,
+                port_objects::<SocketBind>(
+                    &policy.subject,
+                    &policy.allow,
+                    &policy.deny,
+                    &policy.address_rules,
+                    &location,
+                    &mut errors,
+                ),
+            ),
+            Policy::SocketConnect(policy) => (
+                SocketConnect::HOOK,
+                MAX_OBJECT_ENTRIES,
+                &policy.subject,
+                connect_objects::<SocketConnect>(
+                    &policy.subject,
+                    &policy.allow,
+                    &policy.deny,
+                    &policy.port_rules,
+                    &location,
+                    &mut errors,
+                ),
+            ),
+            Policy::SocketConnectUnix(policy) => (
+                SocketConnectUnix::HOOK,
+                MAX_OBJECT_ENTRIES,
+                &policy.subject,
+                objects(&policy.allow, &policy.deny, unix_paths),
+            ),
+            Policy::SocketCreate(policy) => (
+                SocketCreate::HOOK,
+                MAX_OBJECT_ENTRIES,
+                &policy.subject,
+                objects(&policy.allow, &policy.deny, sockets),
+            ),
+            Policy::SocketListen(policy) => (
+                SocketListen::HOOK,
+                MAX_OBJECT_ENTRIES,
+                &policy.subject,
+                port_objects::<SocketListen>(
+                    &policy.subject,
+                    &policy.allow,
+                    &policy.deny,
+                    &policy.address_rules,
+                    &location,
+                    &mut errors,
+                ),
+            ),
+            Policy::TaskFixSetuid(policy) => (
+                TaskFixSetuid::HOOK,
+                MAX_SUBJECT_ENTRIES,
+                &policy.subject,
+                operation::<TaskFixSetuid>(policy.allow),
+            ),
+        };
+
+        let subject_paths = match subject {
+            PolicySubject::Binary(path) | PolicySubject::Cgroup(path) => vec![path],
+            PolicySubject::CgroupBinary { cgroup, binary } => vec![cgroup, binary],
+            PolicySubject::User(_) | PolicySubject::Group(_) | PolicySubject::All => vec![],
+        };
+        for path in subject_paths.into_iter().filter(|path| !path.exists()) {
+            errors.push(EbpfguardError::PathNotFound {
+                location: location.clone(),
+                path: path.to_owned(),
+            });
+        }
+
+        let rules = hooks.entry(hook).or_insert_with(|| HookRules {
+            max_entries,
+            subjects: Vec::new(),
+            policies: Vec::new(),
+            rules: Vec::new(),
+        });
+        let subject_id = match rules.subjects.iter().position(|s| *s == subject) {
+            Some(subject_id) => subject_id,
+            None => {
+                rules.subjects.push(subject);
+                rules.subjects.len() - 1
+            }
+        };
+        // Inode and prefix policies of a subject are loaded side by side.
+        let matching = match policy {
+            Policy::FileOpen(policy) => Some(policy.matching),
+            _ => None,
+        };
+        let mode = policy.mode();
+        match rules
+            .policies
+            .iter()
+            .find(|(s, m, _, _)| *s == subject_id && *m == matching)
+        {
+            Some((_, _, _, first)) => errors.push(EbpfguardError::DuplicateSubject {
+                location: location.clone(),
+                first: first.clone(),
+                hook,
+                subject: Box::new(subject.clone()),
+            }),
+            None => {
+                // All entries of a subject share the same mode.
+                if let Some((_, _, _, first)) = rules
+                    .policies
+                    .iter()
+                    .find(|(s, _, m, _)| *s == subject_id && *m != mode)
+                {
+                    errors.push(EbpfguardError::MixedModes {
+                        location: location.clone(),
+                        first: first.clone(),
+                        hook,
+                        subject: Box::new(subject.clone()),
+                    });
+                }
+                rules
+                    .policies
+                    .push((subject_id, matching, mode, location.clone()))
+            }
+        }
+        for (object, allow) in objects {
+            rules.rules.push(Rule {
+                location: location.clone(),
+                subject: subject_id,
+                object,
+                allow,
+            });
+        }
+    }
+
+    for (hook, rules) in &hooks {
+        rules.check(hook, &mut errors);
+    }
+
+    for (hook, subject, location) in defaults {
+        let scopes: &[Scope] = match hook {
+            Hook::InetConnRequest | Hook::SocketConnect => &[Scope::Ipv4, Scope::Ipv6],
+            _ => &[Scope::Any],
+        };
+        let covered = hooks
+            .get(hook.name())
+            .is_some_and(|rules| rules.covers(subject, scopes));
+        if covered {
+            errors.push(EbpfguardError::UnreachableRule {
+                location,
+                hook: hook.default_name(),
+                subject: Box::new(subject.clone()),
+                object: HookDefaults::describe(&()),
+                reason: "the policy of the subject in the hook allows or denies everything",
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(EbpfguardError::InvalidPolicies(errors))
+    }
+}
+
+/// Object of a rule, as seen by the validation.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Object {
+    /// Objects in different scopes (IPv4 and IPv6 networks) are stored in
+    /// separate maps and the hook decides about them separately.
+    scope: Scope,
+    /// Whether the object covers everything in its scope.
+    all: bool,
+    name: String,
+    /// Kind of access to the object, in hooks which distinguish them.
+    access: Option<Access>,
+}
+
+impl Object {
+    fn describe(&self) -> String {
+        match self.access {
+            Some(access) => format!("{access} access to {}", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Scope {
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+struct Rule {
+    location: Location,
+    subject: usize,
+    object: Object,
+    allow: bool,
+}
+
+/// Rules of all policies of a single hook.
+struct HookRules<'a> {
+    max_entries: u32,
+    subjects: Vec<&'a PolicySubject>,
+    /// Subjects, path matching modes, modes and locations of the policies.
+    policies: Vec<(usize, Option<PathMatch>, PolicyMode, Location)>,
+    rules: Vec<Rule>,
+}
+
+impl HookRules<'_> {
+    fn check(&self, hook: &'static str, errors: &mut Vec<EbpfguardError>) {
+        let mut actions = BTreeMap::new();
+        let mut contradictions = BTreeSet::new();
+        // Objects differing only in the kind of access share a map entry.
+        let mut entries: BTreeMap<(bool, Scope), BTreeSet<(usize, &str)>> = BTreeMap::new();
+
+        for rule in &self.rules {
+            let key = (rule.subject, &rule.object);
+
+            if *actions.entry(key).or_insert(rule.allow) != rule.allow && contradictions.insert(key)
+            {
+                errors.push(EbpfguardError::ContradictoryPolicy {
+                    location: rule.location.clone(),
+                    hook,
+                    subject: Box::new(self.subject(rule).clone()),
+                    object: rule.object.describe(),
+                });
+            }
+
+            let map = entries.entry((rule.allow, rule.object.scope)).or_default();
+            if map.insert((rule.subject, &rule.object.name))
+                && map.len() == self.max_entries as usize + 1
+            {
+                errors.push(EbpfguardError::TooManyEntries {
+                    location: rule.location.clone(),
+                    hook,
+                    max: self.max_entries,
+                });
+            }
+        }
+    }
+
+    /// Returns `true` if the policy of the subject allows or denies
+    /// everything in all of the given scopes (for all kinds of access), so
+    /// its default action is never applied. Objects in [`Scope::Any`] cover
+    /// every scope.
+    fn covers(&self, subject: &PolicySubject, scopes: &[Scope]) -> bool {
+        scopes.iter().all(|scope| {
+            let access: BTreeSet<_> = self
+                .rules
+                .iter()
+                .filter(|rule| {
+                    self.subject(rule) == subject
+                        && (rule.object.scope == *scope || rule.object.scope == Scope::Any)
+                        && rule.object.all
+                })
+                .map(|rule| rule.object.access)
+                .collect();
+            access.contains(&None) || Access::ALL.iter().all(|a| access.contains(&Some(*a)))
+        })
+    }
+
+    fn subject(&self, rule: &Rule) -> &PolicySubject {
+        self.subjects[rule.subject]
+    }
+}
+
+fn objects<T, F>(allow: &T, deny: &T, f: F) -> Vec<(Object, bool)>
+where
+    F: Fn(&T) -> Vec<Object>,
+{
+    let allow = f(allow).into_iter().map(|object| (object, true));
+    let deny = f(deny).into_iter().map(|object| (object, false));
+    allow.chain(deny).collect()
+}
+
+fn operation<M: PolicyMaps<Object = ()>>(allow: bool) -> Vec<(Object, bool)> {
+    let object = Object {
+        scope: Scope::Any,
+        all: true,
+        name: M::describe(&()),
+        access: None,
+    };
+    vec![(object, allow)]
+}
+
+/// Reports paths which don't exist.
+fn check_paths(objects: &[&Paths], location: &Location, errors: &mut Vec<EbpfguardError>) {
+    for objects in objects {
+        if let Paths::Paths(objects) = objects {
+            for path in objects.iter().filter(|path| !path.exists()) {
+                errors.push(EbpfguardError::PathNotFound {
+                    location: location.clone(),
+                    path: path.to_owned(),
+                });
+            }
+        }
+    }
+}
+
+/// Reports invalid glob patterns, or invalid path prefixes in prefix
+/// policies. Paths of `file_open` policies don't have to exist, they are
+/// enforced once they appear.
+fn check_patterns(objects: &[&Paths], matching: PathMatch, errors: &mut Vec<EbpfguardError>) {
+    for objects in objects {
+        if let Paths::Paths(objects) = objects {
+            errors.extend(objects.iter().filter_map(|path| match matching {
+                PathMatch::Inode => fs::check_pattern(path).err(),
+                PathMatch::Prefix => file_open::prefix(path).err(),
+            }));
+        }
+    }
+}
+
+fn paths(paths: &Paths, all: &str) -> Vec<Object> {
+    match paths {
+        Paths::All => vec![Object {
+            scope: Scope::Any,
+            all: true,
+            name: all.to_owned(),
+            access: None,
+        }],
+        Paths::Paths(paths) => paths
+            .iter()
+            .map(|path| Object {
+                scope: Scope::Any,
+                all: false,
+                name: path.display().to_string(),
+                access: None,
+            })
+            .collect(),
+    }
+}
+
+fn file_paths(paths: &Paths, matching: PathMatch, access: &[Access]) -> Vec<Object> {
+    self::paths(paths, "all paths")
+        .into_iter()
+        .map(|object| match matching {
+            PathMatch::Prefix if !object.all => Object {
+                name: format!("paths under {}", object.name),
+                ..object
+            },
+            _ => object,
+        })
+        .flat_map(|object| {
+            access.iter().map(move |access| Object {
+                access: Some(*access),
+                ..object.clone()
+            })
+        })
+        .collect()
+}
+
+fn unix_paths(paths: &UnixPaths) -> Vec<Object> {
+    match paths {
+        UnixPaths::All => vec![Object {
+            scope: Scope::Any,
+            all: true,
+            name: "all sockets".to_owned(),
+            access: None,
+        }],
+        UnixPaths::Paths(paths) => paths
+            .iter()
+            .map(|path| Object {
+                scope: Scope::Any,
+                all: false,
+                name: format!("socket {path}"),
+                access: None,
+            })
+            .collect(),
+    }
+}
+
+fn sockets(sockets: &Sockets) -> Vec<Object> {
+    sockets
+        .to_ids()
+        .iter()
+        .map(|ids| Object {
+            scope: Scope::Any,
+            all: *sockets == Sockets::All,
+            name: SocketCreate::describe(ids),
+            access: None,
+        })
+        .collect()
+}
+
+/// Returns the objects of a `socket_bind` or `socket_listen` policy. Address
+/// rules which can't be loaded are reported.
+fn port_objects<M: PolicyMaps<Object = BindTarget>>(
+    subject: &PolicySubject,
+    allow: &Ports,
+    deny: &Ports,
+    address_rules: &[AddressRule],
+    location: &Location,
+    errors: &mut Vec<EbpfguardError>,
+) -> Vec<(Object, bool)> {
+    let mut objects = objects(allow, deny, ports);
+    for rule in address_rules {
+        for (ports, allow) in [(&rule.allow, true), (&rule.deny, false)] {
+            match BindTarget::addresses(&rule.addresses, ports) {
+                Ok(targets) => {
+                    objects.extend(targets.iter().map(|target| (bind_target(target), allow)))
+                }
+                Err(EbpfguardError::UnsupportedPortRange { network, ports }) => {
+                    errors.push(EbpfguardError::UnsupportedRule {
+                        location: location.clone(),
+                        hook: M::HOOK,
+                        subject: Box::new(subject.clone()),
+                        object: M::describe(&BindTarget {
+                            network: Some(network),
+                            ports,
+                        }),
+                        reason: "port ranges can be combined only with single addresses",
+                    })
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    objects
+}
+
+fn ports(ports: &Ports) -> Vec<Object> {
+    BindTarget::all_addresses(ports)
+        .iter()
+        .map(bind_target)
+        .collect()
+}
+
+fn bind_target(target: &BindTarget) -> Object {
+    let (scope, all_addresses) = match target.network {
+        None => (Scope::Any, true),
+        Some(IpNet::V4(network)) => (Scope::Ipv4, network.prefix_len() == 0),
+        Some(IpNet::V6(network)) => (Scope::Ipv6, network.prefix_len() == 0),
+    };
+    Object {
+        scope,
+        all: all_addresses && target.ports == PortRange::ALL,
+        name: SocketBind::describe(target),
+        access: None,
+    }
+}
+
+/// Returns the objects of a `socket_connect` or `inet_conn_request` policy.
+/// Port rules which can't be loaded are reported.
+fn connect_objects<M: PolicyMaps<Object = ConnectTarget>>(
+    subject: &PolicySubject,
+    allow: &Addresses,
+    deny: &Addresses,
+    port_rules: &[PortRule],
+    location: &Location,
+    errors: &mut Vec<EbpfguardError>,
+) -> Vec<(Object, bool)> {
+    let mut objects = objects(allow, deny, networks);
+    for rule in port_rules {
+        for (ports, allow) in [(&rule.allow, true), (&rule.deny, false)] {
+            match ConnectTarget::port_rule(rule, ports) {
+                Ok(targets) => {
+                    objects.extend(targets.iter().map(|target| (connect_target(target), allow)))
+                }
+                Err(EbpfguardError::UnsupportedPortRange { network, ports }) => {
+                    errors.push(EbpfguardError::UnsupportedRule {
+                        location: location.clone(),
+                        hook: M::HOOK,
+                        subject: Box::new(subject.clone()),
+                        object: M::describe(&ConnectTarget {
+                            protocol: rule.protocol,
+                            network: Some(network),
+                            ports,
+                        }),
+                        reason: "port ranges can be combined only with single addresses",
+                    })
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    objects
+}
+
+fn networks(addresses: &Addresses) -> Vec<Object> {
+    ConnectTarget::networks(addresses)
+        .iter()
+        .map(connect_target)
+        .collect()
+}
+
+fn connect_target(target: &ConnectTarget) -> Object {
+    let (scope, all_addresses) = match target.network {
+        None => (Scope::Any, true),
+        Some(IpNet::V4(network)) => (Scope::Ipv4, network.prefix_len() == 0),
+        Some(IpNet::V6(network)) => (Scope::Ipv6, network.prefix_len() == 0),
+    };
+    Object {
+        scope,
+        all: target.protocol.is_all() && all_addresses && target.ports == PortRange::ALL,
+        name: SocketConnect::describe(target),
+        access: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::policy::account::Account;
+
+    fn invalid(yaml: &str) -> Vec<EbpfguardError> {
+        let policies = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        match validate(&policies, &[]) {
+            Err(EbpfguardError::InvalidPolicies(errors)) => errors,
+            res => panic!("expected validation errors, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let yaml = "
+- !socket_bind
+  subject: all
+  allow: !ports
+    - 8080
+  deny: all
+- !socket_connect
+  subject: all
+  allow: all
+  deny: !addresses
+    - 10.0.0.1
+- !task_fix_setuid
+  subject: all
+  allow: false
+";
+        let policies = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        validate(&policies, &[]).unwrap();
+    }
+
+    #[test]
+    fn test_validate_missing_path() {
+        let errors = invalid(
+            "
+- !bprm_check_security
+  subject: !binary /nonexistent/ebpfguard
+  allow: all
+  deny: !paths
+    - /nonexistent/secret
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::PathNotFound { path: object, .. },
+                EbpfguardError::PathNotFound { path: subject, .. },
+            ] if object.to_str() == Some("/nonexistent/secret")
+                && subject.to_str() == Some("/nonexistent/ebpfguard")
+        ));
+    }
+
+    #[test]
+    fn test_validate_path_patterns() {
+        let yaml = "
+- !file_open
+  subject: all
+  allow: all
+  deny: !paths
+    - /nonexistent/secret
+    - /home/*/.ssh
+";
+        let policies = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        validate(&policies, &[]).unwrap();
+
+        let errors = invalid(
+            "
+- !file_open
+  subject: all
+  allow: all
+  deny: !paths
+    - /etc/[ssl
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [EbpfguardError::InvalidPathPattern(path)] if path.to_str() == Some("/etc/[ssl")
+        ));
+    }
+
+    #[test]
+    fn test_validate_path_prefixes() {
+        let yaml = "
+- !file_open
+  subject: all
+  allow: all
+  deny: !paths
+    - /nonexistent/secret
+  matching: prefix
+- !file_open
+  subject: all
+  allow: !paths
+    - /nonexistent/secret
+  deny: !paths
+    - /nonexistent
+";
+        let policies = serde_yaml::from_str::<Vec<Policy>>(yaml).unwrap();
+        validate(&policies, &[]).unwrap();
+
+        let errors = invalid(
+            "
+- !file_open
+  subject: all
+  allow: all
+  deny: !paths
+    - var/lib
+    - /var/../etc
+  matching: prefix
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::InvalidPathPrefix { path: relative, .. },
+                EbpfguardError::InvalidPathPrefix { path: parent, .. },
+            ] if relative.to_str() == Some("var/lib") && parent.to_str() == Some("/var/../etc")
+        ));
+    }
+
+    #[test]
+    fn test_validate_mixed_modes() {
+        let errors = invalid(
+            "
+- !file_open
+  subject: all
+  allow: all
+  deny: !paths
+    - /nonexistent
+- !file_open
+  subject: all
+  mode: audit
+  allow: all
+  deny: !paths
+    - /nonexistent/secret
+  matching: prefix
+- !socket_bind
+  subject: all
+  mode: audit
+  allow: all
+  deny: !ports
+    - 22
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::MixedModes { location, first, hook: "file_open", .. },
+            ] if location.index == 1 && first.index == 0
+        ));
+    }
+
+    #[test]
+    fn test_validate_contradiction_and_duplicate() {
+        let errors = invalid(
+            "
+- !socket_bind
+  subject: all
+  allow: all
+  deny: !ports
+    - 22
+- !socket_bind
+  subject: all
+  allow: !ports
+    - 22
+  deny: !ports
+    - 23
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::DuplicateSubject { location, first, .. },
+                EbpfguardError::ContradictoryPolicy { object, .. },
+            ] if location.index == 1 && first.index == 0 && object == "port 22"
+        ));
+    }
+
+    #[test]
+    fn test_validate_defaults() {
+        let errors = invalid(
+            "
+- !default
+  hook: socket_bind
+  subject: all
+  action: deny
+- !default
+  hook: socket_bind
+  subject: all
+  action: allow
+- !socket_bind
+  subject: !binary /
+  allow: !ports
+    - 8080
+  deny: all
+- !default
+  hook: socket_bind
+  subject: !binary /
+  action: allow
+- !socket_connect
+  subject: all
+  allow: !networks
+    - 0.0.0.0/0
+  deny: !addresses
+    - 10.0.0.1
+- !default
+  hook: socket_connect
+  subject: all
+  action: deny
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::DuplicateSubject { location, first, .. },
+                EbpfguardError::ContradictoryPolicy { hook: "socket_bind default", .. },
+                EbpfguardError::UnreachableRule { hook: "socket_bind default", subject, .. },
+            ] if location.index == 1
+                && first.index == 0
+                && **subject == PolicySubject::Binary("/".into())
+        ));
+    }
+
+    #[test]
+    fn test_validate_address_rules() {
+        let errors = invalid(
+            "
+- !socket_bind
+  subject: all
+  allow: all
+  deny: !ports []
+  address_rules:
+    - addresses: !addresses
+        - 0.0.0.0
+      allow: !ports []
+      deny: !ranges
+        - 6379
+        - 8000-8999
+    - addresses: !networks
+        - 10.0.0.0/8
+      allow: !ports []
+      deny: !ranges
+        - 8000-8999
+    - addresses: !addresses
+        - 0.0.0.0
+      allow: !ports
+        - 6379
+      deny: !ports []
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::UnsupportedRule { object: unsupported, .. },
+                EbpfguardError::ContradictoryPolicy { object: contradictory, .. },
+            ] if unsupported == "ports 8000-8999 on 10.0.0.0/8"
+                && contradictory == "port 6379 on 0.0.0.0"
+        ));
+    }
+
+    #[test]
+    fn test_validate_port_rules() {
+        let errors = invalid(
+            "
+- !socket_connect
+  subject: all
+  allow: all
+  deny: !addresses []
+  port_rules:
+    - protocol: tcp
+      addresses: !networks
+        - 10.0.0.0/8
+      allow: !ports []
+      deny: !ranges
+        - <1024
+    - protocol: udp
+      allow: !ports []
+      deny: !ports
+        - 53
+    - protocol: udp
+      allow: !ports
+        - 53
+      deny: !ports []
+- !socket_connect
+  subject: !user root
+  allow: !addresses []
+  deny: !addresses []
+  port_rules:
+    - allow: all
+      deny: !ports []
+- !default
+  hook: socket_connect
+  subject: !user root
+  action: deny
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::UnsupportedRule { object: unsupported, .. },
+                EbpfguardError::ContradictoryPolicy { object: contradictory, .. },
+                EbpfguardError::UnreachableRule { hook: "socket_connect default", .. },
+            ] if unsupported == "tcp ports 0-1023 on 10.0.0.0/8"
+                && contradictory == "udp port 53"
+        ));
+    }
+
+    #[test]
+    fn test_validate_access_rules() {
+        let errors = invalid(
+            "
+- !file_open
+  subject: all
+  allow: !paths
+    - /etc
+  deny: !paths []
+  access_rules:
+    - access:
+        - write
+      allow: !paths []
+      deny: !paths
+        - /etc
+- !file_open
+  subject: !user root
+  allow: !paths []
+  deny: !paths []
+  access_rules:
+    - access:
+        - read
+      allow: all
+      deny: !paths []
+- !default
+  hook: file_open
+  subject: !user root
+  action: deny
+- !file_open
+  subject: !group 0
+  allow: !paths []
+  deny: !paths []
+  access_rules:
+    - access:
+        - read
+        - write
+      allow: all
+      deny: !paths []
+    - access:
+        - exec
+      allow: !paths []
+      deny: all
+- !default
+  hook: file_open
+  subject: !group 0
+  action: deny
+",
+        );
+        assert!(matches!(
+            &errors[..],
+            [
+                EbpfguardError::ContradictoryPolicy { object, .. },
+                EbpfguardError::UnreachableRule { hook: "file_open default", subject, .. },
+            ] if object == "write access to /etc"
+                && **subject == PolicySubject::Group(Account::Id(0))
+        ));
+    }
+}
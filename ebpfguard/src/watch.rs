@@ -0,0 +1,144 @@
+use std::{collections::HashMap, io, path::PathBuf};
+
+use ebpfguard_common::inode::Inode;
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use tokio::io::unix::AsyncFd;
+
+use crate::fs;
+
+/// Size of the buffer for reading inotify events. Events are only drained,
+/// their content doesn't matter.
+const EVENT_BUFFER_SIZE: usize = 4096;
+
+/// Watches directories for entries being created, renamed or deleted.
+///
+/// Directories are identified by their inodes, so a directory which is
+/// removed and created again under the same path gets watched again. The
+/// inotify instance is created when the first directory is watched.
+#[derive(Default)]
+pub(crate) struct PathWatcher {
+    inotify: Option<AsyncFd<Inotify>>,
+    watches: HashMap<Inode, WatchDescriptor>,
+    pending: bool,
+}
+
+impl PathWatcher {
+    /// Replaces the set of watched directories. Directories which can't be
+    /// watched (e.g. because they were removed in the meantime) are skipped,
+    /// a change in their parent directory brings them back.
+    pub(crate) fn watch(&mut self, dirs: Vec<PathBuf>) -> io::Result<()> {
+        let mut dirs = dirs
+            .into_iter()
+            .filter_map(|dir| Some((fs::inode(&dir).ok()?, dir)))
+            .collect::<HashMap<_, _>>();
+
+        if self.inotify.is_none() && !dirs.is_empty() {
+            self.inotify = Some(AsyncFd::new(Inotify::init()?)?);
+        }
+        let Some(inotify) = &self.inotify else {
+            return Ok(());
+        };
+        let mut watches = inotify.get_ref().watches();
+
+        let removed = self
+            .watches
+            .keys()
+            .filter(|inode| !dirs.contains_key(inode))
+            .copied()
+            .collect::<Vec<_>>();
+        for inode in removed {
+            if let Some(wd) = self.watches.remove(&inode) {
+                // The watch is already gone if the directory was removed.
+                let _ = watches.remove(wd);
+            }
+        }
+
+        dirs.retain(|inode, _| !self.watches.contains_key(inode));
+        for (inode, dir) in dirs {
+            let mask = WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::DELETE_SELF
+                | WatchMask::MOVE_SELF
+                | WatchMask::ONLYDIR;
+            match watches.add(&dir, mask) {
+                Ok(wd) => {
+                    self.watches.insert(inode, wd);
+                }
+                Err(e) => log::warn!("failed to watch directory {}: {e}", dir.display()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits until any of the watched directories changes. Changes are
+    /// reported until [`done`](Self::done) is called, so the future can be
+    /// safely cancelled, e.g. in `tokio::select!`. Never completes if there
+    /// are no watched directories.
+    pub(crate) async fn changed(&mut self) -> io::Result<()> {
+        if self.pending {
+            return Ok(());
+        }
+        let Some(inotify) = self.inotify.as_mut() else {
+            return std::future::pending().await;
+        };
+
+        let mut buffer = [0; EVENT_BUFFER_SIZE];
+        loop {
+            let mut guard = inotify.readable_mut().await?;
+            loop {
+                match guard.try_io(|inotify| inotify.get_mut().read_events(&mut buffer)) {
+                    Ok(Ok(_)) => self.pending = true,
+                    Ok(Err(e)) => return Err(e),
+                    // All events were read, readiness is cleared.
+                    Err(_would_block) => break,
+                }
+            }
+            if self.pending {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Marks the reported changes as handled.
+    pub(crate) fn done(&mut self) {
+        self.pending = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs as std_fs, time::Duration};
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_path_watcher() {
+        let dir = std::env::temp_dir().join(format!("ebpfguard-watch-{}", std::process::id()));
+        std_fs::create_dir_all(&dir).unwrap();
+
+        let mut watcher = PathWatcher::default();
+        watcher.watch(vec![dir.clone()]).unwrap();
+        std_fs::write(dir.join("id_rsa"), "").unwrap();
+
+        timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        // Changes are reported until they are handled.
+        timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        watcher.done();
+        assert!(timeout(Duration::from_millis(100), watcher.changed())
+            .await
+            .is_err());
+
+        std_fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use cli_table::{Cell, Style, Table, TableStruct};
+use ebpfguard::{policy::Paths, PolicyManager};
+
+pub(crate) async fn list_bprm_check_security(
+    policy_manager: &mut PolicyManager,
+) -> anyhow::Result<TableStruct> {
+    let mut table = Vec::new();
+
+    let bprm_check_security = policy_manager.manage_bprm_check_security()?;
+
+    for policy in bprm_check_security.list_policies().await? {
+        let allow = match policy.allow {
+            Paths::All => "all".to_owned(),
+            Paths::Paths(paths) => paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        let deny = match policy.deny {
+            Paths::All => "all".to_owned(),
+            Paths::Paths(paths) => paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        table.push(vec![
+            policy.subject.to_string(),
+            allow,
+            deny,
+            policy.mode.to_string(),
+        ]);
+    }
+
+    let table = table.table().title(vec![
+        "subject".cell().bold(true),
+        "allowed executables".cell().bold(true),
+        "denied executables".cell().bold(true),
+        "mode".cell().bold(true),
+    ]);
+
+    Ok(table)
+}
@@ -0,0 +1,28 @@
+use cli_table::{Cell, Style, Table, TableStruct};
+use ebpfguard::PolicyManager;
+
+pub(crate) async fn list_defaults(
+    policy_manager: &mut PolicyManager,
+) -> anyhow::Result<TableStruct> {
+    let mut table = Vec::new();
+
+    let defaults = policy_manager.manage_defaults()?;
+
+    for policy in defaults.list_policies().await? {
+        table.push(vec![
+            policy.hook.to_string(),
+            policy.subject.to_string(),
+            policy.action.to_string(),
+            policy.mode.to_string(),
+        ]);
+    }
+
+    let table = table.table().title(vec![
+        "hook".cell().bold(true),
+        "subject".cell().bold(true),
+        "action".cell().bold(true),
+        "mode".cell().bold(true),
+    ]);
+
+    Ok(table)
+}
@@ -1,5 +1,8 @@
 use cli_table::{Cell, Style, Table, TableStruct};
-use ebpfguard::{policy::Paths, PolicyManager};
+use ebpfguard::{
+    policy::{PathMatch, Paths},
+    PolicyManager,
+};
 
 pub(crate) async fn list_file_open(
     policy_manager: &mut PolicyManager,
@@ -9,30 +12,49 @@ pub(crate) async fn list_file_open(
     let file_open = policy_manager.manage_file_open()?;
 
     for policy in file_open.list_policies().await? {
-        let allow = match policy.allow {
-            Paths::All => "all".to_owned(),
-            Paths::Paths(paths) => paths
-                .iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        };
-        let deny = match policy.deny {
-            Paths::All => "all".to_owned(),
-            Paths::Paths(paths) => paths
-                .iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        };
-        table.push(vec![policy.subject.to_string(), allow, deny]);
+        table.push(vec![
+            policy.subject.to_string(),
+            "all".to_owned(),
+            paths(&policy.allow, policy.matching),
+            paths(&policy.deny, policy.matching),
+            policy.mode.to_string(),
+        ]);
+        for rule in policy.access_rules {
+            table.push(vec![
+                policy.subject.to_string(),
+                rule.access
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                paths(&rule.allow, policy.matching),
+                paths(&rule.deny, policy.matching),
+                policy.mode.to_string(),
+            ]);
+        }
     }
 
     let table = table.table().title(vec![
         "subject".cell().bold(true),
+        "access".cell().bold(true),
         "allowed paths".cell().bold(true),
         "denied paths".cell().bold(true),
+        "mode".cell().bold(true),
     ]);
 
     Ok(table)
 }
+
+fn paths(paths: &Paths, matching: PathMatch) -> String {
+    match paths {
+        Paths::All => "all".to_owned(),
+        Paths::Paths(paths) => paths
+            .iter()
+            .map(|p| match matching {
+                PathMatch::Inode => p.to_string_lossy().to_string(),
+                PathMatch::Prefix => p.join("**").to_string_lossy().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
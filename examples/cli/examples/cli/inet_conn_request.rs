@@ -0,0 +1,51 @@
+use cli_table::{Cell, Style, Table, TableStruct};
+use ebpfguard::{
+    policy::{Addresses, Protocol},
+    PolicyManager,
+};
+
+use crate::socket_connect::{addresses, ports};
+
+pub(crate) async fn list_inet_conn_request(
+    policy_manager: &mut PolicyManager,
+) -> anyhow::Result<TableStruct> {
+    let mut table = Vec::new();
+
+    let inet_conn_request = policy_manager.manage_inet_conn_request()?;
+
+    for policy in inet_conn_request.list_policies().await? {
+        table.push(vec![
+            policy.subject.to_string(),
+            "peers".to_owned(),
+            addresses(&policy.allow),
+            addresses(&policy.deny),
+            policy.mode.to_string(),
+        ]);
+        for rule in policy.port_rules {
+            let protocol = match rule.protocol {
+                Protocol::All => String::new(),
+                protocol => format!("{protocol} "),
+            };
+            let addresses = match rule.addresses {
+                Addresses::All => String::new(),
+                ref addresses => format!(" from\n{}", self::addresses(addresses)),
+            };
+            table.push(vec![
+                policy.subject.to_string(),
+                format!("{protocol}local ports{addresses}"),
+                ports(&rule.allow),
+                ports(&rule.deny),
+                policy.mode.to_string(),
+            ]);
+        }
+    }
+
+    let table = table.table().title(vec![
+        "Subject".cell().bold(true),
+        "Rule".cell().bold(true),
+        "Allowed".cell().bold(true),
+        "Denied".cell().bold(true),
+        "Mode".cell().bold(true),
+    ]);
+    Ok(table)
+}
@@ -1,19 +1,34 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::UNIX_EPOCH};
 
 use clap::{Parser, Subcommand};
 use cli_table::{print_stdout, Cell, Style, Table};
 
+mod bprm_check_security;
+mod defaults;
 mod file_open;
+mod inet_conn_request;
 mod sb_mount;
 mod socket_bind;
 mod socket_connect;
+mod socket_connect_unix;
+mod socket_create;
+mod socket_listen;
 mod task_fix_setuid;
 
-use ebpfguard::{policy::reader, PolicyManager};
+use bprm_check_security::list_bprm_check_security;
+use defaults::list_defaults;
+use ebpfguard::{
+    policy::{reader, validate::validate, Policy, PolicySubject},
+    PolicyManager,
+};
 use file_open::list_file_open;
+use inet_conn_request::list_inet_conn_request;
 use sb_mount::list_sb_mount;
 use socket_bind::list_socket_bind;
 use socket_connect::list_socket_connect;
+use socket_connect_unix::list_socket_connect_unix;
+use socket_create::list_socket_create;
+use socket_listen::list_socket_listen;
 use task_fix_setuid::list_task_fix_setuid;
 
 #[derive(Parser)]
@@ -42,8 +57,29 @@ enum SubPolicy {
         #[clap(long)]
         r#path: PathBuf,
     },
+    /// Replace loaded policies with the ones from the given file.
+    Apply {
+        #[clap(long)]
+        r#path: PathBuf,
+    },
     /// List policies.
-    List,
+    List {
+        /// Directories to scan for paths which are missing in the inode
+        /// index.
+        #[clap(long)]
+        scan: Vec<PathBuf>,
+    },
+    /// List paths of the inodes used in policies.
+    Inodes,
+    /// Remove policies of the given subject.
+    Remove {
+        /// Binary which is the subject of the policies. If empty, policies
+        /// for all processes (wildcard subject) are removed.
+        #[clap(long)]
+        subject: Option<PathBuf>,
+    },
+    /// Remove all policies.
+    Clear,
 }
 
 #[tokio::main]
@@ -60,9 +96,26 @@ async fn main() -> anyhow::Result<()> {
                 SubPolicy::Add { r#path } => {
                     add_policies(&mut policy_manager, path).await?;
                 }
-                SubPolicy::List => {
+                SubPolicy::Apply { r#path } => {
+                    apply_policies(&mut policy_manager, path).await?;
+                }
+                SubPolicy::List { scan } => {
+                    if !scan.is_empty() {
+                        let all = policy_manager.manage_all()?;
+                        all.refresh_paths(&scan).await?;
+                    }
                     list_policies(&mut policy_manager).await?;
                 }
+                SubPolicy::Inodes => {
+                    list_inodes(&policy_manager).await?;
+                }
+                SubPolicy::Remove { subject } => {
+                    remove_policies(&mut policy_manager, subject).await?;
+                }
+                SubPolicy::Clear => {
+                    let mut all = policy_manager.manage_all()?;
+                    all.clear()?;
+                }
             }
         }
     }
@@ -70,25 +123,120 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Reads and validates policies from the given YAML file.
+fn read_policies(r#path: PathBuf) -> anyhow::Result<Vec<Policy>> {
+    let (policies, locations) = reader::read_policies_with_locations(r#path)?;
+    validate(&policies, &locations)?;
+    Ok(policies)
+}
+
 async fn add_policies(policy_manager: &mut PolicyManager, r#path: PathBuf) -> anyhow::Result<()> {
     let mut all = policy_manager.manage_all()?;
-    let policies = reader::read_policies(r#path)?;
+    let policies = read_policies(r#path)?;
     for policy in policies {
         all.add_policy(policy).await?;
     }
     Ok(())
 }
 
+async fn apply_policies(policy_manager: &mut PolicyManager, r#path: PathBuf) -> anyhow::Result<()> {
+    let mut all = policy_manager.manage_all()?;
+    let policies = read_policies(r#path)?;
+    let report = all.reconcile(policies).await?;
+
+    if report.is_empty() {
+        println!("Policies are up to date");
+        return Ok(());
+    }
+
+    let table = report
+        .changes
+        .iter()
+        .map(|change| {
+            vec![
+                change.hook.to_owned(),
+                change.subject.to_string(),
+                change.kind.to_string(),
+            ]
+        })
+        .table()
+        .title(vec![
+            "hook".cell().bold(true),
+            "subject".cell().bold(true),
+            "change".cell().bold(true),
+        ]);
+
+    print_stdout(table)?;
+
+    Ok(())
+}
+
+async fn list_inodes(policy_manager: &PolicyManager) -> anyhow::Result<()> {
+    let table = policy_manager
+        .inodes()
+        .await?
+        .into_iter()
+        .map(|(inode, entry)| {
+            let added = entry
+                .added
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            vec![
+                entry.path.display().to_string(),
+                inode.to_string(),
+                entry.policy.unwrap_or_default(),
+                added.to_string(),
+            ]
+        })
+        .table()
+        .title(vec![
+            "path".cell().bold(true),
+            "inode".cell().bold(true),
+            "policy".cell().bold(true),
+            "added".cell().bold(true),
+        ]);
+
+    print_stdout(table)?;
+
+    Ok(())
+}
+
+async fn remove_policies(
+    policy_manager: &mut PolicyManager,
+    subject: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut all = policy_manager.manage_all()?;
+    let subject = match subject {
+        Some(subject) => PolicySubject::Binary(subject),
+        None => PolicySubject::All,
+    };
+    all.remove_policy(&subject).await?;
+    Ok(())
+}
+
 async fn list_policies(policy_manager: &mut PolicyManager) -> anyhow::Result<()> {
+    let defaults = list_defaults(policy_manager).await?;
+    let bprm_check_security = list_bprm_check_security(policy_manager).await?;
     let file_open = list_file_open(policy_manager).await?;
+    let inet_conn_request = list_inet_conn_request(policy_manager).await?;
     let setuid = list_task_fix_setuid(policy_manager).await?;
     let sb_mount = list_sb_mount(policy_manager).await?;
     let socket_bind = list_socket_bind(policy_manager).await?;
     let socket_connect = list_socket_connect(policy_manager).await?;
+    let socket_connect_unix = list_socket_connect_unix(policy_manager).await?;
+    let socket_create = list_socket_create(policy_manager).await?;
+    let socket_listen = list_socket_listen(policy_manager).await?;
 
     let table = vec![
+        vec!["default".cell()],
+        vec![defaults.display()?.cell()],
+        vec!["bprm_check_security".cell()],
+        vec![bprm_check_security.display()?.cell()],
         vec!["file_open".cell()],
         vec![file_open.display()?.cell()],
+        vec!["inet_conn_request".cell()],
+        vec![inet_conn_request.display()?.cell()],
         vec!["setuid".cell()],
         vec![setuid.display()?.cell()],
         vec!["sb_mount".cell()],
@@ -97,6 +245,12 @@ async fn list_policies(policy_manager: &mut PolicyManager) -> anyhow::Result<()>
         vec![socket_bind.display()?.cell()],
         vec!["socket_connect".cell()],
         vec![socket_connect.display()?.cell()],
+        vec!["socket_connect_unix".cell()],
+        vec![socket_connect_unix.display()?.cell()],
+        vec!["socket_create".cell()],
+        vec![socket_create.display()?.cell()],
+        vec!["socket_listen".cell()],
+        vec![socket_listen.display()?.cell()],
     ]
     .table()
     .title(vec!["Policy".cell().bold(true)]);
@@ -1,5 +1,8 @@
 use cli_table::{Cell, Style, Table, TableStruct};
-use ebpfguard::{policy::Ports, PolicyManager};
+use ebpfguard::{
+    policy::{Addresses, Ports},
+    PolicyManager,
+};
 
 pub(crate) async fn list_socket_bind(
     policy_manager: &mut PolicyManager,
@@ -9,30 +12,63 @@ pub(crate) async fn list_socket_bind(
     let socket_bind = policy_manager.manage_socket_bind()?;
 
     for policy in socket_bind.list_policies().await? {
-        let allow = match policy.allow {
-            Ports::All => "all".to_owned(),
-            Ports::Ports(ports) => ports
-                .iter()
-                .map(|p| p.to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        };
-        let deny = match policy.deny {
-            Ports::All => "all".to_owned(),
-            Ports::Ports(ports) => ports
-                .iter()
-                .map(|p| p.to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        };
-        table.push(vec![policy.subject.to_string(), allow, deny]);
+        table.push(vec![
+            policy.subject.to_string(),
+            "all".to_owned(),
+            ports(&policy.allow),
+            ports(&policy.deny),
+            policy.mode.to_string(),
+        ]);
+        for rule in policy.address_rules {
+            table.push(vec![
+                policy.subject.to_string(),
+                addresses(&rule.addresses),
+                ports(&rule.allow),
+                ports(&rule.deny),
+                policy.mode.to_string(),
+            ]);
+        }
     }
 
     let table = table.table().title(vec![
         "subject".cell().bold(true),
+        "addresses".cell().bold(true),
         "allowed ports".cell().bold(true),
         "denied ports".cell().bold(true),
+        "mode".cell().bold(true),
     ]);
 
     Ok(table)
 }
+
+pub(crate) fn ports(ports: &Ports) -> String {
+    match ports {
+        Ports::All => "all".to_owned(),
+        Ports::Ports(ports) => ports
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Ports::Ranges(ranges) => ranges
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+pub(crate) fn addresses(addresses: &Addresses) -> String {
+    match addresses {
+        Addresses::All => "all".to_owned(),
+        Addresses::Addresses(addresses) => addresses
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Addresses::Networks(networks) => networks
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
@@ -1,5 +1,8 @@
 use cli_table::{Cell, Style, Table, TableStruct};
-use ebpfguard::{policy::Addresses, PolicyManager};
+use ebpfguard::{
+    policy::{Addresses, Ports, Protocol},
+    PolicyManager,
+};
 
 pub(crate) async fn list_socket_connect(
     policy_manager: &mut PolicyManager,
@@ -9,29 +12,70 @@ pub(crate) async fn list_socket_connect(
     let socket_connect = policy_manager.manage_socket_connect()?;
 
     for policy in socket_connect.list_policies().await? {
-        let allow = match policy.allow {
-            Addresses::All => "all".to_owned(),
-            Addresses::Addresses(addresses) => addresses
-                .iter()
-                .map(|a| a.to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        };
-        let deny = match policy.deny {
-            Addresses::All => "all".to_owned(),
-            Addresses::Addresses(addresses) => addresses
-                .iter()
-                .map(|a| a.to_string())
-                .collect::<Vec<_>>()
-                .join("\n"),
-        };
-        table.push(vec![policy.subject.to_string(), allow, deny]);
+        table.push(vec![
+            policy.subject.to_string(),
+            "addresses".to_owned(),
+            addresses(&policy.allow),
+            addresses(&policy.deny),
+            policy.mode.to_string(),
+        ]);
+        for rule in policy.port_rules {
+            let protocol = match rule.protocol {
+                Protocol::All => String::new(),
+                protocol => format!("{protocol} "),
+            };
+            let addresses = match rule.addresses {
+                Addresses::All => String::new(),
+                ref addresses => format!(" on\n{}", self::addresses(addresses)),
+            };
+            table.push(vec![
+                policy.subject.to_string(),
+                format!("{protocol}ports{addresses}"),
+                ports(&rule.allow),
+                ports(&rule.deny),
+                policy.mode.to_string(),
+            ]);
+        }
     }
 
     let table = table.table().title(vec![
         "Subject".cell().bold(true),
+        "Rule".cell().bold(true),
         "Allowed".cell().bold(true),
         "Denied".cell().bold(true),
+        "Mode".cell().bold(true),
     ]);
     Ok(table)
 }
+
+pub(crate) fn ports(ports: &Ports) -> String {
+    match ports {
+        Ports::All => "all".to_owned(),
+        Ports::Ports(ports) => ports
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Ports::Ranges(ranges) => ranges
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+pub(crate) fn addresses(addresses: &Addresses) -> String {
+    match addresses {
+        Addresses::All => "all".to_owned(),
+        Addresses::Addresses(addresses) => addresses
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Addresses::Networks(networks) => networks
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
@@ -0,0 +1,39 @@
+use cli_table::{Cell, Style, Table, TableStruct};
+use ebpfguard::{policy::UnixPaths, PolicyManager};
+
+pub(crate) async fn list_socket_connect_unix(
+    policy_manager: &mut PolicyManager,
+) -> anyhow::Result<TableStruct> {
+    let mut table = Vec::new();
+
+    let socket_connect_unix = policy_manager.manage_socket_connect_unix()?;
+
+    for policy in socket_connect_unix.list_policies().await? {
+        table.push(vec![
+            policy.subject.to_string(),
+            paths(&policy.allow),
+            paths(&policy.deny),
+            policy.mode.to_string(),
+        ]);
+    }
+
+    let table = table.table().title(vec![
+        "subject".cell().bold(true),
+        "allowed sockets".cell().bold(true),
+        "denied sockets".cell().bold(true),
+        "mode".cell().bold(true),
+    ]);
+
+    Ok(table)
+}
+
+fn paths(paths: &UnixPaths) -> String {
+    match paths {
+        UnixPaths::All => "all".to_owned(),
+        UnixPaths::Paths(paths) => paths
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
@@ -0,0 +1,39 @@
+use cli_table::{Cell, Style, Table, TableStruct};
+use ebpfguard::{policy::Sockets, PolicyManager};
+
+pub(crate) async fn list_socket_create(
+    policy_manager: &mut PolicyManager,
+) -> anyhow::Result<TableStruct> {
+    let mut table = Vec::new();
+
+    let socket_create = policy_manager.manage_socket_create()?;
+
+    for policy in socket_create.list_policies().await? {
+        table.push(vec![
+            policy.subject.to_string(),
+            sockets(&policy.allow),
+            sockets(&policy.deny),
+            policy.mode.to_string(),
+        ]);
+    }
+
+    let table = table.table().title(vec![
+        "subject".cell().bold(true),
+        "allowed sockets".cell().bold(true),
+        "denied sockets".cell().bold(true),
+        "mode".cell().bold(true),
+    ]);
+
+    Ok(table)
+}
+
+fn sockets(sockets: &Sockets) -> String {
+    match sockets {
+        Sockets::All => "all".to_owned(),
+        Sockets::Sockets(kinds) => kinds
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
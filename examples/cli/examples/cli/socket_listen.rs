@@ -0,0 +1,41 @@
+use cli_table::{Cell, Style, Table, TableStruct};
+use ebpfguard::PolicyManager;
+
+use crate::socket_bind::{addresses, ports};
+
+pub(crate) async fn list_socket_listen(
+    policy_manager: &mut PolicyManager,
+) -> anyhow::Result<TableStruct> {
+    let mut table = Vec::new();
+
+    let socket_listen = policy_manager.manage_socket_listen()?;
+
+    for policy in socket_listen.list_policies().await? {
+        table.push(vec![
+            policy.subject.to_string(),
+            "all".to_owned(),
+            ports(&policy.allow),
+            ports(&policy.deny),
+            policy.mode.to_string(),
+        ]);
+        for rule in policy.address_rules {
+            table.push(vec![
+                policy.subject.to_string(),
+                addresses(&rule.addresses),
+                ports(&rule.allow),
+                ports(&rule.deny),
+                policy.mode.to_string(),
+            ]);
+        }
+    }
+
+    let table = table.table().title(vec![
+        "subject".cell().bold(true),
+        "addresses".cell().bold(true),
+        "allowed ports".cell().bold(true),
+        "denied ports".cell().bold(true),
+        "mode".cell().bold(true),
+    ]);
+
+    Ok(table)
+}
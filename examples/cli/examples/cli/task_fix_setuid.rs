@@ -9,12 +9,17 @@ pub(crate) async fn list_task_fix_setuid(
     let task_fix_setuid = policy_manager.manage_task_fix_setuid()?;
 
     for policy in task_fix_setuid.list_policies().await? {
-        table.push(vec![policy.subject.to_string(), policy.allow.to_string()]);
+        table.push(vec![
+            policy.subject.to_string(),
+            policy.allow.to_string(),
+            policy.mode.to_string(),
+        ]);
     }
 
     let table = table.table().title(vec![
         "action".cell().bold(true),
         "subject".cell().bold(true),
+        "mode".cell().bold(true),
     ]);
 
     Ok(table)
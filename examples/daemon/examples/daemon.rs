@@ -1,9 +1,17 @@
 use std::fs::create_dir_all;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use clap::Parser;
-use ebpfguard::PolicyManager;
-use log::info;
+use ebpfguard::{
+    hooks::{
+        bprm_check_security::BprmCheckSecuritySettings, file_open::FileOpenSettings,
+        inet_conn_request::InetConnRequestSettings,
+    },
+    policy::{reader::read_policies, Policy},
+    PolicyManager,
+};
+use log::{info, warn};
 use tokio::signal;
 
 #[derive(Debug, Parser)]
@@ -14,6 +22,24 @@ struct Opt {
     bpffs_dir: PathBuf,
     #[clap(long)]
     policy: Vec<PathBuf>,
+    /// Number of parent directories checked for file_open policies.
+    #[clap(long)]
+    file_open_depth: Option<u32>,
+    /// Allow (and alert) opening files which are nested deeper than the
+    /// checked directories, unless denied for all paths, instead of denying.
+    #[clap(long)]
+    file_open_allow_truncated: bool,
+    /// Number of parent directories checked for bprm_check_security policies.
+    #[clap(long)]
+    bprm_check_security_depth: Option<u32>,
+    /// Allow (and alert) executing binaries which are nested deeper than the
+    /// checked directories, unless denied for all paths, instead of denying.
+    #[clap(long)]
+    bprm_check_security_allow_truncated: bool,
+    /// Deny, instead of alerting, connections to sockets whose listening
+    /// process is not known when inet_conn_request policies have binaries.
+    #[clap(long)]
+    inet_conn_request_deny_unknown_listeners: bool,
 }
 
 #[tokio::main]
@@ -28,41 +54,123 @@ async fn main() -> Result<(), anyhow::Error> {
     let mut policy_manager = PolicyManager::new(bpf_path)?;
 
     let mut bprm_check_security = policy_manager.attach_bprm_check_security()?;
+    bprm_check_security.set_settings(BprmCheckSecuritySettings {
+        max_depth: opt
+            .bprm_check_security_depth
+            .unwrap_or(BprmCheckSecuritySettings::default().max_depth),
+        deny_truncated: !opt.bprm_check_security_allow_truncated,
+    })?;
     let mut file_open = policy_manager.attach_file_open()?;
+    file_open.set_settings(FileOpenSettings {
+        max_depth: opt
+            .file_open_depth
+            .unwrap_or(FileOpenSettings::default().max_depth),
+        deny_truncated: !opt.file_open_allow_truncated,
+    })?;
     let mut task_fix_setuid = policy_manager.attach_task_fix_setuid()?;
     let mut socket_bind = policy_manager.attach_socket_bind()?;
     let mut socket_connect = policy_manager.attach_socket_connect()?;
+    let mut socket_connect_unix = policy_manager.attach_socket_connect_unix()?;
+    let mut socket_listen = policy_manager.attach_socket_listen()?;
+    let mut inet_conn_request = policy_manager.attach_inet_conn_request()?;
+    inet_conn_request.set_settings(InetConnRequestSettings {
+        deny_unknown_listeners: opt.inet_conn_request_deny_unknown_listeners,
+    })?;
+    let mut socket_create = policy_manager.attach_socket_create()?;
+
+    for path in &opt.policy {
+        for policy in read_policies(path)? {
+            match policy {
+                Policy::BprmCheckSecurity(policy) => bprm_check_security.add_policy(policy).await?,
+                Policy::FileOpen(policy) => file_open.add_policy(policy).await?,
+                Policy::TaskFixSetuid(policy) => task_fix_setuid.add_policy(policy).await?,
+                Policy::SocketBind(policy) => socket_bind.add_policy(policy).await?,
+                Policy::SocketConnect(policy) => socket_connect.add_policy(policy).await?,
+                Policy::SocketConnectUnix(policy) => socket_connect_unix.add_policy(policy).await?,
+                Policy::SocketListen(policy) => socket_listen.add_policy(policy).await?,
+                Policy::InetConnRequest(policy) => inet_conn_request.add_policy(policy).await?,
+                Policy::SocketCreate(policy) => socket_create.add_policy(policy).await?,
+                policy => warn!("skipping {policy:?}, its hook is not attached"),
+            }
+        }
+    }
 
     let mut rx_bprm_check_security = bprm_check_security.alerts().await?;
     let mut rx_file_open = file_open.alerts().await?;
     let mut rx_task_fix_setuid = task_fix_setuid.alerts().await?;
     let mut rx_socket_bind = socket_bind.alerts().await?;
     let mut rx_socket_connect = socket_connect.alerts().await?;
+    let mut rx_socket_connect_unix = socket_connect_unix.alerts().await?;
+    let mut rx_socket_listen = socket_listen.alerts().await?;
+    let mut rx_inet_conn_request = inet_conn_request.alerts().await?;
+    let mut rx_socket_create = socket_create.alerts().await?;
 
     info!("Waiting for Ctrl-C...");
 
     loop {
         tokio::select! {
             Some(alert) = rx_bprm_check_security.recv() => {
-                info!("bprm_check_security: {}", alert.pid);
+                info!("bprm_check_security: pid={} path={}", alert.pid, alert.path.display());
             }
             Some(alert) = rx_file_open.recv() => {
-                info!("file_open: {}", alert.pid);
+                if alert.truncated {
+                    info!("file_open: {} (path too deep to check)", alert.pid);
+                } else {
+                    info!("file_open: {}", alert.pid);
+                }
             }
             Some(alert) = rx_task_fix_setuid.recv() => {
                 info!("task_fix_setuid: pid={} binprm_inode={}", alert.pid, alert.subject);
             }
             Some(alert) = rx_socket_bind.recv() => {
-                info!("socket_bind: pid={}", alert.pid);
+                info!(
+                    "socket_bind: pid={} addr={}",
+                    alert.pid,
+                    SocketAddr::new(alert.addr, alert.port)
+                );
             }
             Some(alert) = rx_socket_connect.recv() => {
                 info!(
-                    "socket_connect: pid={} binprm_inode={} addr={}",
+                    "socket_connect: pid={} binprm_inode={} addr={} protocol={}",
                     alert.pid,
                     alert.subject,
-                    alert.addr
+                    SocketAddr::new(alert.addr, alert.port),
+                    alert.protocol
+                );
+            }
+            Some(alert) = rx_socket_connect_unix.recv() => {
+                info!("socket_connect_unix: pid={} path={}", alert.pid, alert.path);
+            }
+            Some(alert) = rx_socket_listen.recv() => {
+                info!(
+                    "socket_listen: pid={} addr={}",
+                    alert.pid,
+                    SocketAddr::new(alert.addr, alert.port)
                 );
             }
+            Some(alert) = rx_inet_conn_request.recv() => {
+                info!(
+                    "inet_conn_request: pid={} peer={} port={}",
+                    alert.pid,
+                    alert.addr,
+                    alert.port
+                );
+            }
+            Some(alert) = rx_socket_create.recv() => {
+                info!(
+                    "socket_create: pid={} family={} type={} protocol={}",
+                    alert.pid,
+                    alert.family,
+                    alert.socket_type,
+                    alert.protocol
+                );
+            }
+            res = file_open.update_paths() => {
+                let updated = res?;
+                if updated > 0 {
+                    info!("file_open: updated paths of {updated} subjects");
+                }
+            }
             _ = signal::ctrl_c() => {
                 break;
             }
@@ -3,7 +3,7 @@ use std::{fs, path::PathBuf};
 use anyhow::Context;
 use clap::Parser;
 use ebpfguard::{
-    policy::{PolicySubject, Ports, SocketBind},
+    policy::{PolicyMode, PolicySubject, Ports, SocketListen},
     PolicyManager,
 };
 use log::info;
@@ -12,7 +12,7 @@ use log::info;
 struct Opt {
     #[clap(long, default_value = "/sys/fs/bpf")]
     bpffs_path: PathBuf,
-    #[clap(long, default_value = "demo_socket_connect")]
+    #[clap(long, default_value = "demo_socket_listen")]
     bpffs_dir: PathBuf,
     #[clap(long)]
     deny: Vec<u16>,
@@ -34,22 +34,24 @@ async fn main() -> anyhow::Result<()> {
     let mut policy_manager =
         PolicyManager::new(&bpf_path).context("kernel verifier rejected eBPF hooks object file")?;
 
-    let mut socket_bind = policy_manager
-        .attach_socket_bind()
-        .context("couldn't attach socket_bind hook")?;
+    let mut socket_listen = policy_manager
+        .attach_socket_listen()
+        .context("couldn't attach socket_listen hook")?;
 
-    let mut rx = socket_bind
+    let mut rx = socket_listen
         .alerts()
         .await
-        .context("couldn't get alerts channel for bind events")?;
+        .context("couldn't get alerts channel for listen events")?;
 
-    let policy = SocketBind {
+    let policy = SocketListen {
         subject: PolicySubject::All,
+        mode: PolicyMode::Enforce,
         allow: Ports::All,
         deny: Ports::Ports(opt.deny.clone()),
+        address_rules: Vec::new(),
     };
 
-    socket_bind
+    socket_listen
         .add_policy(policy)
         .await
         .context("failed to add policy")?;
@@ -62,7 +64,7 @@ async fn main() -> anyhow::Result<()> {
     for i in 0..4 {
         if let Some(alert) = rx.recv().await {
             info!(
-                "socket_bind: pid={} subject={} port={}, count: {}",
+                "socket_listen: pid={} subject={} port={}, count: {}",
                 alert.pid, alert.subject, alert.port, i
             );
         }
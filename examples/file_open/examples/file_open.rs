@@ -5,7 +5,7 @@ use std::{
 
 use clap::Parser;
 use ebpfguard::{
-    policy::{FileOpen, Paths, PolicySubject},
+    policy::{FileOpen, PathMatch, Paths, PolicyMode, PolicySubject},
     PolicyManager,
 };
 use log::info;
@@ -56,8 +56,11 @@ async fn main() -> anyhow::Result<()> {
     // Define a policy which blocks access to a provided path.
     let policy = FileOpen {
         subject,
+        mode: PolicyMode::Enforce,
         allow: Paths::All,
         deny: Paths::Paths(vec![opt.path_to_deny]),
+        matching: PathMatch::Inode,
+        access_rules: vec![],
     };
 
     // Add the policy to the policy manager.
@@ -69,7 +72,7 @@ async fn main() -> anyhow::Result<()> {
     loop {
         tokio::select! {
             Some(alert) = rx.recv() => {
-                info!("file_open: pid={} subject={} path={}", alert.pid, alert.subject, alert.path.display());
+                info!("file_open: pid={} subject={} path={} access={:?} truncated={}", alert.pid, alert.subject, alert.path.display(), alert.access, alert.truncated);
             }
             _ = tokio::signal::ctrl_c() => {
                 break;
@@ -5,7 +5,7 @@ use std::{
 
 use clap::Parser;
 use ebpfguard::{
-    policy::{PolicySubject, SbMount, SbRemount, SbUmount},
+    policy::{PolicyMode, PolicySubject, SbMount, SbRemount, SbUmount},
     PolicyManager,
 };
 use log::info;
@@ -51,18 +51,21 @@ async fn main() -> anyhow::Result<()> {
     sb_mount
         .add_policy(SbMount {
             subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
             allow: false,
         })
         .await?;
     sb_remount
         .add_policy(SbRemount {
             subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
             allow: false,
         })
         .await?;
     sb_umount
         .add_policy(SbUmount {
             subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
             allow: false,
         })
         .await?;
@@ -70,18 +73,21 @@ async fn main() -> anyhow::Result<()> {
         sb_mount
             .add_policy(SbMount {
                 subject: PolicySubject::Binary(subject.clone()),
+                mode: PolicyMode::Enforce,
                 allow: true,
             })
             .await?;
         sb_remount
             .add_policy(SbRemount {
                 subject: PolicySubject::Binary(subject.clone()),
+                mode: PolicyMode::Enforce,
                 allow: true,
             })
             .await?;
         sb_umount
             .add_policy(SbUmount {
                 subject: PolicySubject::Binary(subject),
+                mode: PolicyMode::Enforce,
                 allow: true,
             })
             .await?;
@@ -1,6 +1,6 @@
 use anyhow::Context;
 use ebpfguard::{
-    policy::{PolicySubject, SbMount},
+    policy::{PolicyMode, PolicySubject, SbMount},
     PolicyManager,
 };
 use log::info;
@@ -34,6 +34,7 @@ async fn main() -> anyhow::Result<()> {
     sb_mount
         .add_policy(SbMount {
             subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
             allow: false,
         })
         .await?;
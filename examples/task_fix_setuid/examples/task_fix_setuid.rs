@@ -5,7 +5,7 @@ use std::{
 
 use clap::Parser;
 use ebpfguard::{
-    policy::{PolicySubject, TaskFixSetuid},
+    policy::{PolicyMode, PolicySubject, TaskFixSetuid},
     PolicyManager,
 };
 use log::info;
@@ -46,12 +46,14 @@ async fn main() -> anyhow::Result<()> {
     // specified subject, if defined).
     let wildcard_deny_policy = TaskFixSetuid {
         subject: PolicySubject::All,
+        mode: PolicyMode::Enforce,
         allow: false,
     };
     task_fix_setuid.add_policy(wildcard_deny_policy).await?;
     if let Some(subject) = opt.allow {
         let subject_allow_policy = TaskFixSetuid {
             subject: PolicySubject::Binary(subject),
+            mode: PolicyMode::Enforce,
             allow: true,
         };
         task_fix_setuid.add_policy(subject_allow_policy).await?;
@@ -1,10 +1,25 @@
-use std::{net::IpAddr, os::unix::fs::MetadataExt, path::PathBuf, time::Duration};
+use std::{
+    net::{IpAddr, Ipv6Addr},
+    os::unix::net::UnixDatagram,
+    path::PathBuf,
+    time::Duration,
+};
 
 use ebpfguard::{
-    policy::{Addresses, PolicySubject, SocketConnect},
+    error::EbpfguardError,
+    hooks::file_open::FileOpenSettings,
+    policy::{
+        AddressRule, Addresses, BprmCheckSecurity, FileOpen, InetConnRequest, PathMatch, Paths,
+        Policy, PolicyMode, PolicySubject, PortRule, Ports, Protocol, SocketBind, SocketConnect,
+        SocketConnectUnix, SocketCreate, SocketFamily, SocketKind, SocketListen, SocketType,
+        Sockets, UnixPath, UnixPaths,
+    },
     PolicyManager,
 };
-use tokio::{net::TcpListener, sync::oneshot};
+use tokio::{
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+    sync::oneshot,
+};
 
 #[tokio::test]
 async fn test_socket_connect_deny_all() {
@@ -21,8 +36,10 @@ async fn test_socket_connect_deny_all() {
         socket_connect
             .add_policy(SocketConnect {
                 subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
                 allow: Addresses::All,
                 deny: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 3])]),
+                port_rules: vec![],
             })
             .await
             .unwrap();
@@ -66,6 +83,89 @@ async fn test_socket_connect_deny_all() {
         .expect("timeout elapsed");
 }
 
+#[tokio::test]
+async fn test_socket_connect_deny_port() {
+    let (tx, rx) = oneshot::channel();
+
+    let handle = tokio::spawn(async move {
+        let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+        let mut socket_connect = mgr.attach_socket_connect().unwrap();
+
+        let mut rx = socket_connect.alerts().await.unwrap();
+
+        println!("registering deny policy");
+        socket_connect
+            .add_policy(SocketConnect {
+                subject: PolicySubject::All,
+                mode: PolicyMode::Enforce,
+                allow: Addresses::All,
+                deny: Addresses::Addresses(vec![]),
+                port_rules: vec![PortRule {
+                    protocol: Protocol::Tcp,
+                    addresses: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 5])]),
+                    allow: Ports::Ports(vec![]),
+                    deny: Ports::Ports(vec![8081]),
+                }],
+            })
+            .await
+            .unwrap();
+
+        tx.send(()).unwrap();
+        println!("listening for alarms");
+
+        while let Some(msg) = rx.recv().await {
+            println!("alert found: {:?}", msg);
+            if msg.addr == IpAddr::from([127, 1, 2, 5]) {
+                assert_eq!(msg.port, 8081);
+                break;
+            }
+        }
+    });
+
+    let _ = rx.await;
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.1.2.5:8080").await.unwrap();
+        println!("listener started");
+
+        loop {
+            let (_, addr) = listener.accept().await.unwrap();
+            println!("somebody connected: {:?}", addr);
+        }
+    });
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.1.2.5:8081").await.unwrap();
+        println!("listener started");
+
+        loop {
+            let (_, addr) = listener.accept().await.unwrap();
+            panic!("somebody connected: {:?}", addr);
+        }
+    });
+
+    let cmd = tokio::process::Command::new("/usr/bin/nc")
+        .args(["-Nd", "127.1.2.5", "8080"])
+        .output()
+        .await
+        .expect("unexpected execution failure");
+
+    assert!(cmd.status.success(), "different port should pass");
+
+    let cmd = tokio::process::Command::new("/usr/bin/nc")
+        .args(["-Nd", "127.1.2.5", "8081"])
+        .output()
+        .await
+        .expect("unexpected execution failure");
+
+    assert!(!cmd.status.success(), "denied port should fail");
+
+    tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("timeout elapsed")
+        .expect("task panicked");
+}
+
 #[ignore = "known failure #58"]
 #[tokio::test]
 async fn test_socket_connect_deny_one() {
@@ -74,10 +174,6 @@ async fn test_socket_connect_deny_one() {
         .await
         .expect("failed to make nc copy");
 
-    let metadata = nc2.metadata().unwrap();
-    let inode = metadata.ino();
-    println!("nc2 inode: {}", inode);
-
     let (tx, rx) = oneshot::channel();
 
     let handle = tokio::spawn(async move {
@@ -91,8 +187,10 @@ async fn test_socket_connect_deny_one() {
         socket_connect
             .add_policy(SocketConnect {
                 subject: PolicySubject::Binary("/usr/bin/nc2".into()),
+                mode: PolicyMode::Enforce,
                 allow: Addresses::All,
                 deny: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 4])]),
+                port_rules: vec![],
             })
             .await
             .unwrap();
@@ -101,8 +199,7 @@ async fn test_socket_connect_deny_one() {
         println!("listening for alarms");
 
         while let Some(msg) = rx.recv().await {
-            // TODO(tjonak): shouldn't PolicySubject::Binary hold a path not an inode?
-            if msg.subject != PolicySubject::Binary(format!("{}", inode).into()) {
+            if msg.subject != PolicySubject::Binary("/usr/bin/nc2".into()) {
                 panic!("unexpected binary: {:?}", msg);
             }
             println!("alert found: {:?}", msg);
@@ -145,3 +242,689 @@ async fn test_socket_connect_deny_one() {
         .expect("timeout elapsed")
         .expect("task panicked");
 }
+
+#[tokio::test]
+async fn test_socket_connect_deny_ipv4_mapped() {
+    let listener = TcpListener::bind("127.1.2.11:8086").await.unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_connect = mgr.attach_socket_connect().unwrap();
+
+    println!("registering deny policy");
+    socket_connect
+        .add_policy(SocketConnect {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Addresses::All,
+            deny: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 11])]),
+            port_rules: vec![],
+        })
+        .await
+        .unwrap();
+
+    // IPv4-mapped IPv6 addresses are matched by IPv4 rules.
+    let err = TcpStream::connect("[::ffff:127.1.2.11]:8086")
+        .await
+        .expect_err("connecting to IPv4-mapped denied address should fail");
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+    socket_connect
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+    drop(listener);
+}
+
+#[tokio::test]
+async fn test_socket_bind_deny_ipv6() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_bind = mgr.attach_socket_bind().unwrap();
+
+    let mut rx = socket_bind.alerts().await.unwrap();
+
+    println!("registering deny policy");
+    socket_bind
+        .add_policy(SocketBind {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Ports::All,
+            deny: Ports::Ports(vec![8082]),
+            address_rules: vec![],
+        })
+        .await
+        .unwrap();
+
+    assert!(
+        TcpListener::bind("[::1]:8082").await.is_err(),
+        "binding IPv6 socket should fail"
+    );
+
+    let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout elapsed")
+        .expect("alert expected");
+    println!("alert found: {:?}", alert);
+    assert_eq!(alert.addr, IpAddr::V6(Ipv6Addr::LOCALHOST));
+    assert_eq!(alert.port, 8082);
+
+    socket_bind
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_bind_deny_address() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_bind = mgr.attach_socket_bind().unwrap();
+
+    println!("registering deny policy");
+    socket_bind
+        .add_policy(SocketBind {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Ports::All,
+            deny: Ports::Ports(vec![]),
+            address_rules: vec![AddressRule {
+                addresses: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 5])]),
+                allow: Ports::Ports(vec![]),
+                deny: Ports::Ports(vec![8083]),
+            }],
+        })
+        .await
+        .unwrap();
+
+    assert!(
+        TcpListener::bind("127.1.2.5:8083").await.is_err(),
+        "binding denied address should fail"
+    );
+    assert!(
+        TcpListener::bind("127.1.2.6:8083").await.is_ok(),
+        "binding other address should pass"
+    );
+
+    socket_bind
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_bind_deny_address_any_port() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_bind = mgr.attach_socket_bind().unwrap();
+
+    println!("registering deny policy");
+    socket_bind
+        .add_policy(SocketBind {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Ports::All,
+            deny: Ports::Ports(vec![]),
+            address_rules: vec![AddressRule {
+                addresses: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 7])]),
+                allow: Ports::Ports(vec![]),
+                deny: Ports::All,
+            }],
+        })
+        .await
+        .unwrap();
+
+    // Port 0 lets the kernel pick a port, rules for the address still apply.
+    assert!(
+        TcpListener::bind("127.1.2.7:0").await.is_err(),
+        "binding denied address with any port should fail"
+    );
+    assert!(
+        TcpListener::bind("127.1.2.8:0").await.is_ok(),
+        "binding other address with any port should pass"
+    );
+
+    socket_bind
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_bind_deny_ipv4_dual_stack() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_bind = mgr.attach_socket_bind().unwrap();
+
+    println!("registering deny policy");
+    socket_bind
+        .add_policy(SocketBind {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Ports::All,
+            deny: Ports::Ports(vec![]),
+            address_rules: vec![
+                AddressRule {
+                    addresses: Addresses::Addresses(vec![IpAddr::from([0, 0, 0, 0])]),
+                    allow: Ports::Ports(vec![]),
+                    deny: Ports::Ports(vec![8084]),
+                },
+                AddressRule {
+                    addresses: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 9])]),
+                    allow: Ports::Ports(vec![]),
+                    deny: Ports::Ports(vec![8085]),
+                },
+            ],
+        })
+        .await
+        .unwrap();
+
+    // Dual-stack sockets bound to `::` accept IPv4 connections on `0.0.0.0`.
+    assert!(
+        TcpListener::bind("[::]:8084").await.is_err(),
+        "binding dual-stack socket to denied port should fail"
+    );
+    assert!(
+        TcpListener::bind("[::ffff:127.1.2.9]:8085").await.is_err(),
+        "binding IPv4-mapped denied address should fail"
+    );
+    assert!(
+        TcpListener::bind("[::ffff:127.1.2.10]:8085").await.is_ok(),
+        "binding other IPv4-mapped address should pass"
+    );
+
+    socket_bind
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_listen_deny() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_listen = mgr.attach_socket_listen().unwrap();
+
+    let mut rx = socket_listen.alerts().await.unwrap();
+
+    println!("registering deny policy");
+    socket_listen
+        .add_policy(SocketListen {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Ports::All,
+            deny: Ports::Ports(vec![8084]),
+            address_rules: vec![],
+        })
+        .await
+        .unwrap();
+
+    // Binding is allowed, listening on the bound port is denied.
+    assert!(
+        TcpListener::bind("127.0.0.1:8084").await.is_err(),
+        "listening on denied port should fail"
+    );
+
+    let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout elapsed")
+        .expect("alert expected");
+    println!("alert found: {:?}", alert);
+    assert_eq!(alert.addr, IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(alert.port, 8084);
+
+    assert!(
+        TcpListener::bind("127.0.0.1:8085").await.is_ok(),
+        "listening on other port should pass"
+    );
+
+    socket_listen
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_inet_conn_request_deny_port() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut inet_conn_request = mgr.attach_inet_conn_request().unwrap();
+
+    let mut rx = inet_conn_request.alerts().await.unwrap();
+
+    println!("registering deny policy");
+    inet_conn_request
+        .add_policy(InetConnRequest {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Addresses::All,
+            deny: Addresses::Addresses(vec![]),
+            port_rules: vec![
+                PortRule {
+                    protocol: Protocol::All,
+                    addresses: Addresses::All,
+                    allow: Ports::Ports(vec![]),
+                    deny: Ports::Ports(vec![8086]),
+                },
+                PortRule {
+                    protocol: Protocol::All,
+                    addresses: Addresses::Addresses(vec![IpAddr::from([127, 0, 0, 2])]),
+                    allow: Ports::Ports(vec![8086]),
+                    deny: Ports::Ports(vec![]),
+                },
+            ],
+        })
+        .await
+        .unwrap();
+
+    let listener = TcpListener::bind("0.0.0.0:8086").await.unwrap();
+
+    // Dropped connection requests are retried by the client, so the
+    // connection never completes.
+    assert!(
+        tokio::time::timeout(Duration::from_secs(1), TcpStream::connect("127.0.0.1:8086"))
+            .await
+            .is_err(),
+        "connecting from denied address should fail"
+    );
+
+    let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout elapsed")
+        .expect("alert expected");
+    println!("alert found: {:?}", alert);
+    assert_eq!(alert.addr, IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(alert.port, 8086);
+
+    let socket = tokio::net::TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.2:0".parse().unwrap()).unwrap();
+    assert!(
+        socket
+            .connect("127.0.0.1:8086".parse().unwrap())
+            .await
+            .is_ok(),
+        "connecting from allowed address should pass"
+    );
+    drop(listener);
+
+    inet_conn_request
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_socket_connect_unix_deny_path() {
+    let path = PathBuf::from("/tmp/ebpfguard-test-deny.sock");
+    let _ = std::fs::remove_file(&path);
+    let _listener = UnixListener::bind(&path).unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_connect_unix = mgr.attach_socket_connect_unix().unwrap();
+
+    let mut rx = socket_connect_unix.alerts().await.unwrap();
+
+    println!("registering deny policy");
+    socket_connect_unix
+        .add_policy(SocketConnectUnix {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: UnixPaths::All,
+            deny: UnixPaths::Paths(vec![UnixPath::Path(path.clone())]),
+        })
+        .await
+        .unwrap();
+
+    assert!(
+        UnixStream::connect(&path).await.is_err(),
+        "connecting to denied socket should fail"
+    );
+
+    let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout elapsed")
+        .expect("alert expected");
+    println!("alert found: {:?}", alert);
+    assert_eq!(alert.path, UnixPath::Path(path.clone()));
+
+    socket_connect_unix
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_socket_create_deny_family_type() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut socket_create = mgr.attach_socket_create().unwrap();
+
+    let mut rx = socket_create.alerts().await.unwrap();
+
+    println!("registering deny policy");
+    socket_create
+        .add_policy(SocketCreate {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Sockets::All,
+            deny: Sockets::Sockets(vec![SocketKind {
+                family: SocketFamily::Unix,
+                socket_type: SocketType::Dgram,
+                protocol: None,
+            }]),
+        })
+        .await
+        .unwrap();
+
+    assert!(
+        UnixDatagram::unbound().is_err(),
+        "creating denied socket should fail"
+    );
+
+    let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timeout elapsed")
+        .expect("alert expected");
+    println!("alert found: {:?}", alert);
+    assert_eq!(alert.family, SocketFamily::Unix);
+    assert_eq!(alert.socket_type, 2);
+
+    socket_create
+        .remove_policy(&PolicySubject::All)
+        .await
+        .unwrap();
+    assert!(UnixDatagram::unbound().is_ok());
+}
+
+#[tokio::test]
+async fn test_file_open_deny_pattern() {
+    let dir = PathBuf::from("/tmp/ebpfguard-test-pattern");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut file_open = mgr.attach_file_open().unwrap();
+
+    println!("registering deny policy");
+    file_open
+        .add_policy(FileOpen {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Paths::All,
+            deny: Paths::Paths(vec![dir.join("*.key")]),
+            matching: PathMatch::Inode,
+            access_rules: vec![],
+        })
+        .await
+        .unwrap();
+
+    // The file doesn't exist when the policy is added.
+    let file = dir.join("server.key");
+    std::fs::write(&file, "secret").unwrap();
+    let updated = tokio::time::timeout(Duration::from_secs(5), file_open.update_paths())
+        .await
+        .expect("timeout elapsed")
+        .unwrap();
+    assert_eq!(updated, 1);
+
+    assert!(
+        std::fs::read(&file).is_err(),
+        "opening denied file should fail"
+    );
+    assert!(std::fs::write(dir.join("server.crt"), "").is_ok());
+
+    file_open.remove_policy(&PolicySubject::All).await.unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_file_open_deny_prefix() {
+    let dir = PathBuf::from("/tmp/ebpfguard-test-prefix");
+    let _ = std::fs::remove_dir_all(&dir);
+    let nested = dir.join("secret/a/b/c");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(nested.join("file"), "secret").unwrap();
+    let sibling = dir.join("secret-not");
+    std::fs::create_dir_all(&sibling).unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut file_open = mgr.attach_file_open().unwrap();
+    file_open
+        .set_settings(FileOpenSettings {
+            max_depth: 2,
+            deny_truncated: false,
+        })
+        .unwrap();
+
+    println!("registering deny policy");
+    file_open
+        .add_policy(FileOpen {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Paths::All,
+            deny: Paths::Paths(vec![dir.join("secret")]),
+            matching: PathMatch::Prefix,
+            access_rules: vec![],
+        })
+        .await
+        .unwrap();
+
+    // The file is nested deeper than the checked parent directories, it's
+    // matched by its path.
+    assert!(
+        std::fs::read(nested.join("file")).is_err(),
+        "opening file under denied prefix should fail"
+    );
+    assert!(std::fs::write(sibling.join("file"), "").is_ok());
+
+    file_open.remove_policy(&PolicySubject::All).await.unwrap();
+    file_open.set_settings(FileOpenSettings::default()).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_file_open_deep_path_without_policies() {
+    let dir = PathBuf::from("/tmp/ebpfguard-test-deep");
+    let _ = std::fs::remove_dir_all(&dir);
+    let nested = (0..20).fold(dir.clone(), |path, i| path.join(i.to_string()));
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut file_open = mgr.attach_file_open().unwrap();
+
+    let mut rx = file_open.alerts().await.unwrap();
+
+    // The file is nested deeper than the checked parent directories, but
+    // there are no directory entries which could match it.
+    assert!(std::fs::write(nested.join("file"), "").is_ok());
+    assert!(std::fs::read(nested.join("file")).is_ok());
+
+    let pid = std::process::id();
+    let alert = tokio::time::timeout(Duration::from_secs(1), async {
+        while let Some(alert) = rx.recv().await {
+            if alert.pid == pid && alert.truncated {
+                return Some(alert);
+            }
+        }
+        None
+    })
+    .await;
+    assert!(
+        !matches!(alert, Ok(Some(_))),
+        "no alert expected: {:?}",
+        alert
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_file_open_allowlist_deep_path() {
+    let cat = PathBuf::from("/usr/bin/cat-allowlist");
+    tokio::fs::copy("/usr/bin/cat", &cat)
+        .await
+        .expect("failed to make cat copy");
+    let dir = PathBuf::from("/tmp/ebpfguard-test-allowlist");
+    let _ = std::fs::remove_dir_all(&dir);
+    let nested = (0..20).fold(dir.join("allowed"), |path, i| path.join(i.to_string()));
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(dir.join("allowed/file"), "allowed").unwrap();
+    std::fs::write(nested.join("file"), "nested").unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut file_open = mgr.attach_file_open().unwrap();
+
+    println!("registering allowlist policy");
+    let subject = PolicySubject::Binary(cat.clone());
+    file_open
+        .add_policy(FileOpen {
+            subject: subject.clone(),
+            mode: PolicyMode::Enforce,
+            allow: Paths::Paths(vec![
+                dir.join("allowed"),
+                "/etc".into(),
+                "/lib".into(),
+                "/lib64".into(),
+                "/usr".into(),
+            ]),
+            deny: Paths::All,
+            matching: PathMatch::Inode,
+            access_rules: vec![],
+        })
+        .await
+        .unwrap();
+
+    let cmd = tokio::process::Command::new(&cat)
+        .arg(dir.join("allowed/file"))
+        .output()
+        .await
+        .expect("unexpected execution failure");
+    assert!(cmd.status.success(), "reading allowed file should pass");
+
+    // The file is nested deeper than the checked parent directories, the
+    // entry for all paths still denies it.
+    let cmd = tokio::process::Command::new(&cat)
+        .arg(nested.join("file"))
+        .output()
+        .await
+        .expect("unexpected execution failure");
+    assert!(
+        !cmd.status.success(),
+        "reading deeply nested file should fail"
+    );
+
+    file_open.remove_policy(&subject).await.unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = tokio::fs::remove_file(&cat).await;
+}
+
+#[tokio::test]
+async fn test_bprm_check_security_allowlist_deep_path() {
+    let sh = PathBuf::from("/usr/bin/sh-allowlist");
+    tokio::fs::copy("/bin/sh", &sh)
+        .await
+        .expect("failed to make sh copy");
+    let dir = PathBuf::from("/tmp/ebpfguard-test-exec-allowlist");
+    let _ = std::fs::remove_dir_all(&dir);
+    let nested = (0..20).fold(dir.join("allowed"), |path, i| path.join(i.to_string()));
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::copy("/usr/bin/true", dir.join("allowed/true")).unwrap();
+    std::fs::copy("/usr/bin/true", nested.join("true")).unwrap();
+
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut bprm_check_security = mgr.attach_bprm_check_security().unwrap();
+
+    println!("registering allowlist policy");
+    let subject = PolicySubject::Binary(sh.clone());
+    bprm_check_security
+        .add_policy(BprmCheckSecurity {
+            subject: subject.clone(),
+            mode: PolicyMode::Enforce,
+            allow: Paths::Paths(vec![dir.join("allowed")]),
+            deny: Paths::All,
+        })
+        .await
+        .unwrap();
+
+    let cmd = tokio::process::Command::new(&sh)
+        .arg("-c")
+        .arg(dir.join("allowed/true"))
+        .output()
+        .await
+        .expect("unexpected execution failure");
+    assert!(cmd.status.success(), "executing allowed binary should pass");
+
+    // The binary is nested deeper than the checked parent directories, the
+    // entry for all paths still denies it.
+    let cmd = tokio::process::Command::new(&sh)
+        .arg("-c")
+        .arg(nested.join("true"))
+        .output()
+        .await
+        .expect("unexpected execution failure");
+    assert!(
+        !cmd.status.success(),
+        "executing deeply nested binary should fail"
+    );
+
+    bprm_check_security.remove_policy(&subject).await.unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = tokio::fs::remove_file(&sh).await;
+}
+
+#[tokio::test]
+async fn test_add_policy_invalid() {
+    let mut mgr: PolicyManager = PolicyManager::with_default_path().unwrap();
+
+    let mut all = mgr.manage_all().unwrap();
+
+    let policy = || {
+        Policy::SocketBind(SocketBind {
+            subject: PolicySubject::All,
+            mode: PolicyMode::Enforce,
+            allow: Ports::All,
+            deny: Ports::Ports(vec![]),
+            address_rules: vec![
+                AddressRule {
+                    addresses: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 12])]),
+                    allow: Ports::Ports(vec![8087]),
+                    deny: Ports::Ports(vec![]),
+                },
+                AddressRule {
+                    addresses: Addresses::Addresses(vec![IpAddr::from([127, 1, 2, 12])]),
+                    allow: Ports::Ports(vec![]),
+                    deny: Ports::Ports(vec![8087]),
+                },
+            ],
+        })
+    };
+
+    let err = all.add_policy(policy()).await.unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            EbpfguardError::InvalidPolicies(errors)
+                if errors
+                    .iter()
+                    .any(|e| matches!(e, EbpfguardError::ContradictoryPolicy { .. }))
+        ),
+        "unexpected error: {err}"
+    );
+    let err = all.reconcile(vec![policy()]).await.unwrap_err();
+    assert!(
+        matches!(&err, EbpfguardError::InvalidPolicies(_)),
+        "unexpected error: {err}"
+    );
+
+    assert!(
+        TcpListener::bind("127.1.2.12:8087").await.is_ok(),
+        "invalid policy shouldn't be loaded"
+    );
+}